@@ -1,4 +1,12 @@
-use std::process::Command;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Directory the pages of the documentation website are written to, if set. See `generate_widget_docs`.
+const DOCS_OUT_DIR_ENV: &str = "EWW_DOCS_OUT_DIR";
+
 fn main() {
     let output = Command::new("git").args(["rev-parse", "HEAD"]).output();
     if let Ok(output) = output {
@@ -13,4 +21,221 @@ fn main() {
             println!("cargo:rustc-env=GIT_COMMIT_DATE={}", date);
         }
     }
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-env-changed={}", DOCS_OUT_DIR_ENV);
+    generate_widget_docs();
+}
+
+/// Extract the `@widget`, `@desc`, `@prop` and `@var` annotations from the widget definitions into `widget_docs.json`,
+/// which is embedded into the binary for `eww doc`.
+///
+/// This is the only place the documentation is generated from. When building the documentation website,
+/// `EWW_DOCS_OUT_DIR` is set to an absolute path, to which the JSON and the markdown pages of the widgets
+/// and magic variables are written, to be included by the handwritten pages.
+fn generate_widget_docs() {
+    let source = std::fs::read_to_string("src/widgets/widget_definitions.rs").expect("Failed to read widget definitions");
+    let docs = parse_widget_docs(&source);
+    let out_dir = std::env::var("OUT_DIR").expect("OUT_DIR not set");
+    let json = serde_json::to_string_pretty(&docs).expect("Failed to serialize widget docs");
+    std::fs::write(Path::new(&out_dir).join("widget_docs.json"), &json).expect("Failed to write widget docs");
+
+    let Some(docs_dir) = std::env::var_os(DOCS_OUT_DIR_ENV).map(PathBuf::from) else { return };
+    let inbuilt_vars = std::fs::read_to_string("src/config/inbuilt.rs").expect("Failed to read magic variable definitions");
+    std::fs::create_dir_all(&docs_dir).expect("Failed to create documentation directory");
+    std::fs::write(docs_dir.join("widgets.json"), &json).expect("Failed to write widget docs");
+    std::fs::write(docs_dir.join("widgets.md"), widget_docs_markdown(&docs)).expect("Failed to write widget docs");
+    std::fs::write(docs_dir.join("magic-vars.md"), magic_var_docs_markdown(&inbuilt_vars))
+        .expect("Failed to write magic variable docs");
+}
+
+/// Render the generated widget documentation as markdown, listing the properties of each widget.
+fn widget_docs_markdown(docs: &serde_json::Value) -> String {
+    let widgets = docs["widgets"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let sections = widgets.iter().map(|widget| {
+        let description = widget["description"].as_str().unwrap_or_default();
+        let props = widget["props"].as_array().map(Vec::as_slice).unwrap_or_default().iter().map(|prop| {
+            format!(
+                "- **`{}`**: *`{}`* {}",
+                prop["name"].as_str().unwrap_or_default(),
+                prop["type"].as_str().unwrap_or_default(),
+                prop["description"].as_str().unwrap_or_default()
+            )
+        });
+        let description = if description.is_empty() { String::new() } else { format!("\n{}", description) };
+        format!(
+            "\n## `{}` {}\n\n**Properties**\n{}\n",
+            widget["name"].as_str().unwrap_or_default(),
+            description,
+            props.collect::<Vec<_>>().join("\n")
+        )
+    });
+    sections.collect::<Vec<_>>().join("\n\n")
+}
+
+/// Render the `@desc` and `@prop` annotations of the magic variables as markdown.
+/// Comment lines directly following an annotation continue it as a new paragraph.
+fn magic_var_docs_markdown(source: &str) -> String {
+    struct MagicVar {
+        name: String,
+        description: String,
+        structure: Option<String>,
+    }
+    let mut vars: Vec<MagicVar> = Vec::new();
+    // whether the previous line was a `@desc` (false) or a `@prop` (true) annotation, or neither
+    let mut continued_prop = None;
+    for line in source.lines() {
+        let comment = doc_comment_content(line);
+        if let Some((name, description)) = comment.and_then(|c| c.strip_prefix("@desc ")).and_then(|c| c.split_once(" - ")) {
+            vars.push(MagicVar { name: name.trim().to_string(), description: description.trim().to_string(), structure: None });
+            continued_prop = Some(false);
+        } else if let (Some(prop), Some(var)) = (comment.and_then(|c| c.strip_prefix("@prop ")), vars.last_mut()) {
+            var.structure = Some(prop.trim().to_string());
+            continued_prop = Some(true);
+        } else if let (Some(text), Some(var), Some(is_prop)) = (comment, vars.last_mut(), continued_prop) {
+            match (is_prop, &mut var.structure) {
+                (false, _) => var.description.push_str(&format!("\n\n{}", text)),
+                (true, Some(structure)) => structure.push_str(&format!("\n\n{}", text)),
+                (true, None) => {}
+            }
+        } else {
+            continued_prop = None;
+        }
+    }
+    let mut markdown = String::new();
+    for var in vars {
+        markdown.push_str(&format!("### `{}`\n{}\n", var.name, var.description));
+        if let Some(structure) = var.structure {
+            markdown.push_str(&format!("#### Structure\n```\n{}\n```\n", structure));
+        }
+        markdown.push('\n');
+    }
+    markdown
+}
+
+struct WidgetAnnotation {
+    name: String,
+    extends: Vec<String>,
+    description: String,
+    props: Vec<serde_json::Value>,
+    is_visible: bool,
+}
+
+fn parse_widget_docs(source: &str) -> serde_json::Value {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut vars = BTreeMap::new();
+    let mut widgets: Vec<WidgetAnnotation> = Vec::new();
+
+    for (line_index, line) in lines.iter().enumerate() {
+        let Some(comment) = doc_comment_content(line) else { continue };
+        if let Some(var) = comment.strip_prefix("@var ") {
+            if let Some((name, values)) = var.split_once(" - ") {
+                vars.insert(name.trim().to_string(), values.trim().to_string());
+            }
+        } else if let Some(widget) = comment.strip_prefix("@widget ") {
+            let (name, extends) = match widget.split_once(" extends ") {
+                Some((name, extends)) => (name, extends.split(',').map(|x| x.trim().to_string()).collect()),
+                None => (widget, Vec::new()),
+            };
+            let (name, is_visible) = match name.trim().strip_prefix('!') {
+                Some(name) => (name, false),
+                None => (name.trim(), true),
+            };
+            widgets.push(WidgetAnnotation {
+                name: name.to_string(),
+                extends,
+                description: String::new(),
+                props: Vec::new(),
+                is_visible,
+            });
+        } else if let Some(description) = comment.strip_prefix("@desc ") {
+            if let Some(widget) = widgets.last_mut() {
+                widget.description = description.trim().to_string();
+            }
+        } else if let Some(prop) = comment.strip_prefix("@prop ") {
+            let (Some(widget), Some((name, description))) = (widgets.last_mut(), prop.split_once(" - ")) else {
+                println!("cargo:warning=Malformed @prop annotation in line {}: {}", line_index + 1, line.trim());
+                continue;
+            };
+            let name = name.trim();
+            if widget.props.iter().any(|prop| prop["name"] == name) {
+                continue;
+            }
+            let prop_type = find_prop_type(&lines[line_index + 1..], name).unwrap_or_else(|| {
+                println!("cargo:warning=Could not find the type of property `{}` of widget `{}`", name, widget.name);
+                "no-type-found".to_string()
+            });
+            widget.props.push(serde_json::json!({ "name": name, "type": prop_type, "description": description.trim() }));
+        }
+    }
+
+    let visible_widgets: Vec<_> = widgets
+        .iter()
+        .filter(|widget| widget.is_visible)
+        .map(|widget| {
+            let inherited_props = widget
+                .extends
+                .iter()
+                .filter_map(|name| widgets.iter().find(|w| &w.name == name))
+                .flat_map(|parent| parent.props.iter().cloned());
+            let props: Vec<_> = widget
+                .props
+                .iter()
+                .cloned()
+                .chain(inherited_props)
+                .map(|mut prop| {
+                    prop["description"] = substitute_vars(prop["description"].as_str().unwrap_or_default(), &vars).into();
+                    prop
+                })
+                .collect();
+            serde_json::json!({ "name": widget.name, "description": widget.description, "props": props })
+        })
+        .collect();
+
+    serde_json::json!({ "widgets": visible_widgets, "vars": vars })
+}
+
+/// Get the content of a `//` or `///` comment line, if the line is a comment.
+fn doc_comment_content(line: &str) -> Option<&str> {
+    Some(line.trim_start().strip_prefix("//")?.trim_start_matches('/').trim())
+}
+
+/// Find the type of a property in the first line of code after its annotation, i.e. `prop(name: as_string)`.
+fn find_prop_type(following_lines: &[&str], prop_name: &str) -> Option<String> {
+    let code_line = following_lines.iter().find(|line| doc_comment_content(line).is_none())?;
+    let pattern = format!("{}: as_", prop_name.replace('-', "_"));
+    let type_start = code_line.match_indices(&pattern).map(|(index, _)| index + pattern.len()).find(|&index| {
+        // make sure we didn't just match the end of a longer property name
+        let name_start = index - pattern.len();
+        name_start == 0 || !code_line[..name_start].ends_with(|c: char| c.is_alphanumeric() || c == '_')
+    })?;
+    let type_name: String = code_line[type_start..].chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+    Some(
+        match type_name.as_str() {
+            "f64" | "f32" => "float",
+            "i32" | "i64" => "int",
+            "optional_duration" => "duration",
+            other => other,
+        }
+        .to_string(),
+    )
+}
+
+/// Replace `$name` references to `@var` annotations with their values.
+fn substitute_vars(text: &str, vars: &BTreeMap<String, String>) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(index) = rest.find('$') {
+        result.push_str(&rest[..index]);
+        let name_len = rest[index + 1..].find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len() - index - 1);
+        let name = &rest[index + 1..index + 1 + name_len];
+        match vars.get(name) {
+            Some(value) => result.push_str(value),
+            None => result.push_str(&rest[index..index + 1 + name_len]),
+        }
+        rest = &rest[index + 1 + name_len..];
+    }
+    result.push_str(rest);
+    result
 }
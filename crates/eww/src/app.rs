@@ -2,19 +2,27 @@ use crate::{
     daemon_response::DaemonResponseSender,
     display_backend::DisplayBackend,
     error_handling_ctx,
-    gtk::prelude::{ContainerExt, CssProviderExt, GtkWindowExt, MonitorExt, StyleContextExt, WidgetExt},
+    gtk::prelude::{
+        Cast, ContainerExt, CssProviderExt, GtkSettingsExt, GtkWindowExt, LabelExt, MonitorExt, RevealerExt, StyleContextExt,
+        WidgetExt, WidgetExtManual,
+    },
     paths::EwwPaths,
+    process_registry::{self, ProcessOwner},
     script_var_handler::ScriptVarHandlerHandle,
-    state::scope_graph::{ScopeGraph, ScopeIndex},
+    server::WatchedFiles,
+    state::scope_graph::{ScopeGraph, ScopeGraphEvent, ScopeIndex},
     widgets::window::Window,
     window_arguments::WindowArguments,
     window_initiator::WindowInitiator,
     *,
 };
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
 use codespan_reporting::files::Files;
 use eww_shared_util::{Span, VarName};
-use gdk::Monitor;
+use gdk::{
+    prelude::{DeviceExt, SeatExt},
+    Monitor,
+};
 use glib::ObjectExt;
 use gtk::{gdk, glib};
 use itertools::Itertools;
@@ -29,15 +37,21 @@ use std::{
 use tokio::sync::mpsc::UnboundedSender;
 use yuck::{
     config::{
+        backend_window_options::WlExclusiveZone,
         monitor::MonitorIdentifier,
         script_var_definition::ScriptVarDefinition,
-        window_geometry::{AnchorPoint, WindowGeometry},
+        window_definition::{WindowStacking, WindowTransition, POINTER_VAR_NAME},
+        window_geometry::{AnchorAlignment, AnchorPoint, WindowGeometry},
     },
     error::DiagError,
     gen_diagnostic,
     value::Coords,
 };
 
+/// Default maximum time to wait for all scripts to stop when shutting down the daemon, configurable via `eww kill --timeout`.
+/// Scripts that don't react to SIGTERM are killed after 10 seconds, so this should be slightly longer than that.
+pub const SCRIPT_SHUTDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(12);
+
 /// A command for the eww daemon.
 /// While these are mostly generated from eww CLI commands (see [`opts::ActionWithServer`]),
 /// they may also be generated from other places internally.
@@ -45,8 +59,17 @@ use yuck::{
 pub enum DaemonCommand {
     NoOp,
     UpdateVars(Vec<(VarName, DynVal)>),
+    /// Update variables as requested by `eww update`, rejecting all of the updates if any value doesn't match the type of its variable.
+    UpdateVarsChecked {
+        mappings: Vec<(VarName, DynVal)>,
+        sender: DaemonResponseSender,
+    },
     ReloadConfigAndCss(DaemonResponseSender),
+    /// Recompile and apply the stylesheet, without reloading the rest of the configuration.
+    ReloadCss(DaemonResponseSender),
+    MonitorsChanged,
     OpenInspector,
+    SetClipboard(String),
     OpenMany {
         windows: Vec<(String, String)>,
         args: Vec<(String, VarName, DynVal)>,
@@ -59,29 +82,82 @@ pub enum DaemonCommand {
         pos: Option<Coords>,
         size: Option<Coords>,
         anchor: Option<AnchorPoint>,
+        at_pointer: bool,
         screen: Option<MonitorIdentifier>,
         should_toggle: bool,
         duration: Option<std::time::Duration>,
         sender: DaemonResponseSender,
         args: Option<Vec<(VarName, DynVal)>>,
+        /// Arguments to update in place if the window is already open, instead of reopening it
+        update_args: Option<Vec<(VarName, DynVal)>>,
     },
     CloseWindows {
         windows: Vec<String>,
         sender: DaemonResponseSender,
     },
-    KillServer,
+    ShowWindow {
+        window_name: String,
+        instance_id: Option<String>,
+        sender: DaemonResponseSender,
+    },
+    HideWindows {
+        windows: Vec<String>,
+        sender: DaemonResponseSender,
+    },
+    FocusWindow {
+        instance_id: String,
+        sender: DaemonResponseSender,
+    },
+    RaiseWindow {
+        instance_id: String,
+        sender: DaemonResponseSender,
+    },
+    /// Stop the daemon, optionally reporting the steps of the shutdown to the client.
+    KillServer {
+        /// Maximum time to wait for all scripts to stop
+        timeout: std::time::Duration,
+        sender: Option<DaemonResponseSender>,
+    },
     CloseAll,
     PrintState {
         all: bool,
         sender: DaemonResponseSender,
     },
+    ExportState {
+        file: std::path::PathBuf,
+        sender: DaemonResponseSender,
+    },
+    ImportState {
+        file: std::path::PathBuf,
+        sender: DaemonResponseSender,
+    },
+    SetBrightness {
+        device: String,
+        percent: f64,
+        sender: DaemonResponseSender,
+    },
     GetVar {
         name: String,
         sender: DaemonResponseSender,
     },
     PrintDebug(DaemonResponseSender),
     PrintGraph(DaemonResponseSender),
+    PrintStats(DaemonResponseSender),
+    ListProcesses {
+        kill: Option<u32>,
+        restart: Option<VarName>,
+        sender: DaemonResponseSender,
+    },
+    PrintDaemonInfo(DaemonResponseSender),
+    PrintWidgetTree {
+        instance_id: String,
+        sender: DaemonResponseSender,
+    },
     ListWindows(DaemonResponseSender),
+    ListCompletions {
+        kind: opts::CompletionKind,
+        sender: DaemonResponseSender,
+    },
     ListActiveWindows(DaemonResponseSender),
 }
 
@@ -91,7 +167,30 @@ pub struct EwwWindow {
     pub name: String,
     pub scope_index: ScopeIndex,
     pub gtk_window: Window,
+    /// The monitor this window was opened on
+    pub monitor: Monitor,
     pub destroy_event_handler_id: Option<glib::SignalHandlerId>,
+    /// Whether the window is currently hidden via `eww hide`, keeping its widgets and scope alive
+    pub hidden: bool,
+    /// Revealer wrapping the root widget, used to animate opening and closing the window
+    pub revealer: Option<gtk::Revealer>,
+    pub close_transition: WindowTransition,
+    pub transition_duration: std::time::Duration,
+    /// Whether the window reserves space on the screen, like a bar. These windows are closed last when shutting down.
+    pub reserves_space: bool,
+    /// The `:z-index` of the window, determining its order relative to other eww windows
+    pub z_index: i32,
+    /// The `:hide-when-idle` duration of the window
+    pub hide_when_idle: Option<std::time::Duration>,
+    /// Whether the window is hidden because the user is idle, such that it is shown again once they're back
+    pub hidden_while_idle: bool,
+    /// Whether the window has been shown at all. Windows that are built hidden run their `:onopen` command once they're
+    /// first shown, and only run their `:onclose` command if that happened.
+    pub was_shown: bool,
+    /// The `:onopen` command of the window, until it has been run
+    pub onopen: Option<(widgets::CommandContext, String)>,
+    /// The `:onclose` command of the window, run once the window got closed
+    pub onclose: Option<(widgets::CommandContext, String)>,
 }
 
 impl EwwWindow {
@@ -99,12 +198,40 @@ impl EwwWindow {
     ///
     /// You need to make sure that the scope get's properly cleaned from the state graph
     /// and that script-vars get cleaned up properly
-    pub fn close(self) {
+    pub fn close(mut self) {
         log::info!("Closing gtk window {}", self.name);
-        self.gtk_window.close();
+        let onclose = self.onclose.take().filter(|_| self.was_shown);
         if let Some(handler_id) = self.destroy_event_handler_id {
             self.gtk_window.disconnect(handler_id);
         }
+        match (self.revealer, transition_to_revealer_type(self.close_transition, true)) {
+            (Some(revealer), Some(transition_type)) if !self.hidden => {
+                // play the close animation before actually closing the window
+                revealer.set_transition_type(transition_type);
+                revealer.set_reveal_child(false);
+                let gtk_window = self.gtk_window;
+                glib::timeout_add_local_once(self.transition_duration, move || gtk_window.close());
+            }
+            _ => self.gtk_window.close(),
+        }
+        if let Some((context, onclose)) = onclose {
+            widgets::run_command(&context, None, &onclose, &[] as &[&str]);
+        }
+    }
+
+    /// Show the window again after it was hidden, running its `:onopen` command if this is the first time it is shown.
+    fn show(&mut self) {
+        self.gtk_window.show();
+        self.hidden = false;
+        self.hidden_while_idle = false;
+        self.mark_shown();
+    }
+
+    fn mark_shown(&mut self) {
+        self.was_shown = true;
+        if let Some((context, onopen)) = self.onopen.take() {
+            widgets::run_command(&context, None, &onopen, &[] as &[&str]);
+        }
     }
 }
 
@@ -121,6 +248,13 @@ pub struct App<B: DisplayBackend> {
     /// When reloading the config, these should be opened again.
     pub failed_windows: HashSet<String>,
     pub css_provider: gtk::CssProvider,
+    /// Css providers of the windows that have a `:stylesheet`, by window name.
+    /// These are added to the style contexts of all widgets of the window, rather than to the whole screen.
+    pub window_css_providers: HashMap<String, gtk::CssProvider>,
+    /// Variables referenced in the stylesheets via `eww-var(name)`. The stylesheets are recompiled whenever one of them changes.
+    pub stylesheet_vars: HashSet<VarName>,
+    /// Used to tell the file watcher which files the configuration consists of, such that included files are watched as well.
+    pub watched_files_send: tokio::sync::watch::Sender<WatchedFiles>,
 
     /// Sender to send [`DaemonCommand`]s
     pub app_evt_send: UnboundedSender<DaemonCommand>,
@@ -129,6 +263,16 @@ pub struct App<B: DisplayBackend> {
     /// Senders that will cancel a windows auto-close timer when started with --duration.
     pub window_close_timer_abort_senders: HashMap<String, futures::channel::oneshot::Sender<()>>,
 
+    /// Window showing recent errors, if enabled via `:error-overlay` in `defconfig`.
+    pub error_overlay: Option<ErrorOverlay>,
+
+    /// When the daemon was started, for `eww stats`.
+    pub started_at: std::time::Instant,
+    /// Hash of the contents of the configuration files, as they were when the configuration was last loaded
+    pub config_hash: u64,
+    /// How often each global variable has been updated, for `eww stats`.
+    pub var_update_stats: HashMap<VarName, VarUpdateStats>,
+
     pub paths: EwwPaths,
     pub phantom: PhantomData<B>,
 }
@@ -152,6 +296,7 @@ impl<B: DisplayBackend> App<B> {
         if let Err(err) = self.try_handle_command(event) {
             error_handling_ctx::print_error(err);
         }
+        crash_report::set_open_windows(self.open_windows.iter().map(|(id, window)| format!("{id}: {}", window.name)).collect());
     }
 
     /// Try to handle a [`DaemonCommand`] event.
@@ -162,34 +307,58 @@ impl<B: DisplayBackend> App<B> {
             DaemonCommand::OpenInspector => {
                 gtk::Window::set_interactive_debugging(true);
             }
+            DaemonCommand::SetClipboard(text) => {
+                crate::widgets::set_clipboard_text(&text);
+            }
             DaemonCommand::UpdateVars(mappings) => {
                 for (var_name, new_value) in mappings {
                     self.update_global_variable(var_name, new_value);
                 }
             }
+            DaemonCommand::UpdateVarsChecked { mappings, sender } => {
+                let errors: Vec<_> =
+                    mappings.iter().filter_map(|(var_name, new_value)| self.check_var_type(var_name, new_value).err()).collect();
+                if errors.is_empty() {
+                    for (var_name, new_value) in mappings {
+                        self.update_global_variable(var_name, new_value);
+                    }
+                }
+                sender.respond_with_error_list(errors)?;
+            }
             DaemonCommand::ReloadConfigAndCss(sender) => {
                 let mut errors = Vec::new();
 
+                if let Some(pre_reload) = &self.eww_config.get_config_options().pre_reload {
+                    if let Err(e) = config::run_command(pre_reload, ProcessOwner::Hook("pre-reload")) {
+                        errors.push(e.context("Failed to run pre-reload command"));
+                    }
+                }
+
                 let config_result = config::read_from_eww_paths(&self.paths);
                 if let Err(e) = config_result.and_then(|new_config| self.load_config(new_config)) {
                     errors.push(e)
                 }
-                match crate::config::scss::parse_scss_from_config(self.paths.get_config_dir()) {
-                    Ok((file_id, css)) => {
-                        if let Err(e) = self.load_css(file_id, &css) {
-                            errors.push(anyhow!(e));
-                        }
-                    }
-                    Err(e) => {
-                        errors.push(e);
+                errors.extend(self.reload_stylesheets());
+
+                if let Some(post_reload) = &self.eww_config.get_config_options().post_reload {
+                    if let Err(e) = config::run_command(post_reload, ProcessOwner::Hook("post-reload")) {
+                        errors.push(e.context("Failed to run post-reload command"));
                     }
                 }
 
+                self.update_error_overlay(&errors);
                 sender.respond_with_error_list(errors)?;
             }
-            DaemonCommand::KillServer => {
+            DaemonCommand::ReloadCss(sender) => {
+                let errors = self.reload_stylesheets();
+                sender.respond_with_error_list(errors)?;
+            }
+            DaemonCommand::MonitorsChanged => {
+                self.reevaluate_window_monitors()?;
+            }
+            DaemonCommand::KillServer { timeout, sender } => {
                 log::info!("Received kill command, stopping server!");
-                self.stop_application();
+                self.stop_application(timeout, sender);
             }
             DaemonCommand::CloseAll => {
                 log::info!("Received close command, closing all windows");
@@ -223,18 +392,24 @@ impl<B: DisplayBackend> App<B> {
                 pos,
                 size,
                 anchor,
+                at_pointer,
                 screen: monitor,
                 should_toggle,
                 duration,
                 sender,
                 args,
+                update_args,
             } => {
                 let instance_id = instance_id.unwrap_or_else(|| window_name.clone());
 
                 let is_open = self.open_windows.contains_key(&instance_id);
+                let should_update = update_args.is_some();
+                let args = args.into_iter().chain(update_args).flatten();
 
                 let result = if should_toggle && is_open {
-                    self.close_window(&instance_id)
+                    self.close_window(&instance_id).map(|_| "closed")
+                } else if should_update && is_open {
+                    self.update_window_args(&instance_id, args.collect()).map(|_| "updated")
                 } else {
                     self.open_window(&WindowArguments {
                         instance_id,
@@ -243,17 +418,51 @@ impl<B: DisplayBackend> App<B> {
                         size,
                         monitor,
                         anchor,
+                        at_pointer,
                         duration,
-                        args: args.unwrap_or_default().into_iter().collect(),
+                        args: args.collect(),
                     })
+                    .map(|_| "opened")
                 };
 
-                sender.respond_with_result(result)?;
+                // when toggling or updating, let the caller know what happened to the window
+                match result {
+                    Ok(action) if should_toggle || should_update => sender.send_success(action.to_string())?,
+                    result => sender.respond_with_result(result)?,
+                }
             }
             DaemonCommand::CloseWindows { windows, sender } => {
                 let errors = windows.iter().map(|window| self.close_window(window)).filter_map(Result::err);
                 sender.respond_with_error_list(errors)?;
             }
+            DaemonCommand::ShowWindow { window_name, instance_id, sender } => {
+                let instance_id = instance_id.unwrap_or_else(|| window_name.clone());
+                let result = self.show_window(&instance_id, window_name);
+                sender.respond_with_result(result)?;
+            }
+            DaemonCommand::HideWindows { windows, sender } => {
+                let errors = windows.iter().map(|window| self.hide_window(window)).filter_map(Result::err);
+                sender.respond_with_error_list(errors)?;
+            }
+            DaemonCommand::FocusWindow { instance_id, sender } => match self.open_windows.get(&instance_id) {
+                Some(window) if !window.hidden => {
+                    log::info!("Focusing window '{}'", instance_id);
+                    B::focus_window(&window.gtk_window);
+                    sender.send_success(String::new())?
+                }
+                Some(_) => sender.send_failure(format!("Window {} is hidden", instance_id))?,
+                None => sender.send_failure(format!("Window {} is not open", instance_id))?,
+            },
+            DaemonCommand::RaiseWindow { instance_id, sender } => match self.open_windows.get(&instance_id) {
+                Some(window) if !window.hidden => {
+                    log::info!("Raising window '{}'", instance_id);
+                    B::raise_window(&window.gtk_window);
+                    self.restack_windows_above(&instance_id);
+                    sender.send_success(String::new())?
+                }
+                Some(_) => sender.send_failure(format!("Window {} is hidden", instance_id))?,
+                None => sender.send_failure(format!("Window {} is not open", instance_id))?,
+            },
             DaemonCommand::PrintState { all, sender } => {
                 let scope_graph = self.scope_graph.borrow();
                 let used_globals_names = scope_graph.currently_used_globals();
@@ -266,6 +475,23 @@ impl<B: DisplayBackend> App<B> {
                     .join("\n");
                 sender.send_success(output)?
             }
+            DaemonCommand::ExportState { file, sender } => match self.export_state(&file) {
+                Ok(count) => sender.send_success(format!("Exported {} variables to {}", count, file.display()))?,
+                Err(err) => sender.respond_with_result(Err::<(), _>(err))?,
+            },
+            DaemonCommand::ImportState { file, sender } => match self.import_state(&file) {
+                Ok(unknown) if unknown.is_empty() => sender.send_success(String::new())?,
+                Ok(unknown) => sender.send_success(format!("Skipped unknown variables: {}", unknown.join(", ")))?,
+                Err(err) => sender.respond_with_result(Err::<(), _>(err))?,
+            },
+            DaemonCommand::SetBrightness { device, percent, sender } => {
+                tokio::spawn(async move {
+                    let result = crate::brightness::set_brightness(&device, percent).await;
+                    if let Err(err) = sender.respond_with_result(result) {
+                        log::error!("Failed to respond to brightness command: {}", err);
+                    }
+                });
+            }
             DaemonCommand::GetVar { name, sender } => {
                 let scope_graph = &*self.scope_graph.borrow();
                 let vars = &scope_graph.global_scope().data;
@@ -278,6 +504,16 @@ impl<B: DisplayBackend> App<B> {
                 let output = self.eww_config.get_windows().keys().join("\n");
                 sender.send_success(output)?
             }
+            DaemonCommand::ListCompletions { kind, sender } => {
+                let output = match kind {
+                    opts::CompletionKind::Windows => self.eww_config.get_windows().keys().sorted().join("\n"),
+                    opts::CompletionKind::OpenWindows => self.open_windows.keys().sorted().join("\n"),
+                    opts::CompletionKind::Variables => {
+                        self.scope_graph.borrow().global_scope().data.keys().map(|name| &name.0).sorted().join("\n")
+                    }
+                };
+                sender.send_success(output)?
+            }
             DaemonCommand::ListActiveWindows(sender) => {
                 let output = self.open_windows.iter().map(|(id, window)| format!("{id}: {}", window.name)).join("\n");
                 sender.send_success(output)?
@@ -287,30 +523,200 @@ impl<B: DisplayBackend> App<B> {
                 sender.send_success(output)?
             }
             DaemonCommand::PrintGraph(sender) => sender.send_success(self.scope_graph.borrow().visualize())?,
+            DaemonCommand::PrintStats(sender) => sender.send_success(self.format_stats())?,
+            DaemonCommand::ListProcesses { kill: Some(pid), sender, .. } => match process_registry::kill(pid) {
+                Ok(()) => sender.send_success(format!("Terminated process {}", pid))?,
+                Err(e) => sender.send_failure(format!("{:#}", e))?,
+            },
+            DaemonCommand::ListProcesses { restart: Some(name), sender, .. } => {
+                let is_used = self.scope_graph.borrow().currently_used_globals().contains(&name);
+                match self.eww_config.get_script_var(&name) {
+                    Ok(var) if is_used => {
+                        self.script_var_handler.stop_for_variable(name.clone());
+                        self.script_var_handler.add(var.clone());
+                        sender.send_success(format!("Restarted {}", name))?
+                    }
+                    Ok(_) => sender.send_failure(format!("{} is not currently in use", name))?,
+                    Err(e) => sender.send_failure(e.to_string())?,
+                }
+            }
+            DaemonCommand::ListProcesses { sender, .. } => sender.send_success(format_processes())?,
+            DaemonCommand::PrintDaemonInfo(sender) => {
+                let info = DaemonInfo {
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                    config_dir: self.paths.get_config_dir().to_path_buf(),
+                    instance_id: self.paths.get_instance_id().map(str::to_string),
+                    config_hash: format!("{:016x}", self.config_hash),
+                };
+                sender.send_success(serde_json::to_string(&info)?)?
+            }
+            DaemonCommand::PrintWidgetTree { instance_id, sender } => match self.open_windows.get(&instance_id) {
+                Some(window) => {
+                    let tree = crate::widgets::build_widget::format_widget_tree(
+                        window.gtk_window.upcast_ref(),
+                        &self.scope_graph.borrow(),
+                    );
+                    sender.send_success(tree)?
+                }
+                None => sender.send_failure(format!("Window {} is not open", instance_id))?,
+            },
         }
         Ok(())
     }
 
     /// Fully stop eww:
     /// close all windows, stop the script_var_handler, quit the gtk appliaction and send the exit instruction to the lifecycle manager
-    fn stop_application(&mut self) {
-        self.script_var_handler.stop_all();
-        for (_, window) in self.open_windows.drain() {
+    /// Close all windows and stop all scripts, then quit the application.
+    /// Windows that reserve space on the screen, such as bars, are closed after all other windows.
+    /// Waiting for the scripts to stop happens in the background, for up to `timeout`, so the UI stays responsive.
+    /// Once done, a description of each step of the shutdown is sent to `sender`.
+    fn stop_application(&mut self, timeout: std::time::Duration, sender: Option<DaemonResponseSender>) {
+        let mut report = Vec::new();
+        let mut windows: Vec<_> = self.open_windows.drain().collect();
+        windows.sort_by_key(|(instance_id, window)| (window.reserves_space, instance_id.clone()));
+        for (instance_id, window) in windows {
             window.close();
+            report.push(format!("Closed window {}", instance_id));
+        }
+
+        let scripts_stopped = self.script_var_handler.stop_all();
+        tokio::spawn(async move {
+            let start = std::time::Instant::now();
+            if tokio::time::timeout(timeout, scripts_stopped).await.is_ok() {
+                report.push(format!("Stopped all scripts in {}ms", start.elapsed().as_millis()));
+            } else {
+                log::warn!("Scripts did not stop within {}s", timeout.as_secs_f64());
+                report.push(format!("Scripts did not stop within {}s", timeout.as_secs_f64()));
+            }
+
+            let watcher_released =
+                tokio::time::timeout(std::time::Duration::from_millis(500), widgets::systray::release_watcher());
+            if let Ok(true) = watcher_released.await {
+                report.push("Released the system tray watcher".to_string());
+            }
+
+            if let Some(sender) = sender {
+                crate::print_result_err!("sending shutdown report", sender.send_success(report.join("\n")));
+            }
+            glib::MainContext::default().invoke(gtk::main_quit);
+            let _ = crate::application_lifecycle::send_exit();
+        });
+    }
+
+    /// Write the values of all global variables, except for the ones built into eww, to the given file as a JSON object.
+    /// Returns the number of exported variables.
+    fn export_state(&self, file: &std::path::Path) -> Result<usize> {
+        let scope_graph = self.scope_graph.borrow();
+        let state: serde_json::Map<String, serde_json::Value> = scope_graph
+            .global_scope()
+            .data
+            .iter()
+            .filter(|(name, _)| !is_builtin_variable(name))
+            .map(|(name, value)| (name.to_string(), serde_json::Value::String(value.as_string().unwrap_or_default())))
+            .collect();
+        let json = serde_json::to_string_pretty(&state)?;
+        std::fs::write(file, json).with_context(|| format!("Failed to write state to {}", file.display()))?;
+        Ok(state.len())
+    }
+
+    /// Set the global variables to the values stored in the given JSON file.
+    /// Returns the names of the variables in the file that aren't defined in the configuration.
+    fn import_state(&mut self, file: &std::path::Path) -> Result<Vec<String>> {
+        let content = std::fs::read_to_string(file).with_context(|| format!("Failed to read state from {}", file.display()))?;
+        let state: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(&content).with_context(|| format!("{} does not contain a JSON object", file.display()))?;
+        let mut unknown = Vec::new();
+        for (name, value) in state {
+            let name = VarName::from(name);
+            let is_known = self.scope_graph.borrow().global_scope().data.contains_key(&name);
+            if !is_known || is_builtin_variable(&name) {
+                unknown.push(name.to_string());
+                continue;
+            }
+            let value = match value {
+                serde_json::Value::String(value) => DynVal::from(value),
+                other => DynVal::from(other.to_string()),
+            };
+            self.update_global_variable(name, value);
+        }
+        Ok(unknown)
+    }
+
+    /// Make sure the value matches the type the variable was declared with, if any.
+    fn check_var_type(&self, name: &VarName, value: &DynVal) -> Result<()> {
+        match self.eww_config.get_var_type(name) {
+            Some(var_type) if !var_type.matches(value) => {
+                Err(anyhow!("Rejected update of `{}`: variable is declared as {}, but got `{}`", name, var_type, value))
+            }
+            _ => Ok(()),
         }
-        gtk::main_quit();
-        let _ = crate::application_lifecycle::send_exit();
     }
 
     fn update_global_variable(&mut self, name: VarName, value: DynVal) {
+        if let Err(err) = self.check_var_type(&name, &value) {
+            error_handling_ctx::print_error(err);
+            return;
+        }
+        crash_report::record_var_update(&name, &value);
+        let changed = {
+            let scope_graph = self.scope_graph.borrow();
+            scope_graph.lookup_variable_in_scope(scope_graph.root_index, &name) != Some(&value)
+        };
+        self.var_update_stats.entry(name.clone()).or_default().record_update(changed);
+        if let Some((span, onchange)) = self.eww_config.get_onchange_command(&name) {
+            if changed {
+                let context = widgets::CommandContext::without_widget(*span, self.paths.get_config_dir());
+                widgets::run_command(&context, Some(std::time::Duration::from_millis(200)), onchange, &[value.clone()]);
+            }
+        }
         let result = self.scope_graph.borrow_mut().update_global_value(&name, value);
         if let Err(err) = result {
             error_handling_ctx::print_error(err);
         }
 
+        if changed && self.stylesheet_vars.contains(&name) {
+            for err in self.reload_stylesheets() {
+                error_handling_ctx::print_error(err);
+            }
+        }
+
+        if name.0 == crate::idle::IDLE_VAR_NAME {
+            self.apply_idle_state();
+        }
+
         self.apply_run_while_expressions_mentioning(&name);
     }
 
+    /// Hide the windows with a `:hide-when-idle` duration that the user has been idle for, and show them again once they're back.
+    fn apply_idle_state(&mut self) {
+        let idle_seconds = {
+            let scope_graph = self.scope_graph.borrow();
+            scope_graph
+                .lookup_variable_in_scope(scope_graph.root_index, &VarName::from(crate::idle::IDLE_VAR_NAME))
+                .and_then(|value| value.as_f64().ok())
+                .unwrap_or(0.)
+        };
+        let mut shown_again = Vec::new();
+        for (instance_id, window) in self.open_windows.iter_mut() {
+            let Some(hide_when_idle) = window.hide_when_idle else { continue };
+            let idle = idle_seconds >= hide_when_idle.as_secs_f64();
+            if idle && !window.hidden {
+                log::info!("Hiding window '{}' while idle", instance_id);
+                window.gtk_window.hide();
+                window.hidden = true;
+                window.hidden_while_idle = true;
+            } else if !idle && window.hidden_while_idle {
+                window.gtk_window.show();
+                window.hidden = false;
+                window.hidden_while_idle = false;
+                shown_again.push(instance_id.clone());
+            }
+        }
+        for instance_id in shown_again {
+            self.restack_windows_above(&instance_id);
+        }
+    }
+
     /// Variables may be referenced in defpoll :run-while expressions.
     /// Thus, when a variable changes, the run-while conditions of all variables
     /// that mention the changed variable need to be reevaluated and reapplied.
@@ -352,7 +758,9 @@ impl<B: DisplayBackend> App<B> {
         self.scope_graph.borrow_mut().remove_scope(scope_index);
 
         let unused_variables = self.scope_graph.borrow().currently_unused_globals();
-        for unused_var in unused_variables {
+        // windows hiding when idle need the idle time, even if none of their widgets use it
+        let idle_var_needed = self.open_windows.values().any(|window| window.hide_when_idle.is_some());
+        for unused_var in unused_variables.iter().filter(|var| !(idle_var_needed && var.0 == crate::idle::IDLE_VAR_NAME)) {
             log::debug!("stopping script-var {}", &unused_var);
             self.script_var_handler.stop_for_variable(unused_var.clone());
         }
@@ -362,7 +770,94 @@ impl<B: DisplayBackend> App<B> {
         Ok(())
     }
 
+    /// Update arguments of an open window in place, re-evaluating everything in the window that depends on them.
+    /// Properties of the window itself, such as its geometry, keep the values they were evaluated with when opening it.
+    fn update_window_args(&mut self, instance_id: &str, args: Vec<(VarName, DynVal)>) -> Result<()> {
+        let scope_index = self
+            .open_windows
+            .get(instance_id)
+            .with_context(|| format!("Tried to update window with id '{instance_id}', but no such window was open"))?
+            .scope_index;
+        let window_args = self.instance_id_to_args.get_mut(instance_id).context("No arguments stored for open window")?;
+        let window_def = self.eww_config.get_window(&window_args.window_name)?;
+        for (name, _) in &args {
+            if !window_def.expected_args.iter().any(|arg| arg.name.0 == name.0) {
+                bail!("Window '{}' does not take an argument named '{}'", window_args.window_name, name);
+            }
+        }
+
+        log::info!("Updating arguments of window '{}'", instance_id);
+        window_args.args.extend(args.iter().cloned());
+        let mut scope_graph = self.scope_graph.borrow_mut();
+        for (name, value) in args {
+            scope_graph.set_value_in_scope(scope_index, &name, value)?;
+        }
+        Ok(())
+    }
+
+    /// Show a hidden window again, or open it if it isn't open yet.
+    fn show_window(&mut self, instance_id: &str, window_name: String) -> Result<()> {
+        match self.open_windows.get_mut(instance_id) {
+            Some(eww_window) => {
+                log::info!("Showing window '{}'", instance_id);
+                eww_window.show();
+                self.restack_windows_above(instance_id);
+                Ok(())
+            }
+            None => self.open_window(&WindowArguments::new_from_args(instance_id.to_string(), window_name, HashMap::new())?),
+        }
+    }
+
+    /// Hide an open window without destroying it, such that it can be shown again without having to be rebuilt.
+    fn hide_window(&mut self, instance_id: &str) -> Result<()> {
+        let eww_window = self
+            .open_windows
+            .get_mut(instance_id)
+            .with_context(|| format!("Tried to hide window with id '{instance_id}', but no such window was open"))?;
+        log::info!("Hiding window '{}'", instance_id);
+        eww_window.gtk_window.hide();
+        eww_window.hidden = true;
+        eww_window.hidden_while_idle = false;
+        Ok(())
+    }
+
+    /// Raise all visible windows with a higher `z-index` than the given window back above it, in order of their z-index.
+    /// Newly opened or raised windows end up on top of their layer, so this restores the order declared via `:z-index`.
+    fn restack_windows_above(&self, instance_id: &str) {
+        let Some(z_index) = self.open_windows.get(instance_id).map(|window| window.z_index) else { return };
+        let mut windows_above: Vec<_> =
+            self.open_windows.values().filter(|window| !window.hidden && window.z_index > z_index).collect();
+        windows_above.sort_by_key(|window| window.z_index);
+        for window in windows_above {
+            B::raise_window(&window.gtk_window);
+        }
+    }
+
+    /// Build all windows that are declared with `:lazy false` and aren't open yet, keeping them hidden until they're shown.
+    /// Windows that fail to build are reported, without keeping the other windows from being prepared.
+    pub fn prepare_eager_windows(&mut self) {
+        let eager_windows: Vec<String> = self
+            .eww_config
+            .get_windows()
+            .values()
+            .filter(|window_def| !window_def.lazy && !self.open_windows.contains_key(&window_def.name))
+            .map(|window_def| window_def.name.clone())
+            .collect();
+        for window_name in eager_windows {
+            let result = WindowArguments::new_from_args(window_name.clone(), window_name.clone(), HashMap::new())
+                .and_then(|window_args| self.build_window(&window_args, true));
+            if let Err(err) = result {
+                error_handling_ctx::print_error(err.context(format!("Failed to prepare window {}", window_name)));
+            }
+        }
+    }
+
     fn open_window(&mut self, window_args: &WindowArguments) -> Result<()> {
+        self.build_window(window_args, false)
+    }
+
+    /// Build a window, keeping it hidden if `hidden` is set. Its `:onopen` command only runs once it is shown.
+    fn build_window(&mut self, window_args: &WindowArguments, hidden: bool) -> Result<()> {
         let instance_id = &window_args.instance_id;
         self.failed_windows.remove(instance_id);
         log::info!("Opening window {} as '{}'", window_args.window_name, instance_id);
@@ -380,7 +875,7 @@ impl<B: DisplayBackend> App<B> {
             let window_def = self.eww_config.get_window(window_name)?.clone();
             assert_eq!(window_def.name, window_name, "window definition name did not equal the called window");
 
-            let initiator = WindowInitiator::new(&window_def, window_args)?;
+            let mut initiator = WindowInitiator::new(&window_def, window_args)?;
 
             let root_index = self.scope_graph.borrow().root_index;
 
@@ -393,19 +888,59 @@ impl<B: DisplayBackend> App<B> {
                 scoped_vars_literal,
             )?;
 
-            let root_widget = crate::widgets::build_widget::build_gtk_widget(
-                &mut self.scope_graph.borrow_mut(),
-                Rc::new(self.eww_config.get_widget_definitions().clone()),
-                window_scope,
-                window_def.widget,
-                None,
-            )?;
+            let strict = window_def.strict.unwrap_or(self.eww_config.get_config_options().strict);
+            let root_widget = {
+                let mut scope_graph = self.scope_graph.borrow_mut();
+                scope_graph.strict = strict;
+                let root_widget = crate::widgets::build_widget::build_gtk_widget(
+                    &mut scope_graph,
+                    Rc::new(self.eww_config.get_widget_definitions().clone()),
+                    window_scope,
+                    window_def.widget,
+                    None,
+                );
+                scope_graph.strict = false;
+                if root_widget.is_err() {
+                    scope_graph.remove_scope(window_scope);
+                }
+                root_widget.with_context(|| {
+                    if strict {
+                        format!("Failed to build window {} in strict mode", instance_id)
+                    } else {
+                        format!("Failed to build window {}", instance_id)
+                    }
+                })?
+            };
 
             root_widget.style_context().add_class(window_name);
 
-            let monitor = get_gdk_monitor(initiator.monitor.clone())?;
+            let display = get_display_for_window::<B>(initiator.force_x11)?;
+            let monitor = match initiator.geometry.filter(|geometry| geometry.at_pointer) {
+                Some(geometry) => {
+                    let (x, y) = get_pointer_position::<B>(&display, initiator.force_x11, &self.open_windows)
+                        .context("Failed to get the position of the pointer. On wayland, it must be above an eww window.")?;
+                    let monitor = display.monitor_at_point(x, y).context("Failed to get the monitor the pointer is on")?;
+                    let natural_size = root_widget.preferred_size().1;
+                    initiator.geometry = Some(get_geometry_at_pointer(
+                        geometry,
+                        (x, y),
+                        (natural_size.width, natural_size.height),
+                        monitor.geometry(),
+                    ));
+                    // The pointer position is relative to the whole monitor, while layer shell margins are relative to the
+                    // space not reserved by other surfaces, such as bars. Ignoring that space keeps the window at the pointer.
+                    if B::IS_WAYLAND && !initiator.force_x11 {
+                        initiator.backend_options.wayland.exclusive = WlExclusiveZone::Ignore;
+                    }
+                    monitor
+                }
+                None => get_gdk_monitor(&display, initiator.monitor.clone())?,
+            };
             let mut eww_window = initialize_window::<B>(&initiator, monitor, root_widget, window_scope)?;
             eww_window.gtk_window.style_context().add_class(window_name);
+            if let Some(css_provider) = self.window_css_providers.get(window_name) {
+                add_css_provider_recursively(eww_window.gtk_window.upcast_ref(), css_provider);
+            }
 
             // initialize script var handlers for variables. As starting a scriptvar with the script_var_handler is idempodent,
             // we can just start script vars that are already running without causing issues
@@ -415,6 +950,14 @@ impl<B: DisplayBackend> App<B> {
                     self.script_var_handler.add(script_var.clone());
                 }
             }
+            if eww_window.hide_when_idle.is_some() {
+                match self.eww_config.get_script_var(&VarName::from(crate::idle::IDLE_VAR_NAME)) {
+                    Ok(script_var) => self.script_var_handler.add(script_var.clone()),
+                    Err(_) => {
+                        log::warn!("Window {} uses :hide-when-idle, but {} is disabled", instance_id, crate::idle::IDLE_VAR_NAME)
+                    }
+                }
+            }
 
             eww_window.destroy_event_handler_id = Some(eww_window.gtk_window.connect_destroy({
                 let app_evt_sender = self.app_evt_send.clone();
@@ -431,6 +974,28 @@ impl<B: DisplayBackend> App<B> {
                 }
             }));
 
+            // window lifecycle commands aren't killed, such that they can start and stop processes that belong to the window
+            let command_context = widgets::CommandContext {
+                window: Some(instance_id.to_string()),
+                ..widgets::CommandContext::without_widget(window_def.span, self.paths.get_config_dir())
+            };
+            eww_window.onopen = window_def.onopen.clone().map(|onopen| (command_context.clone(), onopen));
+            eww_window.onclose = window_def.onclose.clone().map(|onclose| (command_context, onclose));
+            if hidden {
+                eww_window.gtk_window.hide();
+                eww_window.hidden = true;
+            } else {
+                eww_window.mark_shown();
+            }
+
+            if initiator.close_on_unfocus {
+                setup_close_on_unfocus::<B>(&eww_window.gtk_window, self.app_evt_send.clone(), instance_id.to_string());
+            }
+            if initiator.track_pointer {
+                let event_sender = self.scope_graph.borrow().event_sender.clone();
+                setup_pointer_tracking(&eww_window.gtk_window, event_sender, window_scope);
+            }
+
             let duration = window_args.duration;
             if let Some(duration) = duration {
                 let app_evt_sender = self.app_evt_send.clone();
@@ -460,6 +1025,7 @@ impl<B: DisplayBackend> App<B> {
             }
 
             self.open_windows.insert(instance_id.to_string(), eww_window);
+            self.restack_windows_above(instance_id);
             Ok(())
         })();
 
@@ -471,50 +1037,484 @@ impl<B: DisplayBackend> App<B> {
         }
     }
 
+    /// Re-evaluate which monitor each window should be on after monitors were added or removed.
+    /// Windows are reopened if a monitor they prefer (i.e. one earlier in a `:monitor` list) became available,
+    /// or if the monitor they were on disappeared. Windows that previously failed to open are retried.
+    fn reevaluate_window_monitors(&mut self) -> Result<()> {
+        let mut instances_to_reopen: Vec<String> = self.failed_windows.iter().cloned().collect();
+        for (instance_id, window) in &self.open_windows {
+            let Some(window_args) = self.instance_id_to_args.get(instance_id) else { continue };
+            let Ok(window_def) = self.eww_config.get_window(&window_args.window_name) else { continue };
+            let initiator = match WindowInitiator::new(window_def, window_args) {
+                Ok(initiator) => initiator,
+                Err(err) => {
+                    error_handling_ctx::print_error(
+                        err.context(format!("Failed to re-evaluate the monitor of window {}", instance_id)),
+                    );
+                    continue;
+                }
+            };
+            // windows opened at the pointer stay on the monitor they were opened on
+            if initiator.geometry.map_or(false, |geometry| geometry.at_pointer) {
+                continue;
+            }
+            let monitor =
+                get_display_for_window::<B>(initiator.force_x11).and_then(|display| get_gdk_monitor(&display, initiator.monitor));
+            match monitor {
+                Ok(monitor) if monitor == window.monitor => {}
+                Ok(_) => instances_to_reopen.push(instance_id.clone()),
+                Err(err) => log::debug!("Not moving window {}: {}", instance_id, err),
+            }
+        }
+
+        for instance_id in instances_to_reopen {
+            log::info!("Monitors changed, reopening window {}", instance_id);
+            let was_hidden = self.open_windows.get(&instance_id).map_or(false, |window| window.hidden);
+            if let Some(window_args) = self.instance_id_to_args.get(&instance_id).cloned() {
+                if let Err(err) = self.build_window(&window_args, was_hidden) {
+                    error_handling_ctx::print_error(err);
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Load the given configuration, reloading all script-vars and attempting to reopen all windows that where opened.
     pub fn load_config(&mut self, config: config::EwwConfig) -> Result<()> {
         log::info!("Reloading windows");
 
-        self.script_var_handler.stop_all();
+        // joining the thread of the old handler waits for all scripts to stop
+        drop(self.script_var_handler.stop_all());
         let old_handler = std::mem::replace(&mut self.script_var_handler, script_var_handler::init(self.app_evt_send.clone()));
         old_handler.join_thread();
 
         log::trace!("loading config: {:#?}", config);
 
         self.eww_config = config;
+        self.apply_gtk_settings();
+        widgets::set_command_shell(self.eww_config.get_config_options().shell.clone());
+        self.watched_files_send.send_modify(|files| files.config = WatchedFiles::loaded_config_files());
+        self.config_hash = hash_config_files(&self.watched_files_send.borrow().config);
         self.scope_graph.borrow_mut().clear(self.eww_config.generate_initial_state()?);
 
         let open_window_ids: Vec<String> =
             self.open_windows.keys().cloned().chain(self.failed_windows.iter().cloned()).dedup().collect();
+        let hidden_window_ids: Vec<String> =
+            self.open_windows.iter().filter(|(_, window)| window.hidden).map(|(id, _)| id.clone()).collect();
         for instance_id in &open_window_ids {
             let window_arguments = self.instance_id_to_args.get(instance_id).with_context(|| {
                 format!("Cannot reopen window, initial parameters were not saved correctly for {instance_id}")
             })?;
-            self.open_window(&window_arguments.clone())?;
+            self.build_window(&window_arguments.clone(), hidden_window_ids.contains(instance_id))?;
         }
+        self.prepare_eager_windows();
         Ok(())
     }
 
-    /// Load a given CSS string into the gtk css provider, returning a nicely formatted [`DiagError`] when GTK errors out
-    pub fn load_css(&mut self, file_id: usize, css: &str) -> Result<()> {
-        if let Err(err) = self.css_provider.load_from_data(css.as_bytes()) {
-            static PATTERN: Lazy<regex::Regex> = Lazy::new(|| regex::Regex::new(r"[^:]*:(\d+):(\d+)(.*)$").unwrap());
-            let nice_error_option: Option<_> = (|| {
-                let captures = PATTERN.captures(err.message())?;
-                let line = captures.get(1).unwrap().as_str().parse::<usize>().ok()?;
-                let msg = captures.get(3).unwrap().as_str();
-                let db = error_handling_ctx::FILE_DATABASE.read().ok()?;
-                let line_range = db.line_range(file_id, line - 1).ok()?;
-                let span = Span(line_range.start, line_range.end - 1, file_id);
-                Some(DiagError(gen_diagnostic!(msg, span)))
-            })();
-            match nice_error_option {
-                Some(error) => Err(anyhow!(error)),
-                None => Err(anyhow!("CSS error: {}", err.message())),
+    /// Summarize the state of the daemon and how often variables get updated, for `eww stats`.
+    fn format_stats(&self) -> String {
+        let scope_graph = self.scope_graph.borrow();
+        let mut output = format!(
+            "uptime: {}\nopen windows: {}\nscopes: {}\nlisteners: {}\n",
+            format_duration_short(self.started_at.elapsed()),
+            self.open_windows.len(),
+            scope_graph.scope_count(),
+            scope_graph.listener_count(),
+        );
+        if self.var_update_stats.is_empty() {
+            return output;
+        }
+
+        let now = std::time::Instant::now();
+        let rows = self
+            .var_update_stats
+            .iter()
+            .map(|(name, stats)| {
+                let kind = match self.eww_config.get_script_var(name) {
+                    Ok(ScriptVarDefinition::Poll(_)) => "poll",
+                    Ok(ScriptVarDefinition::Listen(_)) => "listen",
+                    Ok(ScriptVarDefinition::DBus(_)) => "dbus",
+                    Ok(ScriptVarDefinition::Watch(_)) => "watch",
+                    Err(_) => "var",
+                };
+                (name, kind, stats, stats.updates_per_minute(now))
+            })
+            .sorted_by(|a, b| b.3.cmp(&a.3).then_with(|| a.0 .0.cmp(&b.0 .0)))
+            .collect_vec();
+        let name_width = rows.iter().map(|(name, ..)| name.0.len()).max().unwrap_or(0).max("variable".len());
+        output.push_str(&format!(
+            "\n{:<name_width$}  {:<6}  {:>8}  {:>8}  {:>10}  last update\n",
+            "variable", "kind", "updates", "changes", "per minute"
+        ));
+        for (name, kind, stats, per_minute) in rows {
+            output.push_str(&format!(
+                "{:<name_width$}  {:<6}  {:>8}  {:>8}  {:>10}  {} ago\n",
+                name.0,
+                kind,
+                stats.updates,
+                stats.changes,
+                per_minute,
+                format_duration_short(now - stats.last_update),
+            ));
+        }
+        output.trim_end().to_string()
+    }
+
+    /// Show an error in the error overlay, if it is enabled.
+    pub fn show_error_overlay(&mut self, message: String) {
+        if !self.eww_config.get_config_options().error_overlay {
+            return;
+        }
+        if self.error_overlay.is_none() {
+            match ErrorOverlay::new::<B>(self.scope_graph.borrow().root_index) {
+                Ok(overlay) => self.error_overlay = Some(overlay),
+                // not using print_error here, as that would try to show the error in the overlay again
+                Err(err) => {
+                    log::error!("Failed to open error overlay: {:?}", err);
+                    return;
+                }
+            }
+        }
+        if let Some(overlay) = self.error_overlay.as_mut() {
+            overlay.push(message);
+        }
+    }
+
+    /// Show the errors of a reload in the error overlay, or close the overlay if the reload succeeded.
+    fn update_error_overlay(&mut self, errors: &[anyhow::Error]) {
+        if errors.is_empty() || !self.eww_config.get_config_options().error_overlay {
+            if let Some(overlay) = self.error_overlay.take() {
+                overlay.window.close();
             }
+        }
+        for error in errors {
+            self.show_error_overlay(error_handling_ctx::format_error_plain(error));
+        }
+    }
+
+    /// Apply the GTK settings overrides from `defconfig`. Settings that are not overridden are reset to the system defaults.
+    pub fn apply_gtk_settings(&self) {
+        let Some(settings) = gtk::Settings::default() else { return };
+        let options = self.eww_config.get_config_options();
+        match &options.icon_theme {
+            Some(icon_theme) => settings.set_gtk_icon_theme_name(Some(icon_theme)),
+            None => settings.reset_property("gtk-icon-theme-name"),
+        }
+        match &options.font_name {
+            Some(font_name) => settings.set_gtk_font_name(Some(font_name)),
+            None => settings.reset_property("gtk-font-name"),
+        }
+        match options.prefer_dark_theme {
+            Some(prefer_dark_theme) => settings.set_gtk_application_prefer_dark_theme(prefer_dark_theme),
+            None => settings.reset_property("gtk-application-prefer-dark-theme"),
+        }
+    }
+
+    /// Compile and load the stylesheet of the configuration and the `:stylesheet`s of the windows,
+    /// using the current values of the global variables.
+    /// Every stylesheet is loaded into its own css provider, so an error in one of them doesn't affect the others.
+    pub fn reload_stylesheets(&mut self) -> Vec<anyhow::Error> {
+        let global_vars = self.scope_graph.borrow().global_scope().data.clone();
+        let config_dir = self.paths.get_config_dir();
+        let mut files = HashSet::new();
+        let mut stylesheets = Vec::new();
+
+        // A configuration without any stylesheet is valid.
+        if ["eww.scss", "eww.css"].iter().any(|file| config_dir.join(file).exists()) {
+            stylesheets.push((self.css_provider.clone(), config::scss::parse_scss_from_config(config_dir, &global_vars)));
         } else {
-            Ok(())
+            let _ = self.css_provider.load_from_data(b"");
+        }
+
+        let window_stylesheets = self.eww_config.get_window_stylesheets();
+        self.window_css_providers.retain(|window_name, css_provider| {
+            let has_stylesheet = window_stylesheets.iter().any(|(name, _)| name == window_name);
+            if !has_stylesheet {
+                let _ = css_provider.load_from_data(b"");
+            }
+            has_stylesheet
+        });
+        for (window_name, stylesheet) in window_stylesheets {
+            let css_provider = self.window_css_providers.entry(window_name.to_string()).or_insert_with(|| {
+                let css_provider = gtk::CssProvider::new();
+                for window in self.open_windows.values().filter(|window| window.name == window_name) {
+                    add_css_provider_recursively(window.gtk_window.upcast_ref(), &css_provider);
+                }
+                css_provider
+            });
+            // watch the file even if it fails to compile, so that fixing it reloads the styles
+            let path = config_dir.join(stylesheet);
+            files.insert(std::fs::canonicalize(&path).unwrap_or(path));
+            let result = config::scss::parse_window_stylesheet(config_dir, stylesheet, &global_vars)
+                .with_context(|| format!("Failed to load the stylesheet of window {}", window_name));
+            stylesheets.push((css_provider.clone(), result));
+        }
+
+        let mut errors = Vec::new();
+        let mut referenced_vars = HashSet::new();
+        for (css_provider, stylesheet) in stylesheets {
+            let result = stylesheet.and_then(|stylesheet| {
+                referenced_vars.extend(stylesheet.referenced_vars);
+                files.extend(stylesheet.files);
+                load_css(&css_provider, stylesheet.file_id, &stylesheet.css)
+            });
+            if let Err(err) = result {
+                errors.push(err);
+            }
         }
+        self.stylesheet_vars = referenced_vars;
+        self.watched_files_send.send_modify(|watched_files| watched_files.stylesheet = files);
+        errors
+    }
+}
+
+/// Load a given CSS string into a gtk css provider, returning a nicely formatted [`DiagError`] when GTK errors out
+fn load_css(css_provider: &gtk::CssProvider, file_id: usize, css: &str) -> Result<()> {
+    if let Err(err) = css_provider.load_from_data(css.as_bytes()) {
+        static PATTERN: Lazy<regex::Regex> = Lazy::new(|| regex::Regex::new(r"[^:]*:(\d+):(\d+)(.*)$").unwrap());
+        let nice_error_option: Option<_> = (|| {
+            let captures = PATTERN.captures(err.message())?;
+            let line = captures.get(1).unwrap().as_str().parse::<usize>().ok()?;
+            let msg = captures.get(3).unwrap().as_str();
+            let db = error_handling_ctx::FILE_DATABASE.read().ok()?;
+            let line_range = db.line_range(file_id, line - 1).ok()?;
+            let span = Span(line_range.start, line_range.end - 1, file_id);
+            Some(DiagError(gen_diagnostic!(msg, span)))
+        })();
+        match nice_error_option {
+            Some(error) => Err(anyhow!(error)),
+            None => Err(anyhow!("CSS error: {}", err.message())),
+        }
+    } else {
+        Ok(())
+    }
+}
+
+/// Add a css provider to the style context of a widget and all of its descendants, including ones added later on.
+/// In GTK3, a provider added to a style context only applies to that single widget, not to its children.
+fn add_css_provider_recursively(widget: &gtk::Widget, css_provider: &gtk::CssProvider) {
+    widget.style_context().add_provider(css_provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+    if let Some(container) = widget.dynamic_cast_ref::<gtk::Container>() {
+        container.forall(|child| add_css_provider_recursively(child, css_provider));
+        let css_provider = css_provider.clone();
+        container.connect_add(move |_, child| add_css_provider_recursively(child, &css_provider));
+    }
+}
+
+/// Update counts of a global variable, shown by `eww stats`.
+#[derive(Debug)]
+pub struct VarUpdateStats {
+    pub updates: u64,
+    /// Amount of updates that actually changed the value
+    pub changes: u64,
+    pub last_update: std::time::Instant,
+    /// Times of the updates within the last minute, oldest first
+    recent_updates: std::collections::VecDeque<std::time::Instant>,
+}
+
+impl Default for VarUpdateStats {
+    fn default() -> Self {
+        Self { updates: 0, changes: 0, last_update: std::time::Instant::now(), recent_updates: Default::default() }
+    }
+}
+
+impl VarUpdateStats {
+    const RATE_WINDOW: std::time::Duration = std::time::Duration::from_secs(60);
+
+    fn record_update(&mut self, changed: bool) {
+        let now = std::time::Instant::now();
+        self.updates += 1;
+        self.changes += changed as u64;
+        self.last_update = now;
+        self.recent_updates.push_back(now);
+        self.forget_old_updates(now);
+    }
+
+    fn forget_old_updates(&mut self, now: std::time::Instant) {
+        while self.recent_updates.front().is_some_and(|time| now.duration_since(*time) > Self::RATE_WINDOW) {
+            self.recent_updates.pop_front();
+        }
+    }
+
+    fn updates_per_minute(&self, now: std::time::Instant) -> usize {
+        self.recent_updates.iter().filter(|time| now.duration_since(**time) <= Self::RATE_WINDOW).count()
+    }
+}
+
+/// Information about a running daemon, as printed by `eww ping --info`.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct DaemonInfo {
+    pub version: String,
+    pub config_dir: std::path::PathBuf,
+    /// Name of the instance, if the daemon was started with `--instance`
+    pub instance_id: Option<String>,
+    /// Hash of the loaded configuration, which changes whenever the daemon loads a changed configuration
+    pub config_hash: String,
+}
+
+/// Hash the paths and contents of the given files, in a way that doesn't depend on their order.
+pub fn hash_config_files(files: &HashSet<std::path::PathBuf>) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for path in files.iter().sorted() {
+        path.hash(&mut hasher);
+        std::fs::read(path).unwrap_or_default().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Whether the variable is provided by eww itself, rather than being defined in the configuration.
+fn is_builtin_variable(name: &VarName) -> bool {
+    config::inbuilt::INBUILT_VAR_NAMES.iter().chain(config::inbuilt::MAGIC_CONSTANT_NAMES).any(|builtin| name.0 == *builtin)
+}
+
+/// List the processes started by eww, for `eww ps`.
+fn format_processes() -> String {
+    let processes = process_registry::list();
+    let now = std::time::Instant::now();
+    let owners = processes.iter().map(|info| info.owner.to_string()).collect_vec();
+    let owner_width = owners.iter().map(String::len).max().unwrap_or(0).max("owner".len());
+    let mut output = format!("{:>8}  {:<owner_width$}  {:>10}  {:>8}  command\n", "pid", "owner", "uptime", "restarts");
+    for (info, owner) in processes.iter().zip(owners) {
+        output.push_str(&format!(
+            "{:>8}  {:<owner_width$}  {:>10}  {:>8}  {}\n",
+            info.pid,
+            owner,
+            format_duration_short(now - info.started_at),
+            info.restarts,
+            info.command,
+        ));
+    }
+    output.trim_end().to_string()
+}
+
+/// Format a duration with the largest fitting units, i.e. `1h 2m 3s`.
+fn format_duration_short(duration: std::time::Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, s) => format!("{m}m {s}s"),
+        (h, m, s) => format!("{h}h {m}m {s}s"),
+    }
+}
+
+/// Maximum amount of errors shown in the error overlay at once.
+const ERROR_OVERLAY_MAX_MESSAGES: usize = 3;
+
+const ERROR_OVERLAY_CSS: &str = r#"
+.eww-error-overlay {
+    background-color: #2d1b1b;
+    color: #f2dede;
+    border: 2px solid #d9534f;
+}
+.eww-error-overlay .title {
+    font-weight: bold;
+    color: #d9534f;
+}
+"#;
+
+/// A small window in the top right corner of the screen, showing the most recent errors.
+/// It is hidden when clicked, and cleared once it is shown again.
+pub struct ErrorOverlay {
+    window: EwwWindow,
+    label: gtk::Label,
+    messages: std::collections::VecDeque<String>,
+}
+
+impl ErrorOverlay {
+    fn new<B: DisplayBackend>(scope_index: ScopeIndex) -> Result<Self> {
+        use yuck::config::backend_window_options::{
+            BackendWindowOptionsDef, WlBackendWindowOptionsDef, X11BackendWindowOptionsDef,
+        };
+        let backend_options = BackendWindowOptionsDef {
+            wayland: WlBackendWindowOptionsDef {
+                exclusive: None,
+                focusable: None,
+                namespace: Some(SimplExpr::synth_literal("eww-error-overlay")),
+            },
+            x11: X11BackendWindowOptionsDef {
+                sticky: None,
+                struts: None,
+                window_type: None,
+                wm_ignore: None,
+                skip_taskbar: None,
+                skip_pager: None,
+                demands_attention: None,
+                above: None,
+                below: None,
+            },
+        };
+        let initiator = WindowInitiator {
+            backend_options: backend_options.eval(&HashMap::new())?,
+            close_on_unfocus: false,
+            close_transition: WindowTransition::None,
+            force_x11: false,
+            geometry: Some(WindowGeometry {
+                anchor_point: AnchorPoint { x: AnchorAlignment::END, y: AnchorAlignment::START },
+                offset: Coords::default(),
+                size: Coords::default(),
+                at_pointer: false,
+            }),
+            hide_when_idle: None,
+            local_variables: HashMap::new(),
+            monitor: None,
+            name: "eww-error-overlay".to_string(),
+            resizable: false,
+            open_transition: WindowTransition::None,
+            stacking: WindowStacking::Overlay,
+            transition_duration: std::time::Duration::ZERO,
+            track_pointer: false,
+            z_index: i32::MAX,
+        };
+
+        let title = gtk::Label::new(Some("eww: error (click to dismiss)"));
+        title.set_xalign(0.0);
+        title.style_context().add_class("title");
+        let label = gtk::Label::new(None);
+        label.set_xalign(0.0);
+        label.set_line_wrap(true);
+        label.set_max_width_chars(100);
+        let attributes = gtk::pango::AttrList::new();
+        attributes.insert(gtk::pango::AttrString::new_family("monospace"));
+        label.set_attributes(Some(&attributes));
+
+        let content = gtk::Box::new(gtk::Orientation::Vertical, 4);
+        content.set_border_width(8);
+        content.add(&title);
+        content.add(&label);
+        let event_box = gtk::EventBox::new();
+        event_box.add(&content);
+
+        let display = get_display_for_window::<B>(false)?;
+        let monitor = get_gdk_monitor(&display, None)?;
+        let window = initialize_window::<B>(&initiator, monitor, event_box.clone().upcast(), scope_index)?;
+
+        let css_provider = gtk::CssProvider::new();
+        css_provider.load_from_data(ERROR_OVERLAY_CSS.as_bytes())?;
+        for style_context in [window.gtk_window.style_context(), title.style_context()] {
+            style_context.add_provider(&css_provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+        }
+        window.gtk_window.style_context().add_class("eww-error-overlay");
+        event_box.connect_button_press_event(
+            glib::clone!(@weak window.gtk_window as gtk_window => @default-return glib::Propagation::Proceed, move |_, _| {
+                gtk_window.hide();
+                glib::Propagation::Stop
+            }),
+        );
+
+        Ok(Self { window, label, messages: std::collections::VecDeque::new() })
+    }
+
+    fn push(&mut self, message: String) {
+        if !self.window.gtk_window.is_visible() {
+            self.messages.clear();
+        }
+        if self.messages.len() >= ERROR_OVERLAY_MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(message.trim_end().to_string());
+        self.label.set_text(&self.messages.iter().join("\n\n"));
+        self.window.gtk_window.show();
     }
 }
 
@@ -532,8 +1532,12 @@ fn initialize_window<B: DisplayBackend>(
         }
         _ => (None, 0, 0),
     };
-    let window = B::initialize_window(window_init, monitor_geometry, x, y)
-        .with_context(|| format!("monitor {} is unavailable", window_init.monitor.clone().unwrap()))?;
+    let window = if window_init.force_x11 && B::IS_WAYLAND {
+        initialize_xwayland_window(window_init, &monitor, x, y)
+    } else {
+        B::initialize_window(window_init, monitor_geometry, x, y)
+    }
+    .with_context(|| format!("monitor {} is unavailable", window_init.monitor.clone().unwrap()))?;
 
     window.set_title(&format!("Eww - {}", window_init.name));
     window.set_position(gtk::WindowPosition::None);
@@ -544,19 +1548,31 @@ fn initialize_window<B: DisplayBackend>(
         window.set_default_size(actual_window_rect.width(), actual_window_rect.height());
     }
     window.set_decorated(false);
-    window.set_skip_taskbar_hint(true);
-    window.set_skip_pager_hint(true);
+    window.set_skip_taskbar_hint(window_init.backend_options.x11.skip_taskbar);
+    window.set_skip_pager_hint(window_init.backend_options.x11.skip_pager);
 
     // run on_screen_changed to set the visual correctly initially.
     on_screen_changed(&window, None);
     window.connect_screen_changed(on_screen_changed);
 
-    window.add(&root_widget);
+    let open_transition = transition_to_revealer_type(window_init.open_transition, false);
+    let revealer = if open_transition.is_some() || window_init.close_transition != WindowTransition::None {
+        let revealer = gtk::Revealer::new();
+        revealer.set_transition_duration(window_init.transition_duration.as_millis() as u32);
+        revealer.set_transition_type(open_transition.unwrap_or(gtk::RevealerTransitionType::None));
+        revealer.set_reveal_child(open_transition.is_none());
+        revealer.add(&root_widget);
+        window.add(&revealer);
+        Some(revealer)
+    } else {
+        window.add(&root_widget);
+        None
+    };
 
     window.realize();
 
     #[cfg(feature = "x11")]
-    if B::IS_X11 {
+    if B::IS_X11 || window_init.force_x11 {
         if let Some(geometry) = window_init.geometry {
             let _ = apply_window_position(geometry, monitor_geometry, &window);
             if window_init.backend_options.x11.window_type != yuck::config::backend_window_options::X11WindowType::Normal {
@@ -566,19 +1582,161 @@ fn initialize_window<B: DisplayBackend>(
                 });
             }
         }
-        display_backend::set_xprops(&window, monitor, window_init)?;
+        display_backend::set_xprops(&window, monitor.clone(), window_init)?;
     }
 
     window.show_all();
 
+    // start the open animation once the window is shown
+    if let Some(revealer) = revealer.as_ref().filter(|_| open_transition.is_some()) {
+        glib::idle_add_local_once(glib::clone!(@weak revealer => move || revealer.set_reveal_child(true)));
+    }
+
     Ok(EwwWindow {
         name: window_init.name.clone(),
         gtk_window: window,
         scope_index: window_scope,
+        monitor,
         destroy_event_handler_id: None,
+        hidden: false,
+        revealer,
+        close_transition: window_init.close_transition,
+        transition_duration: window_init.transition_duration,
+        reserves_space: window_init.backend_options.wayland.exclusive.reserves_space()
+            || window_init.backend_options.x11.window_type == yuck::config::backend_window_options::X11WindowType::Dock,
+        z_index: window_init.z_index,
+        hide_when_idle: window_init.hide_when_idle,
+        hidden_while_idle: false,
+        was_shown: false,
+        onopen: None,
+        onclose: None,
     })
 }
 
+/// Keep the `EWW_POINTER` variable of a window with `:track-pointer true` up to date with the position of the pointer.
+fn setup_pointer_tracking(window: &Window, event_sender: UnboundedSender<ScopeGraphEvent>, window_scope: ScopeIndex) {
+    window
+        .add_events(gdk::EventMask::POINTER_MOTION_MASK | gdk::EventMask::ENTER_NOTIFY_MASK | gdk::EventMask::LEAVE_NOTIFY_MASK);
+    let last_state = Rc::new(std::cell::Cell::new((0, 0, false)));
+    // events may originate from child widgets with their own gdk windows, so the position is queried relative to the toplevel
+    let update = Rc::new(move |window: &Window, device: Option<gdk::Device>, hovered: bool| {
+        let (Some(gdk_window), Some(device)) = (window.window(), device) else { return };
+        let (_, x, y, _) = gdk_window.device_position(&device);
+        if last_state.replace((x, y, hovered)) == (x, y, hovered) {
+            return;
+        }
+        let value = window_initiator::pointer_state(x, y, hovered);
+        let evt = ScopeGraphEvent::UpdateValue(window_scope, VarName::from(POINTER_VAR_NAME), value);
+        if let Err(err) = event_sender.send(evt) {
+            log::error!("Failed to update the pointer position: {}", err);
+        }
+    });
+    window.connect_motion_notify_event(glib::clone!(@strong update => move |window, evt| {
+        update(window, evt.device(), true);
+        glib::Propagation::Proceed
+    }));
+    window.connect_enter_notify_event(glib::clone!(@strong update => move |window, evt| {
+        update(window, evt.device(), true);
+        glib::Propagation::Proceed
+    }));
+    window.connect_leave_notify_event(move |window, evt| {
+        if evt.detail() != gdk::NotifyType::Inferior {
+            update(window, evt.device(), false);
+        }
+        glib::Propagation::Proceed
+    });
+}
+
+/// Close the window when it loses focus or, on X11, when the user clicks anywhere outside of it.
+/// On X11, this grabs the pointer while the window is mapped, such that clicks outside of the window are reported to it.
+fn setup_close_on_unfocus<B: DisplayBackend>(
+    window: &Window,
+    app_evt_sender: UnboundedSender<DaemonCommand>,
+    instance_id: String,
+) {
+    // both a click outside and the resulting focus change may trigger, but the window should only be closed once
+    let closing = Rc::new(std::cell::Cell::new(false));
+    let close = move || {
+        if closing.replace(true) {
+            return;
+        }
+        let (response_sender, _) = daemon_response::create_pair();
+        let command = DaemonCommand::CloseWindows { windows: vec![instance_id.clone()], sender: response_sender };
+        if let Err(err) = app_evt_sender.send(command) {
+            log::error!("Error sending close window command to daemon after window lost focus: {}", err);
+        }
+    };
+    let close = Rc::new(close);
+
+    // On wayland, the window may lose focus before it ever gained it, so only close it after it had focus once.
+    // The window needs to be focused again after having been hidden via `eww hide`.
+    let had_focus = Rc::new(std::cell::Cell::new(false));
+    window.connect_focus_in_event(glib::clone!(@strong had_focus => move |_, _| {
+        had_focus.set(true);
+        glib::Propagation::Proceed
+    }));
+    window.connect_hide(glib::clone!(@strong had_focus => move |_| had_focus.set(false)));
+    window.connect_focus_out_event(glib::clone!(@strong close => move |window, _| {
+        if had_focus.get() && window.is_visible() {
+            close();
+        }
+        glib::Propagation::Proceed
+    }));
+
+    if B::IS_X11 {
+        window.add_events(gdk::EventMask::BUTTON_PRESS_MASK);
+        window.connect_map_event(|window, _| {
+            let seat = window.display().default_seat();
+            if let (Some(seat), Some(gdk_window)) = (seat, window.window()) {
+                let status = seat.grab(&gdk_window, gdk::SeatCapabilities::ALL_POINTING, true, None, None, None);
+                if status != gdk::GrabStatus::Success {
+                    log::warn!("Failed to grab pointer for window with close-on-unfocus: {:?}", status);
+                }
+            }
+            glib::Propagation::Proceed
+        });
+        window.connect_unmap_event(|window, _| {
+            if let Some(seat) = window.display().default_seat() {
+                seat.ungrab();
+            }
+            glib::Propagation::Proceed
+        });
+        window.connect_button_press_event(move |window, evt| {
+            let (x, y) = evt.root();
+            let clicked_outside = window.window().map_or(false, |gdk_window| {
+                let extents = gdk_window.frame_extents();
+                let (x, y) = (x as i32, y as i32);
+                x < extents.x() || y < extents.y() || x >= extents.x() + extents.width() || y >= extents.y() + extents.height()
+            });
+            if clicked_outside {
+                close();
+                glib::Propagation::Stop
+            } else {
+                glib::Propagation::Proceed
+            }
+        });
+    }
+}
+
+/// Get the revealer transition that moves the window in the direction given by the [`WindowTransition`].
+/// As a revealer plays its transition in reverse when hiding its child, the direction is flipped when closing.
+fn transition_to_revealer_type(transition: WindowTransition, closing: bool) -> Option<gtk::RevealerTransitionType> {
+    use gtk::RevealerTransitionType as T;
+    let (slide_up, slide_down, slide_left, slide_right) = if closing {
+        (T::SlideDown, T::SlideUp, T::SlideRight, T::SlideLeft)
+    } else {
+        (T::SlideUp, T::SlideDown, T::SlideLeft, T::SlideRight)
+    };
+    match transition {
+        WindowTransition::None => None,
+        WindowTransition::Fade => Some(T::Crossfade),
+        WindowTransition::SlideUp => Some(slide_up),
+        WindowTransition::SlideDown => Some(slide_down),
+        WindowTransition::SlideLeft => Some(slide_left),
+        WindowTransition::SlideRight => Some(slide_right),
+    }
+}
+
 /// Apply the provided window-positioning rules to the window.
 #[cfg(feature = "x11")]
 fn apply_window_position(mut window_geometry: WindowGeometry, monitor_geometry: gdk::Rectangle, window: &Window) -> Result<()> {
@@ -601,12 +1759,35 @@ fn on_screen_changed(window: &Window, _old_screen: Option<&gdk::Screen>) {
     window.set_visual(visual.as_ref());
 }
 
+/// Get the display a window should be opened on.
+/// When running on wayland, windows with `:force-x11` are opened on a separate connection to XWayland.
+fn get_display_for_window<B: DisplayBackend>(force_x11: bool) -> Result<gdk::Display> {
+    if !(force_x11 && B::IS_WAYLAND) {
+        return gdk::Display::default().context("could not get default display");
+    }
+    let display_name = std::env::var("DISPLAY").context("Can't open window with :force-x11, as $DISPLAY is not set")?;
+    let existing_display = gdk::DisplayManager::get().list_displays().into_iter().find(|display| display.name() == display_name);
+    match existing_display {
+        Some(display) => Ok(display),
+        None => gdk::Display::open(&display_name).with_context(|| format!("Failed to open X11 display {}", display_name)),
+    }
+}
+
+/// Create a window on the display of the given XWayland monitor, using the X11 window setup.
+fn initialize_xwayland_window(window_init: &WindowInitiator, monitor: &Monitor, x: i32, y: i32) -> Option<Window> {
+    #[cfg(feature = "x11")]
+    let window = display_backend::X11Backend::initialize_window(window_init, monitor.geometry(), x, y)?;
+    #[cfg(not(feature = "x11"))]
+    let window = display_backend::NoBackend::initialize_window(window_init, monitor.geometry(), x, y)?;
+    window.set_screen(&monitor.display()?.default_screen());
+    Some(window)
+}
+
 /// Get the monitor geometry of a given monitor, or the default if none is given
-fn get_gdk_monitor(identifier: Option<MonitorIdentifier>) -> Result<Monitor> {
-    let display = gdk::Display::default().expect("could not get default display");
+fn get_gdk_monitor(display: &gdk::Display, identifier: Option<MonitorIdentifier>) -> Result<Monitor> {
     let monitor = match identifier {
         Some(ident) => {
-            let mon = get_monitor_from_display(&display, &ident);
+            let mon = get_monitor_from_display(display, &ident);
             mon.with_context(|| {
                 let head = format!("Failed to get monitor {}\nThe available monitors are:", ident);
                 let mut body = String::new();
@@ -664,6 +1845,45 @@ pub fn get_monitor_from_display(display: &gdk::Display, identifier: &MonitorIden
     }
 }
 
+/// Get the position of the pointer on the given display.
+/// On wayland, this is only known while the pointer is above one of the open windows.
+fn get_pointer_position<B: DisplayBackend>(
+    display: &gdk::Display,
+    force_x11: bool,
+    open_windows: &HashMap<String, EwwWindow>,
+) -> Option<(i32, i32)> {
+    let pointer = display.default_seat()?.pointer()?;
+    if !B::IS_WAYLAND || force_x11 {
+        let (_, x, y) = pointer.position();
+        return Some((x, y));
+    }
+    let toplevel = pointer.window_at_position().0?.toplevel();
+    let window = open_windows.values().find(|window| window.gtk_window.window().as_ref() == Some(&toplevel))?;
+    let (origin_x, origin_y) = B::window_origin(&window.gtk_window, window.monitor.geometry())?;
+    let (_, x, y, _) = toplevel.device_position(&pointer);
+    Some((origin_x + x, origin_y + y))
+}
+
+/// Resolve a geometry with `at_pointer` into one placing the window at the pointer, offset by the geometry's offset.
+/// The window is kept within the monitor, using the larger of its configured and its natural size.
+fn get_geometry_at_pointer(
+    geometry: WindowGeometry,
+    (pointer_x, pointer_y): (i32, i32),
+    (natural_width, natural_height): (i32, i32),
+    monitor_rect: gdk::Rectangle,
+) -> WindowGeometry {
+    let (offset_x, offset_y) = geometry.offset.relative_to(monitor_rect.width(), monitor_rect.height());
+    let (width, height) = geometry.size.relative_to(monitor_rect.width(), monitor_rect.height());
+    let x = (pointer_x - monitor_rect.x() + offset_x).clamp(0, (monitor_rect.width() - width.max(natural_width)).max(0));
+    let y = (pointer_y - monitor_rect.y() + offset_y).clamp(0, (monitor_rect.height() - height.max(natural_height)).max(0));
+    WindowGeometry {
+        anchor_point: AnchorPoint { x: AnchorAlignment::START, y: AnchorAlignment::START },
+        offset: Coords::from_pixels((x, y)),
+        size: geometry.size,
+        at_pointer: false,
+    }
+}
+
 pub fn get_window_rectangle(geometry: WindowGeometry, screen_rect: gdk::Rectangle) -> gdk::Rectangle {
     let (offset_x, offset_y) = geometry.offset.relative_to(screen_rect.width(), screen_rect.height());
     let (width, height) = geometry.size.relative_to(screen_rect.width(), screen_rect.height());
@@ -0,0 +1,176 @@
+//! Bluetooth support via BlueZ.
+//!
+//! This provides the state of the bluetooth adapter and its devices for the `EWW_BLUETOOTH` magic variable,
+//! as well as the `eww bluetooth` commands for controlling them.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context, Result};
+use futures::{FutureExt, StreamExt};
+use tokio::io::AsyncWriteExt;
+use zbus::{
+    fdo::{ManagedObjects, ObjectManagerProxy},
+    zvariant::{OwnedObjectPath, OwnedValue},
+};
+
+use crate::opts::BluetoothAction;
+
+const BLUEZ: &str = "org.bluez";
+const ADAPTER_INTERFACE: &str = "org.bluez.Adapter1";
+const DEVICE_INTERFACE: &str = "org.bluez.Device1";
+const BATTERY_INTERFACE: &str = "org.bluez.Battery1";
+
+/// Time to wait for further signals after a change, as BlueZ usually emits several changes at once.
+const CHANGE_SETTLE_TIME: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Initial value of `EWW_BLUETOOTH`, before BlueZ could be queried.
+pub const INITIAL_STATE: &str = r#"{"powered":false,"discovering":false,"adapter":null,"devices":[]}"#;
+
+#[derive(Debug, serde::Serialize)]
+struct BluetoothState {
+    powered: bool,
+    discovering: bool,
+    /// name of the adapter
+    adapter: Option<String>,
+    devices: Vec<BluetoothDevice>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct BluetoothDevice {
+    address: String,
+    name: String,
+    /// freedesktop icon name describing the type of the device, i.e. `audio-headset`
+    icon: Option<String>,
+    connected: bool,
+    paired: bool,
+    /// battery level in percent, if the device reports it
+    battery: Option<u8>,
+}
+
+fn get_property<T: TryFrom<OwnedValue>>(properties: &HashMap<String, OwnedValue>, name: &str) -> Option<T> {
+    properties.get(name).and_then(|value| T::try_from(value.clone()).ok())
+}
+
+/// The adapter with the lowest object path, i.e. `hci0`, together with its properties.
+fn first_adapter(objects: &ManagedObjects) -> Option<(&OwnedObjectPath, &HashMap<String, OwnedValue>)> {
+    objects
+        .iter()
+        .filter_map(|(path, interfaces)| Some((path, interfaces.get(ADAPTER_INTERFACE)?)))
+        .min_by_key(|(path, _)| path.as_str())
+}
+
+/// Build the state of the first adapter and all devices that are paired or connected from the objects exported by BlueZ.
+fn bluetooth_state(objects: &ManagedObjects) -> BluetoothState {
+    let adapter = first_adapter(objects).map(|(_, adapter)| adapter);
+    let mut devices: Vec<_> = objects
+        .values()
+        .filter_map(|interfaces| {
+            let device = interfaces.get(DEVICE_INTERFACE)?;
+            let battery = interfaces.get(BATTERY_INTERFACE).and_then(|battery| get_property(battery, "Percentage"));
+            let address: String = get_property(device, "Address")?;
+            Some(BluetoothDevice {
+                name: get_property(device, "Alias").unwrap_or_else(|| address.clone()),
+                address,
+                icon: get_property(device, "Icon"),
+                connected: get_property(device, "Connected").unwrap_or(false),
+                paired: get_property(device, "Paired").unwrap_or(false),
+                battery,
+            })
+        })
+        .filter(|device| device.paired || device.connected)
+        .collect();
+    devices.sort_by(|a, b| b.connected.cmp(&a.connected).then_with(|| a.name.cmp(&b.name)));
+
+    BluetoothState {
+        powered: adapter.and_then(|adapter| get_property(adapter, "Powered")).unwrap_or(false),
+        discovering: adapter.and_then(|adapter| get_property(adapter, "Discovering")).unwrap_or(false),
+        adapter: adapter.and_then(|adapter| get_property(adapter, "Alias")),
+        devices,
+    }
+}
+
+async fn object_manager(con: &zbus::Connection) -> Result<ObjectManagerProxy<'_>> {
+    Ok(ObjectManagerProxy::builder(con).destination(BLUEZ)?.path("/")?.build().await?)
+}
+
+/// Write the bluetooth state as a line of JSON whenever it changes, until writing fails.
+pub async fn write_state_updates<W: tokio::io::AsyncWrite + Unpin>(mut writer: W) -> Result<()> {
+    let con = zbus::Connection::system().await?;
+    let object_manager = object_manager(&con).await?;
+
+    let properties_rule = zbus::MatchRule::builder()
+        .msg_type(zbus::MessageType::Signal)
+        .interface("org.freedesktop.DBus.Properties")?
+        .member("PropertiesChanged")?
+        .path_namespace("/org/bluez")?
+        .build();
+    let objects_rule = zbus::MatchRule::builder()
+        .msg_type(zbus::MessageType::Signal)
+        .interface("org.freedesktop.DBus.ObjectManager")?
+        .path("/")?
+        .build();
+    let mut changes = futures::stream::select(
+        zbus::MessageStream::for_match_rule(properties_rule, &con, None).await?,
+        zbus::MessageStream::for_match_rule(objects_rule, &con, None).await?,
+    );
+
+    let mut last_state = String::new();
+    loop {
+        let objects = object_manager.get_managed_objects().await.context("Failed to get the objects exported by BlueZ")?;
+        let state = serde_json::to_string(&bluetooth_state(&objects))?;
+        if state != last_state {
+            writer.write_all(format!("{}\n", state).as_bytes()).await?;
+            writer.flush().await?;
+            last_state = state;
+        }
+
+        if changes.next().await.is_none() {
+            return Ok(());
+        }
+        tokio::time::sleep(CHANGE_SETTLE_TIME).await;
+        while let Some(Some(_)) = changes.next().now_or_never() {}
+    }
+}
+
+/// Find the object path of the device with the given address.
+fn find_device(objects: &ManagedObjects, address: &str) -> Result<OwnedObjectPath> {
+    objects
+        .iter()
+        .find(|(_, interfaces)| {
+            interfaces
+                .get(DEVICE_INTERFACE)
+                .and_then(|device| get_property::<String>(device, "Address"))
+                .map_or(false, |x| x.eq_ignore_ascii_case(address))
+        })
+        .map(|(path, _)| path.clone())
+        .ok_or_else(|| anyhow!("No bluetooth device with the address {} is known", address))
+}
+
+async fn run_action(action: BluetoothAction) -> Result<()> {
+    let con = zbus::Connection::system().await?;
+    let objects =
+        object_manager(&con).await?.get_managed_objects().await.context("Failed to get the objects exported by BlueZ")?;
+    match action {
+        BluetoothAction::Connect { address } => {
+            let proxy = zbus::Proxy::new(&con, BLUEZ, find_device(&objects, &address)?, DEVICE_INTERFACE).await?;
+            proxy.call_method("Connect", &()).await.with_context(|| format!("Failed to connect to {}", address))?;
+        }
+        BluetoothAction::Disconnect { address } => {
+            let proxy = zbus::Proxy::new(&con, BLUEZ, find_device(&objects, &address)?, DEVICE_INTERFACE).await?;
+            proxy.call_method("Disconnect", &()).await.with_context(|| format!("Failed to disconnect from {}", address))?;
+        }
+        BluetoothAction::Toggle => {
+            let (path, adapter) = first_adapter(&objects).context("No bluetooth adapter found")?;
+            let powered: bool = get_property(adapter, "Powered").unwrap_or(false);
+            let proxy = zbus::Proxy::new(&con, BLUEZ, path.clone(), ADAPTER_INTERFACE).await?;
+            proxy.set_property("Powered", !powered).await.context("Failed to toggle the bluetooth adapter")?;
+        }
+    }
+    Ok(())
+}
+
+/// Run an `eww bluetooth` command.
+pub fn handle_action(action: BluetoothAction) -> Result<()> {
+    let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().context("Failed to initialize tokio runtime")?;
+    rt.block_on(run_action(action))
+}
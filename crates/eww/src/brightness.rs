@@ -0,0 +1,119 @@
+//! Backlight brightness, backing the `EWW_BRIGHTNESS` magic variable and `eww brightness`.
+//!
+//! The brightness is read from `/sys/class/backlight`, and changed via logind,
+//! which allows the user of the current session to do so without further permissions.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use tokio::io::AsyncWriteExt;
+
+const BACKLIGHT_DIR: &str = "/sys/class/backlight";
+/// How often the brightness is read again, to notice changes that aren't reported via file events.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, serde::Serialize)]
+struct BacklightState {
+    brightness: u32,
+    max_brightness: u32,
+    percent: u32,
+}
+
+fn read_value(path: &Path) -> Result<u32> {
+    let value = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(value.trim().parse()?)
+}
+
+fn backlight_devices() -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(BACKLIGHT_DIR) else { return Vec::new() };
+    entries.flatten().map(|entry| entry.path()).collect()
+}
+
+fn read_backlight(device: &Path) -> Result<BacklightState> {
+    // actual_brightness also reflects changes made by the hardware, i.e. via brightness keys handled by the firmware
+    let brightness = read_value(&device.join("actual_brightness")).or_else(|_| read_value(&device.join("brightness")))?;
+    let max_brightness = read_value(&device.join("max_brightness"))?;
+    let percent = if max_brightness == 0 { 0 } else { (brightness as f64 / max_brightness as f64 * 100.).round() as u32 };
+    Ok(BacklightState { brightness, max_brightness, percent })
+}
+
+/// The brightness of all backlight devices as JSON, keyed by the device name.
+fn brightness_state() -> String {
+    backlight_devices()
+        .iter()
+        .filter_map(|device| {
+            let name = device.file_name()?.to_string_lossy().into_owned();
+            match read_backlight(device) {
+                Ok(state) => Some((name, serde_json::json!(state))),
+                Err(err) => {
+                    log::warn!("Failed to read brightness of {}: {:?}", name, err);
+                    None
+                }
+            }
+        })
+        .collect::<serde_json::Value>()
+        .to_string()
+}
+
+/// Write the brightness as a line of JSON whenever it changes, until writing fails.
+pub async fn write_state_updates<W: tokio::io::AsyncWrite + Unpin>(mut writer: W) -> Result<()> {
+    use notify::{RecursiveMode, Watcher};
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| match res {
+        Ok(event) if !event.kind.is_access() => {
+            let _ = tx.send(());
+        }
+        Ok(_) => {}
+        Err(err) => log::error!("Encountered error while watching the backlight: {}", err),
+    })?;
+    for device in backlight_devices() {
+        watcher.watch(&device.join("brightness"), RecursiveMode::NonRecursive)?;
+    }
+    // sysfs attributes such as actual_brightness don't emit file events when the hardware changes them, so poll as well
+    let mut poll_interval = tokio::time::interval(POLL_INTERVAL);
+    poll_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    let mut last_state = String::new();
+    loop {
+        let state = brightness_state();
+        if state != last_state {
+            writer.write_all(format!("{}\n", state).as_bytes()).await?;
+            writer.flush().await?;
+            last_state = state;
+        }
+        tokio::select! {
+            event = rx.recv() => if event.is_none() {
+                return Ok(());
+            },
+            _ = poll_interval.tick() => {}
+        }
+    }
+}
+
+/// Set the brightness of a backlight device to the given percentage, using logind.
+pub async fn set_brightness(device: &str, percent: f64) -> Result<()> {
+    let path = Path::new(BACKLIGHT_DIR).join(device);
+    if device.contains('/') || !path.exists() {
+        bail!("No backlight device named {} exists", device);
+    }
+    let max_brightness = read_value(&path.join("max_brightness"))?;
+    let brightness = (percent.clamp(0., 100.) / 100. * max_brightness as f64).round() as u32;
+
+    let con = zbus::Connection::system().await?;
+    let session = zbus::Proxy::new(
+        &con,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1/session/auto",
+        "org.freedesktop.login1.Session",
+    )
+    .await?;
+    session
+        .call_method("SetBrightness", &("backlight", device, brightness))
+        .await
+        .with_context(|| format!("Failed to set the brightness of {} via logind", device))?;
+    Ok(())
+}
@@ -1,11 +1,14 @@
 use std::process::Stdio;
 
 use crate::{
+    bluetooth, config,
     daemon_response::DaemonResponse,
+    error_handling_ctx, logging, modules,
     opts::{self, ActionClientOnly},
     paths::EwwPaths,
+    widget_docs,
 };
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use std::{
     io::{Read, Write},
     os::unix::net::UnixStream,
@@ -13,17 +16,69 @@ use std::{
 
 pub fn handle_client_only_action(paths: &EwwPaths, action: ActionClientOnly) -> Result<()> {
     match action {
-        ActionClientOnly::Logs => {
+        ActionClientOnly::Logs { level, filter, json } if level.is_some() || filter.is_some() || json => {
+            let filter = logging::LogFilter { level: level.map(Into::into), name: filter };
+            logging::follow_structured_logs(paths.get_structured_log_file(), filter, json)?;
+        }
+        ActionClientOnly::Logs { .. } => {
             std::process::Command::new("tail")
                 .args(["-f", paths.get_log_file().to_string_lossy().as_ref()].iter())
                 .stdin(Stdio::null())
                 .spawn()?
                 .wait()?;
         }
+        ActionClientOnly::Check => check_config(paths)?,
+        ActionClientOnly::Widgets { json } => {
+            std::env::set_current_dir(paths.get_config_dir())
+                .with_context(|| format!("Failed to change working directory to {}", paths.get_config_dir().display()))?;
+            let config = config::read_from_eww_paths(paths)?;
+            widget_docs::print_widget_list(config.get_widget_definitions(), json)?;
+        }
+        ActionClientOnly::Module { action: opts::ModuleAction::Add { source, name } } => {
+            modules::add_module(paths.get_config_dir(), &source, name)?;
+        }
+        ActionClientOnly::Bluetooth { action } => bluetooth::handle_action(action)?,
     }
     Ok(())
 }
 
+/// Load and validate the yuck configuration and the stylesheet, printing all errors that were found.
+fn check_config(paths: &EwwPaths) -> Result<()> {
+    // paths in the configuration are relative to the config directory, just like in the daemon
+    std::env::set_current_dir(paths.get_config_dir())
+        .with_context(|| format!("Failed to change working directory to {}", paths.get_config_dir().display()))?;
+    let mut errors = Vec::new();
+    let config = config::read_from_eww_paths(paths).map_err(|err| errors.push(err)).ok();
+    if let Some(Err(err)) = config.as_ref().map(|config| config.validate_widget_names()) {
+        errors.push(err);
+    }
+
+    // the yuck files need to be loaded before the stylesheet, as loading the config resets the file database.
+    // A configuration without any stylesheet is valid.
+    let eww_vars = config.as_ref().map(|config| config.generate_static_initial_state()).unwrap_or_default();
+    let has_stylesheet = ["eww.scss", "eww.css"].iter().any(|file| paths.get_config_dir().join(file).exists());
+    if has_stylesheet {
+        if let Err(err) = config::scss::parse_scss_from_config(paths.get_config_dir(), &eww_vars) {
+            errors.push(err);
+        }
+    }
+    for (window_name, stylesheet) in config.as_ref().map(|config| config.get_window_stylesheets()).unwrap_or_default() {
+        if let Err(err) = config::scss::parse_window_stylesheet(paths.get_config_dir(), stylesheet, &eww_vars) {
+            errors.push(err.context(format!("Failed to load the stylesheet of window {}", window_name)));
+        }
+    }
+
+    if errors.is_empty() {
+        println!("Configuration in {} is valid", paths.get_config_dir().display());
+        return Ok(());
+    }
+    let error_count = errors.len();
+    for error in errors {
+        error_handling_ctx::print_error(error);
+    }
+    bail!("Found {} error(s) in the configuration", error_count)
+}
+
 /// Connect to the daemon and send the given request.
 /// Returns the response from the daemon, or None if the daemon did not provide any useful response. An Ok(None) response does _not_ indicate failure.
 pub fn do_server_call(stream: &mut UnixStream, action: &opts::ActionWithServer) -> Result<Option<DaemonResponse>> {
@@ -37,7 +92,7 @@ pub fn do_server_call(stream: &mut UnixStream, action: &opts::ActionWithServer)
     stream.write_all(&message_bytes).context("Failed to write command to IPC stream")?;
 
     let mut buf = Vec::new();
-    stream.set_read_timeout(Some(std::time::Duration::from_millis(100))).context("Failed to set read timeout")?;
+    stream.set_read_timeout(Some(action.response_timeout())).context("Failed to set read timeout")?;
     stream.read_to_end(&mut buf).context("Error reading response from server")?;
 
     Ok(if buf.is_empty() {
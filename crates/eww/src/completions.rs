@@ -0,0 +1,117 @@
+//! Shell completion scripts.
+//!
+//! On top of the completions generated by clap, the scripts for bash, zsh and fish complete window and variable names
+//! by asking a running daemon via the hidden `eww complete` command.
+
+use clap::CommandFactory as _;
+use clap_complete::Shell;
+
+use crate::opts::RawOpt;
+
+const ZSH_REGISTRATION: &str = r#"if [ "$funcstack[1]" = "_eww" ]; then
+    _eww "$@"
+else
+    compdef _eww eww
+fi
+"#;
+
+const BASH_DYNAMIC_COMPLETIONS: &str = r#"
+_eww_dynamic() {
+    local cur="${COMP_WORDS[COMP_CWORD]}" prev="${COMP_WORDS[COMP_CWORD-1]}"
+    local subcommand="" kind="" word i
+    local -a config_args=()
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        word="${COMP_WORDS[i]}"
+        case "$word" in
+            -c|--config) config_args=(--config "${COMP_WORDS[i+1]}"); ((i++)) ;;
+            -*) ;;
+            *) [[ -z "$subcommand" ]] && subcommand="$word" ;;
+        esac
+    done
+    if [[ "$cur" != -* && "$prev" != -* && "$prev" != "=" && "$cur" != *=* ]]; then
+        case "$subcommand" in
+            open|o|toggle|t|show|open-many) kind=windows ;;
+            close|c|hide|debug-tree) kind=open-windows ;;
+            update|u|get) kind=variables ;;
+        esac
+    fi
+    if [[ -z "$kind" ]]; then
+        _eww "$@"
+        return
+    fi
+    local candidates
+    candidates="$(eww "${config_args[@]}" complete "$kind" 2>/dev/null)"
+    if [[ "$kind" == variables && "$subcommand" != get ]]; then
+        compopt -o nospace
+        COMPREPLY=($(compgen -W "$candidates" -S "=" -- "$cur"))
+    else
+        COMPREPLY=($(compgen -W "$candidates" -- "$cur"))
+    fi
+}
+
+complete -F _eww_dynamic -o bashdefault -o default eww
+"#;
+
+const ZSH_DYNAMIC_COMPLETIONS: &str = r#"
+_eww_dynamic() {
+    local subcommand="" kind="" word i
+    local -a config_args candidates
+    for ((i = 2; i < CURRENT; i++)); do
+        word="${words[i]}"
+        case "$word" in
+            -c|--config) config_args=(--config "${words[i+1]}"); ((i++)) ;;
+            -*) ;;
+            *) [[ -z "$subcommand" ]] && subcommand="$word" ;;
+        esac
+    done
+    if [[ "${words[CURRENT]}" != -* && "${words[CURRENT-1]}" != -* ]]; then
+        case "$subcommand" in
+            open|o|toggle|t|show|open-many) kind=windows ;;
+            close|c|hide|debug-tree) kind=open-windows ;;
+            update|u|get) kind=variables ;;
+        esac
+    fi
+    if [[ -z "$kind" ]]; then
+        _eww "$@"
+        return
+    fi
+    candidates=("${(@f)$(eww "${config_args[@]}" complete "$kind" 2>/dev/null)}")
+    if [[ "$kind" == variables && "$subcommand" != get ]]; then
+        compadd -S "=" -a candidates
+    else
+        compadd -a candidates
+    fi
+}
+
+if [ "$funcstack[1]" = "_eww" ]; then
+    _eww_dynamic "$@"
+else
+    compdef _eww_dynamic eww
+fi
+"#;
+
+const FISH_DYNAMIC_COMPLETIONS: &str = r#"
+complete -c eww -n "__fish_seen_subcommand_from open o toggle t show open-many" -f -a "(eww complete windows 2>/dev/null)"
+complete -c eww -n "__fish_seen_subcommand_from close c hide debug-tree" -f -a "(eww complete open-windows 2>/dev/null)"
+complete -c eww -n "__fish_seen_subcommand_from get" -f -a "(eww complete variables 2>/dev/null)"
+complete -c eww -n "__fish_seen_subcommand_from update u" -f -a "(eww complete variables 2>/dev/null | string replace -r '\$' '=')"
+"#;
+
+/// Print the completion script for the given shell to stdout.
+pub fn print_completions(shell: Shell) {
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut RawOpt::command(), "eww", &mut script);
+    let mut script = String::from_utf8_lossy(&script).into_owned();
+
+    match shell {
+        Shell::Bash => script.push_str(BASH_DYNAMIC_COMPLETIONS),
+        // the generated script registers `_eww` itself at the end, which we need to replace with our wrapper.
+        Shell::Zsh => match script.rfind(ZSH_REGISTRATION) {
+            Some(index) => script.replace_range(index.., ZSH_DYNAMIC_COMPLETIONS),
+            None => log::warn!("Failed to add dynamic completions to the zsh completion script"),
+        },
+        Shell::Fish => script.push_str(FISH_DYNAMIC_COMPLETIONS),
+        _ => {}
+    }
+    print!("{script}");
+}
@@ -1,13 +1,20 @@
 use anyhow::{bail, Context, Result};
-use eww_shared_util::VarName;
-use std::collections::HashMap;
+use eww_shared_util::{Span, VarName};
+use std::collections::{HashMap, HashSet};
 use yuck::{
     config::{
-        script_var_definition::ScriptVarDefinition, validate::ValidationError, widget_definition::WidgetDefinition,
-        window_definition::WindowDefinition, Config,
+        config_options::ConfigOptions,
+        script_var_definition::ScriptVarDefinition,
+        validate::ValidationError,
+        var_definition::{VarDefinition, VarType},
+        widget_definition::WidgetDefinition,
+        widget_use::WidgetUse,
+        window_definition::WindowDefinition,
+        Config,
     },
     error::DiagError,
     format_diagnostic::ToDiagnostic,
+    gen_diagnostic,
 };
 
 use simplexpr::dynval::DynVal;
@@ -30,9 +37,19 @@ pub struct EwwConfig {
     windows: HashMap<String, WindowDefinition>,
     initial_variables: HashMap<VarName, DynVal>,
     script_vars: HashMap<VarName, ScriptVarDefinition>,
+    config_options: ConfigOptions,
 
     // map of variables to all pollvars which refer to them in their run-while-expression
     run_while_mentions: HashMap<VarName, Vec<VarName>>,
+
+    // map of variables to the command that should run whenever their value changes
+    onchange_commands: HashMap<VarName, (Span, String)>,
+
+    // map of variables to the type declared via `:type`
+    var_types: HashMap<VarName, VarType>,
+
+    // built-in variables that aren't referenced anywhere in the configuration, and thus don't need to be computed initially
+    unreferenced_builtins: HashSet<VarName>,
 }
 
 impl EwwConfig {
@@ -57,9 +74,66 @@ impl EwwConfig {
             }
         }
 
-        let Config { widget_definitions, window_definitions, mut var_definitions, mut script_vars } = config;
-        script_vars.extend(inbuilt::get_inbuilt_vars());
+        let Config {
+            widget_definitions,
+            window_definitions,
+            mut var_definitions,
+            mut script_vars,
+            config_options,
+            attrs_definitions: _,
+            macro_definitions: _,
+        } = config;
+        let config_options = config_options.unwrap_or_default();
+        if let Some(name) =
+            config_options.disabled_magic_vars.iter().find(|name| !inbuilt::INBUILT_VAR_NAMES.contains(&name.as_str()))
+        {
+            bail!("Cannot disable `{}`, as it is not a built-in magic variable", name);
+        }
+        // disabled built-ins stay defined, such that configurations referencing them keep working, but never change their value
+        for (name, var) in inbuilt::get_inbuilt_vars() {
+            if config_options.disabled_magic_vars.contains(&name.0) {
+                let empty = VarDefinition {
+                    name: name.clone(),
+                    initial_value: DynVal::from(""),
+                    onchange: None,
+                    var_type: None,
+                    span: Span::DUMMY,
+                };
+                var_definitions.insert(name, empty);
+            } else {
+                script_vars.insert(name, var);
+            }
+        }
         var_definitions.extend(inbuilt::get_magic_constants(eww_paths));
+        if let Some(ScriptVarDefinition::Poll(top)) = script_vars.get_mut(&VarName::from("EWW_TOP")) {
+            top.interval = config_options.top_interval;
+        }
+        super::system_stats::TOP_PROCESS_COUNT.store(config_options.top_processes, std::sync::atomic::Ordering::Relaxed);
+        let battery_from_upower = match config_options.battery_source.as_str() {
+            "sysfs" => false,
+            "upower" => true,
+            other => bail!("Unknown battery source `{}`, expected \"sysfs\" or \"upower\"", other),
+        };
+        super::system_stats::BATTERY_FROM_UPOWER.store(battery_from_upower, std::sync::atomic::Ordering::Relaxed);
+
+        let mut referenced_vars = Vec::new();
+        for window in window_definitions.values() {
+            window.collect_var_refs_into(&mut referenced_vars);
+        }
+        for widget in widget_definitions.values() {
+            widget.widget.collect_var_refs_into(&mut referenced_vars);
+        }
+        for var in script_vars.values() {
+            if let ScriptVarDefinition::Poll(var) = var {
+                var.run_while_expr.collect_var_refs_into(&mut referenced_vars);
+            }
+        }
+        let referenced_vars: HashSet<_> = referenced_vars.into_iter().collect();
+        let unreferenced_builtins = inbuilt::INBUILT_VAR_NAMES
+            .iter()
+            .map(|name| VarName::from(*name))
+            .filter(|name| !referenced_vars.contains(name))
+            .collect();
 
         let mut run_while_mentions = HashMap::<VarName, Vec<VarName>>::new();
         for var in script_vars.values() {
@@ -70,12 +144,33 @@ impl EwwConfig {
             }
         }
 
+        let onchange_commands = var_definitions
+            .values()
+            .filter_map(|var| Some((var.name.clone(), (var.span, var.onchange.clone()?))))
+            .chain(
+                script_vars.values().filter_map(|var| Some((var.name().clone(), (var.name_span(), var.onchange()?.to_string())))),
+            )
+            .collect();
+
+        let var_types = var_definitions
+            .values()
+            .filter_map(|var| Some((var.name.clone(), var.var_type?)))
+            .chain(script_vars.values().filter_map(|var| match var {
+                ScriptVarDefinition::Poll(var) => Some((var.name.clone(), var.var_type?)),
+                _ => None,
+            }))
+            .collect();
+
         Ok(EwwConfig {
             windows: window_definitions,
             widgets: widget_definitions,
             initial_variables: var_definitions.into_iter().map(|(k, v)| (k, v.initial_value)).collect(),
             script_vars,
+            config_options,
             run_while_mentions,
+            onchange_commands,
+            var_types,
+            unreferenced_builtins,
         })
     }
 
@@ -84,12 +179,54 @@ impl EwwConfig {
         let mut vars = self
             .script_vars
             .iter()
-            .map(|(name, var)| Ok((name.clone(), script_var::initial_value(var)?)))
+            .map(|(name, var)| match self.unreferenced_builtins.contains(name) {
+                // these are only used by widgets created via `literal`, if at all, which will start them once they're shown
+                true => Ok((name.clone(), DynVal::from(""))),
+                false => Ok((name.clone(), script_var::initial_value(var)?)),
+            })
             .collect::<Result<HashMap<_, _>>>()?;
         vars.extend(self.initial_variables.clone());
         Ok(vars)
     }
 
+    /// Make sure that every widget used in the configuration is either a builtin widget or defined via `defwidget`.
+    /// Usually, this is only noticed once a window containing the widget is opened.
+    pub fn validate_widget_names(&self) -> Result<()> {
+        let widget_uses = self.windows.values().map(|window| &window.widget).chain(self.widgets.values().map(|def| &def.widget));
+        for widget_use in widget_uses {
+            validate_widget_names_in(&self.widgets, widget_use)?;
+        }
+        Ok(())
+    }
+
+    /// Generate the initial state without running any scripts.
+    /// Variables whose initial value can only be determined by running a script get an empty value.
+    pub fn generate_static_initial_state(&self) -> HashMap<VarName, DynVal> {
+        let mut vars: HashMap<_, _> = self
+            .script_vars
+            .iter()
+            .map(|(name, var)| {
+                let value = match var {
+                    ScriptVarDefinition::Poll(var) => var.initial_value.clone().unwrap_or_else(|| DynVal::from("")),
+                    ScriptVarDefinition::Listen(var) => var.initial_value.clone(),
+                    ScriptVarDefinition::DBus(var) => var.initial_value.clone(),
+                    ScriptVarDefinition::Watch(var) => var.initial_value.clone(),
+                };
+                (name.clone(), value)
+            })
+            .collect();
+        vars.extend(self.initial_variables.clone());
+        vars
+    }
+
+    /// Get the stylesheets of all windows that specify one, as pairs of window name and path, sorted by window name.
+    pub fn get_window_stylesheets(&self) -> Vec<(&str, &str)> {
+        let mut stylesheets: Vec<_> =
+            self.windows.values().filter_map(|window| Some((window.name.as_str(), window.stylesheet.as_deref()?))).collect();
+        stylesheets.sort();
+        stylesheets
+    }
+
     pub fn get_windows(&self) -> &HashMap<String, WindowDefinition> {
         &self.windows
     }
@@ -112,8 +249,66 @@ impl EwwConfig {
         &self.widgets
     }
 
+    pub fn get_config_options(&self) -> &ConfigOptions {
+        &self.config_options
+    }
+
     /// Given a variable name, get the names of all variables that reference that variable in their run-while (active/inactive) state
     pub fn get_run_while_mentions_of(&self, name: &VarName) -> Option<&Vec<VarName>> {
         self.run_while_mentions.get(name)
     }
+
+    /// Get the `:onchange` command of a variable, together with the span of its definition
+    pub fn get_onchange_command(&self, name: &VarName) -> Option<&(Span, String)> {
+        self.onchange_commands.get(name)
+    }
+
+    /// Get the type a variable was declared with via `:type`
+    pub fn get_var_type(&self, name: &VarName) -> Option<VarType> {
+        self.var_types.get(name).copied()
+    }
+}
+
+/// Like [`validate_widget_names_in`], but also allows `row`s, which are only valid as children of a grid.
+fn validate_grid_child_names(widget_defs: &HashMap<String, WidgetDefinition>, widget_use: &WidgetUse) -> Result<()> {
+    match widget_use {
+        WidgetUse::Basic(row) if row.name == widget_definitions::WIDGET_NAME_GRID_ROW => {
+            row.children.iter().try_for_each(|cell| validate_widget_names_in(widget_defs, cell))
+        }
+        WidgetUse::Loop(widget) => validate_grid_child_names(widget_defs, &widget.body),
+        other => validate_widget_names_in(widget_defs, other),
+    }
+}
+
+fn validate_widget_names_in(widget_defs: &HashMap<String, WidgetDefinition>, widget_use: &WidgetUse) -> Result<()> {
+    match widget_use {
+        WidgetUse::Basic(widget) => {
+            if !widget_defs.contains_key(&widget.name)
+                && !widget_definitions::BUILTIN_WIDGET_NAMES.contains(&widget.name.as_str())
+            {
+                return Err(DiagError(gen_diagnostic! {
+                    msg = format!("referenced unknown widget `{}`", widget.name),
+                    label = widget.name_span => "Used here",
+                })
+                .into());
+            }
+            if widget.name == widget_definitions::WIDGET_NAME_GRID {
+                return widget.children.iter().try_for_each(|child| validate_grid_child_names(widget_defs, child));
+            }
+            widget.children.iter().try_for_each(|child| validate_widget_names_in(widget_defs, child))
+        }
+        WidgetUse::Loop(widget) => validate_widget_names_in(widget_defs, &widget.body),
+        WidgetUse::Children(_) => Ok(()),
+        WidgetUse::Conditional(widget) => widget
+            .then_body
+            .iter()
+            .chain(widget.else_body.iter())
+            .try_for_each(|body| validate_widget_names_in(widget_defs, body)),
+        WidgetUse::Case(widget) => widget
+            .branches
+            .iter()
+            .map(|branch| &branch.body)
+            .chain(widget.else_body.as_deref())
+            .try_for_each(|body| validate_widget_names_in(widget_defs, body)),
+    }
 }
@@ -2,7 +2,9 @@ use std::collections::HashMap;
 
 use simplexpr::{dynval::DynVal, SimplExpr};
 use yuck::config::{
-    script_var_definition::{PollScriptVar, ScriptVarDefinition, VarSource},
+    script_var_definition::{
+        ListenExitBehavior, ListenRestartPolicy, ListenScriptVar, ListenSource, PollScriptVar, ScriptVarDefinition, VarSource,
+    },
     var_definition::VarDefinition,
 };
 
@@ -10,10 +12,13 @@ use crate::{config::system_stats::*, paths::EwwPaths};
 use eww_shared_util::VarName;
 
 macro_rules! define_builtin_vars {
-    ($($name:literal [$interval:literal] => $fun:expr),*$(,)?) => {
-        pub static INBUILT_VAR_NAMES: &[&'static str] = &[$($name),*];
+    (
+        $($name:literal [$interval:literal] => $fun:expr),*$(,)?;
+        listen { $($listen_name:literal [initial $initial:expr] => $listener:path),*$(,)? }
+    ) => {
+        pub static INBUILT_VAR_NAMES: &[&'static str] = &[$($name,)* $($listen_name),*];
         pub fn get_inbuilt_vars() -> HashMap<VarName, ScriptVarDefinition> {
-            maplit::hashmap! {
+            let mut vars = maplit::hashmap! {
                 $(
                 VarName::from($name) => ScriptVarDefinition::Poll(PollScriptVar {
                     name: VarName::from($name),
@@ -21,9 +26,38 @@ macro_rules! define_builtin_vars {
                     command: VarSource::Function($fun),
                     initial_value: None,
                     interval: std::time::Duration::from_secs($interval),
+                    rate_limit: Default::default(),
+                    onchange: None,
+                    var_type: None,
                     name_span: eww_shared_util::span::Span::DUMMY,
                 })
                 ),*
+            };
+            $(
+            vars.insert(VarName::from($listen_name), ScriptVarDefinition::Listen(ListenScriptVar {
+                name: VarName::from($listen_name),
+                source: ListenSource::Inbuilt($listen_name.to_string()),
+                initial_value: DynVal::from($initial),
+                rate_limit: Default::default(),
+                // the service providing the data may be restarted or only become available later
+                restart_policy: ListenRestartPolicy {
+                    on_exit: ListenExitBehavior::Restart,
+                    max_restarts: None,
+                    restart_delay: std::time::Duration::from_secs(5),
+                },
+                onchange: None,
+                command_span: eww_shared_util::span::Span::DUMMY,
+                name_span: eww_shared_util::span::Span::DUMMY,
+            }));
+            )*
+            vars
+        }
+
+        /// Produce the lines of the built-in listen var with the given name.
+        pub async fn run_inbuilt_listener<W: tokio::io::AsyncWrite + Unpin>(name: &str, writer: W) -> anyhow::Result<()> {
+            match name {
+                $($listen_name => $listener(writer).await,)*
+                _ => Err(anyhow::anyhow!("Unknown built-in listen var {}", name)),
             }
         }
     }
@@ -42,8 +76,8 @@ define_builtin_vars! {
     // @prop { <mount_point>: { name, total, free, used, used_perc } }
     "EWW_DISK" [2] => || Ok(DynVal::from(get_disks())),
 
-    // @desc EWW_BATTERY - Battery capacity in percent of the main battery
-    // @prop { <name>: { capacity, status } }
+    // @desc EWW_BATTERY - Battery capacity in percent of the main battery. With `(defconfig :battery-source "upower")`, the data is read from UPower instead, which also includes peripherals such as mice and headsets, and provides additional details (Linux only)
+    // @prop { <name>: { capacity, status, type, model, time_to_empty, time_to_full, charge_start_threshold, charge_end_threshold }, total_avg }
     "EWW_BATTERY" [2] => || Ok(DynVal::from(
         match get_battery_capacity() {
             Err(e) => {
@@ -62,8 +96,45 @@ define_builtin_vars! {
     // @prop { <name>: { up, down } }
     "EWW_NET" [2] => || Ok(DynVal::from(net())) ,
 
+    // @desc EWW_TOP - The processes using the most CPU and memory (Linux only). The CPU usage is relative to all cores combined, memory is in bytes. The number of processes and the refresh interval can be set via `(defconfig :top-processes 10 :top-interval "2s")`
+    // @prop { cpu: [{ pid, name, cpu, mem, mem_perc }], mem: [{ pid, name, cpu, mem, mem_perc }] }
+    "EWW_TOP" [2] => || Ok(DynVal::from(
+        match get_top_processes() {
+            Err(e) => {
+                log::error!("Couldn't get the top processes: {:?}", e);
+                "Error: Check `eww log` for more details".to_string()
+            }
+            Ok(o) => o,
+        }
+    )),
+
+    // @desc EWW_GPU - Utilization, VRAM usage in bytes and temperature in degree Celsius of the GPUs (Linux only). AMD and Intel GPUs are read from sysfs, NVIDIA GPUs via NVML if the driver provides it. Values that aren't available for a GPU, such as the utilization of Intel GPUs, are `null`
+    // @prop { <name>: { vendor, name, usage, vram_total, vram_used, vram_used_perc, temp, freq } }
+    "EWW_GPU" [2] => || Ok(DynVal::from(get_gpus())),
+
     // @desc EWW_TIME - the current UNIX timestamp
     "EWW_TIME" [1] => || Ok(DynVal::from(get_time())) ,
+
+    // @desc EWW_ERRORS - The most recent errors eww ran into at runtime (at most 20), oldest first. Example: `{arraylength(EWW_ERRORS) > 0}`
+    // @prop [{ time, message }]
+    "EWW_ERRORS" [1] => || Ok(DynVal::from(&crate::error_handling_ctx::get_recent_errors())) ;
+
+    listen {
+        // @desc EWW_BLUETOOTH - State of the bluetooth adapter and the paired or connected devices, updated whenever BlueZ reports a change. Devices can be controlled via `eww bluetooth`
+        // @prop { powered, discovering, adapter, devices: [{ address, name, icon, connected, paired, battery }] }
+        "EWW_BLUETOOTH" [initial crate::bluetooth::INITIAL_STATE] => crate::bluetooth::write_state_updates,
+
+        // @desc EWW_BRIGHTNESS - Brightness of the backlight devices, updated whenever it is changed via software, and polled every two seconds to pick up changes made by the hardware. Can be set via `eww brightness set <device> <percent>`. Example: `{EWW_BRIGHTNESS.intel_backlight.percent}`
+        // @prop { <device>: { brightness, max_brightness, percent } }
+        "EWW_BRIGHTNESS" [initial "{}"] => crate::brightness::write_state_updates,
+
+        // @desc EWW_KEYBOARD - The active keyboard layout and the state of the lock keys, as reported by Hyprland, Sway or XKB on X11. Sway doesn't report the lock keys, so they are `null` there
+        // @prop { layout, caps_lock, num_lock }
+        "EWW_KEYBOARD" [initial crate::keyboard::INITIAL_STATE] => crate::keyboard::write_state_updates,
+
+        // @desc EWW_IDLE_SECONDS - Seconds since the last user input, using ext-idle-notify on wayland and XScreenSaver on X11. Updated every second while idle
+        "EWW_IDLE_SECONDS" [initial "0"] => crate::idle::write_state_updates,
+    }
 }
 
 macro_rules! define_magic_constants {
@@ -74,6 +145,8 @@ macro_rules! define_magic_constants {
                 $(VarName::from($name) => VarDefinition {
                     name: VarName::from($name),
                     initial_value: $value,
+                    onchange: None,
+                    var_type: None,
                     span: eww_shared_util::span::Span::DUMMY
                 }),*
             }
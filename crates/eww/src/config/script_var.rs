@@ -1,11 +1,12 @@
-use std::process::Command;
+use std::process::{Command, Stdio};
 
+use crate::process_registry::{self, ProcessOwner};
 use anyhow::{anyhow, bail, Context, Result};
 use codespan_reporting::diagnostic::Severity;
 use eww_shared_util::{Span, VarName};
 use simplexpr::dynval::DynVal;
 use yuck::{
-    config::script_var_definition::{ScriptVarDefinition, VarSource},
+    config::script_var_definition::{FileWatchVar, ScriptVarDefinition, VarSource},
     error::DiagError,
     gen_diagnostic,
 };
@@ -19,6 +20,15 @@ pub fn create_script_var_failed_warn(span: Span, var_name: &VarName, error_outpu
     })
 }
 
+pub fn create_http_request_failed_warn(span: Span, var_name: &VarName, error_output: &str) -> DiagError {
+    DiagError(gen_diagnostic! {
+        kind = Severity::Warning,
+        msg = format!("The request for the `{}`-variable failed", var_name),
+        label = span => "Defined here",
+        note = error_output,
+    })
+}
+
 pub fn initial_value(var: &ScriptVarDefinition) -> Result<DynVal> {
     match var {
         ScriptVarDefinition::Poll(x) => match &x.initial_value {
@@ -27,20 +37,25 @@ pub fn initial_value(var: &ScriptVarDefinition) -> Result<DynVal> {
                 VarSource::Function(f) => f()
                     .map_err(|err| anyhow!(err))
                     .with_context(|| format!("Failed to compute initial value for {}", &var.name())),
-                VarSource::Shell(span, command) => {
-                    run_command(command).map_err(|e| anyhow!(create_script_var_failed_warn(*span, var.name(), &e.to_string())))
-                }
+                VarSource::Shell(span, command) => run_command(command, ProcessOwner::Variable(var.name().clone()))
+                    .map_err(|e| anyhow!(create_script_var_failed_warn(*span, var.name(), &e.to_string()))),
+                // requests are only ever made asynchronously by the script var handler
+                VarSource::Http(..) => Ok(DynVal::from(String::new())),
             },
         },
 
         ScriptVarDefinition::Listen(var) => Ok(var.initial_value.clone()),
+        ScriptVarDefinition::DBus(var) => Ok(var.initial_value.clone()),
+        ScriptVarDefinition::Watch(var) => Ok(read_watched_file(var).unwrap_or_else(|_| var.initial_value.clone())),
     }
 }
 
 /// Run a command and get the output
-pub fn run_command(cmd: &str) -> Result<DynVal> {
+pub fn run_command(cmd: &str, owner: ProcessOwner) -> Result<DynVal> {
     log::debug!("Running command: {}", cmd);
-    let command = Command::new("/bin/sh").arg("-c").arg(cmd).output()?;
+    let child = Command::new("/bin/sh").arg("-c").arg(cmd).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let _registration = process_registry::register(Some(child.id()), owner, cmd, 0);
+    let command = child.wait_with_output()?;
     if !command.status.success() {
         bail!("Failed with output:\n{}", String::from_utf8(command.stderr)?);
     }
@@ -48,3 +63,15 @@ pub fn run_command(cmd: &str) -> Result<DynVal> {
     let output = output.trim_matches('\n');
     Ok(DynVal::from(output))
 }
+
+/// Read the contents of the file of a watch var, normalizing it if it is meant to contain JSON.
+pub fn read_watched_file(var: &FileWatchVar) -> Result<DynVal> {
+    let contents = std::fs::read_to_string(&var.path)?;
+    if var.json {
+        let value: serde_json::Value =
+            serde_json::from_str(&contents).with_context(|| format!("{} does not contain valid JSON", var.path.display()))?;
+        Ok(DynVal::from_string(value.to_string()))
+    } else {
+        Ok(DynVal::from(contents.trim_matches('\n')))
+    }
+}
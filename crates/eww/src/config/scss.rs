@@ -1,34 +1,131 @@
-use std::path::Path;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
 
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, bail, Context};
+use eww_shared_util::VarName;
+use simplexpr::dynval::DynVal;
 
-use crate::{error_handling_ctx, util::replace_env_var_references};
+use crate::{error_handling_ctx, regex, util::replace_env_var_references};
 
-/// read an (s)css file, replace all environment variable references within it and
+/// A compiled stylesheet, as loaded from the configuration directory.
+pub struct Stylesheet {
+    pub file_id: usize,
+    pub css: String,
+    /// The eww variables referenced via `eww-var(name)`. The stylesheet needs to be recompiled whenever any of them change.
+    pub referenced_vars: HashSet<VarName>,
+    /// All files the stylesheet was compiled from, including any imported files.
+    pub files: HashSet<PathBuf>,
+}
+
+/// read an (s)css file, replace all environment variable references and `eww-var(name)` references within it and
 /// then parse it into css.
 /// Also adds the CSS to the [`crate::file_database::FileDatabase`]
-pub fn parse_scss_from_config(path: &Path) -> anyhow::Result<(usize, String)> {
+pub fn parse_scss_from_config(path: &Path, eww_vars: &HashMap<VarName, DynVal>) -> anyhow::Result<Stylesheet> {
     let css_file = path.join("eww.css");
     let scss_file = path.join("eww.scss");
     if css_file.exists() && scss_file.exists() {
         return Err(anyhow!("Encountered both an SCSS and CSS file. Only one of these may exist at a time"));
     }
 
+    let fs = EwwVarFs { eww_vars, referenced_vars: RefCell::new(HashSet::new()), files: RefCell::new(HashSet::new()) };
     let (s_css_path, css) = if css_file.exists() {
         let css_file_content = std::fs::read_to_string(&css_file)
             .with_context(|| format!("Given CSS file doesn't exist: {}", css_file.display()))?;
-        let css = replace_env_var_references(css_file_content);
+        let css = fs.replace_eww_var_references(&replace_env_var_references(css_file_content))?;
         (css_file, css)
     } else {
         let scss_file_content =
             std::fs::read_to_string(&scss_file).with_context(|| format!("Given SCSS file doesn't exist! {}", path.display()))?;
-        let file_content = replace_env_var_references(scss_file_content);
-        let grass_config = grass::Options::default().load_path(path);
+        let file_content = fs.replace_eww_var_references(&replace_env_var_references(scss_file_content))?;
+        let grass_config = grass::Options::default().load_path(path).fs(&fs);
         let css = grass::from_string(file_content, &grass_config).map_err(|err| anyhow!("SCSS parsing error: {}", err))?;
         (scss_file, css)
     };
+    finish_stylesheet(fs, s_css_path, css)
+}
+
+/// Read the `:stylesheet` of a window, given relative to the config directory, and parse it like [`parse_scss_from_config`].
+/// Files ending in `.css` are used as-is, everything else is compiled as scss.
+pub fn parse_window_stylesheet(
+    config_dir: &Path,
+    stylesheet: &str,
+    eww_vars: &HashMap<VarName, DynVal>,
+) -> anyhow::Result<Stylesheet> {
+    let stylesheet_path = config_dir.join(stylesheet);
+    let fs = EwwVarFs { eww_vars, referenced_vars: RefCell::new(HashSet::new()), files: RefCell::new(HashSet::new()) };
+    let content = std::fs::read_to_string(&stylesheet_path)
+        .with_context(|| format!("Failed to read stylesheet {}", stylesheet_path.display()))?;
+    let content = fs.replace_eww_var_references(&replace_env_var_references(content))?;
+    let css = if stylesheet_path.extension().is_some_and(|extension| extension == "css") {
+        content
+    } else {
+        let grass_config = grass::Options::default().load_path(config_dir).fs(&fs);
+        grass::from_string(content, &grass_config)
+            .map_err(|err| anyhow!("SCSS parsing error in {}: {}", stylesheet_path.display(), err))?
+    };
+    finish_stylesheet(fs, stylesheet_path, css)
+}
 
+/// Add the compiled css to the [`crate::file_database::FileDatabase`] and collect everything it was compiled from.
+fn finish_stylesheet(fs: EwwVarFs, s_css_path: PathBuf, css: String) -> anyhow::Result<Stylesheet> {
     let mut file_db = error_handling_ctx::FILE_DATABASE.write().unwrap();
     let file_id = file_db.insert_string(s_css_path.display().to_string(), css.clone())?;
-    Ok((file_id, css))
+    let mut files = fs.files.into_inner();
+    files.insert(std::fs::canonicalize(&s_css_path).unwrap_or(s_css_path));
+    Ok(Stylesheet { file_id, css, referenced_vars: fs.referenced_vars.into_inner(), files })
+}
+
+/// File system used by grass, which replaces `eww-var(name)` references in any imported files as well,
+/// and keeps track of the files that were imported.
+#[derive(Debug)]
+struct EwwVarFs<'a> {
+    eww_vars: &'a HashMap<VarName, DynVal>,
+    referenced_vars: RefCell<HashSet<VarName>>,
+    files: RefCell<HashSet<PathBuf>>,
+}
+
+impl EwwVarFs<'_> {
+    /// Replace all references of the format `eww-var(name)` with the current value of the eww variable `name`.
+    fn replace_eww_var_references(&self, input: &str) -> anyhow::Result<String> {
+        let mut unknown_vars = Vec::new();
+        let result = regex!(r"eww-var\(\s*([\w-]+)\s*\)").replace_all(input, |captures: &regex::Captures| {
+            let name = VarName::from(captures.get(1).unwrap().as_str());
+            let value = self.eww_vars.get(&name).map(|value| value.to_string());
+            match value {
+                Some(value) => {
+                    self.referenced_vars.borrow_mut().insert(name);
+                    value
+                }
+                None => {
+                    unknown_vars.push(name.to_string());
+                    String::new()
+                }
+            }
+        });
+        if !unknown_vars.is_empty() {
+            bail!("Unknown variable(s) referenced in stylesheet via eww-var: {}", unknown_vars.join(", "));
+        }
+        Ok(result.into_owned())
+    }
+}
+
+impl grass::Fs for EwwVarFs<'_> {
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        let content = std::fs::read_to_string(path)?;
+        self.files.borrow_mut().insert(std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()));
+        self.replace_eww_var_references(&content)
+            .map(String::into_bytes)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{}: {}", path.display(), err)))
+    }
 }
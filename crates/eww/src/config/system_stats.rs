@@ -1,7 +1,14 @@
 use crate::util::IterAverage;
 use anyhow::{Context, Result};
 use once_cell::sync::Lazy;
-use std::{fs::read_to_string, sync::Mutex};
+use std::{
+    collections::HashMap,
+    fs::read_to_string,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize},
+        Mutex,
+    },
+};
 use sysinfo::System;
 
 struct RefreshTime(std::time::Instant);
@@ -131,9 +138,14 @@ pub fn get_battery_capacity() -> Result<String> {
     ))
 }
 
+/// Whether `EWW_BATTERY` is read from UPower rather than sysfs, configured via `(defconfig :battery-source)`.
+pub static BATTERY_FROM_UPOWER: AtomicBool = AtomicBool::new(false);
+
 #[cfg(target_os = "linux")]
 pub fn get_battery_capacity() -> Result<String> {
-    use std::{collections::HashMap, sync::atomic::AtomicBool};
+    if BATTERY_FROM_UPOWER.load(std::sync::atomic::Ordering::Relaxed) {
+        return upower::get_batteries();
+    }
 
     #[derive(serde::Serialize)]
     struct BatteryData {
@@ -254,6 +266,143 @@ pub fn get_battery_capacity() -> Result<String> {
     Err(anyhow::anyhow!("Eww doesn't support your OS for getting the battery capacity"))
 }
 
+/// Battery information provided by UPower, which, unlike sysfs, includes estimates and peripherals such as wireless mice.
+#[cfg(target_os = "linux")]
+mod upower {
+    use anyhow::{anyhow, Result};
+    use once_cell::sync::Lazy;
+    use std::collections::HashMap;
+
+    const DEVICE_INTERFACE: &str = "org.freedesktop.UPower.Device";
+
+    static RUNTIME: Lazy<tokio::runtime::Runtime> = Lazy::new(|| {
+        tokio::runtime::Builder::new_current_thread().enable_all().build().expect("Failed to initialize tokio runtime")
+    });
+    static CONNECTION: tokio::sync::OnceCell<zbus::Connection> = tokio::sync::OnceCell::const_new();
+
+    #[derive(serde::Serialize)]
+    struct BatteryData {
+        capacity: i64,
+        status: &'static str,
+        #[serde(rename = "type")]
+        kind: &'static str,
+        model: String,
+        /// estimates in seconds, null if UPower doesn't know
+        time_to_empty: Option<i64>,
+        time_to_full: Option<i64>,
+        charge_start_threshold: Option<u32>,
+        charge_end_threshold: Option<u32>,
+    }
+
+    #[derive(serde::Serialize)]
+    struct Data {
+        #[serde(flatten)]
+        batteries: HashMap<String, BatteryData>,
+        total_avg: f64,
+    }
+
+    fn device_type_name(kind: u32) -> &'static str {
+        match kind {
+            2 => "battery",
+            3 => "ups",
+            4 => "monitor",
+            5 => "mouse",
+            6 => "keyboard",
+            7 => "pda",
+            8 => "phone",
+            9 => "media_player",
+            10 => "tablet",
+            11 => "computer",
+            12 => "gaming_input",
+            13 => "pen",
+            14 => "touchpad",
+            15 => "modem",
+            16 => "network",
+            17 => "headset",
+            18 => "speakers",
+            19 => "headphones",
+            _ => "unknown",
+        }
+    }
+
+    /// Translate the UPower device state into the status names used in sysfs.
+    fn state_name(state: u32) -> &'static str {
+        match state {
+            1 => "Charging",
+            2 | 6 => "Discharging",
+            3 => "Empty",
+            4 => "Full",
+            5 => "Not charging",
+            _ => "Unknown",
+        }
+    }
+
+    pub(super) fn get_batteries() -> Result<String> {
+        // this is called from within the runtime of the script var handler, which can't be blocked on from the inside
+        std::thread::scope(|scope| scope.spawn(|| RUNTIME.block_on(query_batteries())).join())
+            .map_err(|_| anyhow!("Querying UPower panicked"))?
+    }
+
+    async fn query_batteries() -> Result<String> {
+        let con = CONNECTION.get_or_try_init(zbus::Connection::system).await?;
+        let upower = zbus::Proxy::new(con, "org.freedesktop.UPower", "/org/freedesktop/UPower", "org.freedesktop.UPower").await?;
+        let devices: Vec<zbus::zvariant::OwnedObjectPath> = upower.call("EnumerateDevices", &()).await?;
+
+        let mut current = 0_f64;
+        let mut total = 0_f64;
+        let mut batteries = HashMap::new();
+        for device in devices {
+            let properties = zbus::fdo::PropertiesProxy::builder(con)
+                .destination("org.freedesktop.UPower")?
+                .path(device.as_ref())?
+                .build()
+                .await?
+                .get_all(zbus::names::InterfaceName::from_static_str_unchecked(DEVICE_INTERFACE))
+                .await?;
+            let get = |name: &str| properties.get(name).cloned();
+            let get_u32 = |name: &str| get(name).and_then(|x| u32::try_from(x).ok());
+            let get_f64 = |name: &str| get(name).and_then(|x| f64::try_from(x).ok());
+            let get_i64 = |name: &str| get(name).and_then(|x| i64::try_from(x).ok()).filter(|x| *x > 0);
+            let get_bool = |name: &str| get(name).and_then(|x| bool::try_from(x).ok()).unwrap_or(false);
+
+            let kind = get_u32("Type").unwrap_or(0);
+            // line power, i.e. the AC adapter, has no charge
+            if kind == 1 || !get_bool("IsPresent") {
+                continue;
+            }
+            let thresholds_supported = get_bool("ChargeThresholdSupported");
+            let name = get("NativePath")
+                .and_then(|x| String::try_from(x).ok())
+                .and_then(|path| path.rsplit('/').next().map(|x| x.to_string()))
+                .filter(|x| !x.is_empty())
+                .unwrap_or_else(|| device.as_str().rsplit('/').next().unwrap_or_default().to_string());
+
+            // only the batteries powering the system count towards the average, not those of peripherals
+            if get_bool("PowerSupply") {
+                if let (Some(energy), Some(energy_full)) = (get_f64("Energy"), get_f64("EnergyFull")) {
+                    current += energy;
+                    total += energy_full;
+                }
+            }
+            batteries.insert(
+                name,
+                BatteryData {
+                    capacity: get_f64("Percentage").unwrap_or(0.).round() as i64,
+                    status: state_name(get_u32("State").unwrap_or(0)),
+                    kind: device_type_name(kind),
+                    model: get("Model").and_then(|x| String::try_from(x).ok()).unwrap_or_default(),
+                    time_to_empty: get_i64("TimeToEmpty"),
+                    time_to_full: get_i64("TimeToFull"),
+                    charge_start_threshold: get_u32("ChargeStartThreshold").filter(|_| thresholds_supported),
+                    charge_end_threshold: get_u32("ChargeEndThreshold").filter(|_| thresholds_supported),
+                },
+            );
+        }
+        let total_avg = if total == 0_f64 { 0_f64 } else { (current / total) * 100_f64 };
+        Ok(serde_json::to_string(&Data { batteries, total_avg })?)
+    }
+}
+
 pub fn net() -> String {
     let (ref mut last_refresh, ref mut networks) = &mut *NETWORKS.lock().unwrap();
 
@@ -271,6 +420,312 @@ pub fn net() -> String {
         .to_string()
 }
 
+/// Number of processes listed in `EWW_TOP`, configured via `(defconfig :top-processes)`.
+pub static TOP_PROCESS_COUNT: AtomicUsize = AtomicUsize::new(10);
+
+/// CPU time counters from the previous `EWW_TOP` refresh, used to compute the usage since then.
+#[cfg(target_os = "linux")]
+#[derive(Default)]
+struct ProcessTimes {
+    total_ticks: u64,
+    process_ticks: HashMap<u32, u64>,
+}
+
+#[cfg(target_os = "linux")]
+static PROCESS_TIMES: Lazy<Mutex<ProcessTimes>> = Lazy::new(Default::default);
+
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, serde::Serialize)]
+struct TopProcess {
+    pid: u32,
+    name: String,
+    cpu: f64,
+    mem: u64,
+    mem_perc: f64,
+}
+
+/// The relevant fields of `/proc/<pid>/stat`: the name, the cpu ticks spent in user and kernel mode, and the resident set size in pages.
+#[cfg(target_os = "linux")]
+fn parse_process_stat(stat: &str) -> Option<(String, u64, u64)> {
+    // the name is wrapped in parentheses, and may itself contain spaces and parentheses
+    let name_start = stat.find('(')?;
+    let name_end = stat.rfind(')')?;
+    let name = stat.get(name_start + 1..name_end)?.to_string();
+    let fields: Vec<&str> = stat.get(name_end + 1..)?.split_whitespace().collect();
+    // fields are counted from the state, which is field 3 in proc(5)
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    let rss: u64 = fields.get(21)?.parse().ok()?;
+    Some((name, utime + stime, rss))
+}
+
+#[cfg(target_os = "linux")]
+pub fn get_top_processes() -> Result<String> {
+    let stat = read_to_string("/proc/stat").context("Couldn't read /proc/stat")?;
+    // guest time is already included in user time, so only the first eight fields are summed up
+    let total_ticks: u64 = stat
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("cpu "))
+        .context("Unexpected format of /proc/stat")?
+        .split_whitespace()
+        .take(8)
+        .filter_map(|x| x.parse::<u64>().ok())
+        .sum();
+
+    let meminfo = read_to_string("/proc/meminfo").context("Couldn't read /proc/meminfo")?;
+    let total_mem = meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemTotal:"))
+        .and_then(|x| x.trim().trim_end_matches("kB").trim().parse::<u64>().ok())
+        .context("Unexpected format of /proc/meminfo")?
+        * 1024;
+
+    let page_size = match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        x if x > 0 => x as u64,
+        _ => 4096,
+    };
+
+    let mut last = PROCESS_TIMES.lock().unwrap();
+    let elapsed_ticks = total_ticks.saturating_sub(last.total_ticks);
+    let mut process_ticks = HashMap::new();
+    let mut processes = Vec::new();
+    for entry in std::fs::read_dir("/proc").context("Couldn't read /proc")?.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|x| x.parse::<u32>().ok()) else {
+            continue;
+        };
+        // processes may exit while we're iterating over them
+        let Some((name, ticks, rss)) = read_to_string(entry.path().join("stat")).ok().and_then(|x| parse_process_stat(&x)) else {
+            continue;
+        };
+        // processes that weren't there during the last refresh have no usage yet
+        let cpu = match last.process_ticks.get(&pid) {
+            Some(previous) if elapsed_ticks > 0 => ticks.saturating_sub(*previous) as f64 / elapsed_ticks as f64 * 100.,
+            _ => 0.,
+        };
+        process_ticks.insert(pid, ticks);
+        let mem = rss * page_size;
+        processes.push(TopProcess { pid, name, cpu, mem, mem_perc: mem as f64 / total_mem as f64 * 100. });
+    }
+    *last = ProcessTimes { total_ticks, process_ticks };
+
+    let count = TOP_PROCESS_COUNT.load(std::sync::atomic::Ordering::Relaxed);
+    let mut by_cpu = processes.clone();
+    by_cpu.sort_by(|a, b| b.cpu.total_cmp(&a.cpu));
+    by_cpu.truncate(count);
+    processes.sort_by(|a, b| b.mem.cmp(&a.mem));
+    processes.truncate(count);
+    Ok(serde_json::json!({ "cpu": by_cpu, "mem": processes }).to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_top_processes() -> Result<String> {
+    Err(anyhow::anyhow!("Eww doesn't support your OS for getting the top processes"))
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Debug, Default, serde::Serialize)]
+struct GpuInfo {
+    vendor: &'static str,
+    name: Option<String>,
+    /// utilization in percent
+    usage: Option<f64>,
+    vram_total: Option<u64>,
+    vram_used: Option<u64>,
+    vram_used_perc: Option<f64>,
+    /// temperature in degree Celsius
+    temp: Option<f64>,
+    /// current clock speed in MHz
+    freq: Option<u64>,
+}
+
+#[cfg(target_os = "linux")]
+fn read_sysfs<T: std::str::FromStr>(path: impl AsRef<std::path::Path>) -> Option<T> {
+    read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Read the information on a GPU exposed by the amdgpu or i915 driver in `/sys/class/drm/<card>/`.
+#[cfg(target_os = "linux")]
+fn read_drm_gpu(card: &std::path::Path) -> Option<GpuInfo> {
+    let device = card.join("device");
+    let vendor = match read_to_string(device.join("vendor")).ok()?.trim() {
+        "0x1002" => "amd",
+        "0x8086" => "intel",
+        // NVIDIA GPUs are handled via NVML
+        _ => return None,
+    };
+    // the first temperature sensor of the GPU, in millidegrees
+    let temp = std::fs::read_dir(device.join("hwmon"))
+        .ok()
+        .and_then(|mut dirs| dirs.find_map(|dir| read_sysfs::<f64>(dir.ok()?.path().join("temp1_input"))))
+        .map(|x| x / 1000.);
+    let mut info = GpuInfo { vendor, name: read_sysfs(device.join("product_name")), temp, ..Default::default() };
+    if vendor == "amd" {
+        info.usage = read_sysfs(device.join("gpu_busy_percent"));
+        info.vram_total = read_sysfs(device.join("mem_info_vram_total"));
+        info.vram_used = read_sysfs(device.join("mem_info_vram_used"));
+        info.freq = read_to_string(device.join("pp_dpm_sclk"))
+            .ok()
+            .and_then(|levels| levels.lines().find(|line| line.ends_with('*')).map(|line| line.to_string()))
+            .and_then(|line| line.split_whitespace().nth(1)?.trim_end_matches("Mhz").parse().ok());
+    } else {
+        // i915 doesn't expose the utilization in sysfs, only the clock speed
+        info.freq = read_sysfs(card.join("gt_act_freq_mhz")).or_else(|| read_sysfs(card.join("gt_cur_freq_mhz")));
+    }
+    if let (Some(total), Some(used)) = (info.vram_total, info.vram_used) {
+        info.vram_used_perc = Some(used as f64 / total as f64 * 100.);
+    }
+    Some(info)
+}
+
+#[cfg(target_os = "linux")]
+pub fn get_gpus() -> String {
+    let mut gpus = serde_json::Map::new();
+    if let Ok(cards) = std::fs::read_dir("/sys/class/drm") {
+        for card in cards.flatten() {
+            let name = card.file_name().to_string_lossy().into_owned();
+            // connectors, such as card0-DP-1, are listed next to the cards themselves
+            let is_card = name.strip_prefix("card").map_or(false, |x| !x.is_empty() && x.chars().all(|c| c.is_ascii_digit()));
+            if let Some(info) = is_card.then(|| read_drm_gpu(&card.path())).flatten() {
+                gpus.insert(name, serde_json::json!(info));
+            }
+        }
+    }
+    for (index, info) in nvml::get_gpus().into_iter().enumerate() {
+        gpus.insert(format!("nvidia{}", index), serde_json::json!(info));
+    }
+    serde_json::Value::Object(gpus).to_string()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_gpus() -> String {
+    "{}".to_string()
+}
+
+/// Minimal bindings to NVML, which is loaded at runtime, such that eww doesn't depend on the NVIDIA driver being installed.
+#[cfg(target_os = "linux")]
+mod nvml {
+    use super::GpuInfo;
+    use once_cell::sync::Lazy;
+    use std::ffi::{c_char, c_int, c_uint, c_void, CStr};
+
+    type Device = *mut c_void;
+    type InitFn = unsafe extern "C" fn() -> c_int;
+    type GetCountFn = unsafe extern "C" fn(*mut c_uint) -> c_int;
+    type GetHandleFn = unsafe extern "C" fn(c_uint, *mut Device) -> c_int;
+    type GetNameFn = unsafe extern "C" fn(Device, *mut c_char, c_uint) -> c_int;
+    type GetUtilizationFn = unsafe extern "C" fn(Device, *mut Utilization) -> c_int;
+    type GetMemoryFn = unsafe extern "C" fn(Device, *mut Memory) -> c_int;
+    type GetTemperatureFn = unsafe extern "C" fn(Device, c_uint, *mut c_uint) -> c_int;
+    type GetClockFn = unsafe extern "C" fn(Device, c_uint, *mut c_uint) -> c_int;
+
+    const SUCCESS: c_int = 0;
+    const TEMPERATURE_GPU: c_uint = 0;
+    const CLOCK_GRAPHICS: c_uint = 0;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct Utilization {
+        gpu: c_uint,
+        memory: c_uint,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct Memory {
+        total: u64,
+        free: u64,
+        used: u64,
+    }
+
+    struct Nvml {
+        get_count: GetCountFn,
+        get_handle: GetHandleFn,
+        get_name: GetNameFn,
+        get_utilization: GetUtilizationFn,
+        get_memory: GetMemoryFn,
+        get_temperature: GetTemperatureFn,
+        get_clock: GetClockFn,
+    }
+
+    static NVML: Lazy<Option<Nvml>> = Lazy::new(|| unsafe { load() });
+
+    /// Load and initialize NVML. The library is never unloaded.
+    unsafe fn load() -> Option<Nvml> {
+        let lib = libc::dlopen(c"libnvidia-ml.so.1".as_ptr(), libc::RTLD_NOW);
+        if lib.is_null() {
+            return None;
+        }
+        macro_rules! symbol {
+            ($name:literal as $t:ty) => {{
+                let symbol = libc::dlsym(lib, $name.as_ptr());
+                if symbol.is_null() {
+                    log::warn!("NVML is missing the symbol {:?}", $name);
+                    return None;
+                }
+                std::mem::transmute::<*mut c_void, $t>(symbol)
+            }};
+        }
+        let init = symbol!(c"nvmlInit_v2" as InitFn);
+        if init() != SUCCESS {
+            log::warn!("Failed to initialize NVML");
+            return None;
+        }
+        Some(Nvml {
+            get_count: symbol!(c"nvmlDeviceGetCount_v2" as GetCountFn),
+            get_handle: symbol!(c"nvmlDeviceGetHandleByIndex_v2" as GetHandleFn),
+            get_name: symbol!(c"nvmlDeviceGetName" as GetNameFn),
+            get_utilization: symbol!(c"nvmlDeviceGetUtilizationRates" as GetUtilizationFn),
+            get_memory: symbol!(c"nvmlDeviceGetMemoryInfo" as GetMemoryFn),
+            get_temperature: symbol!(c"nvmlDeviceGetTemperature" as GetTemperatureFn),
+            get_clock: symbol!(c"nvmlDeviceGetClockInfo" as GetClockFn),
+        })
+    }
+
+    pub(super) fn get_gpus() -> Vec<GpuInfo> {
+        let Some(nvml) = NVML.as_ref() else {
+            return Vec::new();
+        };
+        let mut count = 0;
+        if unsafe { (nvml.get_count)(&mut count) } != SUCCESS {
+            return Vec::new();
+        }
+        (0..count)
+            .filter_map(|index| {
+                let mut device: Device = std::ptr::null_mut();
+                if unsafe { (nvml.get_handle)(index, &mut device) } != SUCCESS {
+                    return None;
+                }
+                let mut info = GpuInfo { vendor: "nvidia", ..Default::default() };
+
+                let mut name = [0 as c_char; 96];
+                if unsafe { (nvml.get_name)(device, name.as_mut_ptr(), name.len() as c_uint) } == SUCCESS {
+                    info.name = Some(unsafe { CStr::from_ptr(name.as_ptr()) }.to_string_lossy().into_owned());
+                }
+                let mut utilization = Utilization::default();
+                if unsafe { (nvml.get_utilization)(device, &mut utilization) } == SUCCESS {
+                    info.usage = Some(utilization.gpu as f64);
+                }
+                let mut memory = Memory::default();
+                if unsafe { (nvml.get_memory)(device, &mut memory) } == SUCCESS {
+                    info.vram_total = Some(memory.total);
+                    info.vram_used = Some(memory.used);
+                    info.vram_used_perc = Some(memory.used as f64 / memory.total as f64 * 100.);
+                }
+                let mut temp = 0;
+                if unsafe { (nvml.get_temperature)(device, TEMPERATURE_GPU, &mut temp) } == SUCCESS {
+                    info.temp = Some(temp as f64);
+                }
+                let mut freq = 0;
+                if unsafe { (nvml.get_clock)(device, CLOCK_GRAPHICS, &mut freq) } == SUCCESS {
+                    info.freq = Some(freq as u64);
+                }
+                Some(info)
+            })
+            .collect()
+    }
+}
+
 pub fn get_time() -> String {
     chrono::offset::Utc::now().timestamp().to_string()
 }
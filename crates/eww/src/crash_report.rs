@@ -0,0 +1,125 @@
+//! Collects information about the state of the daemon, which gets written to a crash report file
+//! when eww panics or GTK emits a critical warning.
+
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    rc::{Rc, Weak},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use anyhow::Result;
+use eww_shared_util::VarName;
+use once_cell::sync::Lazy;
+use simplexpr::dynval::DynVal;
+
+use crate::state::scope_graph::ScopeGraph;
+
+/// Amount of variable updates that are kept around to be included in crash reports.
+const RECENT_VAR_UPDATES_CAPACITY: usize = 50;
+
+/// Ring buffer of the most recent global variable updates, oldest first.
+static RECENT_VAR_UPDATES: Lazy<Mutex<VecDeque<String>>> =
+    Lazy::new(|| Mutex::new(VecDeque::with_capacity(RECENT_VAR_UPDATES_CAPACITY)));
+
+/// Instance ids and window names of the currently open windows.
+static OPEN_WINDOWS: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Only the first GTK critical of a daemon run results in a crash report, to avoid flooding the log directory.
+static WROTE_CRITICAL_REPORT: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    /// The scope graph is only accessible from the main thread, which is where GTK runs.
+    static SCOPE_GRAPH: RefCell<Weak<RefCell<ScopeGraph>>> = const { RefCell::new(Weak::new()) };
+}
+
+/// Install the panic hook and the GTK log handler that write crash reports into the given directory.
+pub fn install(log_dir: &Path) {
+    let log_dir = log_dir.to_path_buf();
+
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(gtk::glib::clone!(@strong log_dir => move |info| {
+        write_report_logging_errors(&log_dir, &format!("eww panicked: {}", info));
+        default_hook(info);
+    })));
+
+    for domain in ["Gtk", "Gdk", "GLib-GObject"] {
+        gtk::glib::log_set_handler(
+            Some(domain),
+            gtk::glib::LogLevels::LEVEL_CRITICAL,
+            false,
+            false,
+            gtk::glib::clone!(@strong log_dir => move |domain, _, message| {
+                let domain = domain.unwrap_or("unknown");
+                log::error!("{} critical: {}", domain, message);
+                if !WROTE_CRITICAL_REPORT.swap(true, Ordering::SeqCst) {
+                    write_report_logging_errors(&log_dir, &format!("{} critical: {}", domain, message));
+                }
+            }),
+        );
+    }
+}
+
+/// Register the scope graph, such that its structure can be included in crash reports.
+pub fn set_scope_graph(scope_graph: &Rc<RefCell<ScopeGraph>>) {
+    SCOPE_GRAPH.with(|graph| *graph.borrow_mut() = Rc::downgrade(scope_graph));
+}
+
+pub fn set_open_windows(windows: Vec<String>) {
+    *OPEN_WINDOWS.lock().unwrap() = windows;
+}
+
+pub fn record_var_update(name: &VarName, value: &DynVal) {
+    let mut updates = RECENT_VAR_UPDATES.lock().unwrap();
+    if updates.len() >= RECENT_VAR_UPDATES_CAPACITY {
+        updates.pop_front();
+    }
+    updates.push_back(format!("[{}] {} = {}", chrono::Local::now().format("%H:%M:%S%.3f"), name, value));
+}
+
+fn write_report_logging_errors(log_dir: &Path, reason: &str) {
+    match write_report(log_dir, reason) {
+        Ok(path) => eprintln!("Wrote crash report to {}", path.display()),
+        Err(err) => eprintln!("Failed to write crash report: {:?}", err),
+    }
+}
+
+fn write_report(log_dir: &Path, reason: &str) -> Result<PathBuf> {
+    let now = chrono::Local::now();
+    let mut report = String::new();
+    writeln!(report, "eww {} crash report", env!("CARGO_PKG_VERSION"))?;
+    writeln!(report, "time: {}", now.to_rfc3339())?;
+    writeln!(report, "reason: {}", reason)?;
+    writeln!(report, "GDK_BACKEND: {}", std::env::var("GDK_BACKEND").unwrap_or_default())?;
+    writeln!(report, "GTK_IM_MODULE: {}", std::env::var("GTK_IM_MODULE").unwrap_or_default())?;
+
+    writeln!(report, "\n== Open windows ==")?;
+    // try_lock, as the report might be written while one of these locks is held
+    match OPEN_WINDOWS.try_lock() {
+        Ok(windows) => windows.iter().try_for_each(|window| writeln!(report, "{}", window))?,
+        Err(_) => writeln!(report, "<unavailable>")?,
+    }
+
+    writeln!(report, "\n== Recent variable updates ==")?;
+    match RECENT_VAR_UPDATES.try_lock() {
+        Ok(updates) => updates.iter().try_for_each(|update| writeln!(report, "{}", update))?,
+        Err(_) => writeln!(report, "<unavailable>")?,
+    }
+
+    writeln!(report, "\n== Scope graph ==")?;
+    let scope_graph = SCOPE_GRAPH
+        .try_with(|graph| graph.borrow().upgrade())
+        .ok()
+        .flatten()
+        .and_then(|graph| graph.try_borrow().ok().map(|graph| graph.visualize()));
+    writeln!(report, "{}", scope_graph.as_deref().unwrap_or("<unavailable>"))?;
+
+    let path = log_dir.join(format!("eww_crash_{}.log", now.format("%Y-%m-%d_%H-%M-%S")));
+    std::fs::write(&path, report)?;
+    Ok(path)
+}
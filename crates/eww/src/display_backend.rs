@@ -13,6 +13,27 @@ pub trait DisplayBackend: Send + Sync + 'static {
     const IS_WAYLAND: bool;
 
     fn initialize_window(window_init: &WindowInitiator, monitor: gdk::Rectangle, x: i32, y: i32) -> Option<Window>;
+
+    /// Ask the compositor or window manager to give keyboard focus to the given window.
+    fn focus_window(window: &Window) {
+        use gtk::prelude::*;
+        window.present();
+    }
+
+    /// Place the given window above all other windows on the same layer.
+    fn raise_window(window: &Window) {
+        use gtk::prelude::*;
+        if let Some(gdk_window) = window.window() {
+            gdk_window.raise();
+        }
+    }
+
+    /// Get the position of the given window on the display, given the geometry of the monitor it is on.
+    fn window_origin(window: &Window, _monitor: gdk::Rectangle) -> Option<(i32, i32)> {
+        use gtk::prelude::*;
+        let (_, x, y) = window.window()?.origin();
+        Some((x, y))
+    }
 }
 
 pub struct NoBackend;
@@ -31,13 +52,18 @@ mod platform_wayland {
     use super::DisplayBackend;
     use crate::{widgets::window::Window, window_initiator::WindowInitiator};
     use gtk::gdk;
+    use gtk::glib;
     use gtk::prelude::*;
-    use gtk_layer_shell::{KeyboardMode, LayerShell};
-    use yuck::config::backend_window_options::WlWindowFocusable;
+    use gtk_layer_shell::{Edge, KeyboardMode, LayerShell};
+    use std::{cell::Cell, rc::Rc, time::Duration};
+    use yuck::config::backend_window_options::{WlExclusiveZone, WlWindowFocusable};
     use yuck::config::{window_definition::WindowStacking, window_geometry::AnchorAlignment};
 
     pub struct WaylandBackend;
 
+    /// How long `focus_window` waits for the compositor to focus a surface before restoring its keyboard mode.
+    const FOCUS_TIMEOUT: Duration = Duration::from_secs(1);
+
     impl DisplayBackend for WaylandBackend {
         const IS_X11: bool = false;
         const IS_WAYLAND: bool = true;
@@ -71,6 +97,8 @@ mod platform_wayland {
 
             // Sets the keyboard interactivity
             match window_init.backend_options.wayland.focusable {
+                // closing on unfocus relies on the window being able to receive keyboard focus
+                WlWindowFocusable::None if window_init.close_on_unfocus => window.set_keyboard_mode(KeyboardMode::OnDemand),
                 WlWindowFocusable::None => window.set_keyboard_mode(KeyboardMode::None),
                 WlWindowFocusable::Exclusive => window.set_keyboard_mode(KeyboardMode::Exclusive),
                 WlWindowFocusable::OnDemand => window.set_keyboard_mode(KeyboardMode::OnDemand),
@@ -113,11 +141,76 @@ mod platform_wayland {
                     window.set_layer_shell_margin(gtk_layer_shell::Edge::Top, yoffset);
                 }
             }
-            if window_init.backend_options.wayland.exclusive {
-                window.auto_exclusive_zone_enable();
+            match window_init.backend_options.wayland.exclusive {
+                WlExclusiveZone::None => {}
+                WlExclusiveZone::Auto => window.auto_exclusive_zone_enable(),
+                WlExclusiveZone::Pixels(pixels) => window.set_exclusive_zone(pixels),
+                WlExclusiveZone::Ignore => window.set_exclusive_zone(-1),
             }
             Some(window)
         }
+
+        fn focus_window(window: &Window) {
+            // Layer shell surfaces can't request focus, so `present` alone doesn't focus them.
+            // Instead, the surface briefly takes exclusive keyboard focus, and then switches to on-demand focus such that
+            // the user can still focus other windows. Once it loses focus again, its configured keyboard mode is restored.
+            let previous_mode = window.keyboard_mode();
+            if previous_mode == KeyboardMode::Exclusive {
+                window.present();
+                return;
+            }
+            window.set_keyboard_mode(KeyboardMode::Exclusive);
+            let focus_in_handler: Rc<Cell<Option<glib::SignalHandlerId>>> = Rc::default();
+            let handler_id = window.connect_focus_in_event(glib::clone!(@strong focus_in_handler => move |window, _| {
+                if let Some(handler_id) = focus_in_handler.take() {
+                    window.disconnect(handler_id);
+                }
+                window.set_keyboard_mode(KeyboardMode::OnDemand);
+                let focus_out_handler: Rc<Cell<Option<glib::SignalHandlerId>>> = Rc::default();
+                let handler_id = window.connect_focus_out_event(glib::clone!(@strong focus_out_handler => move |window, _| {
+                    if let Some(handler_id) = focus_out_handler.take() {
+                        window.disconnect(handler_id);
+                    }
+                    window.set_keyboard_mode(previous_mode);
+                    glib::Propagation::Proceed
+                }));
+                focus_out_handler.set(Some(handler_id));
+                glib::Propagation::Proceed
+            }));
+            focus_in_handler.set(Some(handler_id));
+            // if the compositor doesn't focus the surface, i.e. because it is in a layer that can't be focused, don't keep it exclusive
+            glib::timeout_add_local_once(
+                FOCUS_TIMEOUT,
+                glib::clone!(@weak window => move || {
+                    if let Some(handler_id) = focus_in_handler.take() {
+                        window.disconnect(handler_id);
+                        window.set_keyboard_mode(previous_mode);
+                    }
+                }),
+            );
+            window.present();
+        }
+
+        fn window_origin(window: &Window, monitor: gdk::Rectangle) -> Option<(i32, i32)> {
+            // Wayland doesn't tell clients where their surfaces are, so this derives it from the anchors and margins.
+            // Space reserved by other surfaces is not accounted for.
+            if !window.is_layer_window() {
+                return None;
+            }
+            let (width, height) = window.size();
+            let position = |start: Edge, end: Edge, monitor_start: i32, monitor_size: i32, size: i32| match (
+                window.is_anchor(start),
+                window.is_anchor(end),
+            ) {
+                (true, _) => monitor_start + window.layer_shell_margin(start),
+                (false, true) => monitor_start + monitor_size - size - window.layer_shell_margin(end),
+                (false, false) => monitor_start + (monitor_size - size) / 2,
+            };
+            Some((
+                position(Edge::Left, Edge::Right, monitor.x(), monitor.width(), width),
+                position(Edge::Top, Edge::Bottom, monitor.y(), monitor.height(), height),
+            ))
+        }
     }
 }
 
@@ -153,15 +246,25 @@ mod platform_x11 {
                 if window_init.backend_options.x11.wm_ignore { gtk::WindowType::Popup } else { gtk::WindowType::Toplevel };
             let window = Window::new(window_type, x, y);
             window.set_resizable(window_init.resizable);
-            window.set_keep_above(window_init.stacking == WindowStacking::Foreground);
-            window.set_keep_below(window_init.stacking == WindowStacking::Background);
-            if window_init.backend_options.x11.sticky {
+            let x11_options = &window_init.backend_options.x11;
+            window.set_keep_above(x11_options.above.unwrap_or(window_init.stacking == WindowStacking::Foreground));
+            window.set_keep_below(x11_options.below.unwrap_or(window_init.stacking == WindowStacking::Background));
+            window.set_urgency_hint(x11_options.demands_attention);
+            if x11_options.sticky {
                 window.stick();
             } else {
                 window.unstick();
             }
             Some(window)
         }
+
+        fn focus_window(window: &Window) {
+            window.present();
+            // windows ignored by the window manager are never focused by it, so focus them directly
+            if let Some(gdk_window) = window.window() {
+                gdk_window.focus(gtk::current_event_time());
+            }
+        }
     }
 
     pub fn set_xprops(window: &Window, monitor: Monitor, window_init: &WindowInitiator) -> Result<()> {
@@ -1,29 +1,84 @@
 //! Disgusting global state.
 //! I hate this, but [buffet](https://github.com/buffet) told me that this is what I should do for peak maintainability!
 
-use std::sync::{Arc, RwLock};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, RwLock},
+};
 
 use codespan_reporting::{
-    diagnostic::Diagnostic,
+    diagnostic::{Diagnostic, LabelStyle},
     term::{self, Chars},
 };
 use eww_shared_util::Span;
 use once_cell::sync::Lazy;
 use simplexpr::{dynval::ConversionError, eval::EvalError};
+use tokio::sync::broadcast;
 use yuck::{config::validate::ValidationError, error::DiagError, format_diagnostic::ToDiagnostic};
 
 use crate::file_database::FileDatabase;
 
 pub static FILE_DATABASE: Lazy<Arc<RwLock<FileDatabase>>> = Lazy::new(|| Arc::new(RwLock::new(FileDatabase::new())));
 
+/// Amount of errors that are kept around to be exposed via the `EWW_ERRORS` magic variable.
+const RECENT_ERRORS_CAPACITY: usize = 20;
+
+/// Ring buffer of the most recently printed errors, oldest first.
+static RECENT_ERRORS: Lazy<Mutex<VecDeque<serde_json::Value>>> =
+    Lazy::new(|| Mutex::new(VecDeque::with_capacity(RECENT_ERRORS_CAPACITY)));
+
+/// Errors printed via [`print_error`], formatted without colors, such that they can be shown in the error overlay.
+static PRINTED_ERRORS_SENDER: Lazy<broadcast::Sender<String>> = Lazy::new(|| broadcast::channel(16).0);
+
+/// Receive all errors that are printed via [`print_error`] from now on.
+pub fn subscribe_printed_errors() -> broadcast::Receiver<String> {
+    PRINTED_ERRORS_SENDER.subscribe()
+}
+
 pub fn clear_files() {
     *FILE_DATABASE.write().unwrap() = FileDatabase::new();
 }
 
+/// Get the most recently printed errors as a json array of `{ time, message }` objects, oldest first.
+pub fn get_recent_errors() -> serde_json::Value {
+    serde_json::Value::Array(RECENT_ERRORS.lock().unwrap().iter().cloned().collect())
+}
+
+fn record_error(err: &anyhow::Error) {
+    let message = match anyhow_err_to_diagnostic(err) {
+        Some(diag) => diag.message,
+        None => format!("{:#}", err),
+    };
+    let mut recent_errors = RECENT_ERRORS.lock().unwrap();
+    if recent_errors.len() >= RECENT_ERRORS_CAPACITY {
+        recent_errors.pop_front();
+    }
+    recent_errors.push_back(serde_json::json!({
+        "time": chrono::offset::Utc::now().timestamp(),
+        "message": message,
+    }));
+}
+
 pub fn print_error(err: anyhow::Error) {
+    record_error(&err);
+    // there not being anyone listening is fine
+    let _ = PRINTED_ERRORS_SENDER.send(format_error_plain(&err));
     match anyhow_err_to_diagnostic(&err) {
-        Some(diag) => match stringify_diagnostic(diag) {
-            Ok(diag) => eprintln!("{}", diag),
+        Some(diag) => match stringify_diagnostic(diag.clone()) {
+            Ok(formatted) => {
+                eprintln!("{}", formatted);
+                // diagnostics don't go through the logger, so they need to be added to the structured logs separately
+                let location = diag
+                    .labels
+                    .iter()
+                    .find(|label| label.style == LabelStyle::Primary)
+                    .map(|label| format_span_location(Span(label.range.start, label.range.end, label.file_id)));
+                let message = match location {
+                    Some(location) => format!("{} ({})", diag.message, location),
+                    None => diag.message,
+                };
+                crate::logging::write_structured_record(log::Level::Error, "eww", &message);
+            }
             Err(_) => log::error!("{:?}", err),
         },
         None => log::error!("{:?}", err),
@@ -34,6 +89,17 @@ pub fn format_error(err: &anyhow::Error) -> String {
     anyhow_err_to_diagnostic(err).and_then(|diag| stringify_diagnostic(diag).ok()).unwrap_or_else(|| format!("{:?}", err))
 }
 
+/// Like [`format_error`], but without any terminal colors.
+pub fn format_error_plain(err: &anyhow::Error) -> String {
+    anyhow_err_to_diagnostic(err)
+        .and_then(|diag| {
+            let mut buf = Vec::new();
+            emit_diagnostic(&mut term::termcolor::NoColor::new(&mut buf), diag).ok()?;
+            String::from_utf8(buf).ok()
+        })
+        .unwrap_or_else(|| format!("{:?}", err))
+}
+
 pub fn anyhow_err_to_diagnostic(err: &anyhow::Error) -> Option<Diagnostic<usize>> {
     #[allow(clippy::manual_map)]
     if let Some(err) = err.downcast_ref::<DiagError>() {
@@ -49,7 +115,16 @@ pub fn anyhow_err_to_diagnostic(err: &anyhow::Error) -> Option<Diagnostic<usize>
     }
 }
 
-pub fn stringify_diagnostic(mut diagnostic: codespan_reporting::diagnostic::Diagnostic<usize>) -> anyhow::Result<String> {
+pub fn stringify_diagnostic(diagnostic: codespan_reporting::diagnostic::Diagnostic<usize>) -> anyhow::Result<String> {
+    let mut buf = Vec::new();
+    emit_diagnostic(&mut term::termcolor::Ansi::new(&mut buf), diagnostic)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+fn emit_diagnostic(
+    writer: &mut dyn term::termcolor::WriteColor,
+    mut diagnostic: codespan_reporting::diagnostic::Diagnostic<usize>,
+) -> anyhow::Result<()> {
     diagnostic.labels.retain(|label| !Span(label.range.start, label.range.end, label.file_id).is_dummy());
 
     let mut config = term::Config::default();
@@ -57,9 +132,17 @@ pub fn stringify_diagnostic(mut diagnostic: codespan_reporting::diagnostic::Diag
     chars.single_primary_caret = '─';
     config.chars = chars;
     config.chars.note_bullet = '→';
-    let mut buf = Vec::new();
-    let mut writer = term::termcolor::Ansi::new(&mut buf);
     let files = FILE_DATABASE.read().unwrap();
-    term::emit(&mut writer, &config, &*files, &diagnostic)?;
-    Ok(String::from_utf8(buf)?)
+    term::emit(writer, &config, &*files, &diagnostic)?;
+    Ok(())
+}
+
+/// Format the location of a span as `file:line:column`, falling back to the raw span if the file is unknown.
+pub fn format_span_location(span: Span) -> String {
+    use codespan_reporting::files::Files;
+    let files = FILE_DATABASE.read().unwrap();
+    match (files.name(span.2), files.location(span.2, span.0)) {
+        (Ok(name), Ok(location)) => format!("{}:{}:{}", name, location.line_number, location.column_number),
+        _ => span.to_string(),
+    }
 }
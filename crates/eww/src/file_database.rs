@@ -30,6 +30,14 @@ impl FileDatabase {
         file_id
     }
 
+    /// Paths of all files that were loaded from disk.
+    pub fn file_paths(&self) -> impl Iterator<Item = &std::path::Path> {
+        self.files.values().filter_map(|file| match &file.source {
+            CodeSource::File(path) => Some(path.as_path()),
+            CodeSource::Literal(_) => None,
+        })
+    }
+
     pub fn insert_string(&mut self, name: String, content: String) -> Result<usize, DiagError> {
         let line_starts = codespan_reporting::files::line_starts(&content).collect();
         let code_file = CodeFile { name, line_starts, source_len_bytes: content.len(), source: CodeSource::Literal(content) };
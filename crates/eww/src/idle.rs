@@ -0,0 +1,159 @@
+//! Time since the last user input, backing the `EWW_IDLE_SECONDS` magic variable and `(defwindow :hide-when-idle)`.
+//!
+//! On Wayland, this uses the ext-idle-notify protocol.
+//! On X11, the XScreenSaver extension is queried.
+
+use anyhow::{Context, Result};
+use tokio::io::AsyncWriteExt;
+
+pub const IDLE_VAR_NAME: &str = "EWW_IDLE_SECONDS";
+
+/// Write the amount of seconds since the last user input as a line whenever it changes, until writing fails.
+pub async fn write_state_updates<W: tokio::io::AsyncWrite + Unpin>(mut writer: W) -> Result<()> {
+    let (sender, mut receiver) = tokio::sync::mpsc::channel(1);
+    let watcher = async move {
+        #[cfg(feature = "wayland")]
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            return wayland::watch_idle_seconds(sender).await;
+        }
+        #[cfg(feature = "x11")]
+        if std::env::var_os("DISPLAY").is_some() {
+            return tokio::task::spawn_blocking(move || x11::watch_idle_seconds(sender)).await?;
+        }
+        log::warn!("{} is only supported on Wayland compositors implementing ext-idle-notify and X11", IDLE_VAR_NAME);
+        std::future::pending().await
+    };
+    let forward = async move {
+        let mut last = None;
+        while let Some(seconds) = receiver.recv().await {
+            if last != Some(seconds) {
+                writer.write_all(format!("{}\n", seconds).as_bytes()).await?;
+                writer.flush().await?;
+                last = Some(seconds);
+            }
+        }
+        Ok(())
+    };
+    tokio::try_join!(watcher, forward).map(|_| ())
+}
+
+#[cfg(feature = "wayland")]
+mod wayland {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use tokio::sync::mpsc::Sender;
+    use wayland_client::{
+        delegate_noop,
+        globals::{registry_queue_init, GlobalListContents},
+        protocol::{wl_registry, wl_seat},
+        Connection, Dispatch, QueueHandle,
+    };
+    use wayland_protocols::ext::idle_notify::v1::client::{
+        ext_idle_notification_v1::{self, ExtIdleNotificationV1},
+        ext_idle_notifier_v1::ExtIdleNotifierV1,
+    };
+
+    /// After how long without input the compositor notifies us. The idle time is counted up from there.
+    const NOTIFICATION_TIMEOUT: Duration = Duration::from_secs(1);
+
+    pub(super) async fn watch_idle_seconds(sender: Sender<u64>) -> Result<()> {
+        let (idle_sender, mut idle_receiver) = tokio::sync::mpsc::channel(1);
+        let mut connection = tokio::task::spawn_blocking(move || watch_idle_notifications(idle_sender));
+        let mut idle_since: Option<Instant> = None;
+        let mut tick = tokio::time::interval(Duration::from_secs(1));
+        sender.send(0).await?;
+        loop {
+            tokio::select! {
+                result = &mut connection => {
+                    // the connection only ends on its own if the compositor doesn't support ext-idle-notify,
+                    // in which case retrying wouldn't change anything, so the variable just stays at 0
+                    result??;
+                    return std::future::pending().await;
+                }
+                Some(since) = idle_receiver.recv() => {
+                    idle_since = since;
+                    sender.send(idle_since.map_or(0, |since| since.elapsed().as_secs())).await?;
+                }
+                _ = tick.tick(), if idle_since.is_some() => {
+                    if let Some(since) = idle_since {
+                        sender.send(since.elapsed().as_secs()).await?;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Send the time since which the user is idle whenever the compositor reports a change, until the channel is closed.
+    fn watch_idle_notifications(sender: Sender<Option<Instant>>) -> Result<()> {
+        let connection = Connection::connect_to_env().context("Failed to connect to the wayland compositor")?;
+        let (globals, mut queue) = registry_queue_init::<IdleState>(&connection)?;
+        let queue_handle = queue.handle();
+        let seat: wl_seat::WlSeat = globals.bind(&queue_handle, 1..=1, ())?;
+        let Ok(notifier) = globals.bind::<ExtIdleNotifierV1, _, _>(&queue_handle, 1..=1, ()) else {
+            log::warn!("The wayland compositor doesn't support ext-idle-notify, {} won't be updated", IDLE_VAR_NAME);
+            return Ok(());
+        };
+        let _notification = notifier.get_idle_notification(NOTIFICATION_TIMEOUT.as_millis() as u32, &seat, &queue_handle, ());
+
+        let mut state = IdleState { sender, closed: false };
+        while !state.closed {
+            queue.blocking_dispatch(&mut state)?;
+        }
+        Ok(())
+    }
+
+    struct IdleState {
+        sender: Sender<Option<Instant>>,
+        closed: bool,
+    }
+
+    impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for IdleState {
+        fn event(
+            _: &mut Self,
+            _: &wl_registry::WlRegistry,
+            _: wl_registry::Event,
+            _: &GlobalListContents,
+            _: &Connection,
+            _: &QueueHandle<Self>,
+        ) {
+        }
+    }
+
+    impl Dispatch<ExtIdleNotificationV1, ()> for IdleState {
+        fn event(
+            state: &mut Self,
+            _: &ExtIdleNotificationV1,
+            event: ext_idle_notification_v1::Event,
+            _: &(),
+            _: &Connection,
+            _: &QueueHandle<Self>,
+        ) {
+            let idle_since = match event {
+                ext_idle_notification_v1::Event::Idled => Some(Instant::now() - NOTIFICATION_TIMEOUT),
+                ext_idle_notification_v1::Event::Resumed => None,
+                _ => return,
+            };
+            state.closed |= state.sender.blocking_send(idle_since).is_err();
+        }
+    }
+
+    delegate_noop!(IdleState: ignore wl_seat::WlSeat);
+    delegate_noop!(IdleState: ExtIdleNotifierV1);
+}
+
+#[cfg(feature = "x11")]
+mod x11 {
+    use super::*;
+    use x11rb::{connection::Connection, protocol::screensaver::ConnectionExt as _};
+
+    /// Send the idle time every second, until the channel is closed.
+    pub(super) fn watch_idle_seconds(sender: tokio::sync::mpsc::Sender<u64>) -> Result<()> {
+        let (conn, screen) = x11rb::connect(None)?;
+        let root = conn.setup().roots[screen].root;
+        loop {
+            let info = conn.screensaver_query_info(root)?.reply().context("The X server doesn't support XScreenSaver")?;
+            sender.blocking_send(info.ms_since_user_input as u64 / 1000)?;
+            std::thread::sleep(std::time::Duration::from_secs(1));
+        }
+    }
+}
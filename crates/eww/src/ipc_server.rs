@@ -1,6 +1,5 @@
 use crate::{app, opts};
 use anyhow::{Context, Result};
-use std::time::Duration;
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
     sync::mpsc::*,
@@ -33,13 +32,14 @@ async fn handle_connection(mut stream: tokio::net::UnixStream, evt_send: Unbound
 
     log::debug!("received command from IPC: {:?}", &action);
 
+    let response_timeout = action.response_timeout();
     let (command, maybe_response_recv) = action.into_daemon_command();
 
     evt_send.send(command)?;
 
     if let Some(mut response_recv) = maybe_response_recv {
         log::debug!("Waiting for response for IPC client");
-        if let Ok(Some(response)) = tokio::time::timeout(Duration::from_millis(100), response_recv.recv()).await {
+        if let Ok(Some(response)) = tokio::time::timeout(response_timeout, response_recv.recv()).await {
             let response = bincode::serialize(&response)?;
             let result = &stream_write.write_all(&response).await;
             crate::print_result_err!("sending text response to ipc client", &result);
@@ -0,0 +1,250 @@
+//! Keyboard layout and lock key state, backing the `EWW_KEYBOARD` magic variable.
+//!
+//! The state is read from the compositor's IPC on Hyprland and Sway, and from XKB on X11.
+
+use anyhow::{Context, Result};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::UnixStream,
+};
+
+/// Initial value of `EWW_KEYBOARD`, before the state could be read.
+pub const INITIAL_STATE: &str = r#"{"layout":"","caps_lock":null,"num_lock":null}"#;
+
+/// Interval in which the lock keys are checked on Hyprland, as it doesn't report changes to them.
+const HYPRLAND_LOCK_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+#[derive(Debug, Default, serde::Serialize)]
+struct KeyboardState {
+    /// name of the active layout, i.e. `English (US)`
+    layout: String,
+    /// state of the lock keys, if the source of the state reports them
+    caps_lock: Option<bool>,
+    num_lock: Option<bool>,
+}
+
+/// Write the keyboard state as a line of JSON whenever it changes, until writing fails.
+pub async fn write_state_updates<W: tokio::io::AsyncWrite + Unpin>(writer: W) -> Result<()> {
+    if let Ok(signature) = std::env::var("HYPRLAND_INSTANCE_SIGNATURE") {
+        return hyprland::write_state_updates(&signature, writer).await;
+    }
+    if let Ok(socket) = std::env::var("SWAYSOCK") {
+        return sway::write_state_updates(&socket, writer).await;
+    }
+    #[cfg(feature = "x11")]
+    if std::env::var_os("DISPLAY").is_some() && std::env::var_os("WAYLAND_DISPLAY").is_none() {
+        return x11::write_state_updates(writer).await;
+    }
+    // retrying would only result in the same outcome, so the variable just keeps its initial value
+    log::warn!("EWW_KEYBOARD is only supported on Hyprland, Sway and X11");
+    std::future::pending().await
+}
+
+/// Write the state if it differs from the previously written one.
+async fn write_if_changed<W: tokio::io::AsyncWrite + Unpin>(
+    writer: &mut W,
+    last: &mut String,
+    state: &KeyboardState,
+) -> Result<()> {
+    let state = serde_json::to_string(state)?;
+    if state != *last {
+        writer.write_all(format!("{}\n", state).as_bytes()).await?;
+        writer.flush().await?;
+        *last = state;
+    }
+    Ok(())
+}
+
+mod hyprland {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn socket_dir(signature: &str) -> PathBuf {
+        let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from).map(|dir| dir.join("hypr").join(signature));
+        // older versions of Hyprland place their sockets in /tmp
+        runtime_dir.filter(|dir| dir.exists()).unwrap_or_else(|| PathBuf::from("/tmp/hypr").join(signature))
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Devices {
+        keyboards: Vec<Keyboard>,
+    }
+
+    #[derive(serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Keyboard {
+        #[serde(rename = "active_keymap")]
+        active_keymap: String,
+        caps_lock: bool,
+        num_lock: bool,
+        #[serde(default)]
+        main: bool,
+    }
+
+    async fn query_state(socket_dir: &std::path::Path) -> Result<KeyboardState> {
+        let mut socket = UnixStream::connect(socket_dir.join(".socket.sock")).await.context("Failed to connect to Hyprland")?;
+        socket.write_all(b"j/devices").await?;
+        let mut response = String::new();
+        socket.read_to_string(&mut response).await?;
+        let devices: Devices = serde_json::from_str(&response).context("Failed to parse the devices reported by Hyprland")?;
+        let keyboard = devices.keyboards.iter().find(|keyboard| keyboard.main).or_else(|| devices.keyboards.first());
+        Ok(keyboard
+            .map(|keyboard| KeyboardState {
+                layout: keyboard.active_keymap.clone(),
+                caps_lock: Some(keyboard.caps_lock),
+                num_lock: Some(keyboard.num_lock),
+            })
+            .unwrap_or_default())
+    }
+
+    pub(super) async fn write_state_updates<W: tokio::io::AsyncWrite + Unpin>(signature: &str, mut writer: W) -> Result<()> {
+        let socket_dir = socket_dir(signature);
+        let events = UnixStream::connect(socket_dir.join(".socket2.sock")).await.context("Failed to connect to Hyprland")?;
+        let mut events = BufReader::new(events).lines();
+        let mut lock_poll = tokio::time::interval(HYPRLAND_LOCK_POLL_INTERVAL);
+        let mut last = String::new();
+        loop {
+            write_if_changed(&mut writer, &mut last, &query_state(&socket_dir).await?).await?;
+            // wait for a layout change, or for the lock keys to be checked again
+            loop {
+                tokio::select! {
+                    line = events.next_line() => match line? {
+                        Some(line) if line.starts_with("activelayout>>") => break,
+                        Some(_) => {}
+                        None => return Ok(()),
+                    },
+                    _ = lock_poll.tick() => break,
+                }
+            }
+        }
+    }
+}
+
+mod sway {
+    use super::*;
+
+    const MAGIC: &[u8] = b"i3-ipc";
+    const SUBSCRIBE: u32 = 2;
+    const GET_INPUTS: u32 = 100;
+
+    #[derive(serde::Deserialize)]
+    struct Input {
+        #[serde(rename = "type")]
+        kind: String,
+        xkb_active_layout_name: Option<String>,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct InputEvent {
+        input: Input,
+    }
+
+    async fn send_message(socket: &mut UnixStream, message_type: u32, payload: &str) -> Result<()> {
+        let mut message = MAGIC.to_vec();
+        message.extend((payload.len() as u32).to_ne_bytes());
+        message.extend(message_type.to_ne_bytes());
+        message.extend(payload.as_bytes());
+        Ok(socket.write_all(&message).await?)
+    }
+
+    /// Read the next message, returning its type and payload.
+    async fn read_message(socket: &mut UnixStream) -> Result<(u32, Vec<u8>)> {
+        let mut header = [0; 14];
+        socket.read_exact(&mut header).await?;
+        let length = u32::from_ne_bytes(header[6..10].try_into()?);
+        let message_type = u32::from_ne_bytes(header[10..14].try_into()?);
+        let mut payload = vec![0; length as usize];
+        socket.read_exact(&mut payload).await?;
+        Ok((message_type, payload))
+    }
+
+    fn keyboard_state(input: &Input) -> Option<KeyboardState> {
+        let layout = input.xkb_active_layout_name.clone().filter(|_| input.kind == "keyboard")?;
+        // sway doesn't report the state of the lock keys
+        Some(KeyboardState { layout, caps_lock: None, num_lock: None })
+    }
+
+    pub(super) async fn write_state_updates<W: tokio::io::AsyncWrite + Unpin>(socket: &str, mut writer: W) -> Result<()> {
+        let mut requests = UnixStream::connect(socket).await.context("Failed to connect to sway")?;
+        send_message(&mut requests, GET_INPUTS, "").await?;
+        let (_, inputs) = read_message(&mut requests).await?;
+        let inputs: Vec<Input> = serde_json::from_slice(&inputs).context("Failed to parse the inputs reported by sway")?;
+        let mut last = String::new();
+        if let Some(state) = inputs.iter().find_map(keyboard_state) {
+            write_if_changed(&mut writer, &mut last, &state).await?;
+        }
+
+        let mut events = UnixStream::connect(socket).await.context("Failed to connect to sway")?;
+        send_message(&mut events, SUBSCRIBE, r#"["input"]"#).await?;
+        loop {
+            let (message_type, payload) = read_message(&mut events).await?;
+            // events have the highest bit set, other messages are replies, such as the one to subscribing
+            if message_type & (1 << 31) == 0 {
+                continue;
+            }
+            let Ok(event) = serde_json::from_slice::<InputEvent>(&payload) else { continue };
+            if let Some(state) = keyboard_state(&event.input) {
+                write_if_changed(&mut writer, &mut last, &state).await?;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "x11")]
+mod x11 {
+    use super::*;
+    use x11rb::{
+        connection::Connection,
+        protocol::{
+            xkb::{self, ConnectionExt as _},
+            xproto::{ConnectionExt as _, ModMask},
+        },
+    };
+
+    fn query_state(conn: &impl Connection) -> Result<KeyboardState> {
+        let device = xkb::ID::USE_CORE_KBD.into();
+        let state = conn.xkb_get_state(device)?.reply()?;
+        let names = conn.xkb_get_names(device, xkb::NameDetail::GROUP_NAMES)?.reply()?;
+        let layout = match names.value_list.groups.and_then(|groups| groups.get(u8::from(state.group) as usize).copied()) {
+            Some(atom) => String::from_utf8_lossy(&conn.get_atom_name(atom)?.reply()?.name).into_owned(),
+            None => String::new(),
+        };
+        Ok(KeyboardState {
+            layout,
+            caps_lock: Some(state.locked_mods.contains(ModMask::LOCK)),
+            // num lock is bound to mod2 on practically all setups
+            num_lock: Some(state.locked_mods.contains(ModMask::M2)),
+        })
+    }
+
+    /// Send the keyboard state through the channel whenever XKB reports a change, until the channel is closed.
+    fn watch_state(sender: tokio::sync::mpsc::Sender<KeyboardState>) -> Result<()> {
+        let (conn, _) = x11rb::connect(None)?;
+        conn.xkb_use_extension(1, 0)?.reply()?;
+        let events = xkb::EventType::STATE_NOTIFY | xkb::EventType::NAMES_NOTIFY | xkb::EventType::NEW_KEYBOARD_NOTIFY;
+        conn.xkb_select_events(
+            xkb::ID::USE_CORE_KBD.into(),
+            xkb::EventType::from(0u16),
+            events,
+            xkb::MapPart::from(0u16),
+            xkb::MapPart::from(0u16),
+            &xkb::SelectEventsAux::new(),
+        )?
+        .check()?;
+        loop {
+            sender.blocking_send(query_state(&conn)?)?;
+            conn.wait_for_event()?;
+        }
+    }
+
+    pub(super) async fn write_state_updates<W: tokio::io::AsyncWrite + Unpin>(mut writer: W) -> Result<()> {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(1);
+        // x11rb blocks while waiting for events
+        let watcher = tokio::task::spawn_blocking(move || watch_state(sender));
+        let mut last = String::new();
+        while let Some(state) = receiver.recv().await {
+            write_if_changed(&mut writer, &mut last, &state).await?;
+        }
+        watcher.await?
+    }
+}
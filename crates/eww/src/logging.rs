@@ -0,0 +1,168 @@
+//! Logging setup of eww.
+//!
+//! Next to the human readable log output, the daemon writes every log record as a line of JSON into a separate log file,
+//! which `eww logs` uses to filter the logs by level, window or variable.
+
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufRead, BufReader, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use pretty_env_logger::env_logger;
+use serde::{Deserialize, Serialize};
+
+/// The file structured log records are written to, once the daemon has been started.
+static STRUCTURED_LOG_FILE: Lazy<Mutex<Option<File>>> = Lazy::new(|| Mutex::new(None));
+
+/// How many of the already existing log records `eww logs` shows before following the log file.
+const INITIAL_RECORD_COUNT: usize = 10;
+
+/// A log record, as stored in the structured log file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogRecord {
+    pub time: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+/// Logger that writes to stderr via [`env_logger`], and additionally into the structured log file, if one is set.
+struct EwwLogger {
+    inner: env_logger::Logger,
+}
+
+impl log::Log for EwwLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.inner.matches(record) {
+            self.inner.log(record);
+            write_structured_record(record.level(), record.target(), &record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Install the eww logger as the global logger.
+pub fn init(logger: env_logger::Logger) {
+    log::set_max_level(logger.filter());
+    log::set_boxed_logger(Box::new(EwwLogger { inner: logger })).expect("Failed to initialize logger");
+}
+
+/// Start writing structured log records to the given file.
+pub fn set_structured_log_file(path: impl AsRef<Path>) -> Result<()> {
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Error opening log file ({}) for writing", path.as_ref().display()))?;
+    *STRUCTURED_LOG_FILE.lock().unwrap() = Some(file);
+    Ok(())
+}
+
+/// Write a record into the structured log file, if the daemon has set one.
+pub fn write_structured_record(level: log::Level, target: &str, message: &str) {
+    let mut file = STRUCTURED_LOG_FILE.lock().unwrap();
+    let Some(file) = file.as_mut() else { return };
+    let record = LogRecord {
+        time: chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, false),
+        level: level.to_string(),
+        target: target.to_string(),
+        message: message.to_string(),
+    };
+    if let Ok(line) = serde_json::to_string(&record) {
+        // there's nowhere to report failing to write the logs to
+        let _ = writeln!(file, "{}", line);
+    }
+}
+
+/// Which records `eww logs` should show.
+#[derive(Debug)]
+pub struct LogFilter {
+    /// Only show records of at least this severity
+    pub level: Option<log::Level>,
+    /// Only show records that mention this window or variable
+    pub name: Option<String>,
+}
+
+impl LogFilter {
+    fn matches(&self, record: &LogRecord) -> bool {
+        self.level.map_or(true, |level| record.level.parse::<log::Level>().map_or(true, |record_level| record_level <= level))
+            && self.name.as_ref().map_or(true, |name| mentions_name(&record.message, name))
+    }
+}
+
+/// Check if `message` contains `name` as a whole word, where `-` and `_` are considered part of a word.
+fn mentions_name(message: &str, name: &str) -> bool {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '-' || c == '_';
+    message.match_indices(name).any(|(index, _)| {
+        let before = message[..index].chars().next_back();
+        let after = message[index + name.len()..].chars().next();
+        !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+    })
+}
+
+/// Print the last few records in the structured log file matching the filter, then keep printing new matching records as they are written.
+pub fn follow_structured_logs(path: PathBuf, filter: LogFilter, json: bool) -> Result<()> {
+    let print_record = |line: &str| {
+        let Ok(record) = serde_json::from_str::<LogRecord>(line) else { return };
+        if !filter.matches(&record) {
+            return;
+        }
+        if json {
+            println!("{}", line);
+        } else {
+            println!("{} {:<5} {} > {}", record.time, record.level, record.target, record.message);
+        }
+    };
+
+    let file = File::open(&path).with_context(|| format!("Failed to open log file {}", path.display()))?;
+    let mut reader = BufReader::new(file);
+    let mut recent = VecDeque::new();
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        if !line.ends_with('\n') {
+            reader.seek(SeekFrom::Current(-(line.len() as i64)))?;
+            break;
+        }
+        if serde_json::from_str::<LogRecord>(&line).is_ok_and(|record| filter.matches(&record)) {
+            if recent.len() >= INITIAL_RECORD_COUNT {
+                recent.pop_front();
+            }
+            recent.push_back(line.trim_end().to_string());
+        }
+        line.clear();
+    }
+    recent.iter().for_each(|line| print_record(line));
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? > 0 {
+            // only handle complete lines, the rest of the line will be read once it's written
+            if line.ends_with('\n') {
+                print_record(line.trim_end());
+            } else {
+                reader.seek(SeekFrom::Current(-(line.len() as i64)))?;
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            continue;
+        }
+        // the log file is truncated when the log directory gets cleaned up
+        let position = reader.stream_position()?;
+        if std::fs::metadata(&path).map(|metadata| metadata.len() < position).unwrap_or(false) {
+            reader = BufReader::new(File::open(&path)?);
+        }
+        std::thread::sleep(Duration::from_millis(250));
+    }
+}
@@ -5,7 +5,6 @@ extern crate gtk;
 extern crate gtk_layer_shell as gtk_layer_shell;
 
 use anyhow::{Context, Result};
-use clap::CommandFactory as _;
 use daemon_response::{DaemonResponse, DaemonResponseReceiver};
 use display_backend::DisplayBackend;
 use opts::ActionWithServer;
@@ -16,20 +15,30 @@ use crate::server::ForkResult;
 
 mod app;
 mod application_lifecycle;
+mod bluetooth;
+mod brightness;
 mod client;
+mod completions;
 mod config;
+mod crash_report;
 mod daemon_response;
 mod display_backend;
 mod error_handling_ctx;
 mod file_database;
 mod geometry;
+mod idle;
 mod ipc_server;
+mod keyboard;
+mod logging;
+mod modules;
 mod opts;
 mod paths;
+mod process_registry;
 mod script_var_handler;
 mod server;
 mod state;
 mod util;
+mod widget_docs;
 mod widgets;
 mod window_arguments;
 mod window_initiator;
@@ -39,17 +48,24 @@ fn main() {
     let opts: opts::Opt = opts::Opt::from_env();
 
     let log_level_filter = if opts.log_debug { log::LevelFilter::Debug } else { log::LevelFilter::Info };
-    if std::env::var("RUST_LOG").is_ok() {
-        pretty_env_logger::init_timed();
+    let mut logger_builder = pretty_env_logger::formatted_timed_builder();
+    if let Ok(filters) = std::env::var("RUST_LOG") {
+        logger_builder.parse_filters(&filters);
     } else {
-        pretty_env_logger::formatted_timed_builder()
-            .filter(Some("eww"), log_level_filter)
-            .filter(Some("notifier_host"), log_level_filter)
-            .init();
+        logger_builder.filter(Some("eww"), log_level_filter).filter(Some("notifier_host"), log_level_filter);
     }
+    logging::init(logger_builder.build());
 
     if let opts::Action::ShellCompletions { shell } = opts.action {
-        clap_complete::generate(shell, &mut opts::RawOpt::command(), "eww", &mut std::io::stdout());
+        completions::print_completions(shell);
+        return;
+    }
+
+    if let opts::Action::Doc { widget, json } = opts.action {
+        if let Err(err) = widget_docs::print_docs(widget.as_deref(), json) {
+            error_handling_ctx::print_error(err);
+            std::process::exit(1);
+        }
         return;
     }
 
@@ -92,20 +108,20 @@ fn detect_wayland() -> bool {
 }
 
 fn run<B: DisplayBackend>(opts: opts::Opt, eww_binary_name: String) -> Result<()> {
-    let paths = opts
-        .config_path
-        .map(EwwPaths::from_config_dir)
-        .unwrap_or_else(EwwPaths::default)
-        .context("Failed to initialize eww paths")?;
+    let paths = match opts.config_path {
+        Some(config_dir) => EwwPaths::from_config_dir(config_dir, opts.instance_id.as_deref()),
+        None => EwwPaths::default(opts.instance_id.as_deref()),
+    }
+    .context("Failed to initialize eww paths")?;
 
     let should_restart = match &opts.action {
-        opts::Action::ShellCompletions { .. } => unreachable!(),
+        opts::Action::ShellCompletions { .. } | opts::Action::Doc { .. } => unreachable!(),
         opts::Action::Daemon => opts.restart,
         opts::Action::WithServer(action) => opts.restart && action.can_start_daemon(),
         opts::Action::ClientOnly(_) => false,
     };
     if should_restart {
-        let response = handle_server_command(&paths, &ActionWithServer::KillServer, 1);
+        let response = handle_server_command(&paths, &ActionWithServer::KillServer { timeout: None }, 1);
         if let Ok(Some(response)) = response {
             handle_daemon_response(response);
         }
@@ -113,7 +129,7 @@ fn run<B: DisplayBackend>(opts: opts::Opt, eww_binary_name: String) -> Result<()
     }
 
     let would_show_logs = match opts.action {
-        opts::Action::ShellCompletions { .. } => unreachable!(),
+        opts::Action::ShellCompletions { .. } | opts::Action::Doc { .. } => unreachable!(),
         opts::Action::ClientOnly(action) => {
             client::handle_client_only_action(&paths, action)?;
             false
@@ -122,6 +138,11 @@ fn run<B: DisplayBackend>(opts: opts::Opt, eww_binary_name: String) -> Result<()
         // make sure that there isn't already a Eww daemon running.
         opts::Action::Daemon if check_server_running(paths.get_ipc_socket_file()) => {
             eprintln!("Eww server already running.");
+            if let Ok(Some(DaemonResponse::Success(info))) =
+                handle_server_command(&paths, &ActionWithServer::Ping { info: true }, 1)
+            {
+                warn_on_daemon_version_mismatch(&info);
+            }
             true
         }
         opts::Action::Daemon => {
@@ -131,12 +152,31 @@ fn run<B: DisplayBackend>(opts: opts::Opt, eww_binary_name: String) -> Result<()
             if !opts.show_logs {
                 println!("Run `{} logs` to see any errors while editing your configuration.", eww_binary_name);
             }
-            let fork_result = server::initialize_server::<B>(paths.clone(), None, !opts.no_daemonize)?;
+            let fork_result = server::initialize_server::<B>(paths.clone(), None, !opts.no_daemonize, !opts.no_auto_reload)?;
             opts.no_daemonize || fork_result == ForkResult::Parent
         }
 
-        opts::Action::WithServer(ActionWithServer::KillServer) => {
-            if let Some(response) = handle_server_command(&paths, &ActionWithServer::KillServer, 1)? {
+        // completions should neither wait for nor start the daemon
+        opts::Action::WithServer(action @ ActionWithServer::Complete { .. }) => {
+            if let Ok(Some(response)) = handle_server_command(&paths, &action, 1) {
+                handle_daemon_response(response);
+            }
+            false
+        }
+
+        opts::Action::WithServer(action @ ActionWithServer::KillServer { .. }) => {
+            if let Some(response) = handle_server_command(&paths, &action, 1)? {
+                handle_daemon_response(response);
+            }
+            false
+        }
+
+        opts::Action::WithServer(action @ ActionWithServer::Ping { info: true }) => {
+            let response = handle_server_command(&paths, &action, 1)?;
+            if let Some(DaemonResponse::Success(info)) = &response {
+                warn_on_daemon_version_mismatch(info);
+            }
+            if let Some(response) = response {
                 handle_daemon_response(response);
             }
             false
@@ -163,7 +203,7 @@ fn run<B: DisplayBackend>(opts: opts::Opt, eww_binary_name: String) -> Result<()
 
                     let (command, response_recv) = action.into_daemon_command();
                     // start the daemon and give it the command
-                    let fork_result = server::initialize_server::<B>(paths.clone(), Some(command), true)?;
+                    let fork_result = server::initialize_server::<B>(paths.clone(), Some(command), true, !opts.no_auto_reload)?;
                     let is_parent = fork_result == ForkResult::Parent;
                     if let (Some(recv), true) = (response_recv, is_parent) {
                         listen_for_daemon_response(recv);
@@ -176,7 +216,7 @@ fn run<B: DisplayBackend>(opts: opts::Opt, eww_binary_name: String) -> Result<()
     };
 
     if would_show_logs && opts.show_logs {
-        client::handle_client_only_action(&paths, opts::ActionClientOnly::Logs)?;
+        client::handle_client_only_action(&paths, opts::ActionClientOnly::Logs { level: None, filter: None, json: false })?;
     }
     Ok(())
 }
@@ -215,7 +255,7 @@ fn handle_daemon_response(res: DaemonResponse) {
 fn attempt_connect(socket_path: impl AsRef<Path>, attempts: usize) -> Option<net::UnixStream> {
     for _ in 0..attempts {
         if let Ok(mut con) = net::UnixStream::connect(&socket_path) {
-            if client::do_server_call(&mut con, &opts::ActionWithServer::Ping).is_ok() {
+            if client::do_server_call(&mut con, &opts::ActionWithServer::Ping { info: false }).is_ok() {
                 return net::UnixStream::connect(&socket_path).ok();
             }
         }
@@ -224,10 +264,23 @@ fn attempt_connect(socket_path: impl AsRef<Path>, attempts: usize) -> Option<net
     None
 }
 
+/// Warn if the running daemon, described by the output of `eww ping --info`, is a different version of eww than this client.
+/// This happens when eww got updated without restarting the daemon.
+fn warn_on_daemon_version_mismatch(info: &str) {
+    let Ok(info) = serde_json::from_str::<app::DaemonInfo>(info) else { return };
+    if info.version != env!("CARGO_PKG_VERSION") {
+        eprintln!(
+            "Warning: the running eww daemon is version {}, but this is version {}. Run `eww kill` and start the daemon again to update it.",
+            info.version,
+            env!("CARGO_PKG_VERSION")
+        );
+    }
+}
+
 /// Check if a eww server is currently running by trying to send a ping message to it.
 fn check_server_running(socket_path: impl AsRef<Path>) -> bool {
     let response = net::UnixStream::connect(socket_path)
         .ok()
-        .and_then(|mut stream| client::do_server_call(&mut stream, &opts::ActionWithServer::Ping).ok());
+        .and_then(|mut stream| client::do_server_call(&mut stream, &opts::ActionWithServer::Ping { info: false }).ok());
     response.is_some()
 }
@@ -0,0 +1,189 @@
+//! Installing shared widget modules into the eww configuration directory.
+//!
+//! Modules are git repositories containing yuck, scss and script files. They are vendored into
+//! `<config-dir>/modules/<name>`, and can then be imported in the configuration via `(import "<name>")`.
+//! Installed modules and the exact revision that was installed are recorded in `<config-dir>/modules.lock`.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{bail, Context, Result};
+
+pub const MODULES_DIR: &str = "modules";
+pub const LOCKFILE_NAME: &str = "modules.lock";
+
+/// Where a module is fetched from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleSource {
+    pub name: String,
+    pub git_url: String,
+    pub git_ref: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct LockedModule {
+    /// The source as given to `eww module add`
+    pub source: String,
+    pub git_url: String,
+    /// The commit that was installed
+    pub revision: String,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct Lockfile {
+    pub modules: BTreeMap<String, LockedModule>,
+}
+
+impl Lockfile {
+    fn read(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    fn write(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Parse a module source of the form `github:user/repo`, or a git url, optionally followed by `@<ref>`.
+pub fn parse_module_source(source: &str) -> Result<ModuleSource> {
+    let (location, git_ref) = match source.rsplit_once('@') {
+        // don't mistake the user part of a `git@host:repo` url for a ref
+        Some((location, git_ref)) if !git_ref.contains(':') && !location.is_empty() => (location, Some(git_ref.to_string())),
+        _ => (source, None),
+    };
+
+    if git_ref.as_deref().map_or(false, |git_ref| git_ref.is_empty() || git_ref.starts_with('-')) {
+        bail!("Invalid git ref in module source `{}`", source);
+    }
+
+    let git_url = if let Some(repo) = location.strip_prefix("github:") {
+        if repo.split('/').filter(|part| !part.is_empty()).count() != 2 {
+            bail!("Invalid module source `{}`, expected `github:user/repo`", source);
+        }
+        format!("https://github.com/{}.git", repo.trim_end_matches(".git"))
+    } else if location.contains("://") || location.starts_with("git@") {
+        location.to_string()
+    } else {
+        bail!("Invalid module source `{}`, expected `github:user/repo` or a git url", source);
+    };
+
+    let name = git_url
+        .trim_end_matches('/')
+        .rsplit(['/', ':'])
+        .next()
+        .map(|name| name.trim_end_matches(".git").to_string())
+        .filter(|name| !name.is_empty())
+        .with_context(|| format!("Failed to determine module name from `{}`", source))?;
+
+    Ok(ModuleSource { name, git_url, git_ref })
+}
+
+/// Fetch the module from the given source into the modules directory of the config, and record it in the lockfile.
+pub fn add_module(config_dir: &Path, source: &str, name: Option<String>) -> Result<()> {
+    let mut module_source = parse_module_source(source)?;
+    if let Some(name) = name {
+        module_source.name = name;
+    }
+    if module_source.name.contains(['/', '\\']) || module_source.name.starts_with('.') {
+        bail!("Invalid module name `{}`", module_source.name);
+    }
+
+    let module_dir = config_dir.join(MODULES_DIR).join(&module_source.name);
+    if module_dir.exists() {
+        bail!("Module `{}` is already installed at {}", module_source.name, module_dir.display());
+    }
+
+    let revision = fetch_module(&module_source, &module_dir)?;
+
+    let lockfile_path = config_dir.join(LOCKFILE_NAME);
+    let mut lockfile = Lockfile::read(&lockfile_path)?;
+    lockfile.modules.insert(
+        module_source.name.clone(),
+        LockedModule { source: source.to_string(), git_url: module_source.git_url, revision: revision.clone() },
+    );
+    lockfile.write(&lockfile_path)?;
+
+    println!("Added module `{}` at revision {}", module_source.name, revision);
+    println!("Use it by adding `(import \"{}\")` to your eww.yuck", module_source.name);
+    Ok(())
+}
+
+/// Clone the module into a temporary directory, then move its files into `target_dir`, returning the installed commit.
+fn fetch_module(source: &ModuleSource, target_dir: &Path) -> Result<String> {
+    let parent_dir = target_dir.parent().context("Module directory has no parent")?;
+    std::fs::create_dir_all(parent_dir)?;
+    let checkout_dir: PathBuf = parent_dir.join(format!(".{}.tmp", source.name));
+    if checkout_dir.exists() {
+        std::fs::remove_dir_all(&checkout_dir)?;
+    }
+
+    let result = (|| {
+        let checkout = checkout_dir.to_string_lossy();
+        run_git(&["clone", "--quiet", "--", &source.git_url, &checkout], None)?;
+        if let Some(git_ref) = &source.git_ref {
+            run_git(&["checkout", "--quiet", git_ref], Some(&checkout_dir))?;
+        }
+        let revision = run_git(&["rev-parse", "HEAD"], Some(&checkout_dir))?;
+        std::fs::remove_dir_all(checkout_dir.join(".git"))?;
+        std::fs::rename(&checkout_dir, target_dir)?;
+        Ok(revision)
+    })();
+
+    if result.is_err() && checkout_dir.exists() {
+        let _ = std::fs::remove_dir_all(&checkout_dir);
+    }
+    result
+}
+
+fn run_git(args: &[&str], dir: Option<&Path>) -> Result<String> {
+    let mut command = Command::new("git");
+    command.args(args);
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let output = command.output().context("Failed to run git, is it installed?")?;
+    if !output.status.success() {
+        bail!("`git {}` failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_module_source() {
+        assert_eq!(
+            parse_module_source("github:user/cool-widgets").unwrap(),
+            ModuleSource {
+                name: "cool-widgets".to_string(),
+                git_url: "https://github.com/user/cool-widgets.git".to_string(),
+                git_ref: None
+            }
+        );
+        assert_eq!(
+            parse_module_source("github:user/cool-widgets@v1.0").unwrap(),
+            ModuleSource {
+                name: "cool-widgets".to_string(),
+                git_url: "https://github.com/user/cool-widgets.git".to_string(),
+                git_ref: Some("v1.0".to_string())
+            }
+        );
+        assert_eq!(
+            parse_module_source("git@example.com:user/bar.git").unwrap(),
+            ModuleSource { name: "bar".to_string(), git_url: "git@example.com:user/bar.git".to_string(), git_ref: None }
+        );
+        assert_eq!(parse_module_source("https://example.com/foo/bar/").unwrap().name, "bar");
+        assert!(parse_module_source("github:user").is_err());
+        assert!(parse_module_source("not a source").is_err());
+    }
+}
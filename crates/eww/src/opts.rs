@@ -1,5 +1,5 @@
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use eww_shared_util::VarName;
 use serde::{Deserialize, Serialize};
 use simplexpr::dynval::DynVal;
@@ -21,8 +21,10 @@ pub struct Opt {
     pub show_logs: bool,
     pub restart: bool,
     pub config_path: Option<std::path::PathBuf>,
+    pub instance_id: Option<String>,
     pub action: Action,
     pub no_daemonize: bool,
+    pub no_auto_reload: bool,
 }
 
 #[derive(Parser, Debug, Serialize, Deserialize, PartialEq)]
@@ -41,6 +43,12 @@ pub(super) struct RawOpt {
     #[arg(short, long, global = true)]
     config: Option<std::path::PathBuf>,
 
+    /// Name of the eww instance to talk to. Each instance runs its own daemon, with separate socket and log files,
+    /// which allows running multiple daemons, even with the same configuration directory.
+    // Named `--instance` rather than `--id`, as `--id` already names window instances in `eww open`.
+    #[arg(long = "instance", global = true, value_parser = parse_instance_id)]
+    instance_id: Option<String>,
+
     /// Watch the log output after executing the command
     #[arg(long = "logs", global = true)]
     show_logs: bool,
@@ -49,6 +57,10 @@ pub(super) struct RawOpt {
     #[arg(long = "no-daemonize", global = true)]
     no_daemonize: bool,
 
+    /// Don't reload the configuration when any of its files change.
+    #[arg(long = "no-auto-reload", global = true)]
+    no_auto_reload: bool,
+
     /// Restart the daemon completely before running the command
     #[arg(long = "restart", global = true)]
     restart: bool,
@@ -66,6 +78,17 @@ pub enum Action {
         shell: clap_complete::shells::Shell,
     },
 
+    /// Show the documentation of the built-in widgets.
+    #[command(name = "doc")]
+    Doc {
+        /// Widget to show the documentation for. Lists all widgets if not given.
+        widget: Option<String>,
+
+        /// Print the documentation as JSON, i.e. for use in editor tooling.
+        #[arg(long)]
+        json: bool,
+    },
+
     /// Start the Eww daemon.
     #[command(name = "daemon", alias = "d")]
     Daemon,
@@ -81,14 +104,111 @@ pub enum Action {
 pub enum ActionClientOnly {
     /// Print and watch the eww logs
     #[command(name = "logs")]
-    Logs,
+    Logs {
+        /// Only show log messages of at least this severity
+        #[arg(long)]
+        level: Option<LogLevel>,
+
+        /// Only show log messages that mention the given window or variable
+        #[arg(long)]
+        filter: Option<String>,
+
+        /// Print each log message as a JSON object
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Validate the configuration without starting the daemon.
+    /// Exits with a non-zero status and prints the errors if the configuration is invalid.
+    #[command(name = "check")]
+    Check,
+
+    /// List all built-in widgets and the widgets defined in your configuration, together with their attributes.
+    #[command(name = "widgets")]
+    Widgets {
+        /// Print the list as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Manage widget modules shared by other users
+    #[command(name = "module")]
+    Module {
+        #[command(subcommand)]
+        action: ModuleAction,
+    },
+
+    /// Control bluetooth devices
+    #[command(name = "bluetooth")]
+    Bluetooth {
+        #[command(subcommand)]
+        action: BluetoothAction,
+    },
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ModuleAction {
+    /// Add a module to your configuration, i.e.: `eww module add github:user/repo`.
+    /// A specific branch, tag or commit can be selected by appending `@<ref>`.
+    #[command(name = "add")]
+    Add {
+        /// Where to get the module from. Either `github:user/repo` or a git url.
+        source: String,
+
+        /// Name to install the module as. Defaults to the name of the repository.
+        #[arg(long)]
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum BluetoothAction {
+    /// Connect to a paired device, given by its address
+    #[command(name = "connect")]
+    Connect { address: String },
+
+    /// Disconnect from a device, given by its address
+    #[command(name = "disconnect")]
+    Disconnect { address: String },
+
+    /// Turn the bluetooth adapter on or off
+    #[command(name = "toggle")]
+    Toggle,
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize, PartialEq)]
+pub enum BrightnessAction {
+    /// Set the brightness of a backlight device, as listed in `EWW_BRIGHTNESS`, in percent
+    #[command(name = "set")]
+    Set { device: String, percent: f64 },
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum StateAction {
+    /// Write the current values of all global variables to a JSON file
+    #[command(name = "export")]
+    Export {
+        #[arg(value_parser = parse_absolute_path)]
+        file: std::path::PathBuf,
+    },
+
+    /// Set global variables to the values stored in a JSON file, i.e. one created by `eww state export`
+    #[command(name = "import")]
+    Import {
+        #[arg(value_parser = parse_absolute_path)]
+        file: std::path::PathBuf,
+    },
 }
 
 #[derive(Subcommand, Debug, Serialize, Deserialize, PartialEq)]
 pub enum ActionWithServer {
     /// Ping the eww server, checking if it is reachable.
     #[clap(name = "ping")]
-    Ping,
+    Ping {
+        /// Print the version and configuration directory of the daemon, and a hash of its loaded configuration, as JSON.
+        #[arg(long)]
+        info: bool,
+    },
 
     /// Update the value of a variable, in a running eww instance
     #[clap(name = "update", alias = "u")]
@@ -96,12 +216,21 @@ pub enum ActionWithServer {
         /// variable_name="new_value"-pairs that will be updated
         #[arg(value_parser = parse_var_update_arg)]
         mappings: Vec<(VarName, DynVal)>,
+
+        /// Wait for the daemon to check the values against the `:type` of their variables, and report any mismatches.
+        /// Without this, mismatched values are only rejected in the log of the daemon.
+        #[arg(long)]
+        check: bool,
     },
 
     /// Open the GTK debugger
     #[command(name = "inspector", alias = "debugger")]
     OpenInspector,
 
+    /// Set the contents of the clipboard
+    #[command(name = "clipboard-set")]
+    ClipboardSet { text: String },
+
     /// Open a window
     #[clap(name = "open", alias = "o")]
     OpenWindow {
@@ -128,6 +257,11 @@ pub enum ActionWithServer {
         #[arg(short, long)]
         anchor: Option<AnchorPoint>,
 
+        /// Open the window at the current position of the mouse pointer, keeping it within the monitor.
+        /// The position given via --pos is then relative to the pointer.
+        #[arg(long, conflicts_with = "anchor")]
+        at_pointer: bool,
+
         /// If the window is already open, close it instead
         #[arg(long = "toggle")]
         should_toggle: bool,
@@ -139,6 +273,39 @@ pub enum ActionWithServer {
         /// Define a variable for the window, i.e.: `--arg "var_name=value"`
         #[arg(long = "arg", value_parser = parse_var_update_arg)]
         args: Option<Vec<(VarName, DynVal)>>,
+
+        /// Like --arg, but if the window is already open, update its arguments in place instead of reopening it.
+        /// Prints "opened" or "updated", depending on what happened.
+        #[arg(long = "update-args", value_parser = parse_var_update_arg, conflicts_with = "should_toggle")]
+        update_args: Option<Vec<(VarName, DynVal)>>,
+    },
+
+    /// Toggle a window, opening it if it is closed and closing it if it is open.
+    /// Prints "opened" or "closed", depending on what happened.
+    #[command(name = "toggle", alias = "t")]
+    ToggleWindow {
+        /// Name of the window you want to toggle.
+        window_name: String,
+
+        /// The id of the window instance
+        #[arg(long)]
+        id: Option<String>,
+
+        /// The identifier of the monitor the window should open on
+        #[arg(long)]
+        screen: Option<MonitorIdentifier>,
+
+        /// If the window gets opened, open it at the current position of the mouse pointer
+        #[arg(long)]
+        at_pointer: bool,
+
+        /// If the window gets opened, automatically close it again after the given amount of time, i.e.: 5s
+        #[arg(long = "for", value_parser=parse_duration)]
+        duration: Option<std::time::Duration>,
+
+        /// Define a variable for the window, i.e.: `--arg "var_name=value"`
+        #[arg(long = "arg", value_parser = parse_var_update_arg)]
+        args: Option<Vec<(VarName, DynVal)>>,
     },
 
     /// Open multiple windows at once.
@@ -158,28 +325,71 @@ pub enum ActionWithServer {
         should_toggle: bool,
     },
 
+    /// Show a window. If the window is hidden, it is shown again without being rebuilt, otherwise it gets opened.
+    #[command(name = "show")]
+    ShowWindow {
+        /// Name of the window you want to show.
+        window_name: String,
+
+        /// The id of the window instance
+        #[arg(long)]
+        id: Option<String>,
+    },
+
+    /// Hide the given windows, keeping their widgets and state around such that they can be shown again instantly
+    #[command(name = "hide")]
+    HideWindows { windows: Vec<String> },
+
     /// Close the given windows
     #[command(name = "close", alias = "c")]
     CloseWindows { windows: Vec<String> },
 
+    /// Give keyboard focus to an open window, i.e. to control a launcher or menu with the keyboard
+    #[command(name = "focus")]
+    FocusWindow {
+        /// Id of the window instance to focus
+        window: String,
+    },
+
+    /// Place an open window above all other eww windows that don't have a higher `:z-index`
+    #[command(name = "raise")]
+    RaiseWindow {
+        /// Id of the window instance to raise
+        window: String,
+    },
+
     /// Reload the configuration
     #[command(name = "reload", alias = "r")]
     Reload,
 
     /// Kill the eww daemon
     #[command(name = "kill", alias = "k")]
-    KillServer,
+    KillServer {
+        /// How long to wait for the scripts started by eww to stop, i.e.: 5s. Default: 12s
+        #[arg(long, value_parser = parse_duration)]
+        timeout: Option<std::time::Duration>,
+    },
 
     /// Close all windows, without killing the daemon
     #[command(name = "close-all", alias = "ca")]
     CloseAll,
 
     /// Prints the variables used in all currently open window
-    #[command(name = "state")]
+    #[command(name = "state", args_conflicts_with_subcommands = true)]
     ShowState {
         /// Shows all variables, including not currently used ones
         #[arg(short, long)]
         all: bool,
+
+        #[command(subcommand)]
+        action: Option<StateAction>,
+    },
+
+    /// Control the brightness of the backlight
+    #[command(name = "brightness")]
+    Brightness {
+        #[command(subcommand)]
+        action: BrightnessAction,
     },
 
     /// Get the value of a variable if defined
@@ -201,9 +411,70 @@ pub enum ActionWithServer {
     #[command(name = "debug")]
     ShowDebug,
 
+    /// Print statistics about the daemon, including how often each variable gets updated.
+    /// This helps to find variables that are updated excessively, causing high CPU usage.
+    #[command(name = "stats")]
+    ShowStats,
+
+    /// List the processes started by eww, such as the scripts of variables and commands run by widgets.
+    #[command(name = "ps")]
+    ListProcesses {
+        /// Terminate the process with the given pid. Listen-vars react to this according to their `:on-exit` setting.
+        #[arg(long, value_name = "PID", conflicts_with = "restart")]
+        kill: Option<u32>,
+
+        /// Restart the script of the given variable.
+        #[arg(long, value_name = "VARIABLE")]
+        restart: Option<String>,
+    },
+
     /// Print out the scope graph structure in graphviz dot format.
     #[command(name = "graph")]
     ShowGraph,
+
+    /// Print the widget tree of an open window,
+    /// together with the current values of all attributes and the scope they are evaluated in.
+    #[command(name = "debug-tree")]
+    ShowWidgetTree {
+        /// The id of the window instance
+        window: String,
+    },
+
+    /// List window or variable names, one per line. Used by the shell completion scripts.
+    #[command(name = "complete", hide = true)]
+    Complete { kind: CompletionKind },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for log::Level {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => log::Level::Error,
+            LogLevel::Warn => log::Level::Warn,
+            LogLevel::Info => log::Level::Info,
+            LogLevel::Debug => log::Level::Debug,
+            LogLevel::Trace => log::Level::Trace,
+        }
+    }
+}
+
+/// Names that the shell completions can query from the daemon.
+#[derive(ValueEnum, Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum CompletionKind {
+    /// Names of all defined windows
+    Windows,
+    /// Ids of the currently open windows
+    OpenWindows,
+    /// Names of all global variables
+    Variables,
 }
 
 impl Opt {
@@ -215,9 +486,28 @@ impl Opt {
 
 impl From<RawOpt> for Opt {
     fn from(other: RawOpt) -> Self {
-        let RawOpt { log_debug, force_wayland, config, show_logs, no_daemonize, restart, action } = other;
-        Opt { log_debug, force_wayland, show_logs, restart, config_path: config, action, no_daemonize }
+        let RawOpt { log_debug, force_wayland, config, instance_id, show_logs, no_daemonize, no_auto_reload, restart, action } =
+            other;
+        Opt {
+            log_debug,
+            force_wayland,
+            show_logs,
+            restart,
+            config_path: config,
+            instance_id,
+            action,
+            no_daemonize,
+            no_auto_reload,
+        }
+    }
+}
+
+/// Parse an instance id, which is used in file names and thus may only contain alphanumeric characters, `-` and `_`.
+fn parse_instance_id(s: &str) -> Result<String> {
+    if s.is_empty() || !s.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        anyhow::bail!("Instance ids may only contain alphanumeric characters, `-` and `_`");
     }
+    Ok(s.to_string())
 }
 
 /// Parse a window-name:window-id pair of the form `name:id` or `name` into a tuple of `(name, id)`.
@@ -238,6 +528,11 @@ fn parse_window_id_args(s: &str) -> Result<(String, VarName, DynVal)> {
     Ok((id.to_string(), var_name.into(), value))
 }
 
+/// Resolve a path relative to the working directory of the client, as the daemon runs in a different directory.
+fn parse_absolute_path(s: &str) -> Result<std::path::PathBuf> {
+    Ok(std::env::current_dir().context("Failed to get the current directory")?.join(s))
+}
+
 /// Split the input string at `=`, parsing the value into a [`DynVal`].
 fn parse_var_update_arg(s: &str) -> Result<(VarName, DynVal)> {
     let (name, value) = s
@@ -248,17 +543,41 @@ fn parse_var_update_arg(s: &str) -> Result<(VarName, DynVal)> {
 
 impl ActionWithServer {
     pub fn can_start_daemon(&self) -> bool {
-        matches!(self, ActionWithServer::OpenWindow { .. } | ActionWithServer::OpenMany { .. })
+        matches!(
+            self,
+            ActionWithServer::OpenWindow { .. } | ActionWithServer::OpenMany { .. } | ActionWithServer::ToggleWindow { .. }
+        )
+    }
+
+    /// How long the client waits for the daemon to respond to this action.
+    pub fn response_timeout(&self) -> std::time::Duration {
+        match self {
+            // shutting down waits for all scripts to stop
+            ActionWithServer::KillServer { timeout } => {
+                timeout.unwrap_or(app::SCRIPT_SHUTDOWN_TIMEOUT) + std::time::Duration::from_secs(1)
+            }
+            // this waits for logind
+            ActionWithServer::Brightness { .. } => std::time::Duration::from_secs(2),
+            _ => std::time::Duration::from_millis(100),
+        }
     }
 
     pub fn into_daemon_command(self) -> (app::DaemonCommand, Option<daemon_response::DaemonResponseReceiver>) {
         let command = match self {
-            ActionWithServer::Update { mappings } => app::DaemonCommand::UpdateVars(mappings),
+            ActionWithServer::Update { mappings, check: false } => app::DaemonCommand::UpdateVars(mappings),
+            ActionWithServer::Update { mappings, check: true } => {
+                return with_response_channel(|sender| app::DaemonCommand::UpdateVarsChecked { mappings, sender })
+            }
             ActionWithServer::OpenInspector => app::DaemonCommand::OpenInspector,
+            ActionWithServer::ClipboardSet { text } => app::DaemonCommand::SetClipboard(text),
 
-            ActionWithServer::KillServer => app::DaemonCommand::KillServer,
+            ActionWithServer::KillServer { timeout } => {
+                let timeout = timeout.unwrap_or(app::SCRIPT_SHUTDOWN_TIMEOUT);
+                return with_response_channel(|sender| app::DaemonCommand::KillServer { timeout, sender: Some(sender) });
+            }
             ActionWithServer::CloseAll => app::DaemonCommand::CloseAll,
-            ActionWithServer::Ping => {
+            ActionWithServer::Ping { info: true } => return with_response_channel(app::DaemonCommand::PrintDaemonInfo),
+            ActionWithServer::Ping { info: false } => {
                 let (send, recv) = tokio::sync::mpsc::unbounded_channel();
                 let _ = send.send(DaemonResponse::Success("pong".to_owned()));
                 return (app::DaemonCommand::NoOp, Some(recv));
@@ -266,34 +585,99 @@ impl ActionWithServer {
             ActionWithServer::OpenMany { windows, args, should_toggle } => {
                 return with_response_channel(|sender| app::DaemonCommand::OpenMany { windows, args, should_toggle, sender });
             }
-            ActionWithServer::OpenWindow { window_name, id, pos, size, screen, anchor, should_toggle, duration, args } => {
+            ActionWithServer::OpenWindow {
+                window_name,
+                id,
+                pos,
+                size,
+                screen,
+                anchor,
+                at_pointer,
+                should_toggle,
+                duration,
+                args,
+                update_args,
+            } => {
                 return with_response_channel(|sender| app::DaemonCommand::OpenWindow {
                     window_name,
                     instance_id: id,
                     pos,
                     size,
                     anchor,
+                    at_pointer,
                     screen,
                     should_toggle,
                     duration,
                     sender,
                     args,
+                    update_args,
+                })
+            }
+            ActionWithServer::ToggleWindow { window_name, id, screen, at_pointer, duration, args } => {
+                return with_response_channel(|sender| app::DaemonCommand::OpenWindow {
+                    window_name,
+                    instance_id: id,
+                    pos: None,
+                    size: None,
+                    anchor: None,
+                    at_pointer,
+                    screen,
+                    should_toggle: true,
+                    duration,
+                    sender,
+                    args,
+                    update_args: None,
                 })
             }
+            ActionWithServer::ShowWindow { window_name, id } => {
+                return with_response_channel(|sender| app::DaemonCommand::ShowWindow { window_name, instance_id: id, sender });
+            }
+            ActionWithServer::HideWindows { windows } => {
+                return with_response_channel(|sender| app::DaemonCommand::HideWindows { windows, sender });
+            }
+            ActionWithServer::FocusWindow { window } => {
+                return with_response_channel(|sender| app::DaemonCommand::FocusWindow { instance_id: window, sender });
+            }
+            ActionWithServer::RaiseWindow { window } => {
+                return with_response_channel(|sender| app::DaemonCommand::RaiseWindow { instance_id: window, sender });
+            }
             ActionWithServer::CloseWindows { windows } => {
                 return with_response_channel(|sender| app::DaemonCommand::CloseWindows { windows, sender });
             }
             ActionWithServer::Reload => return with_response_channel(app::DaemonCommand::ReloadConfigAndCss),
             ActionWithServer::ListWindows => return with_response_channel(app::DaemonCommand::ListWindows),
             ActionWithServer::ListActiveWindows => return with_response_channel(app::DaemonCommand::ListActiveWindows),
-            ActionWithServer::ShowState { all } => {
+            ActionWithServer::ShowState { all, action: None } => {
                 return with_response_channel(|sender| app::DaemonCommand::PrintState { all, sender })
             }
+            ActionWithServer::ShowState { action: Some(StateAction::Export { file }), .. } => {
+                return with_response_channel(|sender| app::DaemonCommand::ExportState { file, sender })
+            }
+            ActionWithServer::ShowState { action: Some(StateAction::Import { file }), .. } => {
+                return with_response_channel(|sender| app::DaemonCommand::ImportState { file, sender })
+            }
+            ActionWithServer::Brightness { action: BrightnessAction::Set { device, percent } } => {
+                return with_response_channel(|sender| app::DaemonCommand::SetBrightness { device, percent, sender })
+            }
             ActionWithServer::GetVar { name } => {
                 return with_response_channel(|sender| app::DaemonCommand::GetVar { name, sender })
             }
             ActionWithServer::ShowDebug => return with_response_channel(app::DaemonCommand::PrintDebug),
             ActionWithServer::ShowGraph => return with_response_channel(app::DaemonCommand::PrintGraph),
+            ActionWithServer::ShowStats => return with_response_channel(app::DaemonCommand::PrintStats),
+            ActionWithServer::ListProcesses { kill, restart } => {
+                return with_response_channel(|sender| app::DaemonCommand::ListProcesses {
+                    kill,
+                    restart: restart.map(VarName),
+                    sender,
+                })
+            }
+            ActionWithServer::ShowWidgetTree { window } => {
+                return with_response_channel(|sender| app::DaemonCommand::PrintWidgetTree { instance_id: window, sender })
+            }
+            ActionWithServer::Complete { kind } => {
+                return with_response_channel(|sender| app::DaemonCommand::ListCompletions { kind, sender })
+            }
         };
         (command, None)
     }
@@ -13,10 +13,12 @@ pub struct EwwPaths {
     pub log_dir: PathBuf,
     pub ipc_socket_file: PathBuf,
     pub config_dir: PathBuf,
+    /// Name of the eww instance, given via `--instance`
+    pub instance_id: Option<String>,
 }
 
 impl EwwPaths {
-    pub fn from_config_dir<P: AsRef<Path>>(config_dir: P) -> Result<Self> {
+    pub fn from_config_dir<P: AsRef<Path>>(config_dir: P, instance_id: Option<&str>) -> Result<Self> {
         let config_dir = config_dir.as_ref();
         if config_dir.is_file() {
             bail!("Please provide the path to the config directory, not a file within it")
@@ -32,7 +34,11 @@ impl EwwPaths {
         format!("{}", config_dir.display()).hash(&mut hasher);
         // daemon_id is a hash of the config dir path to ensure that, given a normal XDG_RUNTIME_DIR,
         // the absolute path to the socket stays under the 108 bytes limit. (see #387, man 7 unix)
-        let daemon_id = format!("{:x}", hasher.finish());
+        let daemon_id = match instance_id {
+            // the instance id is kept readable, so that the files of a specific instance are easy to find
+            Some(instance_id) => format!("{:x}_{}", hasher.finish(), instance_id),
+            None => format!("{:x}", hasher.finish()),
+        };
 
         let ipc_socket_file = std::env::var("XDG_RUNTIME_DIR")
             .map(std::path::PathBuf::from)
@@ -44,32 +50,40 @@ impl EwwPaths {
             log::warn!("The IPC socket file's absolute path exceeds 100 bytes, the socket may fail to create.");
         }
 
-        let log_dir = std::env::var("XDG_CACHE_HOME")
-            .map(PathBuf::from)
-            .unwrap_or_else(|_| PathBuf::from(std::env::var("HOME").unwrap()).join(".cache"))
-            .join("eww");
+        let log_dir = get_cache_dir();
 
         if !log_dir.exists() {
             log::info!("Creating log dir");
             std::fs::create_dir_all(&log_dir)?;
         }
 
-        Ok(EwwPaths { config_dir, log_file: log_dir.join(format!("eww_{}.log", daemon_id)), log_dir, ipc_socket_file })
+        Ok(EwwPaths {
+            config_dir,
+            log_file: log_dir.join(format!("eww_{}.log", daemon_id)),
+            log_dir,
+            ipc_socket_file,
+            instance_id: instance_id.map(str::to_string),
+        })
     }
 
-    pub fn default() -> Result<Self> {
+    pub fn default(instance_id: Option<&str>) -> Result<Self> {
         let config_dir = std::env::var("XDG_CONFIG_HOME")
             .map(PathBuf::from)
             .unwrap_or_else(|_| PathBuf::from(std::env::var("HOME").unwrap()).join(".config"))
             .join("eww");
 
-        Self::from_config_dir(config_dir)
+        Self::from_config_dir(config_dir, instance_id)
     }
 
     pub fn get_log_file(&self) -> &Path {
         self.log_file.as_path()
     }
 
+    /// The file the daemon writes its log records to as JSON, one per line.
+    pub fn get_structured_log_file(&self) -> PathBuf {
+        self.log_file.with_extension("json.log")
+    }
+
     pub fn get_log_dir(&self) -> &Path {
         self.log_dir.as_path()
     }
@@ -78,6 +92,10 @@ impl EwwPaths {
         self.ipc_socket_file.as_path()
     }
 
+    pub fn get_instance_id(&self) -> Option<&str> {
+        self.instance_id.as_deref()
+    }
+
     pub fn get_config_dir(&self) -> &Path {
         self.config_dir.as_path()
     }
@@ -95,6 +113,18 @@ impl std::fmt::Display for EwwPaths {
             self.config_dir.display(),
             self.ipc_socket_file.display(),
             self.log_file.display()
-        )
+        )?;
+        if let Some(instance_id) = &self.instance_id {
+            write!(f, ", instance-id: {}", instance_id)?;
+        }
+        Ok(())
     }
 }
+
+/// The directory eww stores its logs and other cached files in, i.e. `~/.cache/eww`
+pub fn get_cache_dir() -> PathBuf {
+    std::env::var("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(std::env::var("HOME").unwrap()).join(".cache"))
+        .join("eww")
+}
@@ -0,0 +1,82 @@
+//! Keeps track of the processes eww spawns for script-vars and widget commands, such that they can be listed and
+//! terminated with `eww ps`.
+
+use std::{collections::HashMap, sync::Mutex, time::Instant};
+
+use anyhow::{Context, Result};
+use eww_shared_util::VarName;
+use nix::{sys::signal, unistd::Pid};
+use once_cell::sync::Lazy;
+
+static PROCESSES: Lazy<Mutex<HashMap<u32, ProcessInfo>>> = Lazy::new(Default::default);
+
+/// What a process was started for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcessOwner {
+    /// The script of a poll or listen var.
+    Variable(VarName),
+    /// A command of a widget or window, identified by the location it is defined at.
+    Command(String),
+    /// A command that is run when reloading the configuration, like `pre-reload`.
+    Hook(&'static str),
+}
+
+impl std::fmt::Display for ProcessOwner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProcessOwner::Variable(name) => write!(f, "var {}", name),
+            ProcessOwner::Command(location) => write!(f, "command at {}", location),
+            ProcessOwner::Hook(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub owner: ProcessOwner,
+    pub command: String,
+    pub started_at: Instant,
+    /// How often the script was restarted before this process was started.
+    pub restarts: u32,
+}
+
+/// Removes a process from the registry once it is dropped, which should happen once the process has exited.
+#[must_use]
+pub struct ProcessRegistration(Option<u32>);
+
+impl Drop for ProcessRegistration {
+    fn drop(&mut self) {
+        if let Some(pid) = self.0 {
+            PROCESSES.lock().unwrap().remove(&pid);
+        }
+    }
+}
+
+/// Register a running process. The `pid` is optional, as a process may already have exited by the time it is registered.
+pub fn register(pid: Option<u32>, owner: ProcessOwner, command: &str, restarts: u32) -> ProcessRegistration {
+    if let Some(pid) = pid {
+        let info = ProcessInfo { pid, owner, command: command.to_string(), started_at: Instant::now(), restarts };
+        PROCESSES.lock().unwrap().insert(pid, info);
+    }
+    ProcessRegistration(pid)
+}
+
+/// All processes that are currently running, sorted by their pid.
+pub fn list() -> Vec<ProcessInfo> {
+    let mut processes: Vec<_> = PROCESSES.lock().unwrap().values().cloned().collect();
+    processes.sort_by_key(|info| info.pid);
+    processes
+}
+
+/// Terminate a process started by eww.
+/// Processes that lead their own process group, like the scripts of listen-vars, get terminated together with their children.
+pub fn kill(pid: u32) -> Result<()> {
+    if !PROCESSES.lock().unwrap().contains_key(&pid) {
+        anyhow::bail!("No process with pid {} was started by eww", pid);
+    }
+    let pid = Pid::from_raw(pid as i32);
+    signal::killpg(pid, signal::SIGTERM)
+        .or_else(|_| signal::kill(pid, signal::SIGTERM))
+        .with_context(|| format!("Failed to terminate process {}", pid))
+}
@@ -1,10 +1,16 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use crate::{
     app,
-    config::{create_script_var_failed_warn, script_var},
+    config::{create_http_request_failed_warn, create_script_var_failed_warn, script_var},
+    process_registry::{self, ProcessOwner},
 };
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use app::DaemonCommand;
 
 use eww_shared_util::VarName;
@@ -16,9 +22,14 @@ use simplexpr::dynval::DynVal;
 use tokio::{
     io::{AsyncBufReadExt, BufReader},
     sync::mpsc::UnboundedSender,
+    time::Instant,
 };
 use tokio_util::sync::CancellationToken;
-use yuck::config::script_var_definition::{ListenScriptVar, PollScriptVar, ScriptVarDefinition, VarSource};
+use yuck::config::script_var_definition::{
+    DBusBusType, DBusListenVar, DBusSubscription, FileWatchVar, HttpRequest, ListenExitBehavior, ListenScriptVar, ListenSource,
+    PollScriptVar, ScriptVarDefinition, UpdateRateLimit, VarSource,
+};
+use zbus::zvariant;
 
 /// Initialize the script var handler, and return a handle to that handler, which can be used to control
 /// the script var execution.
@@ -36,6 +47,9 @@ pub fn init(evt_send: UnboundedSender<DaemonCommand>) -> ScriptVarHandlerHandle
                 let _: Result<_> = async {
                     let mut handler = ScriptVarHandler {
                         listen_handler: ListenVarHandler::new(evt_send.clone())?,
+                        dbus_handler: TaskVarHandler::new("dbus listen-var"),
+                        watch_handler: TaskVarHandler::new("watch var"),
+                        evt_send: evt_send.clone(),
                         poll_handler: PollVarHandler::new(evt_send)?,
                     };
                     crate::loop_select_exiting! {
@@ -46,8 +60,9 @@ pub fn init(evt_send: UnboundedSender<DaemonCommand>) -> ScriptVarHandlerHandle
                             ScriptVarHandlerMsg::Stop(name) => {
                                 handler.stop_for_variable(&name).await?;
                             }
-                            ScriptVarHandlerMsg::StopAll => {
+                            ScriptVarHandlerMsg::StopAll(done) => {
                                 handler.stop_all().await;
+                                let _ = done.send(());
                                 break;
                             }
                         },
@@ -88,11 +103,14 @@ impl ScriptVarHandlerHandle {
     }
 
     /// Stop the execution of all script-vars.
-    pub fn stop_all(&self) {
+    /// The returned receiver resolves once all scripts have terminated, or right away if the handler already stopped.
+    pub fn stop_all(&self) -> tokio::sync::oneshot::Receiver<()> {
+        let (done_send, done_recv) = tokio::sync::oneshot::channel();
         crate::print_result_err!(
             "while forwarding instruction to script-var handler",
-            self.msg_send.send(ScriptVarHandlerMsg::StopAll)
+            self.msg_send.send(ScriptVarHandlerMsg::StopAll(done_send))
         );
+        done_recv
     }
 
     pub fn join_thread(self) {
@@ -101,18 +119,22 @@ impl ScriptVarHandlerHandle {
 }
 
 /// Message enum used by the ScriptVarHandlerHandle to communicate to the ScriptVarHandler
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug)]
 #[allow(clippy::large_enum_variant)]
 enum ScriptVarHandlerMsg {
     AddVar(ScriptVarDefinition),
     Stop(VarName),
-    StopAll,
+    /// Stop all script-vars, notifying the sender once all of them have terminated.
+    StopAll(tokio::sync::oneshot::Sender<()>),
 }
 
 /// Handler that manages running and updating [ScriptVarDefinition]s
 struct ScriptVarHandler {
     listen_handler: ListenVarHandler,
     poll_handler: PollVarHandler,
+    dbus_handler: TaskVarHandler,
+    watch_handler: TaskVarHandler,
+    evt_send: UnboundedSender<DaemonCommand>,
 }
 
 impl ScriptVarHandler {
@@ -120,6 +142,22 @@ impl ScriptVarHandler {
         match script_var {
             ScriptVarDefinition::Poll(var) => self.poll_handler.start(var).await,
             ScriptVarDefinition::Listen(var) => self.listen_handler.start(var).await,
+            ScriptVarDefinition::DBus(var) => {
+                let evt_send = self.evt_send.clone();
+                self.dbus_handler.start(var.name.clone(), async move {
+                    run_dbus_listener(&var, &evt_send)
+                        .await
+                        .with_context(|| format!("Error while listening on dbus for variable {}", var.name))
+                })
+            }
+            ScriptVarDefinition::Watch(var) => {
+                let evt_send = self.evt_send.clone();
+                self.watch_handler.start(var.name.clone(), async move {
+                    run_file_watch(&var, &evt_send)
+                        .await
+                        .with_context(|| format!("Error while watching {} for variable {}", var.path.display(), var.name))
+                })
+            }
         };
     }
 
@@ -128,6 +166,8 @@ impl ScriptVarHandler {
         log::debug!("Stopping script var process for variable {}", name);
         self.listen_handler.stop_for_variable(name).await;
         self.poll_handler.stop_for_variable(name);
+        self.dbus_handler.stop_for_variable(name);
+        self.watch_handler.stop_for_variable(name);
         Ok(())
     }
 
@@ -136,17 +176,25 @@ impl ScriptVarHandler {
         log::debug!("Stopping script-var-handlers");
         self.listen_handler.stop_all().await;
         self.poll_handler.stop_all();
+        self.dbus_handler.stop_all();
+        self.watch_handler.stop_all();
     }
 }
 
 struct PollVarHandler {
     evt_send: UnboundedSender<DaemonCommand>,
     poll_handles: HashMap<VarName, CancellationToken>,
+    /// Client shared by all poll vars that fetch their value via HTTP.
+    http_client: reqwest::Client,
 }
 
 impl PollVarHandler {
     fn new(evt_send: UnboundedSender<DaemonCommand>) -> Result<Self> {
-        let handler = PollVarHandler { evt_send, poll_handles: HashMap::new() };
+        let http_client = reqwest::Client::builder()
+            .user_agent(concat!("eww/", env!("CARGO_PKG_VERSION")))
+            .timeout(HTTP_REQUEST_TIMEOUT)
+            .build()?;
+        let handler = PollVarHandler { evt_send, poll_handles: HashMap::new(), http_client };
         Ok(handler)
     }
 
@@ -159,26 +207,36 @@ impl PollVarHandler {
         let cancellation_token = CancellationToken::new();
         self.poll_handles.insert(var.name.clone(), cancellation_token.clone());
         let evt_send = self.evt_send.clone();
+        let http_client = self.http_client.clone();
         tokio::spawn(async move {
-            let result: Result<_> = (|| {
-                evt_send.send(app::DaemonCommand::UpdateVars(vec![(var.name.clone(), run_poll_once(&var)?)]))?;
-                Ok(())
-            })();
-            if let Err(err) = result {
-                crate::error_handling_ctx::print_error(err);
+            let mut rate_limiter = RateLimiter::new(var.rate_limit.clone());
+            let mut http_cache = HttpCache::default();
+            let send_update = |value: DynVal| {
+                crate::print_result_err!(
+                    "while sending poll var update",
+                    evt_send.send(app::DaemonCommand::UpdateVars(vec![(var.name.clone(), value)]))
+                );
+            };
+
+            // The first value is always applied immediately
+            match run_poll_once(&var, &http_client, &mut http_cache).await {
+                Ok(value) => send_update(value),
+                Err(err) => crate::error_handling_ctx::print_error(err),
             }
 
+            let next_poll = tokio::time::sleep(var.interval);
+            tokio::pin!(next_poll);
             crate::loop_select_exiting! {
                 _ = cancellation_token.cancelled() => break,
-                _ = tokio::time::sleep(var.interval) => {
-                    let result: Result<_> = (|| {
-                        evt_send.send(app::DaemonCommand::UpdateVars(vec![(var.name.clone(), run_poll_once(&var)?)]))?;
-                        Ok(())
-                    })();
-
-                    if let Err(err) = result {
-                        crate::error_handling_ctx::print_error(err);
+                _ = &mut next_poll => {
+                    match run_poll_once(&var, &http_client, &mut http_cache).await {
+                        Ok(value) => rate_limiter.push(value).into_iter().for_each(send_update),
+                        Err(err) => crate::error_handling_ctx::print_error(err),
                     }
+                    next_poll.as_mut().reset(tokio::time::Instant::now() + var.interval);
+                }
+                _ = rate_limiter.wait() => {
+                    rate_limiter.take_pending().into_iter().for_each(send_update);
                 }
             }
         });
@@ -196,21 +254,348 @@ impl PollVarHandler {
     }
 }
 
-fn run_poll_once(var: &PollScriptVar) -> Result<DynVal> {
-    match &var.command {
-        VarSource::Shell(span, command) => {
-            script_var::run_command(command).map_err(|e| anyhow!(create_script_var_failed_warn(*span, &var.name, &e.to_string())))
+/// Applies the `:debounce` and `:throttle` settings of a script var to the values it produces.
+struct RateLimiter {
+    rate_limit: UpdateRateLimit,
+    /// Latest value that is held back until the deadline has passed
+    pending: Option<DynVal>,
+    deadline: Option<Instant>,
+    last_sent: Option<Instant>,
+}
+
+impl RateLimiter {
+    fn new(rate_limit: UpdateRateLimit) -> Self {
+        Self { rate_limit, pending: None, deadline: None, last_sent: None }
+    }
+
+    /// Register a new value, returning it if it should be applied right away.
+    fn push(&mut self, value: DynVal) -> Option<DynVal> {
+        let now = Instant::now();
+        let throttle_end = self.rate_limit.throttle.zip(self.last_sent).map(|(throttle, last_sent)| last_sent + throttle);
+        if let Some(debounce) = self.rate_limit.debounce {
+            self.pending = Some(value);
+            self.deadline = Some(throttle_end.map_or(now + debounce, |throttle_end| throttle_end.max(now + debounce)));
+            None
+        } else if throttle_end.map_or(false, |throttle_end| now < throttle_end) || self.pending.is_some() {
+            self.pending = Some(value);
+            self.deadline = throttle_end;
+            None
+        } else {
+            self.last_sent = Some(now);
+            Some(value)
         }
+    }
+
+    /// Wait until the held back value may be applied. Never completes if there is no such value.
+    async fn wait(&self) {
+        match self.deadline {
+            Some(deadline) => tokio::time::sleep_until(deadline).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Take the value that was held back, if any.
+    fn take_pending(&mut self) -> Option<DynVal> {
+        self.deadline = None;
+        let value = self.pending.take()?;
+        self.last_sent = Some(Instant::now());
+        Some(value)
+    }
+}
+
+async fn run_poll_once(var: &PollScriptVar, http_client: &reqwest::Client, http_cache: &mut HttpCache) -> Result<DynVal> {
+    match &var.command {
+        VarSource::Shell(span, command) => script_var::run_command(command, ProcessOwner::Variable(var.name.clone()))
+            .map_err(|e| anyhow!(create_script_var_failed_warn(*span, &var.name, &e.to_string()))),
+        VarSource::Http(span, request) => fetch_http(http_client, request, http_cache)
+            .await
+            .map_err(|e| anyhow!(create_http_request_failed_warn(*span, &var.name, &format!("{:#}", e)))),
         VarSource::Function(x) => x().map_err(|e| anyhow!(e)),
     }
 }
 
+/// Time after which an HTTP request of a poll var is given up on.
+const HTTP_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Validators and body of the last response to an HTTP poll var,
+/// used to make conditional requests so that unchanged responses don't need to be transferred again.
+#[derive(Default)]
+struct HttpCache {
+    etag: Option<reqwest::header::HeaderValue>,
+    last_modified: Option<reqwest::header::HeaderValue>,
+    body: Option<DynVal>,
+}
+
+/// Perform the GET request of an HTTP poll var, returning the response body.
+async fn fetch_http(client: &reqwest::Client, request: &HttpRequest, cache: &mut HttpCache) -> Result<DynVal> {
+    use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+
+    let mut builder = client.get(&request.url);
+    for (name, value) in &request.headers {
+        builder = builder.header(name, value);
+    }
+    if cache.body.is_some() {
+        if let Some(etag) = &cache.etag {
+            builder = builder.header(IF_NONE_MATCH, etag.clone());
+        }
+        if let Some(last_modified) = &cache.last_modified {
+            builder = builder.header(IF_MODIFIED_SINCE, last_modified.clone());
+        }
+    }
+
+    let response = builder.send().await?;
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        if let Some(body) = &cache.body {
+            log::debug!("{} was not modified, reusing the cached response", request.url);
+            return Ok(body.clone());
+        }
+    }
+    let response = response.error_for_status()?;
+    cache.etag = response.headers().get(ETAG).cloned();
+    cache.last_modified = response.headers().get(LAST_MODIFIED).cloned();
+    let body = DynVal::from(response.text().await?.trim_matches('\n'));
+    cache.body = Some(body.clone());
+    Ok(body)
+}
+
 impl Drop for PollVarHandler {
     fn drop(&mut self) {
         self.stop_all();
     }
 }
 
+/// Handler for variables that are each driven by a single async task, such as dbus listen-vars and watch vars.
+struct TaskVarHandler {
+    /// Kind of variable handled by this handler, used in log messages.
+    kind: &'static str,
+    /// Running tasks by variable, tagged with an id such that a finished task only removes its own entry.
+    tasks: Arc<Mutex<HashMap<VarName, (u64, CancellationToken)>>>,
+    next_task_id: u64,
+}
+
+impl TaskVarHandler {
+    fn new(kind: &'static str) -> Self {
+        TaskVarHandler { kind, tasks: Arc::new(Mutex::new(HashMap::new())), next_task_id: 0 }
+    }
+
+    /// Run the task for a variable, unless that variable is already running.
+    /// Once the task exits on its own, the variable is forgotten again such that it can be restarted later.
+    fn start(&mut self, name: VarName, task: impl Future<Output = Result<()>> + Send + 'static) {
+        let mut tasks = self.tasks.lock().unwrap();
+        if tasks.contains_key(&name) {
+            return;
+        }
+
+        log::debug!("starting {} {}", self.kind, name);
+        let cancellation_token = CancellationToken::new();
+        self.next_task_id += 1;
+        let task_id = self.next_task_id;
+        tasks.insert(name.clone(), (task_id, cancellation_token.clone()));
+        let tasks = self.tasks.clone();
+        let kind = self.kind;
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = cancellation_token.cancelled() => return,
+                result = task => {
+                    if let Err(err) = result {
+                        log::error!("{:?}", err);
+                    }
+                }
+            }
+            let mut tasks = tasks.lock().unwrap();
+            if tasks.get(&name).is_some_and(|(id, _)| *id == task_id) {
+                log::debug!("{} {} exited", kind, name);
+                tasks.remove(&name);
+            }
+        });
+    }
+
+    fn stop_for_variable(&mut self, name: &VarName) {
+        if let Some((_, token)) = self.tasks.lock().unwrap().remove(name) {
+            log::debug!("stopped {} {}", self.kind, name);
+            token.cancel()
+        }
+    }
+
+    fn stop_all(&mut self) {
+        self.tasks.lock().unwrap().drain().for_each(|(_, (_, token))| token.cancel());
+    }
+}
+
+impl Drop for TaskVarHandler {
+    fn drop(&mut self) {
+        self.stop_all();
+    }
+}
+
+/// Subscribe to the signal or property of a dbus listen-var, updating the variable with every value received.
+async fn run_dbus_listener(var: &DBusListenVar, evt_send: &UnboundedSender<DaemonCommand>) -> Result<()> {
+    use futures::StreamExt;
+
+    let con = match var.bus {
+        DBusBusType::Session => zbus::Connection::session().await?,
+        DBusBusType::System => zbus::Connection::system().await?,
+    };
+    let mut rate_limiter = RateLimiter::new(var.rate_limit.clone());
+    let send_update = |value: DynVal| evt_send.send(DaemonCommand::UpdateVars(vec![(var.name.clone(), value)]));
+
+    match &var.subscription {
+        DBusSubscription::Signal(member) => {
+            let mut rule = zbus::MatchRule::builder().msg_type(zbus::MessageType::Signal).member(member.as_str())?;
+            if let Some(destination) = &var.destination {
+                rule = rule.sender(destination.as_str())?;
+            }
+            if let Some(path) = &var.path {
+                rule = rule.path(path.as_str())?;
+            }
+            if let Some(interface) = &var.interface {
+                rule = rule.interface(interface.as_str())?;
+            }
+            let mut messages = zbus::MessageStream::for_match_rule(rule.build(), &con, None).await?;
+            crate::loop_select_exiting! {
+                Some(message) = messages.next() => {
+                    let message = message?;
+                    let value = dbus_message_body_to_json(&message)?;
+                    if let Some(value) = rate_limiter.push(DynVal::from_string(value.to_string())) {
+                        send_update(value)?;
+                    }
+                }
+                _ = rate_limiter.wait() => {
+                    if let Some(value) = rate_limiter.take_pending() {
+                        send_update(value)?;
+                    }
+                }
+                else => break,
+            }
+        }
+        DBusSubscription::Property(property) => {
+            // destination, path and interface are required for property subscriptions when parsing the definition
+            let proxy = zbus::Proxy::new(
+                &con,
+                var.destination.clone().unwrap_or_default(),
+                var.path.clone().unwrap_or_default(),
+                var.interface.clone().unwrap_or_default(),
+            )
+            .await?;
+            let mut changes = proxy.receive_property_changed::<zvariant::OwnedValue>(property).await;
+            let initial = proxy.get_property::<zvariant::OwnedValue>(property).await?;
+            send_update(DynVal::from_string(dbus_value_to_json(&initial).to_string()))?;
+            crate::loop_select_exiting! {
+                Some(change) = changes.next() => {
+                    let value = change.get().await?;
+                    let value = dbus_value_to_json(&value).to_string();
+                    if let Some(value) = rate_limiter.push(DynVal::from_string(value)) {
+                        send_update(value)?;
+                    }
+                }
+                _ = rate_limiter.wait() => {
+                    if let Some(value) = rate_limiter.take_pending() {
+                        send_update(value)?;
+                    }
+                }
+                else => break,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Convert the arguments of a dbus message into a JSON array.
+fn dbus_message_body_to_json(message: &zbus::Message) -> Result<serde_json::Value> {
+    if message.body_signature().map_or(true, |signature| signature.is_empty()) {
+        return Ok(serde_json::Value::Array(Vec::new()));
+    }
+    let body: zvariant::Structure = message.body()?;
+    Ok(serde_json::Value::Array(body.fields().iter().map(dbus_value_to_json).collect()))
+}
+
+fn dbus_value_to_json(value: &zvariant::Value) -> serde_json::Value {
+    use zvariant::Value;
+    match value {
+        Value::U8(x) => (*x).into(),
+        Value::Bool(x) => (*x).into(),
+        Value::I16(x) => (*x).into(),
+        Value::U16(x) => (*x).into(),
+        Value::I32(x) => (*x).into(),
+        Value::U32(x) => (*x).into(),
+        Value::I64(x) => (*x).into(),
+        Value::U64(x) => (*x).into(),
+        Value::F64(x) => (*x).into(),
+        Value::Str(x) => x.as_str().into(),
+        Value::Signature(x) => x.as_str().into(),
+        Value::ObjectPath(x) => x.as_str().into(),
+        Value::Value(x) => dbus_value_to_json(x),
+        Value::Array(x) => x.get().iter().map(dbus_value_to_json).collect(),
+        Value::Structure(x) => x.fields().iter().map(dbus_value_to_json).collect(),
+        Value::Dict(x) => dbus_dict_to_json(x),
+        Value::Fd(_) => serde_json::Value::Null,
+    }
+}
+
+/// Convert a dbus dictionary into a JSON object. Dictionaries with keys that can't be represented as a string
+/// are converted into their textual representation instead.
+fn dbus_dict_to_json(dict: &zvariant::Dict) -> serde_json::Value {
+    macro_rules! try_key_types {
+        ($($key:ty),*) => {$(
+            if let Ok(map) = HashMap::<$key, zvariant::Value>::try_from(dict.clone()) {
+                return map.iter().map(|(key, value)| (key.to_string(), dbus_value_to_json(value))).collect();
+            }
+        )*};
+    }
+    try_key_types!(String, zvariant::ObjectPath, u8, i16, u16, i32, u32, i64, u64);
+    serde_json::Value::String(dict.to_string())
+}
+
+/// Watch the file of a watch var, updating the variable with its contents whenever it changes.
+async fn run_file_watch(var: &FileWatchVar, evt_send: &UnboundedSender<DaemonCommand>) -> Result<()> {
+    use notify::{RecursiveMode, Watcher};
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| match res {
+        Ok(event) if !event.kind.is_access() => {
+            let _ = tx.send(event.paths);
+        }
+        Ok(_) => {}
+        Err(err) => log::error!("Encountered error while watching file: {}", err),
+    })?;
+    // Watch the directory rather than the file itself, such that the file may be replaced or created later on.
+    let directory = var.path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(std::path::Path::new("."));
+    watcher.watch(directory, RecursiveMode::NonRecursive)?;
+
+    let mut rate_limiter = RateLimiter::new(var.rate_limit.clone());
+    let send_update = |value: DynVal| evt_send.send(DaemonCommand::UpdateVars(vec![(var.name.clone(), value)]));
+    let read_file = || match script_var::read_watched_file(var) {
+        Ok(value) => Some(value),
+        Err(err) if err.downcast_ref::<std::io::Error>().is_some_and(|err| err.kind() == std::io::ErrorKind::NotFound) => {
+            Some(var.initial_value.clone())
+        }
+        Err(err) => {
+            log::warn!("Failed to read {} for variable {}: {:?}", var.path.display(), var.name, err);
+            None
+        }
+    };
+
+    if let Some(value) = read_file() {
+        send_update(value)?;
+    }
+    crate::loop_select_exiting! {
+        Some(paths) = rx.recv() => {
+            if paths.iter().any(|path| path.file_name() == var.path.file_name()) {
+                if let Some(value) = read_file().and_then(|value| rate_limiter.push(value)) {
+                    send_update(value)?;
+                }
+            }
+        }
+        _ = rate_limiter.wait() => {
+            if let Some(value) = rate_limiter.take_pending() {
+                send_update(value)?;
+            }
+        }
+        else => break,
+    }
+    Ok(())
+}
+
 struct ListenVarHandler {
     evt_send: UnboundedSender<DaemonCommand>,
     listen_process_handles: HashMap<VarName, cancellation::AwaitableCancelationSender>,
@@ -237,54 +622,77 @@ impl ListenVarHandler {
 
         let evt_send = self.evt_send.clone();
         tokio::spawn(async move {
-            let result: Result<_> = async {
-                let mut handle = unsafe {
-                    tokio::process::Command::new("sh")
-                        .args(["-c", &var.command])
-                        .stdout(std::process::Stdio::piped())
-                        .stderr(std::process::Stdio::piped())
-                        .stdin(std::process::Stdio::null())
-                        .pre_exec(|| {
-                            let _ = setpgid(Pid::from_raw(0), Pid::from_raw(0));
-                            Ok(())
-                        })
-                        .spawn()?
-                };
-                let mut stdout_lines = BufReader::new(handle.stdout.take().unwrap()).lines();
-                let mut stderr_lines = BufReader::new(handle.stderr.take().unwrap()).lines();
-                let mut completion_notify = None;
-                crate::loop_select_exiting! {
-                    _ = handle.wait() => break,
-                    notify = cancel_recv.wait_for_cancel() => {
-                        completion_notify = notify;
-                        break;
+            let policy = &var.restart_policy;
+            let mut consecutive_restarts = 0;
+            let mut total_restarts = 0;
+            loop {
+                let started_at = Instant::now();
+                let exit = match run_listen_source(&var, total_restarts, &evt_send, &mut cancel_recv).await {
+                    Ok(exit) => exit,
+                    Err(err) => {
+                        log::error!(
+                            "[{}:{}] Error while executing listen-var command {}: {:?}",
+                            ::std::file!(),
+                            ::std::line!(),
+                            &var.source,
+                            err
+                        );
+                        return;
                     }
-                    Ok(Some(line)) = stdout_lines.next_line() => {
-                        let new_value = DynVal::from_string(line.to_owned());
-                        evt_send.send(DaemonCommand::UpdateVars(vec![(var.name.to_owned(), new_value)]))?;
+                };
+                match exit {
+                    ListenScriptExit::Exited(status) if status.success() => {
+                        log::info!("listen-var {} exited successfully", var.name)
                     }
-                    Ok(Some(line)) = stderr_lines.next_line() => {
-                        log::warn!("stderr of `{}`: {}", var.name, line);
+                    ListenScriptExit::Exited(status) => log::warn!("listen-var {} exited with {}", var.name, status),
+                    ListenScriptExit::Closed => log::info!("the source of listen-var {} was closed", var.name),
+                    ListenScriptExit::Cancelled(completion_notify) => {
+                        if let Some(completion_notify) = completion_notify {
+                            completion_notify.completed().await;
+                        }
+                        return;
                     }
-                    else => break,
-                };
-                terminate_handle(handle).await;
+                }
 
-                if let Some(completion_notify) = completion_notify {
-                    completion_notify.completed().await;
+                match policy.on_exit {
+                    ListenExitBehavior::Keep => return,
+                    ListenExitBehavior::Clear => {
+                        crate::print_result_err!(
+                            "resetting listen-var after its script exited",
+                            evt_send.send(DaemonCommand::UpdateVars(vec![(var.name.clone(), var.initial_value.clone())]))
+                        );
+                        return;
+                    }
+                    ListenExitBehavior::Restart => {}
                 }
-                Ok(())
-            }
-            .await;
 
-            if let Err(err) = result {
-                log::error!(
-                    "[{}:{}] Error while executing listen-var command {}: {:?}",
-                    ::std::file!(),
-                    ::std::line!(),
-                    &var.command,
-                    err
-                );
+                // A script that ran for a while before exiting isn't failing repeatedly, so start backing off from scratch.
+                if started_at.elapsed() > MAX_LISTEN_RESTART_DELAY {
+                    consecutive_restarts = 0;
+                }
+                if policy.max_restarts.is_some_and(|max| consecutive_restarts >= max) {
+                    log::error!(
+                        "listen-var {} exited {} times in a row, not restarting it again",
+                        var.name,
+                        consecutive_restarts + 1
+                    );
+                    return;
+                }
+                let delay =
+                    policy.restart_delay.saturating_mul(2u32.saturating_pow(consecutive_restarts)).min(MAX_LISTEN_RESTART_DELAY);
+                consecutive_restarts += 1;
+                total_restarts += 1;
+                log::info!("restarting listen-var {} in {}ms", var.name, delay.as_millis());
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    notify = cancel_recv.wait_for_cancel() => {
+                        if let Some(completion_notify) = notify {
+                            completion_notify.completed().await;
+                        }
+                        return;
+                    }
+                    Ok(()) = crate::application_lifecycle::recv_exit() => return,
+                }
             }
         });
     }
@@ -297,9 +705,9 @@ impl ListenVarHandler {
     }
 
     async fn stop_all(&mut self) {
-        for (_, token) in self.listen_process_handles.drain() {
-            token.cancel().await;
-        }
+        // terminate all processes concurrently, as each one may take a while to react to SIGTERM
+        futures::future::join_all(self.listen_process_handles.drain().map(|(_, token)| async move { token.cancel().await }))
+            .await;
     }
 }
 
@@ -321,6 +729,157 @@ impl Drop for ListenVarHandler {
     }
 }
 
+/// Upper bound of the time waited before restarting a listen-var, no matter how often it failed.
+const MAX_LISTEN_RESTART_DELAY: Duration = Duration::from_secs(60);
+
+/// How a single run of a listen-var script ended.
+enum ListenScriptExit {
+    /// The script exited on its own with the given status.
+    Exited(std::process::ExitStatus),
+    /// The socket or pipe the listen-var reads from was closed by the other side.
+    Closed,
+    /// The listen-var was stopped, or eww is shutting down.
+    Cancelled(Option<cancellation::CancelCompletionNotifier>),
+}
+
+/// Read from the source of a listen var once, updating the variable whenever a new line is received.
+/// `restarts` is the amount of times the listen var was restarted before.
+async fn run_listen_source(
+    var: &ListenScriptVar,
+    restarts: u32,
+    evt_send: &UnboundedSender<DaemonCommand>,
+    cancel_recv: &mut cancellation::AwaitableCancelationReceiver,
+) -> Result<ListenScriptExit> {
+    match &var.source {
+        ListenSource::Command(command) => run_listen_script(var, command, restarts, evt_send, cancel_recv).await,
+        ListenSource::Socket(path) => match tokio::net::UnixStream::connect(path).await {
+            Ok(stream) => read_listen_lines(var, BufReader::new(stream).lines(), evt_send, cancel_recv).await,
+            Err(err) => Ok(source_unavailable(var, err.into())),
+        },
+        ListenSource::Fifo(path) => {
+            let pipe = tokio::net::unix::pipe::OpenOptions::new();
+            // Keep a writing end open ourselves, such that the pipe doesn't report EOF whenever a writer disconnects.
+            match pipe.open_receiver(path).and_then(|receiver| Ok((receiver, pipe.open_sender(path)?))) {
+                Ok((receiver, _sender)) => read_listen_lines(var, BufReader::new(receiver).lines(), evt_send, cancel_recv).await,
+                Err(err) => Ok(source_unavailable(var, err.into())),
+            }
+        }
+        ListenSource::Inbuilt(name) => {
+            let (reader, writer) = tokio::io::duplex(4096);
+            tokio::select! {
+                exit = read_listen_lines(var, BufReader::new(reader).lines(), evt_send, cancel_recv) => exit,
+                result = crate::config::inbuilt::run_inbuilt_listener(name, writer) => {
+                    if let Err(err) = result {
+                        log::error!("Inbuilt listener {} of listen-var {} failed: {:?}", name, var.name, err);
+                    }
+                    Ok(ListenScriptExit::Closed)
+                }
+            }
+        }
+    }
+}
+
+/// Report that the source of a listen var could not be opened.
+/// This is treated like the source being closed, such that the restart policy of the var decides whether to try again.
+fn source_unavailable(var: &ListenScriptVar, err: anyhow::Error) -> ListenScriptExit {
+    log::error!("Failed to open {} for listen-var {}: {:?}", var.source, var.name, err);
+    ListenScriptExit::Closed
+}
+
+/// Update a listen var with every line read from a socket or pipe, until it is closed.
+async fn read_listen_lines<R: tokio::io::AsyncBufRead + Unpin>(
+    var: &ListenScriptVar,
+    mut lines: tokio::io::Lines<R>,
+    evt_send: &UnboundedSender<DaemonCommand>,
+    cancel_recv: &mut cancellation::AwaitableCancelationReceiver,
+) -> Result<ListenScriptExit> {
+    let mut exit = ListenScriptExit::Cancelled(None);
+    let mut rate_limiter = RateLimiter::new(var.rate_limit.clone());
+    crate::loop_select_exiting! {
+        notify = cancel_recv.wait_for_cancel() => {
+            exit = ListenScriptExit::Cancelled(notify);
+            break;
+        }
+        line = lines.next_line() => match line? {
+            Some(line) => {
+                if let Some(new_value) = rate_limiter.push(DynVal::from_string(line)) {
+                    evt_send.send(DaemonCommand::UpdateVars(vec![(var.name.to_owned(), new_value)]))?;
+                }
+            }
+            None => {
+                exit = ListenScriptExit::Closed;
+                break;
+            }
+        },
+        _ = rate_limiter.wait() => {
+            if let Some(new_value) = rate_limiter.take_pending() {
+                evt_send.send(DaemonCommand::UpdateVars(vec![(var.name.to_owned(), new_value)]))?;
+            }
+        }
+    };
+    if let Some(new_value) = rate_limiter.take_pending() {
+        evt_send.send(DaemonCommand::UpdateVars(vec![(var.name.to_owned(), new_value)]))?;
+    }
+    Ok(exit)
+}
+
+/// Run the script of a listen var once, updating the variable whenever it outputs a new line.
+async fn run_listen_script(
+    var: &ListenScriptVar,
+    command: &str,
+    restarts: u32,
+    evt_send: &UnboundedSender<DaemonCommand>,
+    cancel_recv: &mut cancellation::AwaitableCancelationReceiver,
+) -> Result<ListenScriptExit> {
+    let mut handle = unsafe {
+        tokio::process::Command::new("sh")
+            .args(["-c", command])
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .stdin(std::process::Stdio::null())
+            .pre_exec(|| {
+                let _ = setpgid(Pid::from_raw(0), Pid::from_raw(0));
+                Ok(())
+            })
+            .spawn()?
+    };
+    let _registration = process_registry::register(handle.id(), ProcessOwner::Variable(var.name.clone()), command, restarts);
+    let mut stdout_lines = BufReader::new(handle.stdout.take().unwrap()).lines();
+    let mut stderr_lines = BufReader::new(handle.stderr.take().unwrap()).lines();
+    let mut exit = ListenScriptExit::Cancelled(None);
+    let mut rate_limiter = RateLimiter::new(var.rate_limit.clone());
+    crate::loop_select_exiting! {
+        status = handle.wait() => {
+            exit = ListenScriptExit::Exited(status?);
+            break;
+        }
+        notify = cancel_recv.wait_for_cancel() => {
+            exit = ListenScriptExit::Cancelled(notify);
+            break;
+        }
+        Ok(Some(line)) = stdout_lines.next_line() => {
+            let new_value = DynVal::from_string(line.to_owned());
+            if let Some(new_value) = rate_limiter.push(new_value) {
+                evt_send.send(DaemonCommand::UpdateVars(vec![(var.name.to_owned(), new_value)]))?;
+            }
+        }
+        _ = rate_limiter.wait() => {
+            if let Some(new_value) = rate_limiter.take_pending() {
+                evt_send.send(DaemonCommand::UpdateVars(vec![(var.name.to_owned(), new_value)]))?;
+            }
+        }
+        Ok(Some(line)) = stderr_lines.next_line() => {
+            log::warn!("stderr of `{}`: {}", var.name, line);
+        }
+        else => break,
+    };
+    if let Some(new_value) = rate_limiter.take_pending() {
+        evt_send.send(DaemonCommand::UpdateVars(vec![(var.name.to_owned(), new_value)]))?;
+    }
+    terminate_handle(handle).await;
+    Ok(exit)
+}
+
 async fn terminate_handle(mut child: tokio::process::Child) {
     if let Some(id) = child.id() {
         log::debug!("Killing process with id {}", id);
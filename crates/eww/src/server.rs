@@ -1,12 +1,14 @@
 use crate::{
     app::{self, App, DaemonCommand},
-    config, daemon_response,
+    config, crash_report, daemon_response,
     display_backend::DisplayBackend,
     error_handling_ctx, ipc_server, script_var_handler,
     state::scope_graph::ScopeGraph,
     EwwPaths,
 };
 use anyhow::{Context, Result};
+use eww_shared_util::VarName;
+use simplexpr::dynval::DynVal;
 
 use std::{
     cell::RefCell,
@@ -14,9 +16,8 @@ use std::{
     io::Write,
     marker::PhantomData,
     os::unix::io::AsRawFd,
-    path::Path,
+    path::{Path, PathBuf},
     rc::Rc,
-    sync::{atomic::Ordering, Arc},
 };
 use tokio::sync::mpsc::*;
 
@@ -24,6 +25,7 @@ pub fn initialize_server<B: DisplayBackend>(
     paths: EwwPaths,
     action: Option<DaemonCommand>,
     should_daemonize: bool,
+    auto_reload: bool,
 ) -> Result<ForkResult> {
     let (ui_send, mut ui_recv) = tokio::sync::mpsc::unbounded_channel();
 
@@ -51,6 +53,9 @@ pub fn initialize_server<B: DisplayBackend>(
             return Ok(ForkResult::Parent);
         }
     }
+    if let Err(err) = crate::logging::set_structured_log_file(paths.get_structured_log_file()) {
+        log::error!("{:?}", err);
+    }
 
     println!(
         r#"
@@ -68,15 +73,28 @@ pub fn initialize_server<B: DisplayBackend>(
         }
     });
 
+    let previous_gdk_backend = std::env::var_os("GDK_BACKEND");
     if B::IS_WAYLAND {
-        std::env::set_var("GDK_BACKEND", "wayland")
+        // x11 is allowed as a fallback, such that windows with `:force-x11` can be opened through XWayland
+        std::env::set_var("GDK_BACKEND", "wayland,x11")
     }
     gtk::init()?;
+    // GDK only reads this while initializing, so it is restored to not leak into the scripts and commands run by eww
+    if B::IS_WAYLAND {
+        match previous_gdk_backend {
+            Some(previous) => std::env::set_var("GDK_BACKEND", previous),
+            None => std::env::remove_var("GDK_BACKEND"),
+        }
+    }
+
+    crash_report::install(paths.get_log_dir());
 
     log::debug!("Initializing script var handler");
     let script_var_handler = script_var_handler::init(ui_send.clone());
 
     let (scope_graph_evt_send, mut scope_graph_evt_recv) = tokio::sync::mpsc::unbounded_channel();
+    let (watched_files_send, watched_files_recv) =
+        tokio::sync::watch::channel(WatchedFiles { config: WatchedFiles::loaded_config_files(), stylesheet: HashSet::new() });
 
     let mut app: App<B> = app::App {
         scope_graph: Rc::new(RefCell::new(ScopeGraph::from_global_vars(
@@ -88,39 +106,94 @@ pub fn initialize_server<B: DisplayBackend>(
         failed_windows: HashSet::new(),
         instance_id_to_args: HashMap::new(),
         css_provider: gtk::CssProvider::new(),
+        window_css_providers: HashMap::new(),
+        stylesheet_vars: HashSet::new(),
+        watched_files_send,
         script_var_handler,
         app_evt_send: ui_send.clone(),
         window_close_timer_abort_senders: HashMap::new(),
+        error_overlay: None,
+        started_at: std::time::Instant::now(),
+        config_hash: app::hash_config_files(&WatchedFiles::loaded_config_files()),
+        var_update_stats: HashMap::new(),
         paths,
         phantom: PhantomData,
     };
+    crash_report::set_scope_graph(&app.scope_graph);
+
+    // re-evaluate window monitors whenever a monitor gets added or removed
+    if let Some(display) = gtk::gdk::Display::default() {
+        display.connect_monitor_added(gtk::glib::clone!(@strong ui_send => move |_, _| {
+            let _ = ui_send.send(app::DaemonCommand::MonitorsChanged);
+        }));
+        display.connect_monitor_removed(gtk::glib::clone!(@strong ui_send => move |_, _| {
+            let _ = ui_send.send(app::DaemonCommand::MonitorsChanged);
+        }));
+    }
 
     if let Some(screen) = gtk::gdk::Screen::default() {
         gtk::StyleContext::add_provider_for_screen(&screen, &app.css_provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
     }
 
-    if let Ok((file_id, css)) = config::scss::parse_scss_from_config(app.paths.get_config_dir()) {
-        if let Err(e) = app.load_css(file_id, &css) {
-            error_handling_ctx::print_error(e);
-        }
+    app.apply_gtk_settings();
+    crate::widgets::set_command_shell(app.eww_config.get_config_options().shell.clone());
+    for err in app.reload_stylesheets() {
+        error_handling_ctx::print_error(err);
     }
 
     // initialize all the handlers and tasks running asyncronously
-    let tokio_handle = init_async_part(app.paths.clone(), ui_send);
+    let tokio_handle = init_async_part(app.paths.clone(), ui_send, auto_reload.then_some(watched_files_recv));
 
     gtk::glib::MainContext::default().spawn_local(async move {
+        app.prepare_eager_windows();
+
         // if an action was given to the daemon initially, execute it first.
         if let Some(action) = action {
             app.handle_command(action);
         }
 
+        let mut printed_errors = error_handling_ctx::subscribe_printed_errors();
+
+        // Variable updates that arrive within a frame of the last applied update are collected and then applied together,
+        // such that a burst of updates to the same variable only results in a single UI update.
+        // An update arriving while nothing happened for a frame is applied right away.
+        let mut pending_updates: Vec<(VarName, DynVal)> = Vec::new();
+        let mut last_flush: Option<tokio::time::Instant> = None;
+        let flush_timer = tokio::time::sleep(std::time::Duration::ZERO);
+        tokio::pin!(flush_timer);
+
         loop {
             tokio::select! {
                 Some(scope_graph_evt) = scope_graph_evt_recv.recv() => {
                     app.scope_graph.borrow_mut().handle_scope_graph_event(scope_graph_evt);
                 },
-                Some(ui_event) = ui_recv.recv() => {
-                    app.handle_command(ui_event);
+                Some(ui_event) = ui_recv.recv() => match ui_event {
+                    DaemonCommand::UpdateVars(mappings) => {
+                        let now = tokio::time::Instant::now();
+                        let next_flush = last_flush.map(|last_flush| last_flush + UPDATE_BATCH_DURATION).filter(|next_flush| *next_flush > now);
+                        if !pending_updates.is_empty() {
+                            pending_updates.extend(mappings);
+                        } else if let Some(next_flush) = next_flush {
+                            flush_timer.as_mut().reset(next_flush);
+                            pending_updates.extend(mappings);
+                        } else {
+                            app.handle_command(DaemonCommand::UpdateVars(coalesce_var_updates(mappings)));
+                            last_flush = Some(now);
+                        }
+                    }
+                    ui_event => {
+                        // apply pending updates first, to keep commands in order
+                        if !pending_updates.is_empty() {
+                            app.handle_command(DaemonCommand::UpdateVars(coalesce_var_updates(std::mem::take(&mut pending_updates))));
+                            last_flush = Some(tokio::time::Instant::now());
+                        }
+                        app.handle_command(ui_event);
+                    }
+                },
+                Ok(error) = printed_errors.recv() => app.show_error_overlay(error),
+                _ = &mut flush_timer, if !pending_updates.is_empty() => {
+                    app.handle_command(DaemonCommand::UpdateVars(coalesce_var_updates(std::mem::take(&mut pending_updates))));
+                    last_flush = Some(tokio::time::Instant::now());
                 }
                 else => break,
             }
@@ -136,7 +209,27 @@ pub fn initialize_server<B: DisplayBackend>(
     Ok(ForkResult::Child)
 }
 
-fn init_async_part(paths: EwwPaths, ui_send: UnboundedSender<app::DaemonCommand>) -> tokio::runtime::Handle {
+/// Duration of a single frame at 60fps, during which variable updates get batched together.
+const UPDATE_BATCH_DURATION: std::time::Duration = std::time::Duration::from_millis(16);
+
+/// Deduplicate a batch of variable updates, keeping only the latest value of each variable.
+/// Variables are applied in the order of their first update.
+fn coalesce_var_updates(updates: Vec<(VarName, DynVal)>) -> Vec<(VarName, DynVal)> {
+    let mut latest_values: HashMap<VarName, DynVal> = HashMap::new();
+    let mut order = Vec::new();
+    for (name, value) in updates {
+        if latest_values.insert(name.clone(), value).is_none() {
+            order.push(name);
+        }
+    }
+    order.into_iter().filter_map(|name| latest_values.remove_entry(&name)).collect()
+}
+
+fn init_async_part(
+    paths: EwwPaths,
+    ui_send: UnboundedSender<app::DaemonCommand>,
+    watched_files: Option<tokio::sync::watch::Receiver<WatchedFiles>>,
+) -> tokio::runtime::Handle {
     let rt = tokio::runtime::Builder::new_multi_thread()
         .thread_name("main-async-runtime")
         .enable_all()
@@ -151,7 +244,12 @@ fn init_async_part(paths: EwwPaths, ui_send: UnboundedSender<app::DaemonCommand>
                 let filewatch_join_handle = {
                     let ui_send = ui_send.clone();
                     let paths = paths.clone();
-                    tokio::spawn(async move { run_filewatch(paths.config_dir, ui_send).await })
+                    tokio::spawn(async move {
+                        match watched_files {
+                            Some(watched_files) => run_filewatch(paths.config_dir, ui_send, watched_files).await,
+                            None => Ok(()),
+                        }
+                    })
                 };
 
                 let ipc_server_join_handle = {
@@ -166,7 +264,8 @@ fn init_async_part(paths: EwwPaths, ui_send: UnboundedSender<app::DaemonCommand>
                         let _ = crate::application_lifecycle::recv_exit().await;
                         log::debug!("Forward task received exit event");
                         // Then forward that to the application
-                        let _ = ui_send.send(app::DaemonCommand::KillServer);
+                        let _ =
+                            ui_send.send(app::DaemonCommand::KillServer { timeout: app::SCRIPT_SHUTDOWN_TIMEOUT, sender: None });
                     })
                 };
 
@@ -182,19 +281,59 @@ fn init_async_part(paths: EwwPaths, ui_send: UnboundedSender<app::DaemonCommand>
     handle
 }
 
+/// What needs to be reloaded after a file changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum FileChange {
+    Stylesheet,
+    Config,
+}
+
+impl FileChange {
+    fn of_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "yuck" => Some(FileChange::Config),
+            "scss" | "css" => Some(FileChange::Stylesheet),
+            _ => None,
+        }
+    }
+}
+
+/// Files the configuration was loaded from, which may be outside of the configuration directory.
+#[derive(Debug, Default)]
+pub struct WatchedFiles {
+    /// The yuck files, including all included files
+    pub config: HashSet<PathBuf>,
+    /// The stylesheet and all files it imports
+    pub stylesheet: HashSet<PathBuf>,
+}
+
+impl WatchedFiles {
+    /// Get the yuck files that were loaded as part of the most recently read configuration.
+    pub fn loaded_config_files() -> HashSet<PathBuf> {
+        let file_db = error_handling_ctx::FILE_DATABASE.read().unwrap();
+        file_db.file_paths().map(|path| std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())).collect()
+    }
+}
+
+/// Time to wait for further changes after a file changed, before reloading.
+/// Editors often write files in multiple steps, and reading the file too early may yield an empty or partial file.
+const FILE_CHANGE_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(200);
+
 /// Watch configuration files for changes, sending reload events to the eww app when the files change.
-async fn run_filewatch<P: AsRef<Path>>(config_dir: P, evt_send: UnboundedSender<app::DaemonCommand>) -> Result<()> {
+/// Changes to the stylesheet only cause the stylesheet to be reapplied, without reloading the rest of the configuration.
+/// Files that are included in the configuration from outside of the config directory are watched as well.
+async fn run_filewatch<P: AsRef<Path>>(
+    config_dir: P,
+    evt_send: UnboundedSender<app::DaemonCommand>,
+    mut watched_files: tokio::sync::watch::Receiver<WatchedFiles>,
+) -> Result<()> {
     use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 
     let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
     let mut watcher: RecommendedWatcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| match res {
         Ok(notify::Event { kind: notify::EventKind::Modify(_), paths, .. }) => {
-            let relevant_files_changed = paths.iter().any(|path| {
-                let ext = path.extension().unwrap_or_default();
-                ext == "yuck" || ext == "scss" || ext == "css"
-            });
-            if relevant_files_changed {
-                if let Err(err) = tx.send(()) {
+            if let Some(change) = paths.iter().filter_map(|path| FileChange::of_path(path)).max() {
+                if let Err(err) = tx.send(change) {
                     log::warn!("Error forwarding file update event: {:?}", err);
                 }
             }
@@ -203,34 +342,58 @@ async fn run_filewatch<P: AsRef<Path>>(config_dir: P, evt_send: UnboundedSender<
         Err(e) => log::error!("Encountered Error While Watching Files: {}", e),
     })?;
     watcher.watch(config_dir.as_ref(), RecursiveMode::Recursive)?;
+    // the watched file paths are canonicalized, so the config dir needs to be as well to check which files are in it
+    let canonical_config_dir = std::fs::canonicalize(config_dir.as_ref()).unwrap_or_else(|_| config_dir.as_ref().to_path_buf());
+    let mut watched_outside_config_dir = HashSet::new();
+    // mark the initial value as changed, such that files outside of the config dir are watched right away
+    watched_files.mark_changed();
 
-    // make sure to not trigger reloads too much by only accepting one reload every 500ms.
-    let debounce_done = Arc::new(std::sync::atomic::AtomicBool::new(true));
+    let mut pending_change: Option<FileChange> = None;
+    let reload_timer = tokio::time::sleep(std::time::Duration::ZERO);
+    tokio::pin!(reload_timer);
 
     crate::loop_select_exiting! {
-        Some(()) = rx.recv() => {
-            let debounce_done = debounce_done.clone();
-            if debounce_done.swap(false, Ordering::SeqCst) {
-                tokio::spawn(async move {
-                    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
-                    debounce_done.store(true, Ordering::SeqCst);
-                });
-
-                let (daemon_resp_sender, mut daemon_resp_response) = daemon_response::create_pair();
-                // without this sleep, reading the config file sometimes gives an empty file.
-                // This is probably a result of editors not locking the file correctly,
-                // and eww being too fast, thus reading the file while it's empty.
-                // There should be some cleaner solution for this, but this will do for now.
-                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
-                evt_send.send(app::DaemonCommand::ReloadConfigAndCss(daemon_resp_sender))?;
-                tokio::spawn(async move {
-                    match daemon_resp_response.recv().await {
-                        Some(daemon_response::DaemonResponse::Success(_)) => log::info!("Reloaded config successfully"),
-                        Some(daemon_response::DaemonResponse::Failure(e)) => eprintln!("{}", e),
-                        None => log::error!("No response to reload configuration-reload request"),
-                    }
-                });
+        Ok(()) = watched_files.changed() => {
+            let files: HashSet<PathBuf> = {
+                let watched_files = watched_files.borrow_and_update();
+                watched_files.config.iter().chain(watched_files.stylesheet.iter())
+                    .filter(|file| !file.starts_with(&canonical_config_dir))
+                    .cloned()
+                    .collect()
+            };
+            for file in watched_outside_config_dir.difference(&files) {
+                let _ = watcher.unwatch(file);
+            }
+            for file in files.difference(&watched_outside_config_dir) {
+                log::debug!("Watching {}", file.display());
+                if let Err(err) = watcher.watch(file, RecursiveMode::NonRecursive) {
+                    log::warn!("Failed to watch {}: {}", file.display(), err);
+                }
             }
+            watched_outside_config_dir = files;
+        },
+        Some(change) = rx.recv() => {
+            pending_change = pending_change.max(Some(change));
+            reload_timer.as_mut().reset(tokio::time::Instant::now() + FILE_CHANGE_DEBOUNCE);
+        },
+        _ = &mut reload_timer, if pending_change.is_some() => {
+            let Some(change) = pending_change.take() else { continue };
+            let (daemon_resp_sender, mut daemon_resp_response) = daemon_response::create_pair();
+            let command = match change {
+                FileChange::Config => app::DaemonCommand::ReloadConfigAndCss(daemon_resp_sender),
+                FileChange::Stylesheet => app::DaemonCommand::ReloadCss(daemon_resp_sender),
+            };
+            evt_send.send(command)?;
+            tokio::spawn(async move {
+                match daemon_resp_response.recv().await {
+                    Some(daemon_response::DaemonResponse::Success(_)) => match change {
+                        FileChange::Config => log::info!("Reloaded config successfully"),
+                        FileChange::Stylesheet => log::info!("Reloaded stylesheet successfully"),
+                    },
+                    Some(daemon_response::DaemonResponse::Failure(e)) => log::error!("Failed to reload configuration:\n{}", e),
+                    None => log::error!("No response to reload configuration-reload request"),
+                }
+            });
         },
         else => break
     };
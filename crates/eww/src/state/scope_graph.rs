@@ -28,6 +28,8 @@ impl ScopeIndex {
 
 pub enum ScopeGraphEvent {
     RemoveScope(ScopeIndex),
+    /// Update a variable as seen from the given scope, i.e. used by widgets to expose their state.
+    UpdateValue(ScopeIndex, VarName, DynVal),
 }
 
 /// A graph structure of scopes where each scope may inherit from another scope,
@@ -60,6 +62,9 @@ pub struct ScopeGraph {
     pub root_index: ScopeIndex,
     // TODO this should be factored out, it doesn't really belong into this module / struct.
     pub event_sender: UnboundedSender<ScopeGraphEvent>,
+    /// Whether evaluation errors are returned, rather than printed and replaced by an empty value.
+    /// This is only enabled while building a window in strict mode, so that the window fails to open.
+    pub strict: bool,
 }
 
 impl ScopeGraph {
@@ -75,7 +80,7 @@ impl ScopeGraph {
         if let Some(scope) = graph.scope_at_mut(root_index) {
             scope.node_index = root_index;
         }
-        Self { graph, root_index, event_sender }
+        Self { graph, root_index, event_sender, strict: false }
     }
 
     pub fn update_global_value(&mut self, var_name: &VarName, value: DynVal) -> Result<()> {
@@ -87,6 +92,14 @@ impl ScopeGraph {
             ScopeGraphEvent::RemoveScope(scope_index) => {
                 self.remove_scope(scope_index);
             }
+            ScopeGraphEvent::UpdateValue(scope_index, var_name, value) => {
+                // The scope might have been removed in the meantime, in which case nobody cares about the update anymore.
+                if self.graph.scope_at(scope_index).is_some() {
+                    if let Err(err) = self.update_value(scope_index, &var_name, value) {
+                        error_handling_ctx::print_error(err);
+                    }
+                }
+            }
         }
     }
 
@@ -119,6 +132,16 @@ impl ScopeGraph {
         self.graph.visualize()
     }
 
+    /// Number of scopes currently in the graph.
+    pub fn scope_count(&self) -> usize {
+        self.graph.scopes().count()
+    }
+
+    /// Number of distinct listeners registered across all scopes.
+    pub fn listener_count(&self) -> usize {
+        self.graph.scopes().flat_map(|scope| scope.listeners.values().flatten()).map(Rc::as_ptr).collect::<HashSet<_>>().len()
+    }
+
     pub fn currently_used_globals(&self) -> HashSet<VarName> {
         self.variables_used_in_self_or_subscopes_of(self.root_index)
     }
@@ -132,19 +155,34 @@ impl ScopeGraph {
         self.graph.scope_at(index)
     }
 
+    /// Get the name of the window a scope belongs to.
+    /// This follows the scopes that created each other up to the window scope, which is created by the root scope.
+    /// Unlike the superscopes, this also works within custom widgets, whose scopes inherit from the root scope directly.
+    pub fn window_name_of(&self, index: ScopeIndex) -> Option<&str> {
+        let mut scope = self.scope_at(index)?;
+        loop {
+            let ancestor = scope.ancestor?;
+            if ancestor == self.root_index {
+                return Some(scope.name.as_str());
+            }
+            scope = self.scope_at(ancestor)?;
+        }
+    }
+
     pub fn global_scope(&self) -> &Scope {
         self.graph.scope_at(self.root_index).expect("No root scope in graph")
     }
 
     /// Evaluate a [SimplExpr] in a given scope. This will return `Err` if any referenced variables
     /// are not available in the scope. If evaluation fails for other reasons (bad types, etc)
-    /// this will print a warning and return an empty string instead.
+    /// this will print a warning and return an empty string instead, unless [`Self::strict`] is set.
     pub fn evaluate_simplexpr_in_scope(&self, index: ScopeIndex, expr: &SimplExpr) -> Result<DynVal> {
         let needed_vars = self.lookup_variables_in_scope(index, &expr.collect_var_refs())?;
         // TODORW
         // TODO allowing it to fail here is painfully ugly
         match expr.eval(&needed_vars) {
             Ok(value) => Ok(value),
+            Err(err) if self.strict => Err(anyhow!(err)),
             Err(err) => {
                 error_handling_ctx::print_error(anyhow!(err));
                 Ok(DynVal::from(""))
@@ -206,10 +244,13 @@ impl ScopeGraph {
     /// If there are no required_variables in the listener, nothing gets registered, but the listener
     /// gets called once.
     /// This should be used to update the gtk widgets that are in a scope.
-    /// This also calls the listener initially.
+    /// This also calls the listener initially. If that initial call fails, the error is printed, or returned if [`Self::strict`] is set.
     pub fn register_listener(&mut self, scope_index: ScopeIndex, listener: Listener) -> Result<()> {
         if listener.needed_variables.is_empty() {
             if let Err(err) = (*listener.f)(self, HashMap::new()).context("Error while updating UI after state change") {
+                if self.strict {
+                    return Err(err);
+                }
                 error_handling_ctx::print_error(err);
             }
         } else {
@@ -224,6 +265,9 @@ impl ScopeGraph {
 
             let required_variables = self.lookup_variables_in_scope(scope_index, &listener.needed_variables)?;
             if let Err(err) = (*listener.f)(self, required_variables).context("Error while updating UI after state change") {
+                if self.strict {
+                    return Err(err);
+                }
                 error_handling_ctx::print_error(err);
             }
 
@@ -263,6 +307,14 @@ impl ScopeGraph {
         Ok(())
     }
 
+    /// Set the value of a variable that is defined in the given scope itself, such as an argument of a window.
+    /// Unlike [`Self::update_value`], this never updates a variable of the same name in a superscope.
+    pub fn set_value_in_scope(&mut self, scope_index: ScopeIndex, var_name: &VarName, value: DynVal) -> Result<()> {
+        let scope = self.graph.scope_at_mut(scope_index).context("Scope not in graph")?;
+        scope.data.insert(var_name.clone(), value);
+        self.notify_value_changed(scope_index, var_name)
+    }
+
     /// Notify a scope that a value has been changed. This triggers the listeners and notifies further subscopes scopes recursively.
     pub fn notify_value_changed(&mut self, scope_index: ScopeIndex, updated_var: &VarName) -> Result<()> {
         // Update scopes that reference the changed variable in their attribute expressions.
@@ -396,6 +448,14 @@ mod internal {
 
         /// Edges from superscopes to subscopes.
         pub(super) inheritance_relations: OneToNElementsMap<ScopeIndex, Inherits>,
+
+        /// Reverse index of [`Self::inheritance_relations`]:
+        /// superscope -> variable -> subscopes that reference that variable from the superscope.
+        subscopes_referencing_index: HashMap<ScopeIndex, HashMap<VarName, HashSet<ScopeIndex>>>,
+
+        /// Reverse index of [`Self::hierarchy_relations`]:
+        /// ancestor -> variable -> descendants that are provided an attribute whose expression references that variable.
+        attr_users_index: HashMap<ScopeIndex, HashMap<VarName, HashSet<ScopeIndex>>>,
     }
 
     impl ScopeGraphInternal {
@@ -405,6 +465,8 @@ mod internal {
                 scopes: HashMap::new(),
                 inheritance_relations: OneToNElementsMap::new(),
                 hierarchy_relations: OneToNElementsMap::new(),
+                subscopes_referencing_index: HashMap::new(),
+                attr_users_index: HashMap::new(),
             }
         }
 
@@ -412,6 +474,12 @@ mod internal {
             self.scopes.clear();
             self.inheritance_relations.clear();
             self.hierarchy_relations.clear();
+            self.subscopes_referencing_index.clear();
+            self.attr_users_index.clear();
+        }
+
+        pub fn scopes(&self) -> impl Iterator<Item = &Scope> {
+            self.scopes.values()
         }
 
         pub fn add_scope(&mut self, scope: Scope) -> ScopeIndex {
@@ -437,6 +505,7 @@ mod internal {
         }
 
         pub fn remove_scope(&mut self, index: ScopeIndex) {
+            self.remove_from_indices(index);
             self.scopes.remove(&index);
             if let Some(descendants) = self.hierarchy_relations.parent_to_children.get(&index).cloned() {
                 for descendant in descendants {
@@ -447,6 +516,26 @@ mod internal {
             self.inheritance_relations.remove(index);
         }
 
+        /// Remove all entries of the given scope from the reverse indices, both as a key and as a value.
+        fn remove_from_indices(&mut self, index: ScopeIndex) {
+            self.subscopes_referencing_index.remove(&index);
+            self.attr_users_index.remove(&index);
+            if let Some((superscope, edge)) = self.inheritance_relations.get_parent_edge_of(index) {
+                if let Some(by_var) = self.subscopes_referencing_index.get_mut(superscope) {
+                    for var_name in &edge.references {
+                        remove_from_index_entry(by_var, var_name, index);
+                    }
+                }
+            }
+            if let Some((ancestor, edges)) = self.hierarchy_relations.get_parent_edge_of(index) {
+                if let Some(by_var) = self.attr_users_index.get_mut(ancestor) {
+                    for var_name in edges.iter().flat_map(|edge| edge.expression.collect_var_refs()) {
+                        remove_from_index_entry(by_var, &var_name, index);
+                    }
+                }
+            }
+        }
+
         pub fn add_inheritance_relation(&mut self, a: ScopeIndex, b: ScopeIndex) {
             self.inheritance_relations.insert(a, b, Inherits { references: HashSet::new() }).unwrap();
         }
@@ -455,6 +544,10 @@ mod internal {
         pub fn register_scope_provides_attr(&mut self, a: ScopeIndex, b: ScopeIndex, edge: ProvidedAttr) {
             if let Some((superscope, edges)) = self.hierarchy_relations.get_parent_edge_mut(b) {
                 assert_eq!(*superscope, a, "Hierarchy map had a different superscope for a given scope than what was given here");
+                let by_var = self.attr_users_index.entry(a).or_default();
+                for var_name in edge.expression.collect_var_refs() {
+                    by_var.entry(var_name).or_default().insert(b);
+                }
                 edges.push(edge);
             } else {
                 log::error!(
@@ -473,12 +566,11 @@ mod internal {
 
         /// List all subscopes that reference a given variable directly (-> the variable is in the [Inherits::references])
         pub fn subscopes_referencing(&self, index: ScopeIndex, var_name: &VarName) -> Vec<ScopeIndex> {
-            self.inheritance_relations
-                .get_children_edges_of(index)
-                .iter()
-                .filter(|(_, edge)| edge.references.contains(var_name))
-                .map(|(scope, _)| *scope)
-                .collect()
+            self.subscopes_referencing_index
+                .get(&index)
+                .and_then(|by_var| by_var.get(var_name))
+                .map(|subscopes| subscopes.iter().copied().collect())
+                .unwrap_or_default()
         }
 
         pub fn superscope_of(&self, index: ScopeIndex) -> Option<ScopeIndex> {
@@ -487,10 +579,13 @@ mod internal {
 
         /// List the scopes that are provided some attribute referencing `var_name` by the given scope `index`.
         pub fn scopes_getting_attr_using(&self, index: ScopeIndex, var_name: &VarName) -> Vec<(ScopeIndex, &ProvidedAttr)> {
-            let edge_mappings = self.hierarchy_relations.get_children_edges_of(index);
-            edge_mappings
+            let Some(descendants) = self.attr_users_index.get(&index).and_then(|by_var| by_var.get(var_name)) else {
+                return Vec::new();
+            };
+            descendants
                 .iter()
-                .flat_map(|(k, v)| v.iter().map(move |edge| (*k, edge)))
+                .filter_map(|descendant| self.hierarchy_relations.get_parent_edge_of(*descendant).map(|(_, v)| (*descendant, v)))
+                .flat_map(|(k, v)| v.iter().map(move |edge| (k, edge)))
                 .filter(|(_, edge)| edge.expression.references_var(var_name))
                 .collect()
         }
@@ -498,11 +593,13 @@ mod internal {
         /// Register that a given scope references a variable from it's direct superscope.
         /// If the given scope does not have a superscope, this will return an `Err`.
         pub fn add_reference_to_inherits_edge(&mut self, subscope: ScopeIndex, var_name: VarName) -> Result<()> {
-            let (_, edge) = self
+            let edge = self
                 .inheritance_relations
                 .get_parent_edge_mut(subscope)
                 .with_context(|| format!("Given scope {:?} does not have any superscope", subscope))?;
-            edge.references.insert(var_name);
+            let superscope = edge.0;
+            edge.1.references.insert(var_name.clone());
+            self.subscopes_referencing_index.entry(superscope).or_default().entry(var_name).or_default().insert(subscope);
             Ok(())
         }
 
@@ -540,6 +637,27 @@ mod internal {
                 }
             }
 
+            for (superscope, by_var) in &self.subscopes_referencing_index {
+                for (var_name, subscopes) in by_var {
+                    for subscope in subscopes {
+                        match self.inheritance_relations.get_parent_edge_of(*subscope) {
+                            Some((parent, edge)) if parent == superscope && edge.references.contains(var_name) => {}
+                            _ => bail!("subscopes_referencing_index lists a reference that is not in inheritance_relations"),
+                        }
+                    }
+                }
+            }
+            for (ancestor, by_var) in &self.attr_users_index {
+                for descendants in by_var.values() {
+                    for descendant in descendants {
+                        match self.hierarchy_relations.get_parent_of(*descendant) {
+                            Some(parent) if parent == *ancestor => {}
+                            _ => bail!("attr_users_index lists a descendant that is not in hierarchy_relations"),
+                        }
+                    }
+                }
+            }
+
             self.hierarchy_relations.validate()?;
             self.inheritance_relations.validate()?;
 
@@ -602,6 +720,17 @@ mod internal {
     }
 }
 
+/// Remove a scope from the set of scopes stored for a variable in one of the reverse indices,
+/// dropping the entry for the variable entirely once it is empty.
+fn remove_from_index_entry(by_var: &mut HashMap<VarName, HashSet<ScopeIndex>>, var_name: &VarName, index: ScopeIndex) {
+    if let Some(scopes) = by_var.get_mut(var_name) {
+        scopes.remove(&index);
+        if scopes.is_empty() {
+            by_var.remove(var_name);
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use maplit::{hashmap, hashset};
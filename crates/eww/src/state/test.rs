@@ -1,6 +1,6 @@
 use super::scope::Listener;
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
+    atomic::{AtomicBool, AtomicUsize, Ordering},
     Arc,
 };
 
@@ -8,7 +8,7 @@ use eww_shared_util::{Span, VarName};
 use maplit::hashmap;
 use simplexpr::{dynval::DynVal, SimplExpr};
 
-use crate::state::scope_graph::ScopeGraph;
+use crate::state::scope_graph::{ScopeGraph, ScopeIndex};
 
 pub fn create_fn_verificator() -> (Arc<AtomicBool>, Box<dyn Fn()>) {
     let check = Arc::new(AtomicBool::new(false));
@@ -156,3 +156,103 @@ fn test_state_updates() {
     scope_graph.update_value(scope_graph.root_index, &"global_2".into(), "new global 2".into()).unwrap();
     assert!(bar_2_verify.load(Ordering::Relaxed), "inherited global update did not trigger properly");
 }
+
+#[test]
+fn test_set_value_in_scope_does_not_touch_superscopes() {
+    let globals = hashmap! {
+        VarName("title".to_string()) => DynVal::from("global title"),
+    };
+    let (send, _recv) = tokio::sync::mpsc::unbounded_channel();
+    let mut scope_graph = ScopeGraph::from_global_vars(globals, send);
+
+    let window_scope = scope_graph
+        .register_new_scope(
+            "window".to_string(),
+            Some(scope_graph.root_index),
+            scope_graph.root_index,
+            hashmap! { "title".into() => SimplExpr::literal(Span::DUMMY, "first".to_string()) },
+        )
+        .unwrap();
+    let widget_scope = scope_graph
+        .register_new_scope(
+            "widget".to_string(),
+            Some(window_scope),
+            window_scope,
+            hashmap! { "text".into() => SimplExpr::var_ref(Span::DUMMY, "title") },
+        )
+        .unwrap();
+
+    let (widget_verify, widget_f) = create_fn_verificator();
+    scope_graph
+        .register_listener(
+            widget_scope,
+            make_listener!(@short |text| {
+                if text == &"second".into() {
+                    widget_f()
+                }
+            }),
+        )
+        .unwrap();
+
+    scope_graph.set_value_in_scope(window_scope, &"title".into(), "second".into()).unwrap();
+    assert!(widget_verify.load(Ordering::Relaxed), "update of the window argument did not reach the widget");
+    assert_eq!(scope_graph.global_scope().data.get(&VarName::from("title")), Some(&DynVal::from("global title")));
+}
+
+/// Build a graph of `n` widget scopes, each receiving an attribute from its own global variable and listening to it,
+/// and return it alongside the widget scopes and a counter of how many listeners were called.
+fn build_wide_scope_graph(n: usize) -> (ScopeGraph, Vec<ScopeIndex>, Arc<AtomicUsize>) {
+    let globals = (0..n).map(|i| (VarName(format!("global_{}", i)), DynVal::from("initial"))).collect();
+    let (send, _recv) = tokio::sync::mpsc::unbounded_channel();
+    let mut scope_graph = ScopeGraph::from_global_vars(globals, send);
+    let calls = Arc::new(AtomicUsize::new(0));
+    let mut widget_scopes = Vec::new();
+
+    for i in 0..n {
+        let widget_scope = scope_graph
+            .register_new_scope(
+                format!("widget_{}", i),
+                Some(scope_graph.root_index),
+                scope_graph.root_index,
+                hashmap! { "arg".into() => SimplExpr::var_ref(Span::DUMMY, format!("global_{}", i)) },
+            )
+            .unwrap();
+        let calls = calls.clone();
+        scope_graph
+            .register_listener(
+                widget_scope,
+                Listener {
+                    needed_variables: vec!["arg".into(), VarName(format!("global_{}", i))],
+                    f: Box::new(move |_, _| {
+                        calls.fetch_add(1, Ordering::Relaxed);
+                        Ok(())
+                    }),
+                },
+            )
+            .unwrap();
+        widget_scopes.push(widget_scope);
+    }
+    calls.store(0, Ordering::Relaxed);
+    (scope_graph, widget_scopes, calls)
+}
+
+#[test]
+fn test_update_only_notifies_dependent_listeners() {
+    let (mut scope_graph, _, calls) = build_wide_scope_graph(50);
+
+    scope_graph.update_global_value(&"global_7".into(), "changed".into()).unwrap();
+    // the listener of widget_7 is triggered once via `arg` and once via the inherited global_7
+    assert_eq!(calls.load(Ordering::Relaxed), 2);
+
+    scope_graph.validate().unwrap();
+}
+
+#[test]
+fn test_removed_scopes_are_not_notified() {
+    let (mut scope_graph, widget_scopes, calls) = build_wide_scope_graph(3);
+    scope_graph.remove_scope(widget_scopes[1]);
+    scope_graph.validate().unwrap();
+
+    scope_graph.update_global_value(&"global_1".into(), "changed".into()).unwrap();
+    assert_eq!(calls.load(Ordering::Relaxed), 0);
+}
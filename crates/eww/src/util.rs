@@ -131,9 +131,31 @@ pub fn unindent(text: &str) -> String {
     result
 }
 
+/// Describe the time between `timestamp` and `now`, both given in seconds, in words, like `5 minutes ago` or `in 2 hours`.
+/// Returns the description, and the amount of seconds until the description changes.
+pub fn describe_relative_time(timestamp: i64, now: i64) -> (String, u64) {
+    let delta = now - timestamp;
+    let secs = delta.unsigned_abs();
+    if secs < 10 {
+        return ("just now".to_string(), if delta >= 0 { 10 - secs } else { 1 });
+    }
+    let (amount, unit, unit_secs) = match secs {
+        0..=59 => (secs, "second", 1),
+        60..=3599 => (secs / 60, "minute", 60),
+        3600..=86399 => (secs / 3600, "hour", 3600),
+        _ => (secs / 86400, "day", 86400),
+    };
+    let plural = if amount == 1 { "" } else { "s" };
+    if delta >= 0 {
+        (format!("{amount} {unit}{plural} ago"), (amount + 1) * unit_secs - secs)
+    } else {
+        (format!("in {amount} {unit}{plural}"), secs - amount * unit_secs + 1)
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::{replace_env_var_references, unindent};
+    use super::{describe_relative_time, replace_env_var_references, unindent};
 
     #[test]
     fn test_replace_env_var_references() {
@@ -152,4 +174,12 @@ mod test {
             line two";
         assert_eq!("line one\nline two", unindent(indented));
     }
+
+    #[test]
+    fn test_describe_relative_time() {
+        assert_eq!(("just now".to_string(), 7), describe_relative_time(100, 103));
+        assert_eq!(("1 minute ago".to_string(), 30), describe_relative_time(0, 90));
+        assert_eq!(("2 hours ago".to_string(), 3600), describe_relative_time(0, 7200));
+        assert_eq!(("in 3 days".to_string(), 1), describe_relative_time(3 * 86400, 0));
+    }
 }
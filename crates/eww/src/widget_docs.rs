@@ -0,0 +1,121 @@
+//! Documentation of the built-in widgets, extracted from the annotations in `widgets/widget_definitions.rs` at build time.
+//! The same data is used for `eww doc`, and can be exported as JSON for external tools such as language servers.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use itertools::Itertools;
+use yuck::config::widget_definition::WidgetDefinition;
+
+/// The raw JSON generated by the build script.
+pub const WIDGET_DOCS_JSON: &str = include_str!(concat!(env!("OUT_DIR"), "/widget_docs.json"));
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct WidgetDocs {
+    pub widgets: Vec<WidgetDoc>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct WidgetDoc {
+    pub name: String,
+    pub description: String,
+    pub props: Vec<PropDoc>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct PropDoc {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub prop_type: String,
+    pub description: String,
+}
+
+pub fn load() -> Result<WidgetDocs> {
+    serde_json::from_str(WIDGET_DOCS_JSON).context("Failed to parse embedded widget documentation")
+}
+
+/// Print the documentation of the given widget, or a list of all widgets if none is given.
+pub fn print_docs(widget_name: Option<&str>, json: bool) -> Result<()> {
+    if json && widget_name.is_none() {
+        println!("{}", WIDGET_DOCS_JSON);
+        return Ok(());
+    }
+    let docs = load()?;
+    let Some(widget_name) = widget_name else {
+        for widget in docs.widgets.iter().filter(|widget| widget.name != "widget") {
+            println!("{:<20} {}", widget.name, widget.description);
+        }
+        return Ok(());
+    };
+
+    let widget = docs.widgets.iter().find(|widget| widget.name == widget_name).with_context(|| {
+        let names = docs.widgets.iter().map(|widget| widget.name.as_str()).collect::<Vec<_>>().join(", ");
+        format!("No widget named `{}` exists. Available widgets: {}", widget_name, names)
+    })?;
+    if json {
+        let raw: serde_json::Value = serde_json::from_str(WIDGET_DOCS_JSON)?;
+        let raw_widget = raw["widgets"].as_array().and_then(|widgets| widgets.iter().find(|w| w["name"] == widget_name));
+        println!("{}", serde_json::to_string_pretty(&raw_widget)?);
+        return Ok(());
+    }
+
+    println!("{}\n{}\n", widget.name, widget.description);
+    for prop in &widget.props {
+        println!("  :{} ({})\n      {}", prop.name, prop.prop_type, prop.description);
+    }
+    if widget.name != "widget" {
+        println!("\nAll widgets additionally support the properties listed in `eww doc widget`.");
+    }
+    Ok(())
+}
+
+/// A widget defined via `defwidget`, as listed by `eww widgets`.
+#[derive(Debug, serde::Serialize)]
+struct CustomWidgetDoc<'a> {
+    name: &'a str,
+    description: Option<&'a str>,
+    args: Vec<CustomWidgetArgDoc<'a>>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct CustomWidgetArgDoc<'a> {
+    name: &'a str,
+    optional: bool,
+}
+
+/// Print all built-in widgets, followed by the given widgets from the configuration.
+pub fn print_widget_list(widget_defs: &HashMap<String, WidgetDefinition>, json: bool) -> Result<()> {
+    let docs = load()?;
+    let custom_widgets = widget_defs
+        .values()
+        .sorted_by_key(|def| &def.name)
+        .map(|def| CustomWidgetDoc {
+            name: &def.name,
+            description: def.doc.as_deref(),
+            args: def.expected_args.iter().map(|arg| CustomWidgetArgDoc { name: &arg.name.0, optional: arg.optional }).collect(),
+        })
+        .collect::<Vec<_>>();
+
+    if json {
+        let builtin = docs.widgets.iter().filter(|widget| widget.name != "widget").collect::<Vec<_>>();
+        println!("{}", serde_json::to_string_pretty(&serde_json::json!({ "builtin": builtin, "custom": custom_widgets }))?);
+        return Ok(());
+    }
+
+    println!("Built-in widgets (run `eww doc <widget>` for their properties):");
+    for widget in docs.widgets.iter().filter(|widget| widget.name != "widget") {
+        println!("  {:<20} {}", widget.name, widget.description);
+    }
+    println!("\nWidgets defined in your configuration:");
+    if custom_widgets.is_empty() {
+        println!("  (none)");
+    }
+    for widget in &custom_widgets {
+        let args = widget.args.iter().map(|arg| if arg.optional { format!("?{}", arg.name) } else { arg.name.to_string() });
+        println!("  {:<20} [{}]", widget.name, args.format(" "));
+        if let Some(description) = widget.description {
+            println!("      {}", description);
+        }
+    }
+    Ok(())
+}
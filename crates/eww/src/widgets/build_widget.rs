@@ -3,21 +3,22 @@ use codespan_reporting::diagnostic::Severity;
 use eww_shared_util::{AttrName, Spanned};
 use gtk::{
     gdk::prelude::Cast,
-    prelude::{BoxExt, ContainerExt, WidgetExt},
+    prelude::{BoxExt, ContainerExt, ObjectType, WidgetExt},
     Orientation,
 };
 use itertools::Itertools;
 use maplit::hashmap;
 use simplexpr::{dynval::DynVal, SimplExpr};
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{cell::RefCell, collections::HashMap, fmt::Write as _, rc::Rc};
 use yuck::{
     config::{
-        attributes::AttrEntry,
+        attributes::{AttrEntry, Attributes},
         widget_definition::WidgetDefinition,
-        widget_use::{BasicWidgetUse, ChildrenWidgetUse, LoopWidgetUse, WidgetUse},
+        widget_use::{BasicWidgetUse, CaseWidgetUse, ChildrenWidgetUse, ConditionalWidgetUse, LoopWidgetUse, WidgetUse},
     },
     error::DiagError,
     gen_diagnostic,
+    parser::from_ast::FromAst,
 };
 
 use crate::{
@@ -57,6 +58,12 @@ pub fn build_gtk_widget(
         WidgetUse::Basic(widget_use) => {
             build_basic_gtk_widget(graph, widget_defs, calling_scope, widget_use, custom_widget_invocation)
         }
+        WidgetUse::Conditional(widget_use) => {
+            build_conditional_special_widget(graph, widget_defs, calling_scope, widget_use, custom_widget_invocation)
+        }
+        WidgetUse::Case(widget_use) => {
+            build_case_special_widget(graph, widget_defs, calling_scope, widget_use, custom_widget_invocation)
+        }
         WidgetUse::Loop(_) | WidgetUse::Children(_) => Err(anyhow::anyhow!(DiagError(gen_diagnostic! {
             msg = "This widget can only be used as a child of some container widget such as box",
             label = widget_use.span(),
@@ -73,6 +80,8 @@ fn build_basic_gtk_widget(
     custom_widget_invocation: Option<Rc<CustomWidgetInvocation>>,
 ) -> Result<gtk::Widget> {
     if let Some(custom_widget) = widget_defs.clone().get(&widget_use.name) {
+        let debug_info =
+            WidgetDebugInfo { name: widget_use.name.clone(), scope: calling_scope, attrs: Rc::new(widget_use.attrs.clone()) };
         let widget_use_attributes = custom_widget
             .expected_args
             .iter()
@@ -101,6 +110,8 @@ fn build_basic_gtk_widget(
             Some(Rc::new(CustomWidgetInvocation { scope: calling_scope, children: widget_use.children })),
         )?;
 
+        add_debug_info(&gtk_widget, debug_info);
+
         let scope_graph_sender = graph.event_sender.clone();
 
         gtk_widget.connect_destroy(move |_| {
@@ -127,8 +138,11 @@ fn build_builtin_gtk_widget(
     widget_use: BasicWidgetUse,
     custom_widget_invocation: Option<Rc<CustomWidgetInvocation>>,
 ) -> Result<gtk::Widget> {
+    let debug_attrs = Rc::new(widget_use.attrs.clone());
+    let debug_info = WidgetDebugInfo { name: widget_use.name.clone(), scope: calling_scope, attrs: debug_attrs.clone() };
+
     let mut bargs = BuilderArgs {
-        unhandled_attrs: widget_use.attrs.attrs.clone(),
+        unhandled_attrs: debug_attrs.attrs.clone(),
         scope_graph: graph,
         calling_scope,
         widget_use,
@@ -142,7 +156,8 @@ fn build_builtin_gtk_widget(
 
         // Only populate children if there haven't been any children added anywhere else
         // TODO this is somewhat hacky
-        if gtk_container.children().is_empty() {
+        // grid populates its children itself, but might not have any yet if they're all created by an empty `for`.
+        if gtk_container.children().is_empty() && gtk_container.dynamic_cast_ref::<gtk::Grid>().is_none() {
             populate_widget_children(
                 bargs.scope_graph,
                 bargs.widget_defs.clone(),
@@ -161,6 +176,7 @@ fn build_builtin_gtk_widget(
         resolve_orientable_attrs(&mut bargs, w)?;
     };
     resolve_widget_attrs(&mut bargs, &gtk_widget)?;
+    add_debug_info(&gtk_widget, debug_info);
 
     for (attr_name, attr_entry) in bargs.unhandled_attrs {
         let diag = error_handling_ctx::stringify_diagnostic(gen_diagnostic! {
@@ -173,9 +189,79 @@ fn build_builtin_gtk_widget(
     Ok(gtk_widget)
 }
 
+/// Describes how a gtk widget was created from the configuration, used for `eww debug-tree`.
+/// The attributes are only evaluated once the tree is printed, keeping this cheap to record for every widget.
+struct WidgetDebugInfo {
+    name: String,
+    /// Scope the attributes of the widget are evaluated in
+    scope: ScopeIndex,
+    attrs: Rc<Attributes>,
+}
+
+thread_local! {
+    /// Debug info of all gtk widgets, keyed by their address, such that this doesn't keep any widget alive.
+    /// As the root widget of a custom widget is the widget it is defined as, a widget may have multiple entries, innermost first.
+    static WIDGET_DEBUG_INFO: RefCell<HashMap<usize, Vec<WidgetDebugInfo>>> = RefCell::default();
+}
+
+fn debug_info_key(widget: &gtk::Widget) -> usize {
+    widget.as_ptr() as usize
+}
+
+fn add_debug_info(widget: &gtk::Widget, info: WidgetDebugInfo) {
+    let is_new = WIDGET_DEBUG_INFO.with(|infos| {
+        let mut infos = infos.borrow_mut();
+        let entry = infos.entry(debug_info_key(widget)).or_default();
+        entry.push(info);
+        entry.len() == 1
+    });
+    // gtk widgets are always destroyed before they are freed, so this removes the entry before the address can be reused
+    if is_new {
+        widget.connect_destroy(|widget| {
+            WIDGET_DEBUG_INFO.with(|infos| infos.borrow_mut().remove(&debug_info_key(widget)));
+        });
+    }
+}
+
+/// Print the hierarchy of widgets below the given gtk widget, together with the current values of their attributes.
+pub fn format_widget_tree(root: &gtk::Widget, graph: &ScopeGraph) -> String {
+    let mut output = String::new();
+    write_widget_tree(&mut output, root, graph, 0);
+    output
+}
+
+fn write_widget_tree(output: &mut String, widget: &gtk::Widget, graph: &ScopeGraph, mut depth: usize) {
+    WIDGET_DEBUG_INFO.with(|infos| {
+        for info in infos.borrow().get(&debug_info_key(widget)).into_iter().flatten().rev() {
+            let indent = "  ".repeat(depth);
+            let hidden = if widget.is_visible() { "" } else { " (hidden)" };
+            let _ = writeln!(output, "{}{} [scope {}]{}", indent, info.name, info.scope.0, hidden);
+            let attrs = info
+                .attrs
+                .attrs
+                .iter()
+                .filter_map(|(name, entry)| Some((name, SimplExpr::from_ast(entry.value.clone()).ok()?)))
+                .sorted_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+            for (name, expr) in attrs {
+                let value = match graph.evaluate_simplexpr_in_scope(info.scope, &expr) {
+                    Ok(value) => format!("{:?}", value.as_string().unwrap_or_default()),
+                    Err(err) => format!("<error: {}>", err),
+                };
+                let _ = writeln!(output, "{}  :{} {} => {}", indent, name, expr, value);
+            }
+            depth += 1;
+        }
+    });
+    if let Some(container) = widget.dynamic_cast_ref::<gtk::Container>() {
+        for child in container.children() {
+            write_widget_tree(output, &child, graph, depth);
+        }
+    }
+}
+
 /// If a gtk widget can take children (→ it is a [`gtk::Container`]) we need to add the provided `widget_use_children`
 /// into that container. Those children might be uses of the special `children`-[`WidgetUse`], which will get expanded here, too.
-fn populate_widget_children(
+pub(super) fn populate_widget_children(
     tree: &mut ScopeGraph,
     widget_defs: Rc<HashMap<String, WidgetDefinition>>,
     calling_scope: ScopeIndex,
@@ -222,6 +308,28 @@ fn build_loop_special_widget(
     widget_use: LoopWidgetUse,
     gtk_container: &gtk::Container,
     custom_widget_invocation: Option<Rc<CustomWidgetInvocation>>,
+) -> Result<()> {
+    let body: WidgetUse = widget_use.body.as_ref().clone();
+    build_loop_special_widget_with(tree, calling_scope, &widget_use, gtk_container, {
+        let gtk_container = gtk_container.clone();
+        move |tree, scope| {
+            let new_child_widget =
+                build_gtk_widget(tree, widget_defs.clone(), scope, body.clone(), custom_widget_invocation.clone())?;
+            gtk_container.add(&new_child_widget);
+            Ok(vec![new_child_widget])
+        }
+    })
+}
+
+/// Register the listener of a `for`, which calls `build_element` with the scope of each element of the array.
+/// `build_element` adds the widgets of an element to the container and returns them, such that they can be removed again
+/// once the array changes.
+pub(super) fn build_loop_special_widget_with(
+    tree: &mut ScopeGraph,
+    calling_scope: ScopeIndex,
+    widget_use: &LoopWidgetUse,
+    gtk_container: &gtk::Container,
+    build_element: impl Fn(&mut ScopeGraph, ScopeIndex) -> Result<Vec<gtk::Widget>> + 'static,
 ) -> Result<()> {
     tree.register_listener(
         calling_scope,
@@ -231,7 +339,6 @@ fn build_loop_special_widget(
                 let elements_expr = widget_use.elements_expr.clone();
                 let elements_expr_span = widget_use.elements_expr_span;
                 let element_name = widget_use.element_name.clone();
-                let body: WidgetUse = widget_use.body.as_ref().clone();
                 let created_children = Rc::new(RefCell::new(Vec::<gtk::Widget>::new()));
                 let created_child_scopes = Rc::new(RefCell::new(Vec::<ScopeIndex>::new()));
                 let gtk_container = gtk_container.clone();
@@ -263,10 +370,7 @@ fn build_loop_special_widget(
                             },
                         )?;
                         created_child_scopes.push(scope);
-                        let new_child_widget =
-                            build_gtk_widget(tree, widget_defs.clone(), scope, body.clone(), custom_widget_invocation.clone())?;
-                        gtk_container.add(&new_child_widget);
-                        created_children.push(new_child_widget);
+                        created_children.extend(build_element(tree, scope)?);
                     }
 
                     Ok(())
@@ -276,6 +380,95 @@ fn build_loop_special_widget(
     )
 }
 
+/// Build the special `when`, `unless` and `if` widgets.
+fn build_conditional_special_widget(
+    tree: &mut ScopeGraph,
+    widget_defs: Rc<HashMap<String, WidgetDefinition>>,
+    calling_scope: ScopeIndex,
+    widget_use: ConditionalWidgetUse,
+    custom_widget_invocation: Option<Rc<CustomWidgetInvocation>>,
+) -> Result<gtk::Widget> {
+    let condition = widget_use.condition.clone();
+    build_branching_special_widget(tree, widget_defs, calling_scope, condition, custom_widget_invocation, move |value| {
+        let condition = value.as_bool()?;
+        let body = if condition { &widget_use.then_body } else { &widget_use.else_body };
+        Ok((condition.to_string(), body.as_deref().cloned()))
+    })
+}
+
+/// Build the special `case` widget.
+fn build_case_special_widget(
+    tree: &mut ScopeGraph,
+    widget_defs: Rc<HashMap<String, WidgetDefinition>>,
+    calling_scope: ScopeIndex,
+    widget_use: CaseWidgetUse,
+    custom_widget_invocation: Option<Rc<CustomWidgetInvocation>>,
+) -> Result<gtk::Widget> {
+    let expr = widget_use.expr.clone();
+    build_branching_special_widget(tree, widget_defs, calling_scope, expr, custom_widget_invocation, move |value| {
+        let (index, body) = widget_use.select_branch(value);
+        Ok((index.to_string(), body.cloned()))
+    })
+}
+
+/// Put the widget of the branch selected by the current value of `expr` into a wrapper box.
+/// `select_branch` returns an identifier of the branch to show for a value, together with its widget, if it has one.
+/// Branches are only built while they're shown, and get rebuilt from scratch whenever a different branch gets selected.
+fn build_branching_special_widget(
+    tree: &mut ScopeGraph,
+    widget_defs: Rc<HashMap<String, WidgetDefinition>>,
+    calling_scope: ScopeIndex,
+    expr: SimplExpr,
+    custom_widget_invocation: Option<Rc<CustomWidgetInvocation>>,
+    select_branch: impl Fn(&DynVal) -> Result<(String, Option<WidgetUse>)> + 'static,
+) -> Result<gtk::Widget> {
+    let wrapper = gtk::Box::new(Orientation::Horizontal, 0);
+    wrapper.set_homogeneous(true);
+
+    tree.register_listener(
+        calling_scope,
+        Listener {
+            needed_variables: expr.collect_var_refs(),
+            f: Box::new({
+                let wrapper = wrapper.clone();
+                // the identifier of the shown branch, together with the scope its widget was built in
+                let shown_branch = Rc::new(RefCell::new(None::<(String, Option<ScopeIndex>)>));
+                move |tree, values| {
+                    let (branch, body) = select_branch(&expr.eval(&values)?)?;
+                    let mut shown_branch = shown_branch.borrow_mut();
+                    if matches!(&*shown_branch, Some((shown, _)) if *shown == branch) {
+                        return Ok(());
+                    }
+                    wrapper.children().iter().for_each(|child| wrapper.remove(child));
+                    if let Some((_, Some(old_scope))) = shown_branch.take() {
+                        tree.remove_scope(old_scope);
+                    }
+
+                    let scope = match body {
+                        Some(body) => {
+                            let scope = tree.register_new_scope(
+                                format!("branch {}", branch),
+                                Some(calling_scope),
+                                calling_scope,
+                                HashMap::new(),
+                            )?;
+                            let new_child_widget =
+                                build_gtk_widget(tree, widget_defs.clone(), scope, body, custom_widget_invocation.clone())?;
+                            wrapper.add(&new_child_widget);
+                            new_child_widget.show_all();
+                            Some(scope)
+                        }
+                        None => None,
+                    };
+                    *shown_branch = Some((branch, scope));
+                    Ok(())
+                }
+            }),
+        },
+    )?;
+    Ok(wrapper.upcast())
+}
+
 /// Handle an invocation of the special `children` [`WidgetUse`].
 /// This widget expands to multiple other widgets, thus we require the `gtk_container` we should expand the widgets into.
 /// The `custom_widget_invocation` will be used here to evaluate the provided children in their
@@ -350,8 +543,8 @@ pub struct CustomWidgetInvocation {
 
 /// Make sure that [`gtk::Bin`] widgets only get a single child.
 fn validate_container_children_count(container: &gtk::Container, widget_use: &BasicWidgetUse) -> Result<(), DiagError> {
-    // ignore for overlay as it can take more than one.
-    if container.dynamic_cast_ref::<gtk::Overlay>().is_some() {
+    // ignore for overlay as it can take more than one, and for popover which takes one child for the button and one for the popover.
+    if container.dynamic_cast_ref::<gtk::Overlay>().is_some() || container.dynamic_cast_ref::<gtk::MenuButton>().is_some() {
         return Ok(());
     }
 
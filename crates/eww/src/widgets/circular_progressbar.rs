@@ -106,30 +106,46 @@ impl ContainerImpl for CircProgPriv {
     }
 }
 
-fn calc_widget_lowest_preferred_dimension(widget: &gtk::Widget) -> (i32, i32) {
+impl CircProgPriv {
+    /// Radius of the empty space inside the ring, given the size allocated to the widget.
+    fn inner_radius(&self, total_width: f64, total_height: f64) -> f64 {
+        let margin = self.obj().style_context().margin(gtk::StateFlags::NORMAL);
+        let circle_width = total_width - margin.left as f64 - margin.right as f64;
+        let circle_height = total_height - margin.top as f64 - margin.bottom as f64;
+        (f64::min(circle_width, circle_height) / 2.0) - *self.thickness.borrow()
+    }
+
+    /// The (minimum, natural) diameter of the ring, such that the child fits inside of it.
+    fn preferred_diameter(&self) -> (i32, i32) {
+        let ring = 2 * *self.thickness.borrow() as i32;
+        match &*self.content.borrow() {
+            Some(child) => {
+                let (min_child, natural_child) = calc_widget_preferred_dimensions(child);
+                (min_child + ring, natural_child + ring)
+            }
+            None => (ring, ring),
+        }
+    }
+}
+
+/// The child is shown inside the ring, so the ring needs to be large enough to contain its largest dimension.
+/// If the ring is given less space, overflowing content of the child gets cropped.
+fn calc_widget_preferred_dimensions(widget: &gtk::Widget) -> (i32, i32) {
     let preferred_width = widget.preferred_width();
     let preferred_height = widget.preferred_height();
     let min_lowest = i32::min(preferred_width.0, preferred_height.0);
-    let natural_lowest = i32::min(preferred_width.1, preferred_height.1);
-    (min_lowest, natural_lowest)
+    let natural_highest = i32::max(preferred_width.1, preferred_height.1);
+    (min_lowest, natural_highest)
 }
 
 impl BinImpl for CircProgPriv {}
 
 impl WidgetImpl for CircProgPriv {
-    // We overwrite preferred_* so that overflowing content from the children gets cropped
-    //  We return min(child_width, child_height)
     fn preferred_width(&self) -> (i32, i32) {
-        let styles = self.obj().style_context();
-        let margin = styles.margin(gtk::StateFlags::NORMAL);
-
-        if let Some(child) = &*self.content.borrow() {
-            let (min_child, natural_child) = calc_widget_lowest_preferred_dimension(child);
-            (min_child + margin.right as i32 + margin.left as i32, natural_child + margin.right as i32 + margin.left as i32)
-        } else {
-            let empty_width = (2 * *self.thickness.borrow() as i32) + margin.right as i32 + margin.left as i32;
-            (empty_width, empty_width)
-        }
+        let margin = self.obj().style_context().margin(gtk::StateFlags::NORMAL);
+        let (min, natural) = self.preferred_diameter();
+        let margin = margin.left as i32 + margin.right as i32;
+        (min + margin, natural + margin)
     }
 
     fn preferred_width_for_height(&self, _height: i32) -> (i32, i32) {
@@ -137,22 +153,32 @@ impl WidgetImpl for CircProgPriv {
     }
 
     fn preferred_height(&self) -> (i32, i32) {
-        let styles = self.obj().style_context();
-        let margin = styles.margin(gtk::StateFlags::NORMAL);
-
-        if let Some(child) = &*self.content.borrow() {
-            let (min_child, natural_child) = calc_widget_lowest_preferred_dimension(child);
-            (min_child + margin.bottom as i32 + margin.top as i32, natural_child + margin.bottom as i32 + margin.top as i32)
-        } else {
-            let empty_height = (2 * *self.thickness.borrow() as i32) + margin.right as i32 + margin.left as i32;
-            (empty_height, empty_height)
-        }
+        let margin = self.obj().style_context().margin(gtk::StateFlags::NORMAL);
+        let (min, natural) = self.preferred_diameter();
+        let margin = margin.top as i32 + margin.bottom as i32;
+        (min + margin, natural + margin)
     }
 
     fn preferred_height_for_width(&self, _width: i32) -> (i32, i32) {
         self.preferred_height()
     }
 
+    // The child is centered inside the ring, in a square with the same size as the inner diameter of the ring.
+    fn size_allocate(&self, allocation: &gtk::Allocation) {
+        self.parent_size_allocate(allocation);
+        if let Some(child) = &*self.content.borrow() {
+            let (width, height) = (allocation.width() as f64, allocation.height() as f64);
+            let diameter = (2.0 * self.inner_radius(width, height)).max(0.0);
+            let child_allocation = gtk::Allocation::new(
+                allocation.x() + ((width - diameter) / 2.0) as i32,
+                allocation.y() + ((height - diameter) / 2.0) as i32,
+                diameter as i32,
+                diameter as i32,
+            );
+            child.size_allocate(&child_allocation);
+        }
+    }
+
     fn draw(&self, cr: &cairo::Context) -> glib::Propagation {
         let res: Result<()> = (|| {
             let value = *self.value.borrow();
@@ -161,7 +187,6 @@ impl WidgetImpl for CircProgPriv {
             let clockwise = *self.clockwise.borrow();
 
             let styles = self.obj().style_context();
-            let margin = styles.margin(gtk::StateFlags::NORMAL);
             // Padding is not supported yet
             let fg_color: gdk::RGBA = styles.color(gtk::StateFlags::NORMAL);
             let bg_color: gdk::RGBA = styles.style_property_for_state("background-color", gtk::StateFlags::NORMAL).get()?;
@@ -172,10 +197,8 @@ impl WidgetImpl for CircProgPriv {
             let total_height = self.obj().allocated_height() as f64;
             let center = (total_width / 2.0, total_height / 2.0);
 
-            let circle_width = total_width - margin.left as f64 - margin.right as f64;
-            let circle_height = total_height - margin.top as f64 - margin.bottom as f64;
-            let outer_ring = f64::min(circle_width, circle_height) / 2.0;
-            let inner_ring = (f64::min(circle_width, circle_height) / 2.0) - thickness;
+            let inner_ring = self.inner_radius(total_width, total_height);
+            let outer_ring = inner_ring + thickness;
 
             cr.save()?;
 
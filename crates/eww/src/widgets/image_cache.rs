@@ -0,0 +1,173 @@
+//! Cache for images shown by the `image` widget that are given as `http(s)://` urls or `data:` uris.
+//!
+//! Downloaded images are revalidated via the `ETag` and `Last-Modified` headers of the response they came from,
+//! such that images whose url stays the same, like the cover of the current song, still update.
+//! The modification time of a cached file is the last time it was used or confirmed to be up to date,
+//! and files that weren't used for a while are removed.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::{Context, Result};
+use gtk::glib;
+use once_cell::sync::Lazy;
+
+/// How long a downloaded image is shown before asking the server whether it changed.
+const REVALIDATE_INTERVAL: Duration = Duration::from_secs(60);
+/// Cached images that weren't used for this long are removed.
+const MAX_UNUSED_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// How often the cache is checked for images to remove.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Client shared by all image downloads.
+static HTTP_CLIENT: Lazy<reqwest::Client> = Lazy::new(reqwest::Client::new);
+static LAST_PRUNE: Mutex<Option<Instant>> = Mutex::new(None);
+static TMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Validators of the response an image was downloaded from, stored next to the image.
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+struct Validators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+fn cache_dir() -> PathBuf {
+    crate::paths::get_cache_dir().join("images")
+}
+
+/// Path of the file a remote or inline image with the given source is cached in.
+/// The extension is kept, as it is used to determine how the image is loaded.
+pub fn cache_file(source: &str, extension: Option<&str>) -> PathBuf {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    source.hash(&mut hasher);
+    let file_name = match extension {
+        Some(extension) => format!("{:x}.{}", hasher.finish(), extension),
+        None => format!("{:x}", hasher.finish()),
+    };
+    cache_dir().join(file_name)
+}
+
+pub fn url_file_extension(url: &str) -> Option<&str> {
+    let (_, url_path) = url.split(['?', '#']).next()?.split_once("://")?.1.split_once('/')?;
+    let file_name = url_path.rsplit('/').next()?;
+    let (_, extension) = file_name.rsplit_once('.')?;
+    Some(extension)
+}
+
+fn validators_file(cache_file: &Path) -> PathBuf {
+    let mut file_name = cache_file.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".validators");
+    cache_file.with_file_name(file_name)
+}
+
+fn time_since_modified(path: &Path) -> Option<Duration> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()?.elapsed().ok()
+}
+
+/// Mark a cached file as used just now, keeping it from being pruned.
+fn touch(path: &Path) -> Result<()> {
+    std::fs::File::options().write(true).open(path)?.set_modified(SystemTime::now())?;
+    Ok(())
+}
+
+/// Whether a downloaded image should be checked for changes, as it wasn't confirmed to be up to date recently.
+pub fn needs_revalidation(cache_file: &Path) -> bool {
+    time_since_modified(cache_file).map_or(true, |age| age > REVALIDATE_INTERVAL)
+}
+
+/// Download the image at the given url into its cache file, unless the server reports that the cached file is up to date.
+/// Returns whether the cache file changed.
+pub async fn fetch_url(url: String, cache_file: PathBuf) -> Result<bool> {
+    use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+
+    let is_cached = cache_file.is_file();
+    let validators: Validators = match std::fs::read(validators_file(&cache_file)) {
+        Ok(contents) if is_cached => serde_json::from_slice(&contents).unwrap_or_default(),
+        _ => Validators::default(),
+    };
+    let mut request = HTTP_CLIENT.get(&url);
+    if let Some(etag) = &validators.etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &validators.last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+
+    let response = request.send().await.with_context(|| format!("Failed to download image from {}", url))?;
+    if is_cached && response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        touch(&cache_file)?;
+        // the validators are pruned together with the image, so they have to stay around as long as it is used
+        touch(&validators_file(&cache_file))?;
+        return Ok(false);
+    }
+    let response = response.error_for_status().with_context(|| format!("Failed to download image from {}", url))?;
+    let header = |name| response.headers().get(name).and_then(|value| value.to_str().ok()).map(str::to_string);
+    let validators = Validators { etag: header(ETAG), last_modified: header(LAST_MODIFIED) };
+    let contents = response.bytes().await.with_context(|| format!("Failed to download image from {}", url))?;
+    write_file(&cache_file, &contents)?;
+    write_file(&validators_file(&cache_file), &serde_json::to_vec(&validators)?)?;
+    Ok(true)
+}
+
+/// Decode a base64 encoded `data:` uri (given without the `data:` prefix) into a file in the image cache,
+/// returning the path of that file.
+pub fn cache_data_uri(data_uri: &str) -> Result<PathBuf> {
+    let (media_type, data) = data_uri.split_once(',').context("Invalid data uri, expected a `,`")?;
+    let mime_type = media_type.strip_suffix(";base64").context("Only base64 encoded data uris are supported")?;
+    let extension = match mime_type.split(';').next().and_then(|x| x.strip_prefix("image/")) {
+        Some("svg+xml") => Some("svg"),
+        Some(subtype) if !subtype.is_empty() => Some(subtype),
+        _ => None,
+    };
+    let cache_file = cache_file(data_uri, extension);
+    if !cache_file.is_file() {
+        write_file(&cache_file, &glib::base64_decode(data))?;
+    } else if time_since_modified(&cache_file).map_or(true, |age| age > REVALIDATE_INTERVAL) {
+        touch(&cache_file)?;
+    }
+    Ok(cache_file)
+}
+
+fn write_file(path: &Path, contents: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // write to a temporary file first, such that a partially written file is never picked up from the cache.
+    // The name is unique, as the same image may be downloaded by multiple widgets or eww instances at once.
+    let counter = TMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let mut tmp_file_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_file_name.push(format!(".{}.{}.part", std::process::id(), counter));
+    let tmp_file = path.with_file_name(tmp_file_name);
+    std::fs::write(&tmp_file, contents)?;
+    std::fs::rename(&tmp_file, path)?;
+    prune_if_due();
+    Ok(())
+}
+
+/// Remove cached images that weren't used for a while, at most once every [`PRUNE_INTERVAL`].
+fn prune_if_due() {
+    {
+        let mut last_prune = LAST_PRUNE.lock().unwrap();
+        if last_prune.is_some_and(|last_prune| last_prune.elapsed() < PRUNE_INTERVAL) {
+            return;
+        }
+        *last_prune = Some(Instant::now());
+    }
+    let Ok(entries) = std::fs::read_dir(cache_dir()) else { return };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if time_since_modified(&path).is_some_and(|age| age > MAX_UNUSED_AGE) {
+            log::debug!("Removing unused image {} from the cache", path.display());
+            if let Err(err) = std::fs::remove_file(&path) {
+                log::warn!("Failed to remove {} from the image cache: {}", path.display(), err);
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use anyhow::{anyhow, Result};
+use gtk::glib::{self, object_subclass, wrapper, Properties};
+use gtk::{cairo, gdk, pango, prelude::*, subclass::prelude::*};
+use std::cell::{Cell, RefCell};
+
+use crate::error_handling_ctx;
+
+wrapper! {
+    pub struct Marquee(ObjectSubclass<MarqueePriv>)
+    @extends gtk::Bin, gtk::Container, gtk::Widget;
+}
+
+#[derive(Properties)]
+#[properties(wrapper_type = Marquee)]
+pub struct MarqueePriv {
+    #[property(get, set, nick = "Text", blurb = "The text to display", default = "")]
+    text: RefCell<String>,
+
+    #[property(get, set, nick = "Speed", blurb = "Scrolling speed in pixels per second", minimum = 0f64, maximum = f64::MAX, default = 30f64)]
+    speed: RefCell<f64>,
+
+    #[property(get, set, nick = "Delay", blurb = "Time in ms to wait before scrolling", minimum = 0u64, maximum = u64::MAX, default = 1000u64)]
+    delay: RefCell<u64>,
+
+    #[property(get, set, nick = "Gap", blurb = "Space in pixels between the end and the start of the text", minimum = 0f64, maximum = f64::MAX, default = 40f64)]
+    gap: RefCell<f64>,
+
+    #[property(get, set, nick = "Loop Mode", blurb = "How the text loops", default = "wrap")]
+    loop_mode: RefCell<String>,
+
+    /// Frame time (in microseconds) at which the current text started scrolling
+    start_time: Cell<Option<i64>>,
+    /// Frame time (in microseconds) of the most recent frame
+    frame_time: Cell<i64>,
+    /// Layout of the current text, created when first needed after the text or style changed
+    layout: RefCell<Option<pango::Layout>>,
+    /// Tick callback animating the text, only installed while the text overflows the widget
+    tick_callback: RefCell<Option<gtk::TickCallbackId>>,
+}
+
+impl Default for MarqueePriv {
+    fn default() -> Self {
+        MarqueePriv {
+            text: RefCell::new(String::new()),
+            speed: RefCell::new(30.0),
+            delay: RefCell::new(1000),
+            gap: RefCell::new(40.0),
+            loop_mode: RefCell::new("wrap".to_string()),
+            start_time: Cell::new(None),
+            frame_time: Cell::new(0),
+            layout: RefCell::new(None),
+            tick_callback: RefCell::new(None),
+        }
+    }
+}
+
+impl MarqueePriv {
+    fn layout(&self) -> pango::Layout {
+        self.layout.borrow_mut().get_or_insert_with(|| self.obj().create_pango_layout(Some(&self.text.borrow()))).clone()
+    }
+
+    /// Drop the cached layout after the text or the style changed, and check whether the text needs to scroll now.
+    fn invalidate_layout(&self) {
+        self.layout.replace(None);
+        self.update_scrolling();
+    }
+
+    /// Install the tick callback while the text overflows the widget, and remove it once the text fits.
+    fn update_scrolling(&self) {
+        let (text_width, _) = self.layout().pixel_size();
+        let overflows = text_width > self.obj().allocated_width();
+        let mut tick_callback = self.tick_callback.borrow_mut();
+        if overflows && tick_callback.is_none() {
+            *tick_callback = Some(self.obj().add_tick_callback(|widget, frame_clock| widget.imp().on_tick(frame_clock)));
+        } else if !overflows {
+            if let Some(tick_callback) = tick_callback.take() {
+                tick_callback.remove();
+                self.start_time.set(None);
+            }
+        }
+    }
+
+    fn on_tick(&self, frame_clock: &gdk::FrameClock) -> glib::ControlFlow {
+        let now = frame_clock.frame_time();
+        self.frame_time.set(now);
+        if self.start_time.get().is_none() {
+            self.start_time.set(Some(now));
+        }
+        self.obj().queue_draw();
+        glib::ControlFlow::Continue
+    }
+
+    /// Calculate how far the text should be shifted to the left, given the width that is overflowing the widget.
+    fn scroll_offset(&self, text_width: f64, overflow: f64) -> Result<f64> {
+        let elapsed = (self.frame_time.get() - self.start_time.get().unwrap_or(self.frame_time.get())) as f64 / 1_000_000.0;
+        let speed = *self.speed.borrow();
+        let delay = *self.delay.borrow() as f64 / 1000.0;
+        if speed <= 0.0 {
+            return Ok(0.0);
+        }
+        Ok(match self.loop_mode.borrow().as_str() {
+            "wrap" => {
+                let distance = text_width + *self.gap.borrow();
+                let cycle = delay + distance / speed;
+                ((elapsed % cycle - delay).max(0.0) * speed).min(distance)
+            }
+            "bounce" => {
+                let travel_time = overflow / speed;
+                let cycle = 2.0 * (delay + travel_time);
+                let t = elapsed % cycle;
+                if t < delay {
+                    0.0
+                } else if t < delay + travel_time {
+                    (t - delay) * speed
+                } else if t < 2.0 * delay + travel_time {
+                    overflow
+                } else {
+                    overflow - (t - 2.0 * delay - travel_time) * speed
+                }
+            }
+            "none" => ((elapsed - delay).max(0.0) * speed).min(overflow),
+            x => return Err(anyhow!("Error, the value: {} for attribute loop-mode is not valid", x)),
+        })
+    }
+}
+
+impl ObjectImpl for MarqueePriv {
+    fn properties() -> &'static [glib::ParamSpec] {
+        Self::derived_properties()
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+        // fonts may be changed through the stylesheet, which changes the size of the text
+        self.obj().connect_style_updated(|widget| widget.imp().invalidate_layout());
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "text" => {
+                self.text.replace(value.get().unwrap());
+                // start scrolling from the beginning whenever the text changes
+                self.start_time.set(None);
+                self.invalidate_layout();
+                self.obj().queue_resize();
+            }
+            "speed" => {
+                self.speed.replace(value.get().unwrap());
+            }
+            "delay" => {
+                self.delay.replace(value.get().unwrap());
+            }
+            "gap" => {
+                self.gap.replace(value.get().unwrap());
+            }
+            "loop-mode" => {
+                self.loop_mode.replace(value.get().unwrap());
+                self.start_time.set(None);
+            }
+            x => panic!("Tried to set inexistant property of Marquee: {}", x,),
+        }
+    }
+
+    fn property(&self, id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        self.derived_property(id, pspec)
+    }
+}
+
+#[object_subclass]
+impl ObjectSubclass for MarqueePriv {
+    type ParentType = gtk::Bin;
+    type Type = Marquee;
+
+    const NAME: &'static str = "Marquee";
+
+    fn class_init(klass: &mut Self::Class) {
+        klass.set_css_name("marquee");
+    }
+}
+
+impl Default for Marquee {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Marquee {
+    pub fn new() -> Self {
+        glib::Object::new::<Self>()
+    }
+}
+
+impl ContainerImpl for MarqueePriv {
+    fn add(&self, _widget: &gtk::Widget) {
+        error_handling_ctx::print_error(anyhow!("Error, Marquee widget shoudln't have any children"));
+    }
+}
+
+impl BinImpl for MarqueePriv {}
+impl WidgetImpl for MarqueePriv {
+    fn size_allocate(&self, allocation: &gtk::Allocation) {
+        self.parent_size_allocate(allocation);
+        self.update_scrolling();
+    }
+
+    // If a width was explicitly requested, stick to it, such that the text scrolls instead of the widget growing.
+    fn preferred_width(&self) -> (i32, i32) {
+        let (text_width, _) = self.layout().pixel_size();
+        match self.obj().width_request() {
+            width if width > 0 => (width, width),
+            _ => (0, text_width),
+        }
+    }
+
+    fn preferred_height(&self) -> (i32, i32) {
+        let (_, text_height) = self.layout().pixel_size();
+        (text_height, text_height)
+    }
+
+    fn draw(&self, cr: &cairo::Context) -> glib::Propagation {
+        let res: Result<()> = (|| {
+            let layout = self.layout();
+            let (text_width, text_height) = layout.pixel_size();
+            let width = self.obj().allocated_width() as f64;
+            let height = self.obj().allocated_height() as f64;
+            let y = (height - text_height as f64) / 2.0;
+            let styles = self.obj().style_context();
+
+            cr.save()?;
+            cr.rectangle(0.0, 0.0, width, height);
+            cr.clip();
+
+            let overflow = text_width as f64 - width;
+            if overflow <= 0.0 {
+                gtk::render_layout(&styles, cr, 0.0, y, &layout);
+            } else {
+                let offset = self.scroll_offset(text_width as f64, overflow)?;
+                gtk::render_layout(&styles, cr, -offset, y, &layout);
+                if self.loop_mode.borrow().as_str() == "wrap" {
+                    // draw the start of the text again, following the end of the text
+                    let next_x = -offset + text_width as f64 + *self.gap.borrow();
+                    gtk::render_layout(&styles, cr, next_x, y, &layout);
+                }
+            }
+
+            cr.restore()?;
+            Ok(())
+        })();
+
+        if let Err(error) = res {
+            error_handling_ctx::print_error(error)
+        };
+
+        glib::Propagation::Proceed
+    }
+}
@@ -1,46 +1,294 @@
-use std::process::Command;
+use std::{
+    collections::HashMap,
+    io::Read,
+    process::{Command, ExitStatus, Stdio},
+    sync::{Mutex, RwLock},
+};
+
+use anyhow::Result;
+use codespan_reporting::diagnostic::Severity;
+use eww_shared_util::{AttrName, Span, VarName};
+use itertools::Itertools;
+use once_cell::sync::Lazy;
+use simplexpr::dynval::DynVal;
+use tokio::sync::mpsc::UnboundedSender;
+use yuck::{error::DiagError, gen_diagnostic};
+
+use crate::{
+    error_handling_ctx,
+    process_registry::{self, ProcessOwner},
+    state::scope_graph::{ScopeGraphEvent, ScopeIndex},
+};
+
+use self::build_widget::BuilderArgs;
 
 pub mod build_widget;
 pub mod circular_progressbar;
 pub mod def_widget_macro;
 pub mod graph;
-mod systray;
+pub mod image_cache;
+pub mod marquee;
+pub mod systray;
 pub mod transform;
 pub mod widget_definitions;
 pub mod window;
 
+/// Amount of times the same widget command needs to fail in a row before a warning is shown.
+const COMMAND_FAILURE_WARN_THRESHOLD: usize = 3;
+
+/// Maximum amount of commands whose failures are tracked at the same time.
+const MAX_TRACKED_COMMANDS: usize = 256;
+
+/// Number of consecutive failures of the commands run from widgets, by the span the command was defined at.
+static COMMAND_FAILURES: Lazy<Mutex<HashMap<Span, usize>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Shell commands from widgets are run with, as configured via `(defconfig :shell)`.
+static COMMAND_SHELL: Lazy<RwLock<Option<String>>> = Lazy::new(|| RwLock::new(None));
+
+/// Shell used to run commands from widgets if no other shell is configured.
+const DEFAULT_COMMAND_SHELL: &str = "/bin/sh";
+
+/// Set the shell commands from widgets are run with. `None` resets it to `/bin/sh`.
+pub fn set_command_shell(shell: Option<String>) {
+    *COMMAND_SHELL.write().unwrap() = shell;
+}
+
+/// Information about where a command was defined, which is passed on to the command as environment variables.
+#[derive(Debug, Clone)]
+pub struct CommandContext {
+    /// Span of the widget or variable the command was defined on, used when reporting failures.
+    pub span: Span,
+    /// Scope the widget was built in, which tells apart multiple instances of the same widget.
+    pub scope: Option<ScopeIndex>,
+    /// Name of the builtin widget the command was defined on.
+    pub widget: Option<String>,
+    /// Instance id of the window the widget is part of.
+    pub window: Option<String>,
+    /// Directory of the eww configuration, taken from the `EWW_CONFIG_DIR` magic variable.
+    pub config_dir: Option<String>,
+    /// Variable the result of the command is written to once it finished, given by the `:result-var` attribute of the widget.
+    pub result_var: Option<CommandResultVar>,
+}
+
+impl CommandContext {
+    /// Context for commands defined on the widget that is currently being built.
+    /// This handles the `:result-var` attribute of the widget.
+    pub fn new(bargs: &mut BuilderArgs) -> Result<Self> {
+        bargs.unhandled_attrs.remove(&AttrName("result-var".to_string()));
+        let result_var = bargs.widget_use.attrs.primitive_optional::<String, _>("result-var")?.map(|name| CommandResultVar {
+            event_sender: bargs.scope_graph.event_sender.clone(),
+            scope: bargs.calling_scope,
+            name: VarName(name),
+        });
+        let graph = &bargs.scope_graph;
+        Ok(Self {
+            span: bargs.widget_use.span,
+            scope: Some(bargs.calling_scope),
+            widget: Some(bargs.widget_use.name.clone()),
+            window: graph.window_name_of(bargs.calling_scope).map(|name| name.to_string()),
+            config_dir: graph
+                .lookup_variable_in_scope(graph.root_index, &VarName::from("EWW_CONFIG_DIR"))
+                .map(|dir| dir.as_string().unwrap_or_default()),
+            result_var,
+        })
+    }
+
+    /// Context for commands that don't belong to any widget, such as the `:onchange` of a variable.
+    pub fn without_widget(span: Span, config_dir: &std::path::Path) -> Self {
+        Self {
+            span,
+            scope: None,
+            widget: None,
+            window: None,
+            config_dir: Some(config_dir.to_string_lossy().into_owned()),
+            result_var: None,
+        }
+    }
+
+    /// The environment variables a command run in this context gets.
+    fn env_vars(&self, value: Option<String>) -> Vec<(&'static str, String)> {
+        let location = error_handling_ctx::format_span_location(self.span);
+        let id = match self.scope {
+            Some(scope) => format!("{}:{}", scope.0, location),
+            None => location,
+        };
+        let mut vars = vec![("EWW_WIDGET_ID", id)];
+        vars.extend(self.widget.clone().map(|widget| ("EWW_WIDGET", widget)));
+        vars.extend(self.window.clone().map(|window| ("EWW_WINDOW", window)));
+        vars.extend(self.config_dir.clone().map(|dir| ("EWW_CONFIG_DIR", dir)));
+        vars.extend(value.map(|value| ("EWW_VALUE", value)));
+        vars
+    }
+}
+
+/// A variable that the exit code and output of a command are written to, as seen from the scope of the widget the command was defined on.
+#[derive(Debug, Clone)]
+pub struct CommandResultVar {
+    event_sender: UnboundedSender<ScopeGraphEvent>,
+    scope: ScopeIndex,
+    name: VarName,
+}
+
+impl CommandResultVar {
+    /// Set the variable to a JSON object containing the exit code and the output of the command.
+    fn set(&self, status: ExitStatus, stdout: &str) {
+        let result = serde_json::json!({ "exit_code": status.code(), "stdout": stdout.trim_end() });
+        let evt = ScopeGraphEvent::UpdateValue(self.scope, self.name.clone(), DynVal::from(&result));
+        crate::print_result_err!("while setting the result of a command", self.event_sender.send(evt));
+    }
+}
+
 /// Run a command that was provided as an attribute.
 /// This command may use placeholders which will be replaced by the values of the arguments given.
 /// This can either be the placeholder `{}`, which will be replaced by the first argument,
 /// Or a placeholder like `{0}`, `{1}`, etc, which will refer to the respective argument.
-fn run_command<T>(timeout: std::time::Duration, cmd: &str, args: &[T])
+/// A command of the form `{copy:text}` copies the text into the clipboard instead of running a shell command.
+/// A command given as a JSON array of strings is run directly, without a shell, with the placeholders replaced in each argument.
+///
+/// The command gets information about the widget it was defined on, given by `context`, and the first argument
+/// as environment variables, see [`CommandContext::env_vars`].
+///
+/// The command is killed once it runs for longer than `timeout`. Without a timeout, it runs until it exits by itself.
+pub fn run_command<T>(context: &CommandContext, timeout: Option<std::time::Duration>, cmd: &str, args: &[T])
 where
     T: 'static + std::fmt::Display + Send + Sync + Clone,
 {
     use wait_timeout::ChildExt;
-    let cmd = replace_placeholders(cmd, args);
+    let (program, program_args, cmd) = match parse_exec_command(cmd) {
+        Some(argv) => {
+            let mut argv = argv.iter().map(|arg| replace_placeholders(arg, args));
+            let Some(program) = argv.next() else {
+                log::error!("Command from widget at {} is an empty list", error_handling_ctx::format_span_location(context.span));
+                return;
+            };
+            let program_args: Vec<_> = argv.collect();
+            let cmd = std::iter::once(program.as_str()).chain(program_args.iter().map(String::as_str)).join(" ");
+            (program, program_args, cmd)
+        }
+        None => {
+            let cmd = replace_placeholders(cmd, args);
+            if let Some(text) = parse_copy_command(&cmd) {
+                log::debug!("Copying text from widget to clipboard: {}", text);
+                set_clipboard_text(text);
+                return;
+            }
+            let shell = COMMAND_SHELL.read().unwrap().clone().unwrap_or_else(|| DEFAULT_COMMAND_SHELL.to_string());
+            (shell, vec!["-c".to_string(), cmd.clone()], cmd)
+        }
+    };
+    let span = context.span;
+    let result_var = context.result_var.clone();
+    let env_vars = context.env_vars(args.first().map(|value| value.to_string()));
     std::thread::Builder::new()
         .name("command-execution-thread".to_string())
         .spawn(move || {
-            log::debug!("Running command from widget [timeout: {}ms]: {}", timeout.as_millis(), cmd);
-            let child = Command::new("/bin/sh").arg("-c").arg(&cmd).spawn();
-            match child {
-                Ok(mut child) => match child.wait_timeout(timeout) {
-                    // child timed out
-                    Ok(None) => {
-                        log::error!("WARNING: command {} timed out", &cmd);
-                        let _ = child.kill();
-                        let _ = child.wait();
+            let location = error_handling_ctx::format_span_location(span);
+            let timeout_description = timeout.map_or_else(|| "none".to_string(), |timeout| format!("{}ms", timeout.as_millis()));
+            log::debug!("Running command from widget at {} [timeout: {}]: {}", location, timeout_description, cmd);
+            let start_time = std::time::Instant::now();
+            let mut command = Command::new(&program);
+            command.args(&program_args).envs(env_vars);
+            if result_var.is_some() {
+                command.stdout(Stdio::piped());
+            }
+            let mut child = match command.spawn() {
+                Ok(child) => child,
+                Err(err) => {
+                    log::error!("Failed to launch child process {}: {}", program, err);
+                    return;
+                }
+            };
+            let _registration =
+                process_registry::register(Some(child.id()), ProcessOwner::Command(location.to_string()), &cmd, 0);
+            // read the output while waiting, such that the command can't get stuck writing into a full pipe
+            let stdout_reader = child.stdout.take().map(|mut stdout| {
+                std::thread::spawn(move || {
+                    let mut output = String::new();
+                    let _ = stdout.read_to_string(&mut output);
+                    output
+                })
+            });
+            let status = match timeout {
+                Some(timeout) => child.wait_timeout(timeout),
+                None => child.wait().map(Some),
+            };
+            match status {
+                // child timed out
+                Ok(None) => {
+                    log::error!("WARNING: command {} timed out", &cmd);
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    track_command_result(span, &cmd, None);
+                }
+                Err(err) => log::error!("Failed to execute command {}: {}", cmd, err),
+                Ok(Some(status)) => {
+                    log::debug!(
+                        "Command from widget at {} exited with {} after {}ms: {}",
+                        location,
+                        status,
+                        start_time.elapsed().as_millis(),
+                        cmd
+                    );
+                    track_command_result(span, &cmd, Some(status));
+                    if let Some(result_var) = result_var {
+                        let stdout = stdout_reader.and_then(|reader| reader.join().ok()).unwrap_or_default();
+                        result_var.set(status, &stdout);
                     }
-                    Err(err) => log::error!("Failed to execute command {}: {}", cmd, err),
-                    Ok(Some(_)) => {}
-                },
-                Err(err) => log::error!("Failed to launch child process: {}", err),
+                }
             }
         })
         .expect("Failed to start command-execution-thread");
 }
 
+/// Keep track of how often a command failed in a row, showing a warning once it failed repeatedly.
+/// A `status` of `None` means that the command timed out.
+fn track_command_result(span: Span, cmd: &str, status: Option<ExitStatus>) {
+    let mut failures = COMMAND_FAILURES.lock().unwrap();
+    if status.map_or(false, |status| status.success()) {
+        failures.remove(&span);
+        return;
+    }
+    if !failures.contains_key(&span) && failures.len() >= MAX_TRACKED_COMMANDS {
+        // forget about another command, such that commands from old configurations don't pile up
+        if let Some(evicted) = failures.keys().next().copied() {
+            failures.remove(&evicted);
+        }
+    }
+    let failure_count = failures.entry(span).or_insert(0);
+    *failure_count += 1;
+    if *failure_count == COMMAND_FAILURE_WARN_THRESHOLD {
+        let last_failure = status.map_or_else(|| "a timeout".to_string(), |status| status.to_string());
+        error_handling_ctx::print_error(anyhow::anyhow!(DiagError(gen_diagnostic! {
+            kind = Severity::Warning,
+            msg = format!("Command failed {} times in a row, last with {}: {}", failure_count, last_failure, cmd),
+            label = span => "Defined here",
+        })));
+    }
+}
+
+/// Set the contents of the clipboard. This needs to be called from the GTK main thread.
+pub fn set_clipboard_text(text: &str) {
+    let clipboard = gtk::Clipboard::get(&gtk::gdk::SELECTION_CLIPBOARD);
+    clipboard.set_text(text);
+    // hand the contents over to the clipboard manager, if there is one, such that they survive eww closing
+    clipboard.store();
+}
+
+/// Get the arguments of a command given as a JSON array, such as `["notify-send", "hi"]`.
+/// Anything else, including shell commands like `[ -f file ] && ...`, is run with a shell.
+fn parse_exec_command(cmd: &str) -> Option<Vec<String>> {
+    let cmd = cmd.trim();
+    if !cmd.starts_with('[') {
+        return None;
+    }
+    serde_json::from_str(cmd).ok()
+}
+
+/// Get the text of a `{copy:text}` command.
+fn parse_copy_command(cmd: &str) -> Option<&str> {
+    cmd.trim().strip_prefix("{copy:")?.strip_suffix('}')
+}
+
 fn replace_placeholders<T>(cmd: &str, args: &[T]) -> String
 where
     T: 'static + std::fmt::Display + Send + Sync + Clone,
@@ -64,4 +312,21 @@ mod test {
         assert_eq!("bar foo baz", replace_placeholders("{0} foo {1}", &["bar", "baz"]),);
         assert_eq!("baz foo bar", replace_placeholders("{1} foo {0}", &["bar", "baz"]),);
     }
+
+    #[test]
+    fn test_parse_copy_command() {
+        assert_eq!(Some("hi"), parse_copy_command("{copy:hi}"));
+        assert_eq!(Some("{a} b"), parse_copy_command(" {copy:{a} b} "));
+        assert_eq!(Some(""), parse_copy_command("{copy:}"));
+        assert_eq!(None, parse_copy_command("echo {copy:hi}"));
+        assert_eq!(None, parse_copy_command("{copy:hi"));
+    }
+
+    #[test]
+    fn test_parse_exec_command() {
+        assert_eq!(Some(vec!["notify-send".to_string(), "a b".to_string()]), parse_exec_command(r#" ["notify-send", "a b"] "#));
+        assert_eq!(Some(vec![]), parse_exec_command("[]"));
+        assert_eq!(None, parse_exec_command("[ -f foo ] && echo hi"));
+        assert_eq!(None, parse_exec_command("echo [1]"));
+    }
 }
@@ -6,17 +6,22 @@ use gtk::{
     glib,
     prelude::*,
 };
-use std::{cell::RefCell, future::Future, rc::Rc};
+use std::{
+    cell::{Cell, RefCell},
+    future::Future,
+    rc::Rc,
+};
 
 // DBus state shared between systray instances, to avoid creating too many connections etc.
 struct DBusSession {
+    con: zbus::Connection,
     snw: notifier_host::proxy::StatusNotifierWatcherProxy<'static>,
 }
 
-async fn dbus_session() -> zbus::Result<&'static DBusSession> {
-    // TODO make DBusSession reference counted so it's dropped when not in use?
+// TODO make DBusSession reference counted so it's dropped when not in use?
+static DBUS_STATE: tokio::sync::OnceCell<DBusSession> = tokio::sync::OnceCell::const_new();
 
-    static DBUS_STATE: tokio::sync::OnceCell<DBusSession> = tokio::sync::OnceCell::const_new();
+async fn dbus_session() -> zbus::Result<&'static DBusSession> {
     DBUS_STATE
         .get_or_try_init(|| async {
             let con = zbus::Connection::session().await?;
@@ -24,11 +29,21 @@ async fn dbus_session() -> zbus::Result<&'static DBusSession> {
 
             let (_, snw) = notifier_host::register_as_host(&con).await?;
 
-            Ok(DBusSession { snw })
+            Ok(DBusSession { con, snw })
         })
         .await
 }
 
+/// Stop providing the StatusNotifierWatcher, so that other trays can take over immediately instead of once eww exits.
+/// Returns false if there was no systray running.
+pub async fn release_watcher() -> bool {
+    let Some(session) = DBUS_STATE.get() else { return false };
+    if let Err(e) = notifier_host::Watcher::detach_from(&session.con).await {
+        log::warn!("Failed to release StatusNotifierWatcher: {}", e);
+    }
+    true
+}
+
 fn run_async_task<F: Future>(f: F) -> F::Output {
     let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().expect("Failed to initialize tokio runtime");
     rt.block_on(f)
@@ -37,12 +52,16 @@ fn run_async_task<F: Future>(f: F) -> F::Output {
 pub struct Props {
     icon_size_tx: tokio::sync::watch::Sender<i32>,
     pub prepend_new: Rc<RefCell<bool>>,
+    pub overflow: Rc<Overflow>,
+    pub actions: Rc<ItemActions>,
 }
 
 impl Props {
-    pub fn new() -> Self {
+    pub fn new(container: &gtk::Box) -> Self {
         let (icon_size_tx, _) = tokio::sync::watch::channel(24);
-        Self { icon_size_tx, prepend_new: Rc::new(RefCell::new(false)) }
+        let prepend_new = Rc::new(RefCell::new(false));
+        let overflow = Rc::new(Overflow::new(container, prepend_new.clone()));
+        Self { icon_size_tx, prepend_new, overflow, actions: Rc::new(ItemActions::default()) }
     }
 
     pub fn icon_size(&self, value: i32) {
@@ -57,12 +76,145 @@ impl Props {
     }
 }
 
+/// Which interactions with tray items are forwarded to the applications providing them.
+#[derive(Debug)]
+pub struct ItemActions {
+    /// Forward scrolling over an item to its `Scroll` method.
+    pub scroll: Cell<bool>,
+    /// Call `SecondaryActivate` when an item is clicked with the middle mouse button.
+    pub secondary_activate: Cell<bool>,
+}
+
+impl Default for ItemActions {
+    fn default() -> Self {
+        Self { scroll: Cell::new(true), secondary_activate: Cell::new(true) }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowMode {
+    /// Passive items are not shown at all.
+    None,
+    /// Passive items are shown in a section that can be expanded by clicking a toggle button.
+    Reveal,
+}
+
+/// Manages where tray items are placed, depending on whether they are passive or not.
+pub struct Overflow {
+    container: gtk::Box,
+    revealer: gtk::Revealer,
+    items_box: gtk::Box,
+    toggle: gtk::Button,
+    prepend_new: Rc<RefCell<bool>>,
+    show_passive: Cell<bool>,
+    mode: Cell<OverflowMode>,
+}
+
+impl Overflow {
+    fn new(container: &gtk::Box, prepend_new: Rc<RefCell<bool>>) -> Self {
+        let items_box = gtk::Box::new(container.orientation(), 0);
+        items_box.show();
+
+        let revealer = gtk::Revealer::new();
+        revealer.style_context().add_class("overflow");
+        revealer.set_transition_type(gtk::RevealerTransitionType::SlideLeft);
+        revealer.add(&items_box);
+        revealer.set_no_show_all(true);
+
+        let toggle = gtk::Button::with_label("…");
+        toggle.style_context().add_class("overflow-toggle");
+        toggle.set_no_show_all(true);
+        toggle.connect_clicked(glib::clone!(@weak revealer => move |toggle| {
+            let expanded = !revealer.reveals_child();
+            revealer.set_reveal_child(expanded);
+            if expanded {
+                toggle.style_context().add_class("expanded");
+            } else {
+                toggle.style_context().remove_class("expanded");
+            }
+        }));
+
+        // items that are packed at the end later on will end up before these
+        container.pack_end(&toggle, false, false, 0);
+        container.pack_end(&revealer, false, false, 0);
+
+        Self {
+            container: container.clone(),
+            revealer,
+            items_box,
+            toggle,
+            prepend_new,
+            show_passive: Cell::new(false),
+            mode: Cell::new(OverflowMode::Reveal),
+        }
+    }
+
+    pub fn set_show_passive(&self, show_passive: bool) {
+        self.show_passive.set(show_passive);
+        self.relayout();
+    }
+
+    pub fn set_mode(&self, mode: OverflowMode) {
+        self.mode.set(mode);
+        self.relayout();
+    }
+
+    pub fn set_orientation(&self, orientation: gtk::Orientation) {
+        self.items_box.set_orientation(orientation);
+        self.revealer.set_transition_type(match orientation {
+            gtk::Orientation::Vertical => gtk::RevealerTransitionType::SlideUp,
+            _ => gtk::RevealerTransitionType::SlideLeft,
+        });
+    }
+
+    fn pack(&self, target: &gtk::Box, widget: &gtk::EventBox) {
+        if *self.prepend_new.borrow() {
+            target.pack_end(widget, true, true, 0);
+        } else {
+            target.pack_start(widget, true, true, 0);
+        }
+    }
+
+    /// Move an item into the section it belongs to, according to its status.
+    fn place_item(&self, widget: &gtk::EventBox) {
+        let passive = widget.style_context().has_class("passive");
+        let in_overflow = passive && !self.show_passive.get() && self.mode.get() == OverflowMode::Reveal;
+        let target = if in_overflow { &self.items_box } else { &self.container };
+        if widget.parent().as_ref() != Some(target.upcast_ref()) {
+            remove_from_parent(widget);
+            self.pack(target, widget);
+        }
+        widget.set_visible(!passive || self.show_passive.get() || in_overflow);
+        self.update_toggle();
+    }
+
+    fn relayout(&self) {
+        let items = self.container.children().into_iter().chain(self.items_box.children());
+        for item in items.filter_map(|child| child.downcast::<gtk::EventBox>().ok()) {
+            self.place_item(&item);
+        }
+    }
+
+    fn update_toggle(&self) {
+        let has_overflow = !self.items_box.children().is_empty();
+        self.toggle.set_visible(has_overflow);
+        self.revealer.set_visible(has_overflow);
+    }
+}
+
+fn remove_from_parent(widget: &gtk::EventBox) {
+    if let Some(parent) = widget.parent().and_then(|parent| parent.downcast::<gtk::Container>().ok()) {
+        parent.remove(widget);
+    }
+}
+
 struct Tray {
     container: gtk::Box,
     items: std::collections::HashMap<String, Item>,
 
     icon_size: tokio::sync::watch::Receiver<i32>,
-    prepend_new: Rc<RefCell<bool>>,
+    overflow: Rc<Overflow>,
+    actions: Rc<ItemActions>,
 }
 
 pub fn spawn_systray(container: &gtk::Box, props: &Props) {
@@ -70,7 +222,8 @@ pub fn spawn_systray(container: &gtk::Box, props: &Props) {
         container: container.clone(),
         items: Default::default(),
         icon_size: props.icon_size_tx.subscribe(),
-        prepend_new: props.prepend_new.clone(),
+        overflow: props.overflow.clone(),
+        actions: props.actions.clone(),
     };
 
     let task = glib::MainContext::default().spawn_local(async move {
@@ -95,25 +248,34 @@ pub fn spawn_systray(container: &gtk::Box, props: &Props) {
 
 impl notifier_host::Host for Tray {
     fn add_item(&mut self, id: &str, item: notifier_host::Item) {
-        let item = Item::new(id.to_owned(), item, self.icon_size.clone());
-        if *self.prepend_new.borrow() {
-            self.container.pack_end(&item.widget, true, true, 0);
-        } else {
-            self.container.pack_start(&item.widget, true, true, 0);
-        }
+        let item = Item::new(id.to_owned(), item, self.icon_size.clone(), self.overflow.clone(), self.actions.clone());
+        self.overflow.pack(&self.container, &item.widget);
         if let Some(old_item) = self.items.insert(id.to_string(), item) {
-            self.container.remove(&old_item.widget);
+            remove_from_parent(&old_item.widget);
         }
+        self.overflow.update_toggle();
     }
 
     fn remove_item(&mut self, id: &str) {
-        if let Some(item) = self.items.get(id) {
-            self.container.remove(&item.widget);
-            self.items.remove(id);
+        if let Some(item) = self.items.remove(id) {
+            remove_from_parent(&item.widget);
+            self.overflow.update_toggle();
         } else {
             log::warn!("Tried to remove nonexistent item {:?} from systray", id);
         }
     }
+
+    fn watcher_changed(&mut self, status: notifier_host::WatcherStatus) {
+        let style_context = self.container.style_context();
+        for class in ["watcher-internal", "watcher-external", "watcher-missing"] {
+            style_context.remove_class(class);
+        }
+        style_context.add_class(match status {
+            notifier_host::WatcherStatus::Internal => "watcher-internal",
+            notifier_host::WatcherStatus::External => "watcher-external",
+            notifier_host::WatcherStatus::Missing => "watcher-missing",
+        });
+    }
 }
 
 /// Item represents a single icon being shown in the system tray.
@@ -134,7 +296,13 @@ impl Drop for Item {
 }
 
 impl Item {
-    fn new(id: String, item: notifier_host::Item, icon_size: tokio::sync::watch::Receiver<i32>) -> Self {
+    fn new(
+        id: String,
+        item: notifier_host::Item,
+        icon_size: tokio::sync::watch::Receiver<i32>,
+        overflow: Rc<Overflow>,
+        actions: Rc<ItemActions>,
+    ) -> Self {
         let gtk_widget = gtk::EventBox::new();
 
         // Support :hover selector
@@ -155,7 +323,7 @@ impl Item {
         let out_widget = gtk_widget.clone(); // copy so we can return it
 
         let task = glib::MainContext::default().spawn_local(async move {
-            if let Err(e) = Item::maintain(gtk_widget.clone(), item, icon_size).await {
+            if let Err(e) = Item::maintain(gtk_widget.clone(), item, icon_size, overflow, actions).await {
                 log::error!("error for systray item {}: {}", id, e);
             }
         });
@@ -167,6 +335,8 @@ impl Item {
         widget: gtk::EventBox,
         mut item: notifier_host::Item,
         mut icon_size: tokio::sync::watch::Receiver<i32>,
+        overflow: Rc<Overflow>,
+        actions: Rc<ItemActions>,
     ) -> zbus::Result<()> {
         // init icon
         let icon = gtk::Image::new();
@@ -182,10 +352,7 @@ impl Item {
         // refactor without making the borrow checker angry
 
         // set status
-        match item.status().await? {
-            notifier_host::Status::Passive => widget.hide(),
-            notifier_host::Status::Active | notifier_host::Status::NeedsAttention => widget.show(),
-        }
+        set_item_status(&widget, &overflow, item.status().await?);
 
         // set title
         widget.set_tooltip_text(Some(&item.sni.title().await?));
@@ -197,8 +364,8 @@ impl Item {
         let item = Rc::new(item);
         let window =
             widget.toplevel().expect("Failed to obtain toplevel window").downcast::<Window>().expect("Failed to downcast window");
-        widget.add_events(gdk::EventMask::BUTTON_PRESS_MASK);
-        widget.connect_button_press_event(glib::clone!(@strong item => move |_, evt| {
+        widget.add_events(gdk::EventMask::BUTTON_PRESS_MASK | gdk::EventMask::SCROLL_MASK | gdk::EventMask::SMOOTH_SCROLL_MASK);
+        widget.connect_button_press_event(glib::clone!(@strong item, @strong actions => move |_, evt| {
             let (x, y) = (evt.root().0 as i32 + window.x(), evt.root().1 as i32 + window.y());
             let item_is_menu = run_async_task(async { item.sni.item_is_menu().await });
             let have_item_is_menu = item_is_menu.is_ok();
@@ -225,7 +392,10 @@ impl Item {
                         result
                     }
                 }
-                (gdk::BUTTON_MIDDLE, _) => run_async_task(async { item.sni.secondary_activate(x, y).await }),
+                (gdk::BUTTON_MIDDLE, _) if actions.secondary_activate.get() => {
+                    run_async_task(async { item.sni.secondary_activate(x, y).await })
+                }
+                (gdk::BUTTON_MIDDLE, _) => return glib::Propagation::Proceed,
                 (gdk::BUTTON_SECONDARY, _) | (gdk::BUTTON_PRIMARY, true) => {
                     run_async_task(async { item.popup_menu( evt, x, y).await })
                 }
@@ -237,6 +407,37 @@ impl Item {
             glib::Propagation::Stop
         }));
 
+        // smooth scrolling reports fractions of a scroll step, which are accumulated until they add up to a full step
+        let smooth_scroll_delta = Cell::new((0.0, 0.0));
+        widget.connect_scroll_event(glib::clone!(@strong item => move |_, evt| {
+            if !actions.scroll.get() {
+                return glib::Propagation::Proceed;
+            }
+            let (dx, dy) = match evt.direction() {
+                gdk::ScrollDirection::Up => (0, -1),
+                gdk::ScrollDirection::Down => (0, 1),
+                gdk::ScrollDirection::Left => (-1, 0),
+                gdk::ScrollDirection::Right => (1, 0),
+                gdk::ScrollDirection::Smooth => {
+                    let (acc_x, acc_y) = smooth_scroll_delta.get();
+                    let (delta_x, delta_y) = evt.delta();
+                    let (acc_x, acc_y) = (acc_x + delta_x, acc_y + delta_y);
+                    let (dx, dy) = (acc_x.trunc(), acc_y.trunc());
+                    smooth_scroll_delta.set((acc_x - dx, acc_y - dy));
+                    (dx as i32, dy as i32)
+                }
+                _ => (0, 0),
+            };
+            for (delta, orientation) in [(dx, "horizontal"), (dy, "vertical")] {
+                if delta != 0 {
+                    if let Err(e) = run_async_task(async { item.sni.scroll(delta, orientation).await }) {
+                        log::error!("failed to forward scroll to tray item: {}", e);
+                    }
+                }
+            }
+            glib::Propagation::Stop
+        }));
+
         // updates
         let mut status_updates = item.sni.receive_new_status().await?;
         let mut title_updates = item.sni.receive_new_title().await?;
@@ -246,10 +447,7 @@ impl Item {
             tokio::select! {
                 Some(_) = status_updates.next() => {
                     // set status
-                    match item.status().await? {
-                        notifier_host::Status::Passive => widget.hide(),
-                        notifier_host::Status::Active | notifier_host::Status::NeedsAttention => widget.show(),
-                    }
+                    set_item_status(&widget, &overflow, item.status().await?);
                 }
                 Ok(_) = icon_size.changed() => {
                     // set icon
@@ -268,17 +466,30 @@ impl Item {
     }
 }
 
+fn set_item_status(widget: &gtk::EventBox, overflow: &Overflow, status: notifier_host::Status) {
+    match status {
+        notifier_host::Status::Passive => widget.style_context().add_class("passive"),
+        notifier_host::Status::Active | notifier_host::Status::NeedsAttention => widget.style_context().remove_class("passive"),
+    }
+    overflow.place_item(widget);
+}
+
 async fn load_icon_for_item(icon: &gtk::Image, item: &notifier_host::Item, size: i32, scale: i32) {
     if let Some(pixbuf) = item.icon(size, scale).await {
-        let surface = unsafe {
-            // gtk::cairo::Surface will destroy the underlying surface on drop
-            let ptr = gdk_cairo_surface_create_from_pixbuf(
-                pixbuf.as_ptr(),
-                scale,
-                icon.window().map_or(std::ptr::null_mut(), |v| v.as_ptr()),
-            );
-            Surface::from_raw_full(ptr)
-        };
-        icon.set_from_surface(surface.ok().as_ref());
+        set_image_from_pixbuf(icon, &pixbuf, scale);
     }
 }
+
+/// Show a pixbuf that was loaded for the given scale factor in an image, without gtk scaling it up again.
+pub fn set_image_from_pixbuf(image: &gtk::Image, pixbuf: &gtk::gdk_pixbuf::Pixbuf, scale: i32) {
+    let surface = unsafe {
+        // gtk::cairo::Surface will destroy the underlying surface on drop
+        let ptr = gdk_cairo_surface_create_from_pixbuf(
+            pixbuf.as_ptr(),
+            scale,
+            image.window().map_or(std::ptr::null_mut(), |v| v.as_ptr()),
+        );
+        Surface::from_raw_full(ptr)
+    };
+    image.set_from_surface(surface.ok().as_ref());
+}
@@ -1,7 +1,10 @@
 use anyhow::{anyhow, Result};
 use gtk::glib::{self, object_subclass, wrapper, Properties};
-use gtk::{prelude::*, subclass::prelude::*};
-use std::{cell::RefCell, str::FromStr};
+use gtk::{gdk, prelude::*, subclass::prelude::*};
+use std::{
+    cell::{Cell, RefCell},
+    str::FromStr,
+};
 use yuck::value::NumWithUnit;
 
 use crate::error_handling_ctx;
@@ -35,7 +38,53 @@ pub struct TransformPriv {
     #[property(get, set, nick = "Scale y", blurb = "The amount to scale in y", default = None)]
     scale_y: RefCell<Option<String>>,
 
+    #[property(get, set, nick = "Duration", blurb = "Duration of the transition in ms", minimum = 0u64, maximum = u64::MAX, default = 0u64)]
+    duration: RefCell<u64>,
+
+    #[property(get, set, nick = "Easing", blurb = "The easing function used for transitions", default = "linear")]
+    easing: RefCell<String>,
+
     content: RefCell<Option<gtk::Widget>>,
+
+    /// The values that were drawn last, used as the starting point of a transition
+    displayed: RefCell<Option<TransformValues>>,
+    animation: RefCell<Option<Animation>>,
+    ticking: Cell<bool>,
+}
+
+/// Transformation values, resolved to pixels / factors for the current allocation
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TransformValues {
+    rotate: f64,
+    transform_origin_x: f64,
+    transform_origin_y: f64,
+    translate_x: f64,
+    translate_y: f64,
+    scale_x: f64,
+    scale_y: f64,
+}
+
+impl TransformValues {
+    fn interpolate(&self, target: &Self, t: f64) -> Self {
+        let lerp = |a: f64, b: f64| a + (b - a) * t;
+        TransformValues {
+            rotate: lerp(self.rotate, target.rotate),
+            transform_origin_x: lerp(self.transform_origin_x, target.transform_origin_x),
+            transform_origin_y: lerp(self.transform_origin_y, target.transform_origin_y),
+            translate_x: lerp(self.translate_x, target.translate_x),
+            translate_y: lerp(self.translate_y, target.translate_y),
+            scale_x: lerp(self.scale_x, target.scale_x),
+            scale_y: lerp(self.scale_y, target.scale_y),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Animation {
+    from: TransformValues,
+    /// Frame time (in microseconds) of the first frame of the animation
+    start_time: Option<i64>,
+    progress: f64,
 }
 
 // This should match the default values from the ParamSpecs
@@ -49,7 +98,12 @@ impl Default for TransformPriv {
             translate_y: RefCell::new(None),
             scale_x: RefCell::new(None),
             scale_y: RefCell::new(None),
+            duration: RefCell::new(0),
+            easing: RefCell::new("linear".to_string()),
             content: RefCell::new(None),
+            displayed: RefCell::new(None),
+            animation: RefCell::new(None),
+            ticking: Cell::new(false),
         }
     }
 }
@@ -63,32 +117,45 @@ impl ObjectImpl for TransformPriv {
         match pspec.name() {
             "rotate" => {
                 self.rotate.replace(value.get().unwrap());
+                self.start_animation();
                 self.obj().queue_draw(); // Queue a draw call with the updated value
             }
             "transform-origin-x" => {
                 self.transform_origin_x.replace(value.get().unwrap());
+                self.start_animation();
                 self.obj().queue_draw(); // Queue a draw call with the updated value
             }
             "transform-origin-y" => {
                 self.transform_origin_y.replace(value.get().unwrap());
+                self.start_animation();
                 self.obj().queue_draw(); // Queue a draw call with the updated value
             }
             "translate-x" => {
                 self.translate_x.replace(value.get().unwrap());
+                self.start_animation();
                 self.obj().queue_draw(); // Queue a draw call with the updated value
             }
             "translate-y" => {
                 self.translate_y.replace(value.get().unwrap());
+                self.start_animation();
                 self.obj().queue_draw(); // Queue a draw call with the updated value
             }
             "scale-x" => {
                 self.scale_x.replace(value.get().unwrap());
+                self.start_animation();
                 self.obj().queue_draw(); // Queue a draw call with the updated value
             }
             "scale-y" => {
                 self.scale_y.replace(value.get().unwrap());
+                self.start_animation();
                 self.obj().queue_draw(); // Queue a draw call with the updated value
             }
+            "duration" => {
+                self.duration.replace(value.get().unwrap());
+            }
+            "easing" => {
+                self.easing.replace(value.get().unwrap());
+            }
             x => panic!("Tried to set inexistant property of Transform: {}", x,),
         }
     }
@@ -98,6 +165,47 @@ impl ObjectImpl for TransformPriv {
     }
 }
 
+impl TransformPriv {
+    /// Start a transition from the currently displayed values towards the newly set ones.
+    /// Does nothing if no duration is set, or if the widget hasn't been drawn yet.
+    fn start_animation(&self) {
+        let duration = *self.duration.borrow();
+        let Some(from) = *self.displayed.borrow() else { return };
+        if duration == 0 {
+            self.animation.replace(None);
+            return;
+        }
+        self.animation.replace(Some(Animation { from, start_time: None, progress: 0.0 }));
+        if !self.ticking.get() {
+            self.ticking.set(true);
+            self.obj().add_tick_callback(|widget, frame_clock| widget.imp().on_tick(frame_clock));
+        }
+    }
+
+    fn on_tick(&self, frame_clock: &gdk::FrameClock) -> glib::ControlFlow {
+        let mut animation = self.animation.borrow_mut();
+        let Some(anim) = animation.as_mut() else {
+            self.ticking.set(false);
+            return glib::ControlFlow::Break;
+        };
+        let now = frame_clock.frame_time();
+        let start_time = *anim.start_time.get_or_insert(now);
+        let duration_micros = (*self.duration.borrow() * 1000).max(1) as f64;
+        anim.progress = ((now - start_time) as f64 / duration_micros).clamp(0.0, 1.0);
+        let finished = anim.progress >= 1.0;
+        if finished {
+            *animation = None;
+            self.ticking.set(false);
+        }
+        self.obj().queue_draw();
+        if finished {
+            glib::ControlFlow::Break
+        } else {
+            glib::ControlFlow::Continue
+        }
+    }
+}
+
 #[object_subclass]
 impl ObjectSubclass for TransformPriv {
     type ParentType = gtk::Bin;
@@ -173,10 +281,18 @@ impl WidgetImpl for TransformPriv {
                 None => 1.0,
             };
 
-            cr.translate(transform_origin_x, transform_origin_y);
-            cr.rotate(perc_to_rad(rotate));
-            cr.translate(translate_x - transform_origin_x, translate_y - transform_origin_y);
-            cr.scale(scale_x, scale_y);
+            let target =
+                TransformValues { rotate, transform_origin_x, transform_origin_y, translate_x, translate_y, scale_x, scale_y };
+            let values = match *self.animation.borrow() {
+                Some(anim) => anim.from.interpolate(&target, apply_easing(&self.easing.borrow(), anim.progress)?),
+                None => target,
+            };
+            self.displayed.replace(Some(values));
+
+            cr.translate(values.transform_origin_x, values.transform_origin_y);
+            cr.rotate(perc_to_rad(values.rotate));
+            cr.translate(values.translate_x - values.transform_origin_x, values.translate_y - values.transform_origin_y);
+            cr.scale(values.scale_x, values.scale_y);
 
             // Children widget
             if let Some(child) = &*self.content.borrow() {
@@ -198,3 +314,20 @@ impl WidgetImpl for TransformPriv {
 fn perc_to_rad(n: f64) -> f64 {
     (n / 100f64) * 2f64 * std::f64::consts::PI
 }
+
+/// Map the linear progress `t` (0 to 1) of a transition onto the given easing curve
+fn apply_easing(easing: &str, t: f64) -> Result<f64> {
+    Ok(match easing {
+        "linear" => t,
+        "ease-in" => t * t * t,
+        "ease-out" => 1.0 - (1.0 - t).powi(3),
+        "ease-in-out" => {
+            if t < 0.5 {
+                4.0 * t * t * t
+            } else {
+                1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+            }
+        }
+        _ => Err(anyhow!("Error, the value: {} for attribute easing is not valid", easing))?,
+    })
+}
@@ -1,13 +1,25 @@
 #![allow(clippy::option_map_unit_fn)]
-use super::{build_widget::BuilderArgs, circular_progressbar::*, run_command, transform::*};
+use super::{
+    build_widget::{build_loop_special_widget_with, populate_widget_children, BuilderArgs, CustomWidgetInvocation},
+    circular_progressbar::*,
+    marquee::*,
+    run_command,
+    transform::*,
+    CommandContext,
+};
 use crate::{
     def_widget, enum_parse, error_handling_ctx,
+    state::{
+        scope::Listener,
+        scope_graph::{ScopeGraph, ScopeGraphEvent, ScopeIndex},
+    },
     util::{self, list_difference},
-    widgets::{build_widget::build_gtk_widget, systray},
+    widgets::{build_widget::build_gtk_widget, image_cache, systray},
 };
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::Datelike;
 use codespan_reporting::diagnostic::Severity;
-use eww_shared_util::Spanned;
+use eww_shared_util::{Span, Spanned, VarName};
 
 use gdk::{ModifierType, NotifyType};
 use glib::translate::FromGlib;
@@ -15,16 +27,22 @@ use gtk::{self, glib, prelude::*, DestDefaults, TargetEntry, TargetList};
 use gtk::{gdk, pango};
 use itertools::Itertools;
 use once_cell::sync::Lazy;
+use simplexpr::{dynval::DynVal, SimplExpr};
 
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     cmp::Ordering,
     collections::{HashMap, HashSet},
+    fmt::Write as _,
     rc::Rc,
     time::Duration,
 };
 use yuck::{
-    config::file_provider::YuckFileProvider,
+    config::{
+        file_provider::YuckFileProvider,
+        widget_definition::WidgetDefinition,
+        widget_use::{BasicWidgetUse, WidgetUse},
+    },
     error::{DiagError, DiagResult},
     format_diagnostic::{span_to_secondary_label, DiagnosticExt},
     gen_diagnostic,
@@ -55,22 +73,22 @@ macro_rules! connect_signal_handler {
     }};
 }
 
-// TODO figure out how to
-// TODO https://developer.gnome.org/gtk3/stable/GtkFixed.html
-
 pub const BUILTIN_WIDGET_NAMES: &[&str] = &[
     WIDGET_NAME_BOX,
     WIDGET_NAME_CENTERBOX,
     WIDGET_NAME_EVENTBOX,
     WIDGET_NAME_TOOLTIP,
+    WIDGET_NAME_POPOVER,
     WIDGET_NAME_CIRCULAR_PROGRESS,
     WIDGET_NAME_GRAPH,
     WIDGET_NAME_TRANSFORM,
     WIDGET_NAME_SCALE,
     WIDGET_NAME_PROGRESS,
     WIDGET_NAME_IMAGE,
+    WIDGET_NAME_ICON,
     WIDGET_NAME_BUTTON,
     WIDGET_NAME_LABEL,
+    WIDGET_NAME_MARQUEE,
     WIDGET_NAME_LITERAL,
     WIDGET_NAME_INPUT,
     WIDGET_NAME_CALENDAR,
@@ -82,7 +100,11 @@ pub const BUILTIN_WIDGET_NAMES: &[&str] = &[
     WIDGET_NAME_REVEALER,
     WIDGET_NAME_SCROLL,
     WIDGET_NAME_OVERLAY,
+    WIDGET_NAME_FIXED,
+    WIDGET_NAME_GRID,
     WIDGET_NAME_STACK,
+    WIDGET_NAME_NOTEBOOK,
+    WIDGET_NAME_LISTBOX,
     WIDGET_NAME_SYSTRAY,
 ];
 
@@ -93,14 +115,17 @@ pub(super) fn widget_use_to_gtk_widget(bargs: &mut BuilderArgs) -> Result<gtk::W
         WIDGET_NAME_CENTERBOX => build_center_box(bargs)?.upcast(),
         WIDGET_NAME_EVENTBOX => build_gtk_event_box(bargs)?.upcast(),
         WIDGET_NAME_TOOLTIP => build_tooltip(bargs)?.upcast(),
+        WIDGET_NAME_POPOVER => build_popover(bargs)?.upcast(),
         WIDGET_NAME_CIRCULAR_PROGRESS => build_circular_progress_bar(bargs)?.upcast(),
         WIDGET_NAME_GRAPH => build_graph(bargs)?.upcast(),
         WIDGET_NAME_TRANSFORM => build_transform(bargs)?.upcast(),
         WIDGET_NAME_SCALE => build_gtk_scale(bargs)?.upcast(),
         WIDGET_NAME_PROGRESS => build_gtk_progress(bargs)?.upcast(),
         WIDGET_NAME_IMAGE => build_gtk_image(bargs)?.upcast(),
+        WIDGET_NAME_ICON => build_icon(bargs)?.upcast(),
         WIDGET_NAME_BUTTON => build_gtk_button(bargs)?.upcast(),
         WIDGET_NAME_LABEL => build_gtk_label(bargs)?.upcast(),
+        WIDGET_NAME_MARQUEE => build_marquee(bargs)?.upcast(),
         WIDGET_NAME_LITERAL => build_gtk_literal(bargs)?.upcast(),
         WIDGET_NAME_INPUT => build_gtk_input(bargs)?.upcast(),
         WIDGET_NAME_CALENDAR => build_gtk_calendar(bargs)?.upcast(),
@@ -112,7 +137,11 @@ pub(super) fn widget_use_to_gtk_widget(bargs: &mut BuilderArgs) -> Result<gtk::W
         WIDGET_NAME_REVEALER => build_gtk_revealer(bargs)?.upcast(),
         WIDGET_NAME_SCROLL => build_gtk_scrolledwindow(bargs)?.upcast(),
         WIDGET_NAME_OVERLAY => build_gtk_overlay(bargs)?.upcast(),
+        WIDGET_NAME_FIXED => build_gtk_fixed(bargs)?.upcast(),
+        WIDGET_NAME_GRID => build_gtk_grid(bargs)?.upcast(),
         WIDGET_NAME_STACK => build_gtk_stack(bargs)?.upcast(),
+        WIDGET_NAME_NOTEBOOK => build_gtk_notebook(bargs)?.upcast(),
+        WIDGET_NAME_LISTBOX => build_gtk_listbox(bargs)?.upcast(),
         WIDGET_NAME_SYSTRAY => build_systray(bargs)?.upcast(),
         _ => {
             return Err(DiagError(gen_diagnostic! {
@@ -201,6 +230,14 @@ pub(super) fn resolve_widget_attrs(bargs: &mut BuilderArgs, gtk_widget: &gtk::Wi
         },
         // @prop active - If this widget can be interacted with
         prop(active: as_bool = true) { gtk_widget.set_sensitive(active) },
+        // @prop focusable - whether this widget can receive keyboard focus, i.e. when cycling through widgets with tab or the arrow keys
+        prop(focusable: as_bool) { gtk_widget.set_can_focus(focusable) },
+        // @prop autofocus - give this widget keyboard focus when it is first shown. Default: false
+        prop(autofocus: as_bool = false) {
+            if autofocus {
+                connect_first_map(&gtk_widget, |w| w.grab_focus());
+            }
+        },
         // @prop tooltip - tooltip text (on hover)
         prop(tooltip: as_string) {
             gtk_widget.set_tooltip_text(Some(&tooltip));
@@ -246,6 +283,11 @@ pub(super) fn resolve_range_attrs(bargs: &mut BuilderArgs, gtk_widget: &gtk::Ran
     let last_set_value = Rc::new(RefCell::new(None));
     let last_set_value_clone = last_set_value.clone();
 
+    // Value the user dragged the range to, which has not yet been passed to onchange because of the "on-release" onchange-mode
+    let pending_value = Rc::new(RefCell::new(None::<f64>));
+    let is_being_dragged_clone = is_being_dragged.clone();
+
+    let cmd_context = CommandContext::new(bargs)?;
     def_widget!(bargs, _g, gtk_widget, {
         // @prop value - the value
         prop(value: as_f64) {
@@ -258,17 +300,40 @@ pub(super) fn resolve_range_attrs(bargs: &mut BuilderArgs, gtk_widget: &gtk::Ran
         prop(min: as_f64) { gtk_widget.adjustment().set_lower(min)},
         // @prop max - the maximum value
         prop(max: as_f64) { gtk_widget.adjustment().set_upper(max)},
-        // @prop timeout - timeout of the command. Default: "200ms"
+        // @prop secondary-value - a secondary value, shown as a fill level in the trough, i.e. to show how much of a track is buffered. Style it via `trough > fill`.
+        prop(secondary_value: as_f64) {
+            gtk_widget.set_restrict_to_fill_level(false);
+            gtk_widget.set_fill_level(secondary_value);
+            gtk_widget.set_show_fill_level(true);
+        },
+        // @prop timeout - timeout of the command, or "none" to let it run until it exits. Default: "200ms"
         // @prop onchange - command executed once the value is changes. The placeholder `{}`, used in the command will be replaced by the new value.
-        prop(timeout: as_duration = Duration::from_millis(200), onchange: as_string) {
+        // @prop onchange-mode - when to run the onchange command. possible values: $onchange_mode. Default: "continuous"
+        prop(timeout: as_optional_duration = Duration::from_millis(200), onchange: as_string, onchange_mode: as_string = "continuous") {
+            let on_release = parse_onchange_mode(&onchange_mode)?;
             gtk_widget.set_sensitive(true);
             gtk_widget.add_events(gdk::EventMask::PROPERTY_CHANGE_MASK);
             let last_set_value = last_set_value_clone.clone();
-            connect_signal_handler!(gtk_widget, gtk_widget.connect_value_changed(move |gtk_widget| {
+            let is_being_dragged = is_being_dragged_clone.clone();
+            let pending_value = pending_value.clone();
+            pending_value.replace(None);
+            let onchange = Rc::new(onchange);
+            let cmd_context = cmd_context.clone();
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_value_changed(glib::clone!(@strong onchange, @strong pending_value, @strong cmd_context => move |gtk_widget| {
                 let value = gtk_widget.value();
                 if last_set_value.borrow_mut().take() != Some(value) {
-                    run_command(timeout, &onchange, &[value]);
+                    if on_release && *is_being_dragged.borrow() {
+                        *pending_value.borrow_mut() = Some(value);
+                    } else {
+                        run_command(&cmd_context, timeout, &onchange, &[value]);
+                    }
+                }
+            })));
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_button_release_event(move |_, _| {
+                if let Some(value) = pending_value.borrow_mut().take() {
+                    run_command(&cmd_context, timeout, &onchange, &[value]);
                 }
+                glib::Propagation::Proceed
             }));
         }
     });
@@ -291,25 +356,80 @@ const WIDGET_NAME_COMBO_BOX_TEXT: &str = "combo-box-text";
 /// @desc A combo box allowing the user to choose between several items.
 fn build_gtk_combo_box_text(bargs: &mut BuilderArgs) -> Result<gtk::ComboBoxText> {
     let gtk_widget = gtk::ComboBoxText::new();
+    let cmd_context = CommandContext::new(bargs)?;
+
+    // Whether the selection is currently being changed by eww rather than the user, in which case onchange is not run
+    let is_updating = Rc::new(RefCell::new(false));
+    let selected_value: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+    let (is_updating_selected, is_updating_onchange) = (is_updating.clone(), is_updating.clone());
+    let selected_value_clone = selected_value.clone();
+
     def_widget!(bargs, _g, gtk_widget, {
-        // @prop items - Items that should be displayed in the combo box
-        prop(items: as_vec) {
+        // @prop items - Items that should be displayed in the combo box. Either a list of strings, or a JSON array of objects like `{"label": "Foo", "value": "foo"}`, where the value is what gets passed to `onchange` and compared against `selected`.
+        prop(items: as_string) {
+            let items = parse_combo_box_items(&DynVal::from(items))?;
+            *is_updating.borrow_mut() = true;
             gtk_widget.remove_all();
-            for i in items {
-                gtk_widget.append_text(&i);
+            for (label, value) in items {
+                gtk_widget.append(Some(&value), &label);
             }
+            if let Some(selected) = selected_value.borrow().as_deref() {
+                gtk_widget.set_active_id(Some(selected));
+            }
+            *is_updating.borrow_mut() = false;
+        },
+        // @prop selected - The value of the item that should be selected
+        prop(selected: as_string) {
+            *is_updating_selected.borrow_mut() = true;
+            if !gtk_widget.set_active_id(Some(&selected)) {
+                gtk_widget.set_active(None);
+            }
+            *is_updating_selected.borrow_mut() = false;
+            *selected_value_clone.borrow_mut() = Some(selected);
         },
-        // @prop timeout - timeout of the command: Default: "200ms"
-        // @prop onchange - runs the code when a item was selected, replacing {} with the item as a string
-        prop(timeout: as_duration = Duration::from_millis(200), onchange: as_string) {
+        // @prop timeout - timeout of the command, or "none" to let it run until it exits. Default: "200ms"
+        // @prop onchange - runs the code when a item was selected, replacing {} with the value of the item
+        prop(timeout: as_optional_duration = Duration::from_millis(200), onchange: as_string) {
+            let is_updating = is_updating_onchange.clone();
+            let cmd_context = cmd_context.clone();
             connect_signal_handler!(gtk_widget, gtk_widget.connect_changed(move |gtk_widget| {
-                run_command(timeout, &onchange, &[gtk_widget.active_text().unwrap_or_else(|| "".into())]);
+                if !*is_updating.borrow() {
+                    run_command(&cmd_context, timeout, &onchange, &[gtk_widget.active_id().unwrap_or_else(|| "".into())]);
+                }
             }));
         },
     });
     Ok(gtk_widget)
 }
 
+/// Parse the items of a combo box into `(label, value)` pairs.
+/// Accepts JSON arrays of strings, numbers or `{"label", "value"}` objects, as well as eww's plain list syntax (`[a,b]`).
+fn parse_combo_box_items(items: &DynVal) -> Result<Vec<(String, String)>> {
+    let Ok(json_items) = items.as_json_array() else {
+        return Ok(items.as_vec()?.into_iter().map(|item| (item.clone(), item)).collect());
+    };
+    json_items
+        .into_iter()
+        .map(|item| match item {
+            serde_json::Value::String(text) => Ok((text.clone(), text)),
+            serde_json::Value::Object(mut obj) => {
+                let value = match obj.remove("value") {
+                    Some(serde_json::Value::String(value)) => value,
+                    Some(value) => value.to_string(),
+                    None => bail!("combo-box item is missing a `value` field: {}", serde_json::Value::Object(obj)),
+                };
+                let label = match obj.remove("label") {
+                    Some(serde_json::Value::String(label)) => label,
+                    Some(label) => label.to_string(),
+                    None => value.clone(),
+                };
+                Ok((label, value))
+            }
+            other => Ok((other.to_string(), other.to_string())),
+        })
+        .collect()
+}
+
 const WIDGET_NAME_EXPANDER: &str = "expander";
 /// @widget expander
 /// @desc A widget that can expand and collapse, showing/hiding it's children. Should contain
@@ -364,31 +484,107 @@ const WIDGET_NAME_REVEALER: &str = "revealer";
 /// @desc A widget that can reveal a child with an animation.
 fn build_gtk_revealer(bargs: &mut BuilderArgs) -> Result<gtk::Revealer> {
     let gtk_widget = gtk::Revealer::new();
+    let calling_scope = bargs.calling_scope;
+    let event_sender = bargs.scope_graph.event_sender.clone();
+    let cmd_context = CommandContext::new(bargs)?;
+    // the value of `reveal`, which is restored once the pointer leaves the widget when using `reveal-on-hover`
+    let base_reveal = Rc::new(Cell::new(false));
+    let hover_reveal_enabled = Rc::new(Cell::new(false));
+    let hover_reveal_connected = Cell::new(false);
+    let hover_base_reveal = base_reveal.clone();
     def_widget!(bargs, _g, gtk_widget, {
         // @prop transition - the name of the transition. Possible values: $transition
         prop(transition: as_string = "crossfade") { gtk_widget.set_transition_type(parse_revealer_transition(&transition)?); },
         // @prop reveal - sets if the child is revealed or not
-        prop(reveal: as_bool) { gtk_widget.set_reveal_child(reveal); },
+        prop(reveal: as_bool) {
+            base_reveal.set(reveal);
+            gtk_widget.set_reveal_child(reveal);
+        },
+        // @prop reveal-on-hover - reveal the child while the pointer is over the closest eventbox surrounding this revealer
+        prop(reveal_on_hover: as_bool = false) {
+            hover_reveal_enabled.set(reveal_on_hover);
+            if reveal_on_hover && !hover_reveal_connected.replace(true) {
+                let (enabled, base_reveal) = (hover_reveal_enabled.clone(), hover_base_reveal.clone());
+                connect_first_map(&gtk_widget, move |revealer| connect_reveal_on_hover(revealer, enabled.clone(), base_reveal.clone()));
+            }
+        },
         // @prop duration - the duration of the reveal transition. Default: "500ms"
         prop(duration: as_duration = Duration::from_millis(500)) { gtk_widget.set_transition_duration(duration.as_millis() as u32); },
+        // @prop transition-running-var - name of a variable that is set to true while the reveal transition is running, and to false once it finished
+        prop(transition_running_var: as_string) {
+            let (event_sender, var_name) = (event_sender.clone(), VarName(transition_running_var));
+            let set_running = move |running: bool| set_transition_running_var(&event_sender, calling_scope, &var_name, running);
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_reveal_child_notify(glib::clone!(@strong set_running => move |gtk_widget| {
+                set_running(gtk_widget.reveals_child() != gtk_widget.is_child_revealed());
+            })));
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_child_revealed_notify(move |_| set_running(false)));
+        },
+        // @prop timeout - timeout of the command, or "none" to let it run until it exits. Default: "200ms"
+        // @prop onrevealed - command to run once the reveal transition finished and the child is fully visible
+        // @prop onhidden - command to run once the hide transition finished and the child is fully hidden
+        prop(timeout: as_optional_duration = Duration::from_millis(200), onrevealed: as_string = "", onhidden: as_string = "") {
+            let cmd_context = cmd_context.clone();
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_child_revealed_notify(move |gtk_widget| {
+                let cmd = if gtk_widget.is_child_revealed() { &onrevealed } else { &onhidden };
+                if !cmd.is_empty() {
+                    run_command(&cmd_context, timeout, cmd, &[] as &[&str]);
+                }
+            }));
+        },
     });
     Ok(gtk_widget)
 }
 
+/// Reveal the child of a revealer while the pointer is over the closest eventbox containing it, as long as `enabled` is set.
+/// Once the pointer leaves, the revealer goes back to `base_reveal`.
+fn connect_reveal_on_hover(revealer: &gtk::Revealer, enabled: Rc<Cell<bool>>, base_reveal: Rc<Cell<bool>>) {
+    let Some(eventbox) = revealer.ancestor(gtk::EventBox::static_type()) else {
+        log::warn!("A revealer uses :reveal-on-hover, but isn't placed inside of an eventbox");
+        return;
+    };
+    eventbox.add_events(gdk::EventMask::ENTER_NOTIFY_MASK | gdk::EventMask::LEAVE_NOTIFY_MASK);
+    let weak_revealer = revealer.downgrade();
+    eventbox.connect_enter_notify_event(glib::clone!(@strong enabled, @strong weak_revealer => move |_, evt| {
+        if let Some(revealer) = weak_revealer.upgrade().filter(|_| enabled.get() && evt.detail() != NotifyType::Inferior) {
+            revealer.set_reveal_child(true);
+        }
+        glib::Propagation::Proceed
+    }));
+    eventbox.connect_leave_notify_event(move |_, evt| {
+        if let Some(revealer) = weak_revealer.upgrade().filter(|_| enabled.get() && evt.detail() != NotifyType::Inferior) {
+            revealer.set_reveal_child(base_reveal.get());
+        }
+        glib::Propagation::Proceed
+    });
+}
+
+/// Expose whether a widgets transition is currently running through the given variable.
+fn set_transition_running_var(
+    event_sender: &tokio::sync::mpsc::UnboundedSender<ScopeGraphEvent>,
+    calling_scope: ScopeIndex,
+    var_name: &VarName,
+    running: bool,
+) {
+    let evt = ScopeGraphEvent::UpdateValue(calling_scope, var_name.clone(), DynVal::from(running));
+    crate::print_result_err!("while updating transition state", event_sender.send(evt));
+}
+
 const WIDGET_NAME_CHECKBOX: &str = "checkbox";
-/// @widget a checkbox
+/// @widget checkbox
 /// @desc A checkbox that can trigger events on checked / unchecked.
 fn build_gtk_checkbox(bargs: &mut BuilderArgs) -> Result<gtk::CheckButton> {
     let gtk_widget = gtk::CheckButton::new();
+    let cmd_context = CommandContext::new(bargs)?;
     def_widget!(bargs, _g, gtk_widget, {
         // @prop checked - whether the checkbox is toggled or not when created
-        // @prop timeout - timeout of the command. Default: "200ms"
+        // @prop timeout - timeout of the command, or "none" to let it run until it exits. Default: "200ms"
         // @prop onchecked - action (command) to be executed when checked by the user
         // @prop onunchecked - similar to onchecked but when the widget is unchecked
-        prop(checked: as_bool = false, timeout: as_duration = Duration::from_millis(200), onchecked: as_string = "", onunchecked: as_string = "") {
+        prop(checked: as_bool = false, timeout: as_optional_duration = Duration::from_millis(200), onchecked: as_string = "", onunchecked: as_string = "") {
             gtk_widget.set_active(checked);
+            let cmd_context = cmd_context.clone();
             connect_signal_handler!(gtk_widget, gtk_widget.connect_toggled(move |gtk_widget| {
-                run_command(timeout, if gtk_widget.is_active() { &onchecked } else { &onunchecked }, &[] as &[&str]);
+                run_command(&cmd_context, timeout, if gtk_widget.is_active() { &onchecked } else { &onunchecked }, &[] as &[&str]);
             }));
        }
     });
@@ -401,15 +597,17 @@ const WIDGET_NAME_COLOR_BUTTON: &str = "color-button";
 /// @desc A button opening a color chooser window
 fn build_gtk_color_button(bargs: &mut BuilderArgs) -> Result<gtk::ColorButton> {
     let gtk_widget = gtk::ColorButton::builder().build();
+    let cmd_context = CommandContext::new(bargs)?;
     def_widget!(bargs, _g, gtk_widget, {
         // @prop use-alpha - bool to whether or not use alpha
         prop(use_alpha: as_bool) {gtk_widget.set_use_alpha(use_alpha);},
 
         // @prop onchange - runs the code when the color was selected
-        // @prop timeout - timeout of the command. Default: "200ms"
-        prop(timeout: as_duration = Duration::from_millis(200), onchange: as_string) {
+        // @prop timeout - timeout of the command, or "none" to let it run until it exits. Default: "200ms"
+        prop(timeout: as_optional_duration = Duration::from_millis(200), onchange: as_string) {
+            let cmd_context = cmd_context.clone();
             connect_signal_handler!(gtk_widget, gtk_widget.connect_color_set(move |gtk_widget| {
-                run_command(timeout, &onchange, &[gtk_widget.rgba()]);
+                run_command(&cmd_context, timeout, &onchange, &[gtk_widget.rgba()]);
             }));
         }
     });
@@ -422,15 +620,17 @@ const WIDGET_NAME_COLOR_CHOOSER: &str = "color-chooser";
 /// @desc A color chooser widget
 fn build_gtk_color_chooser(bargs: &mut BuilderArgs) -> Result<gtk::ColorChooserWidget> {
     let gtk_widget = gtk::ColorChooserWidget::new();
+    let cmd_context = CommandContext::new(bargs)?;
     def_widget!(bargs, _g, gtk_widget, {
         // @prop use-alpha - bool to wether or not use alpha
         prop(use_alpha: as_bool) {gtk_widget.set_use_alpha(use_alpha);},
 
         // @prop onchange - runs the code when the color was selected
-        // @prop timeout - timeout of the command. Default: "200ms"
-        prop(timeout: as_duration = Duration::from_millis(200), onchange: as_string) {
+        // @prop timeout - timeout of the command, or "none" to let it run until it exits. Default: "200ms"
+        prop(timeout: as_optional_duration = Duration::from_millis(200), onchange: as_string) {
+            let cmd_context = cmd_context.clone();
             connect_signal_handler!(gtk_widget, gtk_widget.connect_color_activated(move |_a, color| {
-                run_command(timeout, &onchange, &[*color]);
+                run_command(&cmd_context, timeout, &onchange, &[*color]);
             }));
         }
     });
@@ -460,7 +660,25 @@ fn build_gtk_scale(bargs: &mut BuilderArgs) -> Result<gtk::Scale> {
         prop(draw_value: as_bool = false) { gtk_widget.set_draw_value(draw_value) },
 
         // @prop round-digits - Sets the number of decimals to round the value to when it changes
-        prop(round_digits: as_i32 = 0) { gtk_widget.set_round_digits(round_digits) }
+        prop(round_digits: as_i32 = 0) { gtk_widget.set_round_digits(round_digits) },
+
+        // @prop step - the value changes in steps of this size, both when dragging and when using the arrow keys or scrolling
+        prop(step: as_f64) {
+            if step <= 0.0 {
+                bail!("step must be greater than 0, but got {}", step);
+            }
+            gtk_widget.adjustment().set_step_increment(step);
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_change_value(move |gtk_widget, _, value| {
+                let adjustment = gtk_widget.adjustment();
+                let lower = adjustment.lower();
+                let snapped = lower + ((value - lower) / step).round() * step;
+                gtk_widget.set_value(snapped.clamp(lower, adjustment.upper()));
+                glib::Propagation::Stop
+            }));
+        },
+
+        // @prop page - the amount the value changes by when using page up / page down or clicking the trough
+        prop(page: as_f64) { gtk_widget.adjustment().set_page_increment(page) },
 
     });
     Ok(gtk_widget)
@@ -490,23 +708,27 @@ const WIDGET_NAME_INPUT: &str = "input";
 /// @desc An input field. For this to be useful, set `focusable="true"` on the window.
 fn build_gtk_input(bargs: &mut BuilderArgs) -> Result<gtk::Entry> {
     let gtk_widget = gtk::Entry::new();
+    let cmd_context = CommandContext::new(bargs)?;
+    let cmd_context_clone = cmd_context.clone();
     def_widget!(bargs, _g, gtk_widget, {
         // @prop value - the content of the text field
         prop(value: as_string) {
             gtk_widget.set_text(&value);
         },
         // @prop onchange - Command to run when the text changes. The placeholder `{}` will be replaced by the value
-        // @prop timeout - timeout of the command. Default: "200ms"
-        prop(timeout: as_duration = Duration::from_millis(200), onchange: as_string) {
+        // @prop timeout - timeout of the command, or "none" to let it run until it exits. Default: "200ms"
+        prop(timeout: as_optional_duration = Duration::from_millis(200), onchange: as_string) {
+            let cmd_context = cmd_context.clone();
             connect_signal_handler!(gtk_widget, gtk_widget.connect_changed(move |gtk_widget| {
-                run_command(timeout, &onchange, &[gtk_widget.text().to_string()]);
+                run_command(&cmd_context, timeout, &onchange, &[gtk_widget.text().to_string()]);
             }));
         },
         // @prop onaccept - Command to run when the user hits return in the input field. The placeholder `{}` will be replaced by the value
-        // @prop timeout - timeout of the command. Default: "200ms"
-        prop(timeout: as_duration = Duration::from_millis(200), onaccept: as_string) {
+        // @prop timeout - timeout of the command, or "none" to let it run until it exits. Default: "200ms"
+        prop(timeout: as_optional_duration = Duration::from_millis(200), onaccept: as_string) {
+            let cmd_context = cmd_context_clone.clone();
             connect_signal_handler!(gtk_widget, gtk_widget.connect_activate(move |gtk_widget| {
-                run_command(timeout, &onaccept, &[gtk_widget.text().to_string()]);
+                run_command(&cmd_context, timeout, &onaccept, &[gtk_widget.text().to_string()]);
             }));
         },
         // @prop password - if the input is obscured
@@ -523,10 +745,11 @@ const WIDGET_NAME_BUTTON: &str = "button";
 fn build_gtk_button(bargs: &mut BuilderArgs) -> Result<gtk::Button> {
     let gtk_widget = gtk::Button::new();
 
+    let cmd_context = CommandContext::new(bargs)?;
     def_widget!(bargs, _g, gtk_widget, {
         prop(
-            // @prop timeout - timeout of the command. Default: "200ms"
-            timeout: as_duration = Duration::from_millis(200),
+            // @prop timeout - timeout of the command, or "none" to let it run until it exits. Default: "200ms"
+            timeout: as_optional_duration = Duration::from_millis(200),
             // @prop onclick - command to run when the button is activated either by leftclicking or keyboard
             onclick: as_string = "",
             // @prop onmiddleclick - command to run when the button is middleclicked
@@ -541,23 +764,24 @@ fn build_gtk_button(bargs: &mut BuilderArgs) -> Result<gtk::Button> {
                 glib::Propagation::Proceed
             }));
             let onclick_ = onclick.clone();
+            let cmd_context = cmd_context.clone();
             // mouse click events
-            connect_signal_handler!(gtk_widget, gtk_widget.connect_button_release_event(move |_, evt| {
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_button_release_event(glib::clone!(@strong cmd_context => move |_, evt| {
                 match evt.button() {
-                    1 => run_command(timeout, &onclick, &[] as &[&str]),
-                    2 => run_command(timeout, &onmiddleclick, &[] as &[&str]),
-                    3 => run_command(timeout, &onrightclick, &[] as &[&str]),
+                    1 => run_command(&cmd_context, timeout, &onclick, &[] as &[&str]),
+                    2 => run_command(&cmd_context, timeout, &onmiddleclick, &[] as &[&str]),
+                    3 => run_command(&cmd_context, timeout, &onrightclick, &[] as &[&str]),
                     _ => {},
                 }
                 glib::Propagation::Proceed
-            }));
+            })));
             // keyboard events
             connect_signal_handler!(gtk_widget, gtk_widget.connect_key_release_event(move |_, evt| {
                 match evt.scancode() {
                     // return
-                    36 => run_command(timeout, &onclick_, &[] as &[&str]),
+                    36 => run_command(&cmd_context, timeout, &onclick_, &[] as &[&str]),
                     // space
-                    65 => run_command(timeout, &onclick_, &[] as &[&str]),
+                    65 => run_command(&cmd_context, timeout, &onclick_, &[] as &[&str]),
                     _ => {},
                 }
                 glib::Propagation::Proceed
@@ -584,40 +808,50 @@ const WIDGET_NAME_IMAGE: &str = "image";
 /// @desc A widget displaying an image
 fn build_gtk_image(bargs: &mut BuilderArgs) -> Result<gtk::Image> {
     let gtk_widget = gtk::Image::new();
+    // The most recently requested path, used to avoid showing images from outdated downloads
+    let requested_path = Rc::new(RefCell::new(String::new()));
     def_widget!(bargs, _g, gtk_widget, {
-        // @prop path - path to the image file
+        // @prop path - path to the image file. Can also be a `http(s)://` url, which is downloaded and cached, and checked for changes every minute while it is shown, or a base64 encoded `data:` uri
         // @prop image-width - width of the image
         // @prop image-height - height of the image
         // @prop preserve-aspect-ratio - whether to keep the aspect ratio when resizing an image. Default: true, false doesn't work for all image types
         // @prop fill-svg - sets the color of svg images
         prop(path: as_string, image_width: as_i32 = -1, image_height: as_i32 = -1, preserve_aspect_ratio: as_bool = true, fill_svg: as_string = "") {
-            if !path.ends_with(".svg") && !fill_svg.is_empty() {
-                log::warn!("Fill attribute ignored, file is not an svg image");
-            }
-
-            if path.ends_with(".gif") {
-                let pixbuf_animation = gtk::gdk_pixbuf::PixbufAnimation::from_file(std::path::PathBuf::from(path))?;
-                gtk_widget.set_from_animation(&pixbuf_animation);
-            } else {
-                let pixbuf;
-                // populate the pixel buffer
-                if path.ends_with(".svg") && !fill_svg.is_empty() {
-                    let svg_data = std::fs::read_to_string(std::path::PathBuf::from(path.clone()))?;
-                    // The fastest way to add/change fill color
-                    let svg_data = if svg_data.contains("fill=") {
-                        let reg = regex::Regex::new(r#"fill="[^"]*""#)?;
-                        reg.replace(&svg_data, &format!("fill=\"{}\"", fill_svg))
-                    } else {
-                        let reg = regex::Regex::new(r"<svg")?;
-                        reg.replace(&svg_data, &format!("<svg fill=\"{}\"", fill_svg))
-                    };
-                    let stream = gtk::gio::MemoryInputStream::from_bytes(&gtk::glib::Bytes::from(svg_data.as_bytes()));
-                    pixbuf = gtk::gdk_pixbuf::Pixbuf::from_stream_at_scale(&stream, image_width, image_height, preserve_aspect_ratio, None::<&gtk::gio::Cancellable>)?;
-                    stream.close(None::<&gtk::gio::Cancellable>)?;
-                } else {
-                    pixbuf = gtk::gdk_pixbuf::Pixbuf::from_file_at_scale(std::path::PathBuf::from(path), image_width, image_height, preserve_aspect_ratio)?;
+            let options = ImageOptions { image_width, image_height, preserve_aspect_ratio, fill_svg };
+            *requested_path.borrow_mut() = path.clone();
+            if let Some(data_uri) = path.strip_prefix("data:") {
+                let cache_file = image_cache::cache_data_uri(data_uri)?;
+                set_image_from_path(&gtk_widget, &cache_file.to_string_lossy(), &options)?;
+            } else if path.starts_with("http://") || path.starts_with("https://") {
+                let cache_file = image_cache::cache_file(&path, image_cache::url_file_extension(&path));
+                let is_cached = cache_file.is_file();
+                // show the cached image right away, even if it might be outdated
+                if is_cached {
+                    set_image_from_path(&gtk_widget, &cache_file.to_string_lossy(), &options)?;
                 }
-                gtk_widget.set_from_pixbuf(Some(&pixbuf));
+                if !is_cached || image_cache::needs_revalidation(&cache_file) {
+                    let download = tokio::spawn(image_cache::fetch_url(path.clone(), cache_file.clone()));
+                    let gtk_widget = gtk_widget.downgrade();
+                    let requested_path = requested_path.clone();
+                    glib::MainContext::default().spawn_local(async move {
+                        let result: Result<()> = async {
+                            let changed = download.await??;
+                            // Only show the image if the path hasn't changed while it was being downloaded
+                            if let Some(gtk_widget) = gtk_widget.upgrade().filter(|_| changed) {
+                                if *requested_path.borrow() == path {
+                                    set_image_from_path(&gtk_widget, &cache_file.to_string_lossy(), &options)?;
+                                }
+                            }
+                            Ok(())
+                        }
+                        .await;
+                        if let Err(err) = result {
+                            error_handling_ctx::print_error(err);
+                        }
+                    });
+                }
+            } else {
+                set_image_from_path(&gtk_widget, &path, &options)?;
             }
         },
         // @prop icon - name of a theme icon
@@ -629,9 +863,82 @@ fn build_gtk_image(bargs: &mut BuilderArgs) -> Result<gtk::Image> {
     Ok(gtk_widget)
 }
 
+struct ImageOptions {
+    image_width: i32,
+    image_height: i32,
+    preserve_aspect_ratio: bool,
+    fill_svg: String,
+}
+
+/// Load the image file at the given path into the image widget.
+fn set_image_from_path(gtk_widget: &gtk::Image, path: &str, options: &ImageOptions) -> Result<()> {
+    let ImageOptions { image_width, image_height, preserve_aspect_ratio, fill_svg } = options;
+    if !path.ends_with(".svg") && !fill_svg.is_empty() {
+        log::warn!("Fill attribute ignored, file is not an svg image");
+    }
+
+    if path.ends_with(".gif") {
+        let pixbuf_animation = gtk::gdk_pixbuf::PixbufAnimation::from_file(std::path::PathBuf::from(path))?;
+        gtk_widget.set_from_animation(&pixbuf_animation);
+    } else {
+        let pixbuf;
+        // populate the pixel buffer
+        if path.ends_with(".svg") && !fill_svg.is_empty() {
+            let svg_data = std::fs::read_to_string(std::path::PathBuf::from(path))?;
+            // The fastest way to add/change fill color
+            let svg_data = if svg_data.contains("fill=") {
+                let reg = regex::Regex::new(r#"fill="[^"]*""#)?;
+                reg.replace(&svg_data, &format!("fill=\"{}\"", fill_svg))
+            } else {
+                let reg = regex::Regex::new(r"<svg")?;
+                reg.replace(&svg_data, &format!("<svg fill=\"{}\"", fill_svg))
+            };
+            let stream = gtk::gio::MemoryInputStream::from_bytes(&gtk::glib::Bytes::from(svg_data.as_bytes()));
+            pixbuf = gtk::gdk_pixbuf::Pixbuf::from_stream_at_scale(
+                &stream,
+                *image_width,
+                *image_height,
+                *preserve_aspect_ratio,
+                None::<&gtk::gio::Cancellable>,
+            )?;
+            stream.close(None::<&gtk::gio::Cancellable>)?;
+        } else {
+            pixbuf = gtk::gdk_pixbuf::Pixbuf::from_file_at_scale(
+                std::path::PathBuf::from(path),
+                *image_width,
+                *image_height,
+                *preserve_aspect_ratio,
+            )?;
+        }
+        gtk_widget.set_from_pixbuf(Some(&pixbuf));
+    }
+    Ok(())
+}
+
+const WIDGET_NAME_ICON: &str = "icon";
+/// @widget icon
+/// @desc A widget displaying an icon from the current icon theme
+fn build_icon(bargs: &mut BuilderArgs) -> Result<gtk::Image> {
+    let gtk_widget = gtk::Image::new();
+    def_widget!(bargs, _g, gtk_widget, {
+        // @prop icon - name of the icon, or an absolute path to an image file
+        // @prop size - size of the icon in pixels
+        // @prop fallbacks - list of icon names to try if the icon can't be found, i.e. `"[audio-volume-high, audio-speakers]"`
+        // @prop symbolic - whether to prefer the symbolic variant of the icon
+        prop(icon: as_string, size: as_i32 = 24, fallbacks: as_vec = "", symbolic: as_bool = false) {
+            let icon_names = std::iter::once(icon).chain(fallbacks).collect_vec();
+            let scale = gtk_widget.scale_factor();
+            if let Some(pixbuf) = notifier_host::load_icon_from_names(&icon_names, size, scale, symbolic) {
+                systray::set_image_from_pixbuf(&gtk_widget, &pixbuf, scale);
+            }
+        },
+    });
+    Ok(gtk_widget)
+}
+
 const WIDGET_NAME_BOX: &str = "box";
 /// @widget box
-/// @desc the main layout container
+/// @desc the main layout container. Children can be given an `:order` on the child itself, which sorts them in ascending order without rebuilding them whenever the order changes. Children without an `:order` have an order of 0. Children created by `for` keep their position.
 fn build_gtk_box(bargs: &mut BuilderArgs) -> Result<gtk::Box> {
     let gtk_widget = gtk::Box::new(gtk::Orientation::Horizontal, 0);
     def_widget!(bargs, _g, gtk_widget, {
@@ -642,9 +949,89 @@ fn build_gtk_box(bargs: &mut BuilderArgs) -> Result<gtk::Box> {
         // @prop space-evenly - space the widgets evenly.
         prop(space_evenly: as_bool = true) { gtk_widget.set_homogeneous(space_evenly) },
     });
+
+    let has_ordered_children = bargs.widget_use.children.iter().any(|child| {
+        matches!(child, WidgetUse::Basic(child) if child.attrs.attrs.contains_key(&eww_shared_util::AttrName("order".to_string())))
+    });
+    if has_ordered_children {
+        populate_ordered_box_children(bargs, &gtk_widget)?;
+    }
     Ok(gtk_widget)
 }
 
+/// A child of a box together with its current `:order`
+type OrderedChild = (glib::WeakRef<gtk::Widget>, i32);
+
+/// Add the children of a box, keeping the children that were given directly sorted by their `:order` attribute.
+fn populate_ordered_box_children(bargs: &mut BuilderArgs, gtk_widget: &gtk::Box) -> Result<()> {
+    let ordered_children: Rc<RefCell<Vec<OrderedChild>>> = Rc::new(RefCell::new(Vec::new()));
+    for child in bargs.widget_use.children.iter() {
+        let WidgetUse::Basic(child) = child else {
+            populate_widget_children(
+                bargs.scope_graph,
+                bargs.widget_defs.clone(),
+                bargs.calling_scope,
+                gtk_widget.upcast_ref(),
+                vec![child.clone()],
+                bargs.custom_widget_invocation.clone(),
+            )?;
+            continue;
+        };
+        let mut child = child.clone();
+        let order = child.attrs.ast_optional::<SimplExpr>("order")?.unwrap_or_else(|| SimplExpr::synth_literal(0));
+        let child_widget = build_gtk_widget(
+            bargs.scope_graph,
+            bargs.widget_defs.clone(),
+            bargs.calling_scope,
+            WidgetUse::Basic(child),
+            bargs.custom_widget_invocation.clone(),
+        )?;
+        gtk_widget.add(&child_widget);
+        child_widget.show();
+
+        let index = {
+            let mut ordered_children = ordered_children.borrow_mut();
+            ordered_children.push((child_widget.downgrade(), 0));
+            ordered_children.len() - 1
+        };
+        bargs.scope_graph.register_listener(
+            bargs.calling_scope,
+            Listener {
+                needed_variables: order.collect_var_refs(),
+                f: Box::new({
+                    let gtk_widget = gtk_widget.downgrade();
+                    let ordered_children = ordered_children.clone();
+                    move |_, values| {
+                        let Some(gtk_widget) = gtk_widget.upgrade() else { return Ok(()) };
+                        ordered_children.borrow_mut()[index].1 = order.eval(&values)?.as_i32()?;
+                        sort_box_children(&gtk_widget, &ordered_children.borrow());
+                        Ok(())
+                    }
+                }),
+            },
+        )?;
+    }
+    Ok(())
+}
+
+/// Sort the given children of a box by their order, moving them between the positions they currently occupy.
+/// Children with the same order keep their relative position, and other children of the box aren't moved.
+fn sort_box_children(gtk_widget: &gtk::Box, ordered_children: &[OrderedChild]) {
+    let mut sorted: Vec<_> = ordered_children.iter().filter_map(|(child, order)| Some((child.upgrade()?, *order))).collect();
+    sorted.sort_by_key(|(_, order)| *order);
+    let slots: Vec<_> = gtk_widget
+        .children()
+        .iter()
+        .enumerate()
+        .filter(|(_, child)| sorted.iter().any(|(sorted_child, _)| sorted_child == *child))
+        .map(|(slot, _)| slot as i32)
+        .collect();
+    // filling the slots front to back never moves a child that has already been placed
+    for ((child, _), slot) in sorted.iter().zip(slots) {
+        gtk_widget.reorder_child(child, slot);
+    }
+}
+
 const WIDGET_NAME_OVERLAY: &str = "overlay";
 /// @widget overlay
 /// @desc a widget that places its children on top of each other. The overlay widget takes the size of its first child.
@@ -682,6 +1069,260 @@ fn build_gtk_overlay(bargs: &mut BuilderArgs) -> Result<gtk::Overlay> {
     }
 }
 
+const WIDGET_NAME_FIXED: &str = "fixed";
+/// @widget fixed
+/// @desc a container that places each of its children at absolute coordinates. The position of a child is given via `:x` and `:y` (in pixels) on the child itself, defaulting to 0.
+fn build_gtk_fixed(bargs: &mut BuilderArgs) -> Result<gtk::Fixed> {
+    let gtk_widget = gtk::Fixed::new();
+
+    // no def_widget because this widget has no props, the positions are set on the children.
+
+    for child in bargs.widget_use.children.iter() {
+        let mut child = child.clone();
+        let (x, y) = match &mut child {
+            WidgetUse::Basic(child) => (child.attrs.ast_optional::<SimplExpr>("x")?, child.attrs.ast_optional::<SimplExpr>("y")?),
+            _ => (None, None),
+        };
+        let child_widget = build_gtk_widget(
+            bargs.scope_graph,
+            bargs.widget_defs.clone(),
+            bargs.calling_scope,
+            child,
+            bargs.custom_widget_invocation.clone(),
+        )?;
+        gtk_widget.put(&child_widget, 0, 0);
+        child_widget.show();
+
+        let x = x.unwrap_or_else(|| SimplExpr::synth_literal(0));
+        let y = y.unwrap_or_else(|| SimplExpr::synth_literal(0));
+        bargs.scope_graph.register_listener(
+            bargs.calling_scope,
+            Listener {
+                needed_variables: x.collect_var_refs().into_iter().chain(y.collect_var_refs()).collect(),
+                f: Box::new({
+                    let gtk_widget = gtk_widget.downgrade();
+                    let child_widget = child_widget.downgrade();
+                    move |_, values| {
+                        let (Some(gtk_widget), Some(child_widget)) = (gtk_widget.upgrade(), child_widget.upgrade()) else {
+                            return Ok(());
+                        };
+                        gtk_widget.move_(&child_widget, x.eval(&values)?.as_i32()?, y.eval(&values)?.as_i32()?);
+                        Ok(())
+                    }
+                }),
+            },
+        )?;
+    }
+    Ok(gtk_widget)
+}
+
+pub const WIDGET_NAME_GRID: &str = "grid";
+/// Name of the pseudo-widget that puts its children into a single row of a `grid`.
+pub const WIDGET_NAME_GRID_ROW: &str = "row";
+/// @widget grid
+/// @desc A container that lays out its children in rows and columns. A child can be placed in a specific cell via `:row` and `:col` on the child itself, and span multiple cells via `:colspan` and `:rowspan`. All other children, including the ones created by `for`, fill up the grid from left to right, starting a new row after `columns` cells. Wrapping children in a `(row ...)` puts them into a row of their own, which can be used as the body of a `for` to build one row per element of a JSON array.
+fn build_gtk_grid(bargs: &mut BuilderArgs) -> Result<gtk::Grid> {
+    let gtk_widget = gtk::Grid::new();
+
+    let flow = Rc::new(RefCell::new(GridFlow::default()));
+    gtk_widget.connect_add(glib::clone!(@strong flow => move |gtk_widget, child| {
+        flow.borrow_mut().children.push(child.clone());
+        flow.borrow().layout(gtk_widget);
+    }));
+    gtk_widget.connect_remove(glib::clone!(@strong flow => move |gtk_widget, child| {
+        let mut flow_mut = flow.borrow_mut();
+        flow_mut.children.retain(|x| x != child);
+        flow_mut.row_ids.remove(child);
+        drop(flow_mut);
+        flow.borrow().layout(gtk_widget);
+    }));
+
+    let grid = GridBuilder {
+        grid: gtk_widget.clone(),
+        flow: flow.clone(),
+        widget_defs: bargs.widget_defs.clone(),
+        custom_widget_invocation: bargs.custom_widget_invocation.clone(),
+    };
+    for child in bargs.widget_use.children.iter() {
+        match child {
+            WidgetUse::Basic(row) if row.name == WIDGET_NAME_GRID_ROW => {
+                grid.build_row(bargs.scope_graph, bargs.calling_scope, row)?;
+            }
+            WidgetUse::Loop(loop_use) => match loop_use.body.as_ref() {
+                WidgetUse::Basic(row) if row.name == WIDGET_NAME_GRID_ROW => {
+                    let (grid, row) = (grid.clone(), row.clone());
+                    build_loop_special_widget_with(
+                        bargs.scope_graph,
+                        bargs.calling_scope,
+                        loop_use,
+                        gtk_widget.upcast_ref(),
+                        move |tree, scope| grid.build_row(tree, scope, &row),
+                    )?;
+                }
+                _ => populate_widget_children(
+                    bargs.scope_graph,
+                    bargs.widget_defs.clone(),
+                    bargs.calling_scope,
+                    gtk_widget.upcast_ref(),
+                    vec![child.clone()],
+                    bargs.custom_widget_invocation.clone(),
+                )?,
+            },
+            WidgetUse::Children(_) => populate_widget_children(
+                bargs.scope_graph,
+                bargs.widget_defs.clone(),
+                bargs.calling_scope,
+                gtk_widget.upcast_ref(),
+                vec![child.clone()],
+                bargs.custom_widget_invocation.clone(),
+            )?,
+            _ => {
+                grid.build_child(bargs.scope_graph, bargs.calling_scope, child.clone(), None)?;
+            }
+        }
+    }
+
+    def_widget!(bargs, _g, gtk_widget, {
+        // @prop columns - number of columns children without an explicit position are laid out in. By default, they're all put into a single row.
+        prop(columns: as_i32 = 0) {
+            flow.borrow_mut().columns = columns;
+            flow.borrow().layout(&gtk_widget);
+        },
+        // @prop row-spacing - spacing between rows
+        prop(row_spacing: as_i32 = 0) { gtk_widget.set_row_spacing(row_spacing as u32) },
+        // @prop column-spacing - spacing between columns
+        prop(column_spacing: as_i32 = 0) { gtk_widget.set_column_spacing(column_spacing as u32) },
+        // @prop row-homogeneous - whether all rows should have the same height
+        prop(row_homogeneous: as_bool = false) { gtk_widget.set_row_homogeneous(row_homogeneous) },
+        // @prop column-homogeneous - whether all columns should have the same width
+        prop(column_homogeneous: as_bool = false) { gtk_widget.set_column_homogeneous(column_homogeneous) },
+    });
+    Ok(gtk_widget)
+}
+
+/// Placement of the children of a grid that have no explicit position.
+#[derive(Default)]
+struct GridFlow {
+    /// Children without an explicit position, in the order they were added
+    children: Vec<gtk::Widget>,
+    /// The cells of `row`s, mapped to an id of the row they belong to. Each `row` is put into a row of its own.
+    row_ids: HashMap<gtk::Widget, u64>,
+    next_row_id: u64,
+    /// Number of columns to wrap the children into, or 0 to put them all into a single row
+    columns: i32,
+}
+
+impl GridFlow {
+    /// Place the children one after another, wrapping into a new row after `columns` cells and around every `row`.
+    fn layout(&self, grid: &gtk::Grid) {
+        let (mut left, mut top) = (0, 0);
+        let mut previous_row_id = None;
+        for child in &self.children {
+            let width = grid.cell_width(child);
+            let row_id = self.row_ids.get(child);
+            let wraps = self.columns > 0 && left + width > self.columns;
+            if left > 0 && (wraps || row_id != previous_row_id) {
+                left = 0;
+                top += 1;
+            }
+            grid.set_cell_left_attach(child, left);
+            grid.set_cell_top_attach(child, top);
+            left += width;
+            previous_row_id = row_id;
+        }
+    }
+}
+
+/// Everything needed to build the children of a grid, including the ones built later on by a `for`.
+#[derive(Clone)]
+struct GridBuilder {
+    grid: gtk::Grid,
+    flow: Rc<RefCell<GridFlow>>,
+    widget_defs: Rc<HashMap<String, WidgetDefinition>>,
+    custom_widget_invocation: Option<Rc<CustomWidgetInvocation>>,
+}
+
+impl GridBuilder {
+    /// Build the children of a `row` into a new row of the grid, returning the created widgets.
+    fn build_row(&self, tree: &mut ScopeGraph, scope: ScopeIndex, row: &BasicWidgetUse) -> Result<Vec<gtk::Widget>> {
+        let row_id = {
+            let mut flow = self.flow.borrow_mut();
+            flow.next_row_id += 1;
+            flow.next_row_id
+        };
+        row.children.iter().map(|cell| self.build_child(tree, scope, cell.clone(), Some(row_id))).collect()
+    }
+
+    /// Build a child of the grid and add it at the position given by its `:row` and `:col`, or after the previous children.
+    /// Children without an explicit position that have a `row_id` are cells of the `row` with that id.
+    fn build_child(
+        &self,
+        tree: &mut ScopeGraph,
+        scope: ScopeIndex,
+        child: WidgetUse,
+        row_id: Option<u64>,
+    ) -> Result<gtk::Widget> {
+        let literal_one = || SimplExpr::synth_literal(1);
+        let (child, row, col, colspan, rowspan) = match child {
+            WidgetUse::Basic(mut child) => {
+                let row = child.attrs.ast_optional::<SimplExpr>("row")?;
+                let col = child.attrs.ast_optional::<SimplExpr>("col")?;
+                let colspan = child.attrs.ast_optional::<SimplExpr>("colspan")?.unwrap_or_else(literal_one);
+                let rowspan = child.attrs.ast_optional::<SimplExpr>("rowspan")?.unwrap_or_else(literal_one);
+                (WidgetUse::Basic(child), row, col, colspan, rowspan)
+            }
+            other => (other, None, None, literal_one(), literal_one()),
+        };
+        let child_widget = build_gtk_widget(tree, self.widget_defs.clone(), scope, child, self.custom_widget_invocation.clone())?;
+        let position = if row.is_none() && col.is_none() {
+            if let Some(row_id) = row_id {
+                self.flow.borrow_mut().row_ids.insert(child_widget.clone(), row_id);
+            }
+            self.grid.add(&child_widget);
+            None
+        } else {
+            self.grid.attach(&child_widget, 0, 0, 1, 1);
+            let row = row.unwrap_or_else(|| SimplExpr::synth_literal(0));
+            let col = col.unwrap_or_else(|| SimplExpr::synth_literal(0));
+            Some((row, col))
+        };
+        child_widget.show();
+
+        let needed_variables = [Some(&colspan), Some(&rowspan), position.as_ref().map(|x| &x.0), position.as_ref().map(|x| &x.1)]
+            .into_iter()
+            .flatten()
+            .flat_map(|x| x.collect_var_refs())
+            .collect();
+        tree.register_listener(
+            scope,
+            Listener {
+                needed_variables,
+                f: Box::new({
+                    let gtk_widget = self.grid.downgrade();
+                    let child_widget = child_widget.downgrade();
+                    let flow = self.flow.clone();
+                    move |_, values| {
+                        let (Some(gtk_widget), Some(child_widget)) = (gtk_widget.upgrade(), child_widget.upgrade()) else {
+                            return Ok(());
+                        };
+                        gtk_widget.set_cell_width(&child_widget, colspan.eval(&values)?.as_i32()?.max(1));
+                        gtk_widget.set_cell_height(&child_widget, rowspan.eval(&values)?.as_i32()?.max(1));
+                        match &position {
+                            Some((row, col)) => {
+                                gtk_widget.set_cell_top_attach(&child_widget, row.eval(&values)?.as_i32()?);
+                                gtk_widget.set_cell_left_attach(&child_widget, col.eval(&values)?.as_i32()?);
+                            }
+                            None => flow.borrow().layout(&gtk_widget),
+                        }
+                        Ok(())
+                    }
+                }),
+            },
+        )?;
+        Ok(child_widget)
+    }
+}
+
 const WIDGET_NAME_TOOLTIP: &str = "tooltip";
 /// @widget tooltip
 /// @desc A widget that have a custom tooltip. The first child is the content of the tooltip, the second one is the content of the widget.
@@ -726,27 +1367,28 @@ fn build_tooltip(bargs: &mut BuilderArgs) -> Result<gtk::Box> {
     }
 }
 
-const WIDGET_NAME_CENTERBOX: &str = "centerbox";
-/// @widget centerbox
-/// @desc a box that must contain exactly three children, which will be layed out at the start, center and end of the container.
-fn build_center_box(bargs: &mut BuilderArgs) -> Result<gtk::Box> {
-    let gtk_widget = gtk::Box::new(gtk::Orientation::Horizontal, 0);
-    def_widget!(bargs, _g, gtk_widget, {
-        // @prop orientation - orientation of the centerbox. possible values: $orientation
-        prop(orientation: as_string) { gtk_widget.set_orientation(parse_orientation(&orientation)?) },
-    });
+const WIDGET_NAME_POPOVER: &str = "popover";
+/// @widget popover
+/// @desc A button that opens a popover anchored to it when clicked, i.e. to build dropdown menus. The first child is the content of the popover, the second one is the content of the button. Note that the popover can't extend beyond the window it is in, so it gets cut off in small windows like bars. To show a dropdown from a bar, open a separate window instead.
+fn build_popover(bargs: &mut BuilderArgs) -> Result<gtk::MenuButton> {
+    let gtk_widget = gtk::MenuButton::new();
+    let popover = gtk::Popover::new(Some(&gtk_widget));
+    gtk_widget.set_popover(Some(&popover));
 
-    match bargs.widget_use.children.len().cmp(&3) {
+    match bargs.widget_use.children.len().cmp(&2) {
         Ordering::Less => {
-            Err(DiagError(gen_diagnostic!("centerbox must contain exactly 3 elements", bargs.widget_use.span)).into())
+            return Err(DiagError(gen_diagnostic!("popover must contain exactly 2 elements", bargs.widget_use.span)).into());
         }
         Ordering::Greater => {
-            let (_, additional_children) = bargs.widget_use.children.split_at(3);
-            // we know that there is more than three children, so unwrapping on first and left here is fine.
+            let (_, additional_children) = bargs.widget_use.children.split_at(2);
+            // we know that there is more than two children, so unwrapping on first and last here is fine.
             let first_span = additional_children.first().unwrap().span();
             let last_span = additional_children.last().unwrap().span();
-            Err(DiagError(gen_diagnostic!("centerbox must contain exactly 3 elements, but got more", first_span.to(last_span)))
-                .into())
+            return Err(DiagError(gen_diagnostic!(
+                "popover must contain exactly 2 elements, but got more",
+                first_span.to(last_span)
+            ))
+            .into());
         }
         Ordering::Equal => {
             let mut children = bargs.widget_use.children.iter().map(|child| {
@@ -758,17 +1400,129 @@ fn build_center_box(bargs: &mut BuilderArgs) -> Result<gtk::Box> {
                     bargs.custom_widget_invocation.clone(),
                 )
             });
-            // we know that we have exactly three children here, so we can unwrap here.
-            let (first, center, end) = children.next_tuple().unwrap();
-            let (first, center, end) = (first?, center?, end?);
-            gtk_widget.pack_start(&first, true, true, 0);
-            gtk_widget.set_center_widget(Some(&center));
-            gtk_widget.pack_end(&end, true, true, 0);
-            first.show();
-            center.show();
-            end.show();
-            Ok(gtk_widget)
+            // we know that we have exactly two children here, so we can unwrap here.
+            let (popover_content, content) = children.next_tuple().unwrap();
+            let (popover_content, content) = (popover_content?, content?);
+            popover.add(&popover_content);
+            popover_content.show();
+            gtk_widget.add(&content);
+        }
+    }
+
+    let cmd_context = CommandContext::new(bargs)?;
+    let (popover_position, popover_modal) = (popover.clone(), popover.clone());
+    def_widget!(bargs, _g, gtk_widget, {
+        // @prop open - whether the popover is currently shown
+        prop(open: as_bool) { gtk_widget.set_active(open) },
+        // @prop position - the side of the button the popover is shown on. possible values: $side
+        prop(position: as_string = "bottom") { popover_position.set_position(parse_side(&position)?) },
+        // @prop modal - whether the popover grabs the keyboard and pointer and closes when clicking outside of it. Default: true
+        prop(modal: as_bool = true) { popover_modal.set_modal(modal) },
+        // @prop timeout - timeout of the command, or "none" to let it run until it exits. Default: "200ms"
+        // @prop onopen - command to run when the popover is opened
+        // @prop onclose - command to run when the popover is closed
+        prop(timeout: as_optional_duration = Duration::from_millis(200), onopen: as_string = "", onclose: as_string = "") {
+            let cmd_context = cmd_context.clone();
+            connect_signal_handler!(popover, popover.connect_show(glib::clone!(@strong cmd_context => move |_| {
+                run_command(&cmd_context, timeout, &onopen, &[] as &[&str]);
+            })));
+            connect_signal_handler!(popover, popover.connect_closed(move |_| {
+                run_command(&cmd_context, timeout, &onclose, &[] as &[&str]);
+            }));
+        },
+    });
+
+    Ok(gtk_widget)
+}
+
+const WIDGET_NAME_CENTERBOX: &str = "centerbox";
+/// @widget centerbox
+/// @desc a box with up to three children, which will be layed out at the start, center and end of the container. By default, it must contain exactly three children. Use `sides` to leave out some of them.
+fn build_center_box(bargs: &mut BuilderArgs) -> Result<gtk::Box> {
+    let gtk_widget = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+    let child_spans: Vec<_> = bargs.widget_use.children.iter().map(|child| child.span()).collect();
+    let children: Vec<gtk::Widget> = bargs
+        .widget_use
+        .children
+        .iter()
+        .map(|child| {
+            build_gtk_widget(
+                bargs.scope_graph,
+                bargs.widget_defs.clone(),
+                bargs.calling_scope,
+                child.clone(),
+                bargs.custom_widget_invocation.clone(),
+            )
+        })
+        .collect::<Result<_>>()?;
+    let span = bargs.widget_use.span;
+
+    // Report a wrong amount of children right away whenever the sides are known upfront,
+    // rather than only once the sides prop is first applied.
+    let literal_sides = match bargs.widget_use.attrs.attrs.get("sides").map(|x| x.value.as_simplexpr()).transpose()? {
+        None => Some("start-center-end".to_string()),
+        Some(SimplExpr::Literal(sides)) => Some(sides.as_string()?),
+        Some(_) => None,
+    };
+    if let Some(sides) = literal_sides {
+        validate_centerbox_children(parse_centerbox_sides(&sides)?, &child_spans, span)?;
+    }
+
+    def_widget!(bargs, _g, gtk_widget, {
+        // @prop orientation - orientation of the centerbox. possible values: $orientation
+        prop(orientation: as_string) { gtk_widget.set_orientation(parse_orientation(&orientation)?) },
+        // @prop sides - which sides the children are placed at, in order. The other sides stay empty and don't take up any space. possible values: $centerbox_sides. Default: "start-center-end"
+        prop(sides: as_string = "start-center-end") {
+            let (has_start, has_center, has_end) = parse_centerbox_sides(&sides)?;
+            validate_centerbox_children((has_start, has_center, has_end), &child_spans, span)?;
+
+            for child in gtk_widget.children() {
+                gtk_widget.remove(&child);
+            }
+            // we know that we have exactly one child per side here, so we can unwrap here.
+            let mut children = children.iter();
+            if has_start {
+                let first = children.next().unwrap();
+                gtk_widget.pack_start(first, true, true, 0);
+                first.show();
+            }
+            if has_center {
+                let center = children.next().unwrap();
+                gtk_widget.set_center_widget(Some(center));
+                center.show();
+            }
+            if has_end {
+                let end = children.next().unwrap();
+                gtk_widget.pack_end(end, true, true, 0);
+                end.show();
+            }
+        },
+    });
+    Ok(gtk_widget)
+}
+
+/// Ensure that a centerbox has exactly one child per side it places children at.
+fn validate_centerbox_children(
+    (has_start, has_center, has_end): (bool, bool, bool),
+    child_spans: &[Span],
+    span: Span,
+) -> Result<()> {
+    let expected = [has_start, has_center, has_end].into_iter().filter(|side| *side).count();
+    match child_spans.len().cmp(&expected) {
+        Ordering::Less => {
+            Err(DiagError(gen_diagnostic!(format!("centerbox must contain exactly {} elements", expected), span)).into())
+        }
+        Ordering::Greater => {
+            // we know that there are more children than expected, so unwrapping on first and last here is fine.
+            let additional_children = &child_spans[expected..];
+            let (first_span, last_span) = (additional_children.first().unwrap(), additional_children.last().unwrap());
+            Err(DiagError(gen_diagnostic!(
+                format!("centerbox must contain exactly {} elements, but got more", expected),
+                first_span.to(*last_span)
+            ))
+            .into())
         }
+        Ordering::Equal => Ok(()),
     }
 }
 
@@ -779,6 +1533,23 @@ fn build_gtk_scrolledwindow(bargs: &mut BuilderArgs) -> Result<gtk::ScrolledWind
     // I don't have single idea of what those two generics are supposed to be, but this works.
     let gtk_widget = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
 
+    // The size of the content may change after the scroll position was set (i.e. once it is first allocated),
+    // so re-apply the requested position whenever that happens.
+    let scroll_x_position = Rc::new(RefCell::new(None));
+    let scroll_y_position = Rc::new(RefCell::new(None));
+    gtk_widget.hadjustment().connect_changed(glib::clone!(@strong scroll_x_position => move |adjustment| {
+        if let Some(position) = *scroll_x_position.borrow() {
+            set_clamped_adjustment_value(adjustment, position);
+        }
+    }));
+    gtk_widget.vadjustment().connect_changed(glib::clone!(@strong scroll_y_position => move |adjustment| {
+        if let Some(position) = *scroll_y_position.borrow() {
+            set_clamped_adjustment_value(adjustment, position);
+        }
+    }));
+
+    let cmd_context = CommandContext::new(bargs)?;
+    let cmd_context_clone = cmd_context.clone();
     def_widget!(bargs, _g, gtk_widget, {
         // @prop hscroll - scroll horizontally
         // @prop vscroll - scroll vertically
@@ -788,107 +1559,169 @@ fn build_gtk_scrolledwindow(bargs: &mut BuilderArgs) -> Result<gtk::ScrolledWind
                 if vscroll { gtk::PolicyType::Automatic } else { gtk::PolicyType::Never },
             )
         },
+        // @prop scroll-x - horizontal scroll position in pixels. Values past the end scroll to the end.
+        prop(scroll_x: as_f64) {
+            *scroll_x_position.borrow_mut() = Some(scroll_x);
+            set_clamped_adjustment_value(&gtk_widget.hadjustment(), scroll_x);
+        },
+        // @prop scroll-y - vertical scroll position in pixels. Values past the end scroll to the end.
+        prop(scroll_y: as_f64) {
+            *scroll_y_position.borrow_mut() = Some(scroll_y);
+            set_clamped_adjustment_value(&gtk_widget.vadjustment(), scroll_y);
+        },
+        // @prop timeout - timeout of the command, or "none" to let it run until it exits. Default: "200ms"
+        // @prop onreachstart - command to run when the content is scrolled to the top or left edge. `{}` will be replaced by "top" or "left".
+        prop(timeout: as_optional_duration = Duration::from_millis(200), onreachstart: as_string) {
+            let cmd_context = cmd_context.clone();
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_edge_reached(move |_, position| {
+                match position {
+                    gtk::PositionType::Top => run_command(&cmd_context, timeout, &onreachstart, &["top"]),
+                    gtk::PositionType::Left => run_command(&cmd_context, timeout, &onreachstart, &["left"]),
+                    _ => {}
+                }
+            }));
+        },
+        // @prop onreachend - command to run when the content is scrolled to the bottom or right edge. `{}` will be replaced by "bottom" or "right".
+        prop(timeout: as_optional_duration = Duration::from_millis(200), onreachend: as_string) {
+            let cmd_context = cmd_context_clone.clone();
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_edge_reached(move |_, position| {
+                match position {
+                    gtk::PositionType::Bottom => run_command(&cmd_context, timeout, &onreachend, &["bottom"]),
+                    gtk::PositionType::Right => run_command(&cmd_context, timeout, &onreachend, &["right"]),
+                    _ => {}
+                }
+            }));
+        },
     });
 
     Ok(gtk_widget)
 }
 
+/// Set the value of an adjustment, clamping it to the range the adjustment can actually scroll to.
+fn set_clamped_adjustment_value(adjustment: &gtk::Adjustment, value: f64) {
+    let max = (adjustment.upper() - adjustment.page_size()).max(adjustment.lower());
+    adjustment.set_value(value.clamp(adjustment.lower(), max));
+}
+
 const WIDGET_NAME_EVENTBOX: &str = "eventbox";
 /// @widget eventbox
 /// @desc a container which can receive events and must contain exactly one child. Supports `:hover` and `:active` css selectors.
 fn build_gtk_event_box(bargs: &mut BuilderArgs) -> Result<gtk::EventBox> {
     let gtk_widget = gtk::EventBox::new();
+    // Input is handled by event controllers rather than the event signals of the widget, as only the former exist in gtk4
+    let controllers = EventBoxControllers::attach(&gtk_widget);
 
     // Support :hover selector
-    gtk_widget.connect_enter_notify_event(|gtk_widget, evt| {
-        if evt.detail() != NotifyType::Inferior {
-            gtk_widget.set_state_flags(gtk::StateFlags::PRELIGHT, false);
+    controllers.motion.connect_enter(|controller, _, _| {
+        if let Some(widget) = controller.widget().filter(|_| !is_inferior_crossing()) {
+            widget.set_state_flags(gtk::StateFlags::PRELIGHT, false);
         }
-        glib::Propagation::Proceed
     });
-
-    gtk_widget.connect_leave_notify_event(|gtk_widget, evt| {
-        if evt.detail() != NotifyType::Inferior {
-            gtk_widget.unset_state_flags(gtk::StateFlags::PRELIGHT);
+    controllers.motion.connect_leave(|controller| {
+        if let Some(widget) = controller.widget().filter(|_| !is_inferior_crossing()) {
+            widget.unset_state_flags(gtk::StateFlags::PRELIGHT);
         }
-        glib::Propagation::Proceed
     });
 
     // Support :active selector
-    gtk_widget.connect_button_press_event(|gtk_widget, _| {
-        gtk_widget.set_state_flags(gtk::StateFlags::ACTIVE, false);
-        glib::Propagation::Proceed
+    controllers.click.connect_pressed(|gesture, _, _, _| {
+        if let Some(widget) = gesture.widget() {
+            widget.set_state_flags(gtk::StateFlags::ACTIVE, false);
+        }
     });
-
-    gtk_widget.connect_button_release_event(|gtk_widget, _| {
-        gtk_widget.unset_state_flags(gtk::StateFlags::ACTIVE);
-        glib::Propagation::Proceed
+    controllers.click.connect_released(|gesture, _, _, _| {
+        if let Some(widget) = gesture.widget() {
+            widget.unset_state_flags(gtk::StateFlags::ACTIVE);
+        }
+    });
+    controllers.click.connect_cancel(|gesture, _| {
+        if let Some(widget) = gesture.widget() {
+            widget.unset_state_flags(gtk::StateFlags::ACTIVE);
+        }
     });
 
+    let cmd_context = CommandContext::new(bargs)?;
+    let (hover_context, hoverlost_context, dropped_context, click_context) =
+        (cmd_context.clone(), cmd_context.clone(), cmd_context.clone(), cmd_context.clone());
     def_widget!(bargs, _g, gtk_widget, {
-        // @prop timeout - timeout of the command. Default: "200ms"
+        // @prop timeout - timeout of the command, or "none" to let it run until it exits. Default: "200ms"
         // @prop onscroll - event to execute when the user scrolls with the mouse over the widget. The placeholder `{}` used in the command will be replaced with either `up` or `down`.
-        prop(timeout: as_duration = Duration::from_millis(200), onscroll: as_string) {
+        prop(timeout: as_optional_duration = Duration::from_millis(200), onscroll: as_string) {
             gtk_widget.add_events(gdk::EventMask::SCROLL_MASK);
             gtk_widget.add_events(gdk::EventMask::SMOOTH_SCROLL_MASK);
-            connect_signal_handler!(gtk_widget, gtk_widget.connect_scroll_event(move |_, evt| {
-                let delta = evt.delta().1;
+            let scroll = EventBoxControllers::of(&gtk_widget).scroll;
+            let cmd_context = cmd_context.clone();
+            connect_signal_handler!(scroll, scroll.connect_scroll(move |_, _, delta| {
                 if delta != 0f64 { // Ignore the first event https://bugzilla.gnome.org/show_bug.cgi?id=675959
-                    run_command(timeout, &onscroll, &[if delta < 0f64 { "up" } else { "down" }]);
+                    run_command(&cmd_context, timeout, &onscroll, &[if delta < 0f64 { "up" } else { "down" }]);
                 }
-                glib::Propagation::Proceed
             }));
         },
-        // @prop timeout - timeout of the command. Default: "200ms"
+        // @prop timeout - timeout of the command, or "none" to let it run until it exits. Default: "200ms"
         // @prop onhover - event to execute when the user hovers over the widget
-        prop(timeout: as_duration = Duration::from_millis(200), onhover: as_string) {
-            gtk_widget.add_events(gdk::EventMask::ENTER_NOTIFY_MASK);
-            connect_signal_handler!(gtk_widget, gtk_widget.connect_enter_notify_event(move |_, evt| {
-                if evt.detail() != NotifyType::Inferior {
-                    run_command(timeout, &onhover, &[evt.position().0, evt.position().1]);
+        prop(timeout: as_optional_duration = Duration::from_millis(200), onhover: as_string) {
+            let motion = EventBoxControllers::of(&gtk_widget).motion;
+            let cmd_context = hover_context.clone();
+            connect_signal_handler!(motion, motion.connect_enter(move |_, x, y| {
+                if !is_inferior_crossing() {
+                    run_command(&cmd_context, timeout, &onhover, &[x, y]);
                 }
-                glib::Propagation::Proceed
             }));
         },
-        // @prop timeout - timeout of the command. Default: "200ms"
+        // @prop timeout - timeout of the command, or "none" to let it run until it exits. Default: "200ms"
         // @prop onhoverlost - event to execute when the user losts hovers over the widget
-        prop(timeout: as_duration = Duration::from_millis(200), onhoverlost: as_string) {
-            gtk_widget.add_events(gdk::EventMask::LEAVE_NOTIFY_MASK);
-            connect_signal_handler!(gtk_widget, gtk_widget.connect_leave_notify_event(move |_, evt| {
-                if evt.detail() != NotifyType::Inferior {
-                    run_command(timeout, &onhoverlost, &[evt.position().0, evt.position().1]);
+        prop(timeout: as_optional_duration = Duration::from_millis(200), onhoverlost: as_string) {
+            let motion = EventBoxControllers::of(&gtk_widget).motion;
+            let cmd_context = hoverlost_context.clone();
+            connect_signal_handler!(motion, motion.connect_leave(move |_| {
+                if !is_inferior_crossing() {
+                    // unlike entering, leaving doesn't report a position, so it is taken from the event that caused it
+                    let (x, y) = gtk::current_event().and_then(|evt| evt.coords()).unwrap_or_default();
+                    run_command(&cmd_context, timeout, &onhoverlost, &[x, y]);
                 }
-                glib::Propagation::Proceed
             }));
         },
         // @prop cursor - Cursor to show while hovering (see [gtk3-cursors](https://docs.gtk.org/gdk3/ctor.Cursor.new_from_name.html) for possible names)
         prop(cursor: as_string) {
-            gtk_widget.add_events(gdk::EventMask::ENTER_NOTIFY_MASK);
-            gtk_widget.add_events(gdk::EventMask::LEAVE_NOTIFY_MASK);
-
-            connect_signal_handler!(gtk_widget, gtk_widget.connect_enter_notify_event(move |widget, _evt| {
-                if _evt.detail() != NotifyType::Inferior {
+            let motion = EventBoxControllers::of(&gtk_widget).motion;
+            connect_signal_handler!(motion, motion.connect_enter(move |controller, _, _| {
+                if !is_inferior_crossing() {
                     let display = gdk::Display::default();
-                    let gdk_window = widget.window();
+                    let gdk_window = controller.widget().and_then(|widget| widget.window());
                     if let (Some(display), Some(gdk_window)) = (display, gdk_window) {
                         gdk_window.set_cursor(gdk::Cursor::from_name(&display, &cursor).as_ref());
                     }
                 }
-                glib::Propagation::Proceed
             }));
-            connect_signal_handler!(gtk_widget, gtk_widget.connect_leave_notify_event(move |widget, _evt| {
-                if _evt.detail() != NotifyType::Inferior {
-                    let gdk_window = widget.window();
-                    if let Some(gdk_window) = gdk_window {
+            connect_signal_handler!(motion, motion.connect_leave(move |controller| {
+                if !is_inferior_crossing() {
+                    if let Some(gdk_window) = controller.widget().and_then(|widget| widget.window()) {
                         gdk_window.set_cursor(None);
                     }
                 }
-                glib::Propagation::Proceed
             }));
         },
-        // @prop timeout - timeout of the command. Default: "200ms"
+        // @prop drag-window - move the window by dragging this widget with the left mouse button, i.e. for movable desktop widgets. On wayland, this changes the margins of the window, relative to the edges it is anchored to.
+        prop(drag_window: as_bool = false) {
+            let drag = EventBoxControllers::of(&gtk_widget).drag;
+            let drag_start = Rc::new(Cell::new(None));
+            connect_signal_handler!(drag, drag.connect_drag_begin(glib::clone!(@strong drag_start => move |gesture, _, _| {
+                if let (true, Some(widget), Some(evt)) = (drag_window, gesture.widget(), gtk::current_event()) {
+                    drag_start.set(begin_window_drag(&widget, &evt));
+                }
+            })));
+            connect_signal_handler!(drag, drag.connect_drag_update(glib::clone!(@strong drag_start => move |gesture, _, _| {
+                if let (Some(start), Some(widget), Some(evt)) = (drag_start.get(), gesture.widget(), gtk::current_event()) {
+                    update_window_drag(&widget, &evt, start);
+                }
+            })));
+            connect_signal_handler!(drag, drag.connect_drag_end(move |_, _, _| {
+                drag_start.set(None);
+            }));
+        },
+        // @prop timeout - timeout of the command, or "none" to let it run until it exits. Default: "200ms"
         // @prop ondropped - Command to execute when something is dropped on top of this element. The placeholder `{}` used in the command will be replaced with the uri to the dropped thing.
-        prop(timeout: as_duration = Duration::from_millis(200), ondropped: as_string) {
+        prop(timeout: as_optional_duration = Duration::from_millis(200), ondropped: as_string) {
             gtk_widget.drag_dest_set(
                 DestDefaults::ALL,
                 &[
@@ -897,11 +1730,12 @@ fn build_gtk_event_box(bargs: &mut BuilderArgs) -> Result<gtk::EventBox> {
                 ],
                 gdk::DragAction::COPY,
             );
+            let cmd_context = dropped_context.clone();
             connect_signal_handler!(gtk_widget, gtk_widget.connect_drag_data_received(move |_, _, _x, _y, selection_data, _target_type, _timestamp| {
                 if let Some(data) = selection_data.uris().first(){
-                    run_command(timeout, &ondropped, &[data.to_string(), "file".to_string()]);
+                    run_command(&cmd_context, timeout, &ondropped, &[data.to_string(), "file".to_string()]);
                 } else if let Some(data) = selection_data.text(){
-                    run_command(timeout, &ondropped, &[data.to_string(), "text".to_string()]);
+                    run_command(&cmd_context, timeout, &ondropped, &[data.to_string(), "text".to_string()]);
                 }
             }));
         },
@@ -933,8 +1767,8 @@ fn build_gtk_event_box(bargs: &mut BuilderArgs) -> Result<gtk::EventBox> {
             }));
         },
         prop(
-            // @prop timeout - timeout of the command. Default: "200ms"
-            timeout: as_duration = Duration::from_millis(200),
+            // @prop timeout - timeout of the command, or "none" to let it run until it exits. Default: "200ms"
+            timeout: as_optional_duration = Duration::from_millis(200),
             // @prop onclick - command to run when the widget is clicked
             onclick: as_string = "",
             // @prop onmiddleclick - command to run when the widget is middleclicked
@@ -942,26 +1776,75 @@ fn build_gtk_event_box(bargs: &mut BuilderArgs) -> Result<gtk::EventBox> {
             // @prop onrightclick - command to run when the widget is rightclicked
             onrightclick: as_string = ""
         ) {
-            gtk_widget.add_events(gdk::EventMask::BUTTON_PRESS_MASK);
-            connect_signal_handler!(gtk_widget, gtk_widget.connect_button_release_event(move |_, evt| {
-                match evt.button() {
-                    1 => run_command(timeout, &onclick, &[] as &[&str]),
-                    2 => run_command(timeout, &onmiddleclick, &[] as &[&str]),
-                    3 => run_command(timeout, &onrightclick, &[] as &[&str]),
+            let click = EventBoxControllers::of(&gtk_widget).click;
+            let cmd_context = click_context.clone();
+            connect_signal_handler!(click, click.connect_released(move |gesture, _, _, _| {
+                match gesture.current_button() {
+                    1 => run_command(&cmd_context, timeout, &onclick, &[] as &[&str]),
+                    2 => run_command(&cmd_context, timeout, &onmiddleclick, &[] as &[&str]),
+                    3 => run_command(&cmd_context, timeout, &onrightclick, &[] as &[&str]),
                     _ => {},
                 }
-                glib::Propagation::Proceed
             }));
         }
     });
     Ok(gtk_widget)
 }
 
+/// Event controllers handling the input of an eventbox.
+#[derive(Clone)]
+struct EventBoxControllers {
+    motion: gtk::EventControllerMotion,
+    scroll: gtk::EventControllerScroll,
+    click: gtk::GestureMultiPress,
+    drag: gtk::GestureDrag,
+}
+
+impl EventBoxControllers {
+    const KEY: &'static str = "eww-event-controllers";
+
+    /// Create the controllers of an eventbox.
+    /// Gtk3 widgets don't keep their controllers alive, so they are stored on the widget.
+    fn attach(widget: &gtk::EventBox) -> Self {
+        widget.add_events(
+            gdk::EventMask::ENTER_NOTIFY_MASK
+                | gdk::EventMask::LEAVE_NOTIFY_MASK
+                | gdk::EventMask::BUTTON_PRESS_MASK
+                | gdk::EventMask::BUTTON_RELEASE_MASK
+                | gdk::EventMask::BUTTON1_MOTION_MASK,
+        );
+        let click = gtk::GestureMultiPress::new(widget);
+        // handle all buttons, rather than only the primary one
+        click.set_button(0);
+        let controllers = Self {
+            motion: gtk::EventControllerMotion::new(widget),
+            scroll: gtk::EventControllerScroll::new(widget, gtk::EventControllerScrollFlags::VERTICAL),
+            click,
+            drag: gtk::GestureDrag::new(widget),
+        };
+        unsafe { widget.set_data(Self::KEY, controllers.clone()) };
+        controllers
+    }
+
+    fn of(widget: &gtk::EventBox) -> Self {
+        unsafe { widget.data::<Self>(Self::KEY).expect("eventbox without event controllers").as_ref().clone() }
+    }
+}
+
+/// Whether the event currently being handled is the pointer crossing into or out of a child of the widget,
+/// which still leaves the pointer within the widget.
+fn is_inferior_crossing() -> bool {
+    gtk::current_event()
+        .and_then(|evt| evt.downcast::<gdk::EventCrossing>().ok())
+        .is_some_and(|evt| evt.detail() == NotifyType::Inferior)
+}
+
 const WIDGET_NAME_LABEL: &str = "label";
 /// @widget label
 /// @desc A text widget giving you more control over how the text is displayed
 fn build_gtk_label(bargs: &mut BuilderArgs) -> Result<gtk::Label> {
     let gtk_widget = gtk::Label::new(None);
+    let timestamp_generation = Rc::new(Cell::new(0u64));
 
     def_widget!(bargs, _g, gtk_widget, {
         // @prop text - the text to display
@@ -1034,6 +1917,33 @@ fn build_gtk_label(bargs: &mut BuilderArgs) -> Result<gtk::Label> {
 
             gtk_widget.set_markup(&markup);
         },
+        // @prop timestamp - a unix timestamp (in seconds) to display relative to the current time, like "5 minutes ago", instead of `text`. The label keeps this up to date by itself.
+        // @prop timestamp-format - how to display timestamps that are further away than `relative-limit`, as a strftime format string. Default: "%c"
+        // @prop relative-limit - how far away a timestamp may be to still be displayed relative to the current time. Default: "none"
+        prop(timestamp: as_i64, timestamp_format: as_string = "%c", relative_limit: as_optional_duration = "none") {
+            timestamp_generation.set(timestamp_generation.get() + 1);
+            update_timestamp_label(&gtk_widget, timestamp, timestamp_format, relative_limit, timestamp_generation.clone());
+        },
+        // @prop font-family - the font family to use, like "JetBrains Mono"
+        // @prop font-size - the font size, in points
+        // @prop font-weight - the font weight. Possible values: $font_weight, or a number between 100 and 1000
+        // @prop letter-spacing - additional space between letters, in pixels
+        prop(font_family: as_string = "", font_size: as_f64 = 0, font_weight: as_string = "", letter_spacing: as_f64 = 0) {
+            let attrs = pango::AttrList::new();
+            if !font_family.is_empty() {
+                attrs.insert(pango::AttrString::new_family(&font_family));
+            }
+            if font_size > 0.0 {
+                attrs.insert(pango::AttrSize::new((font_size * pango::SCALE as f64) as i32));
+            }
+            if !font_weight.is_empty() {
+                attrs.insert(pango::AttrInt::new_weight(parse_font_weight(&font_weight)?));
+            }
+            if letter_spacing != 0.0 {
+                attrs.insert(pango::AttrInt::new_letter_spacing((letter_spacing * pango::SCALE as f64) as i32));
+            }
+            gtk_widget.set_attributes(Some(&attrs));
+        },
         // @prop wrap - Wrap the text. This mainly makes sense if you set the width of this widget.
         prop(wrap: as_bool) { gtk_widget.set_line_wrap(wrap) },
         // @prop angle - the angle of rotation for the label (between 0 - 360)
@@ -1054,6 +1964,69 @@ fn build_gtk_label(bargs: &mut BuilderArgs) -> Result<gtk::Label> {
     Ok(gtk_widget)
 }
 
+/// Show a timestamp in a label, scheduling the next update for when the text changes.
+/// Updates stop once `generation` changes, which happens when the label is given a new timestamp.
+fn update_timestamp_label(
+    label: &gtk::Label,
+    timestamp: i64,
+    format: String,
+    relative_limit: Option<Duration>,
+    generation: Rc<Cell<u64>>,
+) {
+    let now = chrono::Local::now().timestamp();
+    let secs_until_change = match relative_limit {
+        Some(limit) if (now - timestamp).unsigned_abs() > limit.as_secs() => {
+            let Some(time) = chrono::DateTime::from_timestamp(timestamp, 0) else {
+                log::warn!("Invalid timestamp {} given to label", timestamp);
+                return;
+            };
+            let mut text = String::new();
+            if write!(text, "{}", time.with_timezone(&chrono::Local).format(&format)).is_err() {
+                log::warn!("Invalid timestamp-format given to label: {}", format);
+            }
+            label.set_text(&text);
+            // a timestamp in the future switches to relative text once it is within the limit, one in the past never does
+            if timestamp <= now {
+                return;
+            }
+            (timestamp - now) as u64 - limit.as_secs()
+        }
+        _ => {
+            let (text, secs_until_change) = util::describe_relative_time(timestamp, now);
+            label.set_text(&text);
+            secs_until_change
+        }
+    };
+
+    let current_generation = generation.get();
+    let label = label.downgrade();
+    glib::timeout_add_local_once(Duration::from_secs(secs_until_change), move || {
+        if let Some(label) = label.upgrade().filter(|_| generation.get() == current_generation) {
+            update_timestamp_label(&label, timestamp, format, relative_limit, generation);
+        }
+    });
+}
+
+const WIDGET_NAME_MARQUEE: &str = "marquee";
+/// @widget marquee
+/// @desc A label that scrolls its text horizontally if it doesn't fit. Use `:width` to set the width of the visible area.
+fn build_marquee(bargs: &mut BuilderArgs) -> Result<Marquee> {
+    let w = Marquee::new();
+    def_widget!(bargs, _g, w, {
+        // @prop text - the text to display
+        prop(text: as_string) { w.set_property("text", text); },
+        // @prop speed - scrolling speed in pixels per second. Default: 30
+        prop(speed: as_f64) { w.set_property("speed", speed); },
+        // @prop delay - how long to wait before (re)starting to scroll. Default: "1s"
+        prop(delay: as_duration) { w.set_property("delay", delay.as_millis() as u64); },
+        // @prop gap - space in pixels between the end of the text and its repetition when using the "wrap" loop mode. Default: 40
+        prop(gap: as_f64) { w.set_property("gap", gap); },
+        // @prop loop-mode - how the text should loop. Possible values: $marquee_loop_mode. Default: "wrap"
+        prop(loop_mode: as_string) { w.set_property("loop-mode", parse_marquee_loop_mode(&loop_mode)?); },
+    });
+    Ok(w)
+}
+
 const WIDGET_NAME_LITERAL: &str = "literal";
 /// @widget literal
 /// @desc A widget that allows you to render arbitrary yuck.
@@ -1110,6 +2083,15 @@ const WIDGET_NAME_CALENDAR: &str = "calendar";
 /// @desc A widget that displays a calendar
 fn build_gtk_calendar(bargs: &mut BuilderArgs) -> Result<gtk::Calendar> {
     let gtk_widget = gtk::Calendar::new();
+
+    // gtk only stores marks per day of the displayed month, so they need to be re-applied whenever the month changes.
+    let calendar_marks: Rc<RefCell<Vec<CalendarMark>>> = Rc::new(RefCell::new(Vec::new()));
+    gtk_widget.connect_month_changed(glib::clone!(@strong calendar_marks => move |w| {
+        apply_calendar_marks(w, &calendar_marks.borrow());
+    }));
+
+    let cmd_context = CommandContext::new(bargs)?;
+    let cmd_context_clone = cmd_context.clone();
     def_widget!(bargs, _g, gtk_widget, {
         // @prop day - the selected day
         prop(day: as_f64) {
@@ -1138,22 +2120,68 @@ fn build_gtk_calendar(bargs: &mut BuilderArgs) -> Result<gtk::Calendar> {
         // @prop show-week-numbers - show week numbers
         prop(show_week_numbers: as_bool) { gtk_widget.set_show_week_numbers(show_week_numbers) },
         // @prop onclick - command to run when the user selects a date. The `{0}` placeholder will be replaced by the selected day, `{1}` will be replaced by the month, and `{2}` by the year.
-        // @prop timeout - timeout of the command. Default: "200ms"
-        prop(timeout: as_duration = Duration::from_millis(200), onclick: as_string) {
+        // @prop timeout - timeout of the command, or "none" to let it run until it exits. Default: "200ms"
+        prop(timeout: as_optional_duration = Duration::from_millis(200), onclick: as_string) {
+            let cmd_context = cmd_context.clone();
             connect_signal_handler!(gtk_widget, gtk_widget.connect_day_selected(move |w| {
                 run_command(
-                    timeout,
+                    &cmd_context, timeout,
                     &onclick,
                     &[w.day(), w.month(), w.year()]
                 )
             }));
+        },
+        // @prop marked-days - json list of days to mark. Each entry is either a day of the month (marked in every month), or a date in the format `"YYYY-MM-DD"`.
+        prop(marked_days: as_json_array) {
+            let marks = marked_days.iter().map(parse_calendar_mark).collect::<Result<Vec<_>>>()?;
+            apply_calendar_marks(&gtk_widget, &marks);
+            *calendar_marks.borrow_mut() = marks;
+        },
+        // @prop onmonthchange - command to run when the displayed month changes. The `{0}` placeholder will be replaced by the month, and `{1}` by the year.
+        prop(timeout: as_optional_duration = Duration::from_millis(200), onmonthchange: as_string) {
+            let cmd_context = cmd_context_clone.clone();
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_month_changed(move |w| {
+                run_command(&cmd_context, timeout, &onmonthchange, &[w.month() + 1, w.year()])
+            }));
         }
-
     });
 
     Ok(gtk_widget)
 }
 
+/// A day to mark in a calendar. Marks without a year and month apply to every month.
+struct CalendarMark {
+    year_month: Option<(i32, i32)>,
+    day: u32,
+}
+
+fn parse_calendar_mark(value: &serde_json::Value) -> Result<CalendarMark> {
+    match value {
+        serde_json::Value::Number(day) => {
+            let day =
+                day.as_u64().filter(|day| (1..=31).contains(day)).with_context(|| format!("Invalid day to mark: {}", day))?;
+            Ok(CalendarMark { year_month: None, day: day as u32 })
+        }
+        serde_json::Value::String(date) => {
+            let date = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .with_context(|| format!("Invalid date to mark: {}, expected YYYY-MM-DD", date))?;
+            // gtk counts months starting at 0
+            Ok(CalendarMark { year_month: Some((date.year(), date.month0() as i32)), day: date.day() })
+        }
+        other => bail!("Invalid day to mark: {}, expected a number or a date string", other),
+    }
+}
+
+fn apply_calendar_marks(calendar: &gtk::Calendar, marks: &[CalendarMark]) {
+    calendar.clear_marks();
+    let displayed = (calendar.year(), calendar.month());
+    for mark in marks {
+        if mark.year_month.map_or(true, |year_month| year_month == displayed) {
+            calendar.mark_day(mark.day);
+        }
+    }
+}
+
 const WIDGET_NAME_STACK: &str = "stack";
 /// @widget stack
 /// @desc A widget that displays one of its children at a time
@@ -1180,18 +2208,144 @@ fn build_gtk_stack(bargs: &mut BuilderArgs) -> Result<gtk::Stack> {
         child.show();
     }
 
+    let calling_scope = bargs.calling_scope;
+    let event_sender = bargs.scope_graph.event_sender.clone();
     def_widget!(bargs, _g, gtk_widget, {
         // @prop selected - index of child which should be shown
         prop(selected: as_i32) { gtk_widget.set_visible_child_name(&selected.to_string()); },
         // @prop transition - the name of the transition. Possible values: $transition
         prop(transition: as_string = "crossfade") { gtk_widget.set_transition_type(parse_stack_transition(&transition)?); },
         // @prop same-size - sets whether all children should be the same size
-        prop(same_size: as_bool = false) { gtk_widget.set_homogeneous(same_size); }
+        prop(same_size: as_bool = false) { gtk_widget.set_homogeneous(same_size); },
+        // @prop transition-running-var - name of a variable that is set to true while the transition between children is running, and to false once it finished
+        prop(transition_running_var: as_string) {
+            let (event_sender, var_name) = (event_sender.clone(), VarName(transition_running_var));
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_transition_running_notify(move |gtk_widget| {
+                set_transition_running_var(&event_sender, calling_scope, &var_name, gtk_widget.is_transition_running());
+            }));
+        }
+    });
+
+    Ok(gtk_widget)
+}
+
+const WIDGET_NAME_NOTEBOOK: &str = "notebook";
+/// @widget notebook
+/// @desc A container that shows one of its children at a time, with a row of tabs to switch between them.
+/// The text of the tab of a child is given via `:label` on the child itself, defaulting to its position (starting at 1).
+fn build_gtk_notebook(bargs: &mut BuilderArgs) -> Result<gtk::Notebook> {
+    let gtk_widget = gtk::Notebook::new();
+
+    if bargs.widget_use.children.is_empty() {
+        return Err(DiagError(gen_diagnostic!("notebook must contain at least one element", bargs.widget_use.span)).into());
+    }
+
+    for (i, child) in bargs.widget_use.children.iter().enumerate() {
+        let mut child = child.clone();
+        let label = match &mut child {
+            WidgetUse::Basic(child) => child.attrs.ast_optional::<SimplExpr>("label")?,
+            _ => None,
+        };
+        let child_widget = build_gtk_widget(
+            bargs.scope_graph,
+            bargs.widget_defs.clone(),
+            bargs.calling_scope,
+            child,
+            bargs.custom_widget_invocation.clone(),
+        )?;
+        let tab_label = gtk::Label::new(None);
+        gtk_widget.append_page(&child_widget, Some(&tab_label));
+        child_widget.show();
+
+        let label = label.unwrap_or_else(|| SimplExpr::synth_literal((i + 1).to_string()));
+        bargs.scope_graph.register_listener(
+            bargs.calling_scope,
+            Listener {
+                needed_variables: label.collect_var_refs(),
+                f: Box::new({
+                    let tab_label = tab_label.downgrade();
+                    move |_, values| {
+                        if let Some(tab_label) = tab_label.upgrade() {
+                            tab_label.set_text(&label.eval(&values)?.as_string()?);
+                        }
+                        Ok(())
+                    }
+                }),
+            },
+        )?;
+    }
+
+    let cmd_context = CommandContext::new(bargs)?;
+    def_widget!(bargs, _g, gtk_widget, {
+        // @prop page - index of the child which should be shown
+        prop(page: as_i32) { gtk_widget.set_current_page(u32::try_from(page).ok()); },
+        // @prop show-tabs - whether to show the tabs. Default: true
+        prop(show_tabs: as_bool = true) { gtk_widget.set_show_tabs(show_tabs); },
+        // @prop tab-position - the side the tabs are shown on. possible values: $side. Default: "top"
+        prop(tab_position: as_string = "top") { gtk_widget.set_tab_pos(parse_side(&tab_position)?); },
+        // @prop timeout - timeout of the command, or "none" to let it run until it exits. Default: "200ms"
+        // @prop onpagechanged - command to run when the shown page changes. `{}` will be replaced with the index of the new page.
+        prop(timeout: as_optional_duration = Duration::from_millis(200), onpagechanged: as_string) {
+            let cmd_context = cmd_context.clone();
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_switch_page(move |_, _, page| {
+                run_command(&cmd_context, timeout, &onpagechanged, &[page]);
+            }));
+        },
     });
 
     Ok(gtk_widget)
 }
 
+const WIDGET_NAME_LISTBOX: &str = "listbox";
+/// @widget listbox
+/// @desc A vertical list of rows that can be selected and activated. Each child is placed in its own row.
+fn build_gtk_listbox(bargs: &mut BuilderArgs) -> Result<gtk::ListBox> {
+    let gtk_widget = gtk::ListBox::new();
+
+    // Rows may be added after the selection was set (i.e. when using a `for` loop), so re-apply it whenever a row is added.
+    let selected_index = Rc::new(RefCell::new(None));
+    gtk_widget.connect_add(glib::clone!(@strong selected_index => move |gtk_widget, _| {
+        if let Some(index) = *selected_index.borrow() {
+            select_listbox_row(gtk_widget, index);
+        }
+    }));
+
+    let cmd_context = CommandContext::new(bargs)?;
+    let cmd_context_clone = cmd_context.clone();
+    def_widget!(bargs, _g, gtk_widget, {
+        // @prop selection-mode - how rows can be selected. Possible values: $selection_mode. Default: "single"
+        prop(selection_mode: as_string = "single") { gtk_widget.set_selection_mode(parse_selection_mode(&selection_mode)?); },
+        // @prop selected - index of the row that should be selected. Values outside of the range of rows unselect all rows.
+        prop(selected: as_i32) {
+            *selected_index.borrow_mut() = Some(selected);
+            select_listbox_row(&gtk_widget, selected);
+        },
+        // @prop timeout - timeout of the command, or "none" to let it run until it exits. Default: "200ms"
+        // @prop onactivated - command to run when a row is activated (i.e. clicked or activated via keyboard). `{}` will be replaced by the index of the row.
+        prop(timeout: as_optional_duration = Duration::from_millis(200), onactivated: as_string) {
+            let cmd_context = cmd_context.clone();
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_row_activated(move |_, row| {
+                run_command(&cmd_context, timeout, &onactivated, &[row.index()]);
+            }));
+        },
+        // @prop onselected - command to run when the selected row changes. `{}` will be replaced by the index of the row, or -1 if no row is selected.
+        prop(timeout: as_optional_duration = Duration::from_millis(200), onselected: as_string) {
+            let cmd_context = cmd_context_clone.clone();
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_row_selected(move |_, row| {
+                run_command(&cmd_context, timeout, &onselected, &[row.map_or(-1, |row| row.index())]);
+            }));
+        },
+    });
+    Ok(gtk_widget)
+}
+
+fn select_listbox_row(listbox: &gtk::ListBox, index: i32) {
+    match listbox.row_at_index(index) {
+        Some(row) => listbox.select_row(Some(&row)),
+        None => listbox.unselect_all(),
+    }
+}
+
 const WIDGET_NAME_TRANSFORM: &str = "transform";
 /// @widget transform
 /// @desc A widget that applies transformations to its content. They are applied in the following order: rotate -> translate -> scale
@@ -1212,13 +2366,17 @@ fn build_transform(bargs: &mut BuilderArgs) -> Result<Transform> {
         prop(scale_x: as_string) { w.set_property("scale-x", scale_x); },
         // @prop scale-y - the amount to scale in the y direction (px or %)
         prop(scale_y: as_string) { w.set_property("scale-y", scale_y); },
+        // @prop duration - the duration of the transition when any of the transformation values change. Defaults to no transition.
+        prop(duration: as_duration) { w.set_property("duration", duration.as_millis() as u64); },
+        // @prop easing - the easing function used for transitions. Possible values: $easing
+        prop(easing: as_string) { w.set_property("easing", parse_easing(&easing)?); },
     });
     Ok(w)
 }
 
 const WIDGET_NAME_CIRCULAR_PROGRESS: &str = "circular-progress";
 /// @widget circular-progress
-/// @desc A widget that displays a circular progress bar
+/// @desc A widget that displays a circular progress bar. A child widget is shown centered inside the ring, sized to fit its inner diameter.
 fn build_circular_progress_bar(bargs: &mut BuilderArgs) -> Result<CircProg> {
     let w = CircProg::new();
     def_widget!(bargs, _g, w, {
@@ -1274,18 +2432,27 @@ fn build_graph(bargs: &mut BuilderArgs) -> Result<super::graph::Graph> {
 
 const WIDGET_NAME_SYSTRAY: &str = "systray";
 /// @widget systray
-/// @desc Tray for system notifier icons
+/// @desc Tray for system notifier icons. If another tray, such as waybar, already provides the StatusNotifierWatcher, eww takes over once that tray exits. The tray has the class `watcher-internal`, `watcher-external` or `watcher-missing`, depending on who provides the watcher.
 fn build_systray(bargs: &mut BuilderArgs) -> Result<gtk::Box> {
     let gtk_widget = gtk::Box::new(gtk::Orientation::Horizontal, 0);
-    let props = Rc::new(systray::Props::new());
+    let props = Rc::new(systray::Props::new(&gtk_widget));
     let props_clone = props.clone(); // copies for def_widget
     let props_clone2 = props.clone(); // copies for def_widget
+    let overflow = props.overflow.clone(); // copies for def_widget
+    let overflow_clone = props.overflow.clone(); // copies for def_widget
+    let overflow_clone2 = props.overflow.clone(); // copies for def_widget
+    let actions = props.actions.clone(); // copies for def_widget
+    let actions_clone = props.actions.clone(); // copies for def_widget
 
     def_widget!(bargs, _g, gtk_widget, {
         // @prop spacing - spacing between elements
         prop(spacing: as_i32 = 0) { gtk_widget.set_spacing(spacing) },
         // @prop orientation - orientation of the box. possible values: $orientation
-        prop(orientation: as_string) { gtk_widget.set_orientation(parse_orientation(&orientation)?) },
+        prop(orientation: as_string) {
+            let orientation = parse_orientation(&orientation)?;
+            gtk_widget.set_orientation(orientation);
+            overflow.set_orientation(orientation);
+        },
         // @prop space-evenly - space the widgets evenly.
         prop(space_evenly: as_bool = true) { gtk_widget.set_homogeneous(space_evenly) },
         // @prop icon-size - size of icons in the tray
@@ -1300,6 +2467,14 @@ fn build_systray(bargs: &mut BuilderArgs) -> Result<gtk::Box> {
         prop(prepend_new: as_bool = true) {
             *props_clone2.prepend_new.borrow_mut() = prepend_new;
         },
+        // @prop show-passive - show passive items, which don't convey any important information, like any other item
+        prop(show_passive: as_bool = false) { overflow_clone.set_show_passive(show_passive) },
+        // @prop overflow - what to do with passive items that aren't shown. Possible values: $systray_overflow
+        prop(overflow: as_string = "reveal") { overflow_clone2.set_mode(parse_systray_overflow(&overflow)?) },
+        // @prop scroll - forward scrolling over an icon to the application, i.e. to change the volume. Default: true
+        prop(scroll: as_bool = true) { actions.scroll.set(scroll) },
+        // @prop secondary-activate - run the secondary action of an icon when it is clicked with the middle mouse button. Default: true
+        prop(secondary_activate: as_bool = true) { actions_clone.secondary_activate.set(secondary_activate) },
     });
 
     systray::spawn_systray(&gtk_widget, &props_clone);
@@ -1307,6 +2482,139 @@ fn build_systray(bargs: &mut BuilderArgs) -> Result<gtk::Box> {
     Ok(gtk_widget)
 }
 
+/// @var systray_overflow - "none", "reveal"
+fn parse_systray_overflow(o: &str) -> Result<systray::OverflowMode> {
+    enum_parse! { "overflow", o,
+        "none" => systray::OverflowMode::None,
+        "reveal" => systray::OverflowMode::Reveal,
+    }
+}
+
+/// @var side - "top", "bottom", "left", "right"
+fn parse_side(s: &str) -> Result<gtk::PositionType> {
+    enum_parse! { "side", s,
+        "top" => gtk::PositionType::Top,
+        "bottom" => gtk::PositionType::Bottom,
+        "left" => gtk::PositionType::Left,
+        "right" => gtk::PositionType::Right,
+    }
+}
+
+/// @var onchange_mode - "continuous", "on-release"
+fn parse_onchange_mode(m: &str) -> Result<bool> {
+    enum_parse! { "onchange-mode", m,
+        "continuous" => false,
+        "on-release" => true,
+    }
+}
+
+/// @var easing - "linear", "ease-in", "ease-out", "ease-in-out"
+fn parse_easing(e: &str) -> Result<&'static str> {
+    enum_parse! { "easing", e,
+        "linear" => "linear",
+        "ease-in" => "ease-in",
+        "ease-out" => "ease-out",
+        "ease-in-out" => "ease-in-out",
+    }
+}
+
+/// @var selection_mode - "none", "single", "browse", "multiple"
+fn parse_selection_mode(m: &str) -> Result<gtk::SelectionMode> {
+    enum_parse! { "selection-mode", m,
+        "none" => gtk::SelectionMode::None,
+        "single" => gtk::SelectionMode::Single,
+        "browse" => gtk::SelectionMode::Browse,
+        "multiple" => gtk::SelectionMode::Multiple,
+    }
+}
+
+/// @var marquee_loop_mode - "wrap", "bounce", "none"
+fn parse_marquee_loop_mode(m: &str) -> Result<&'static str> {
+    enum_parse! { "loop-mode", m,
+        "wrap" => "wrap",
+        "bounce" => "bounce",
+        "none" => "none",
+    }
+}
+
+/// Which sides of a centerbox are filled, as (start, center, end)
+/// @var centerbox_sides - "start-center-end", "start-center", "center-end", "start-end", "center"
+fn parse_centerbox_sides(sides: &str) -> Result<(bool, bool, bool)> {
+    enum_parse! { "centerbox sides", sides,
+        "start-center-end" => (true, true, true),
+        "start-center" => (true, true, false),
+        "center-end" => (false, true, true),
+        "start-end" => (true, false, true),
+        "center" => (false, true, false),
+    }
+}
+
+/// Where the pointer and window were when a window drag started that eww handles itself
+#[derive(Debug, Clone, Copy)]
+struct WindowDragStart {
+    pointer: (f64, f64),
+    window: (i32, i32),
+}
+
+/// Start moving the window of the given widget with the pointer.
+/// Windows managed by the window manager are moved by the window manager, in which case `None` is returned.
+/// For all other windows, the returned position has to be passed to [`update_window_drag`] when the pointer moves.
+fn begin_window_drag(widget: &impl IsA<gtk::Widget>, evt: &gdk::Event) -> Option<WindowDragStart> {
+    let window = widget.toplevel()?.downcast::<gtk::Window>().ok()?;
+    #[cfg(feature = "wayland")]
+    if gtk_layer_shell::LayerShell::is_layer_window(&window) {
+        // the position of a layer surface is unknown, so it is moved by the distance the pointer moved within the window
+        return Some(WindowDragStart { pointer: evt.coords()?, window: (0, 0) });
+    }
+    let (root_x, root_y) = evt.root_coords()?;
+    if window.window_type() == gtk::WindowType::Popup {
+        return Some(WindowDragStart { pointer: (root_x, root_y), window: window.position() });
+    }
+    window.begin_move_drag(evt.button()? as i32, root_x as i32, root_y as i32, evt.time());
+    None
+}
+
+/// Move the window of the given widget along with the pointer, during a drag started via [`begin_window_drag`].
+fn update_window_drag(widget: &impl IsA<gtk::Widget>, evt: &gdk::Event, start: WindowDragStart) {
+    let Some(window) = widget.toplevel().and_then(|toplevel| toplevel.downcast::<gtk::Window>().ok()) else { return };
+    #[cfg(feature = "wayland")]
+    if gtk_layer_shell::LayerShell::is_layer_window(&window) {
+        let Some((x, y)) = evt.coords() else { return };
+        move_layer_window(&window, (x - start.pointer.0) as i32, (y - start.pointer.1) as i32);
+        return;
+    }
+    let Some((root_x, root_y)) = evt.root_coords() else { return };
+    window.move_(start.window.0 + (root_x - start.pointer.0) as i32, start.window.1 + (root_y - start.pointer.1) as i32);
+}
+
+/// Move a layer shell window by the given distance by changing its margins.
+/// Windows that are centered along an axis get anchored to the left or top edge first, keeping their current position.
+#[cfg(feature = "wayland")]
+fn move_layer_window(window: &gtk::Window, dx: i32, dy: i32) {
+    use gtk_layer_shell::{Edge, LayerShell};
+    let Some(monitor) = window.window().and_then(|gdk_window| window.display().monitor_at_window(&gdk_window)) else { return };
+    let monitor_geometry = monitor.geometry();
+    let axes = [
+        (Edge::Left, Edge::Right, dx, monitor_geometry.width(), window.allocated_width()),
+        (Edge::Top, Edge::Bottom, dy, monitor_geometry.height(), window.allocated_height()),
+    ];
+    for (start_edge, end_edge, distance, monitor_size, window_size) in axes {
+        // windows anchored to both edges fill the whole axis, so they can't move along it
+        if distance == 0 || (window.is_anchor(start_edge) && window.is_anchor(end_edge)) {
+            continue;
+        }
+        if window.is_anchor(end_edge) {
+            window.set_layer_shell_margin(end_edge, window.layer_shell_margin(end_edge) - distance);
+        } else {
+            if !window.is_anchor(start_edge) {
+                window.set_anchor(start_edge, true);
+                window.set_layer_shell_margin(start_edge, (monitor_size - window_size) / 2);
+            }
+            window.set_layer_shell_margin(start_edge, window.layer_shell_margin(start_edge) + distance);
+        }
+    }
+}
+
 /// @var orientation - "vertical", "v", "horizontal", "h"
 fn parse_orientation(o: &str) -> Result<gtk::Orientation> {
     enum_parse! { "orientation", o,
@@ -1373,6 +2681,28 @@ fn parse_justification(j: &str) -> Result<gtk::Justification> {
     }
 }
 
+/// @var font_weight - "thin", "ultralight", "light", "normal", "medium", "semibold", "bold", "ultrabold", "heavy"
+fn parse_font_weight(weight: &str) -> Result<pango::Weight> {
+    if let Ok(weight) = weight.parse::<i32>() {
+        if !(100..=1000).contains(&weight) {
+            bail!("Font weight must be between 100 and 1000, but got {}", weight);
+        }
+        // pango accepts any weight within this range, not just the named ones
+        return Ok(unsafe { pango::Weight::from_glib(weight) });
+    }
+    enum_parse! { "font weight", weight,
+        "thin" => pango::Weight::Thin,
+        "ultralight" => pango::Weight::Ultralight,
+        "light" => pango::Weight::Light,
+        "normal" => pango::Weight::Normal,
+        "medium" => pango::Weight::Medium,
+        "semibold" => pango::Weight::Semibold,
+        "bold" => pango::Weight::Bold,
+        "ultrabold" => pango::Weight::Ultrabold,
+        "heavy" => pango::Weight::Heavy,
+    }
+}
+
 /// @var gravity - "south", "east", "west", "north", "auto"
 fn parse_gravity(g: &str) -> Result<gtk::pango::Gravity> {
     enum_parse! { "gravity", g,
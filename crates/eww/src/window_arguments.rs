@@ -25,6 +25,8 @@ pub struct WindowArguments {
     /// Instance ID of the window
     pub instance_id: String,
     pub anchor: Option<AnchorPoint>,
+    /// Whether the window should be placed at the position of the pointer
+    pub at_pointer: bool,
     pub args: HashMap<VarName, DynVal>,
     pub duration: Option<std::time::Duration>,
     pub monitor: Option<MonitorIdentifier>,
@@ -41,6 +43,7 @@ impl WindowArguments {
             size: parse_value_from_args::<Coords>("size", &mut args)?,
             monitor: parse_value_from_args::<MonitorIdentifier>("screen", &mut args)?,
             anchor: parse_value_from_args::<AnchorPoint>("anchor", &mut args)?,
+            at_pointer: parse_value_from_args::<bool>("at-pointer", &mut args)?.unwrap_or(false),
             duration: parse_value_from_args::<DynVal>("duration", &mut args)?
                 .map(|x| x.as_duration())
                 .transpose()
@@ -5,41 +5,69 @@ use std::collections::HashMap;
 use yuck::config::{
     backend_window_options::BackendWindowOptions,
     monitor::MonitorIdentifier,
-    window_definition::{WindowDefinition, WindowStacking},
+    window_definition::{WindowDefinition, WindowStacking, WindowTransition, POINTER_VAR_NAME},
     window_geometry::WindowGeometry,
 };
 
 use crate::window_arguments::WindowArguments;
 
+/// The value of the `EWW_POINTER` variable of a window, given the position of the pointer relative to the window.
+pub fn pointer_state(x: i32, y: i32, hovered: bool) -> DynVal {
+    DynVal::from(serde_json::json!({ "x": x, "y": y, "hovered": hovered }).to_string())
+}
+
 /// This stores all the information required to create a window and is created
 /// via combining information from the [`WindowDefinition`] and the [`WindowInitiator`]
 #[derive(Debug, Clone)]
 pub struct WindowInitiator {
     pub backend_options: BackendWindowOptions,
+    pub close_on_unfocus: bool,
+    pub close_transition: WindowTransition,
+    pub force_x11: bool,
     pub geometry: Option<WindowGeometry>,
+    pub hide_when_idle: Option<std::time::Duration>,
     pub local_variables: HashMap<VarName, DynVal>,
     pub monitor: Option<MonitorIdentifier>,
     pub name: String,
     pub resizable: bool,
+    pub open_transition: WindowTransition,
     pub stacking: WindowStacking,
+    pub transition_duration: std::time::Duration,
+    pub track_pointer: bool,
+    pub z_index: i32,
 }
 
 impl WindowInitiator {
     pub fn new(window_def: &WindowDefinition, args: &WindowArguments) -> Result<Self> {
-        let vars = args.get_local_window_variables(window_def)?;
+        let mut vars = args.get_local_window_variables(window_def)?;
 
-        let geometry = match &window_def.geometry {
+        let mut geometry = match &window_def.geometry {
             Some(geo) => Some(geo.eval(&vars)?.override_if_given(args.anchor, args.pos, args.size)),
+            None if args.at_pointer => Some(WindowGeometry::default().override_if_given(None, args.pos, args.size)),
             None => None,
         };
+        if let Some(geometry) = geometry.as_mut().filter(|_| args.at_pointer) {
+            geometry.at_pointer = true;
+        }
         let monitor = if args.monitor.is_none() { window_def.eval_monitor(&vars)? } else { args.monitor.clone() };
+        if window_def.track_pointer {
+            vars.insert(VarName::from(POINTER_VAR_NAME), pointer_state(0, 0, false));
+        }
         Ok(WindowInitiator {
             backend_options: window_def.backend_options.eval(&vars)?,
+            close_on_unfocus: window_def.eval_close_on_unfocus(&vars)?,
+            close_transition: window_def.eval_close_transition(&vars)?,
+            force_x11: window_def.force_x11,
             geometry,
+            hide_when_idle: window_def.eval_hide_when_idle(&vars)?,
             monitor,
             name: window_def.name.clone(),
             resizable: window_def.eval_resizable(&vars)?,
+            open_transition: window_def.eval_open_transition(&vars)?,
             stacking: window_def.eval_stacking(&vars)?,
+            transition_duration: window_def.eval_transition_duration(&vars)?,
+            track_pointer: window_def.track_pointer,
+            z_index: window_def.eval_z_index(&vars)?,
             local_variables: vars,
         })
     }
@@ -2,7 +2,7 @@
 /// - the start location
 /// - the end location
 /// - the file id
-#[derive(Eq, PartialEq, Clone, Copy, serde::Serialize, serde::Deserialize)]
+#[derive(Eq, PartialEq, Hash, Clone, Copy, serde::Serialize, serde::Deserialize)]
 pub struct Span(pub usize, pub usize, pub usize);
 
 impl Span {
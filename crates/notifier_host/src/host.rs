@@ -1,6 +1,10 @@
 use crate::*;
 
-use zbus::export::ordered_stream::{self, OrderedStreamExt};
+use std::collections::HashSet;
+use zbus::export::{
+    futures_util::StreamExt,
+    ordered_stream::{self, OrderedStreamExt},
+};
 
 /// Trait for system tray implementations, to be notified of changes to what items are in the tray.
 pub trait Host {
@@ -10,6 +14,10 @@ pub trait Host {
 
     /// Called when an item is removed from the tray.
     fn remove_item(&mut self, id: &str);
+
+    /// Called when the StatusNotifierWatcher changes, i.e. when another tray that provided it exits and eww takes
+    /// over. This is also called with the initial status when starting [`run_host`].
+    fn watcher_changed(&mut self, _status: WatcherStatus) {}
 }
 
 // TODO We aren't really thinking about what happens when we shut down a host. Currently, we don't
@@ -84,47 +92,64 @@ pub async fn run_host(host: &mut dyn Host, snw: &proxy::StatusNotifierWatcherPro
     let new_items = try_!(snw.receive_status_notifier_item_registered().await);
     let gone_items = try_!(snw.receive_status_notifier_item_unregistered().await);
 
-    let mut item_names = std::collections::HashSet::new();
+    // the watcher may be replaced while we're running, i.e. when the tray providing it exits
+    let mut owner_changes = try_!(snw.receive_owner_changed().await);
+    let dbus = try_!(zbus::fdo::DBusProxy::new(snw.connection()).await);
+    let watcher_bus = zbus::names::BusName::try_from(names::WATCHER_BUS).expect("watcher bus name is invalid");
+    let owner = dbus.get_name_owner(watcher_bus).await.ok().map(|owner| owner.into_inner());
+    host.watcher_changed(watcher_status(snw, owner.as_ref()));
+
+    let mut item_names = HashSet::new();
 
     // initial items first
     for svc in try_!(snw.registered_status_notifier_items().await) {
-        match Item::from_address(snw.connection(), &svc).await {
-            Ok(item) => {
-                item_names.insert(svc.to_owned());
-                host.add_item(&svc, item);
-            }
-            Err(e) => {
-                log::warn!("Could not create StatusNotifierItem from address {:?}: {:?}", svc, e);
-            }
-        }
+        add_item(host, snw, &mut item_names, &svc).await;
     }
 
     let mut ev_stream = ordered_stream::join(
         OrderedStreamExt::map(new_items, ItemEvent::NewItem),
         OrderedStreamExt::map(gone_items, ItemEvent::GoneItem),
     );
-    while let Some(ev) = ev_stream.next().await {
-        match ev {
-            ItemEvent::NewItem(sig) => {
-                let svc = try_!(sig.args()).service;
-                if item_names.contains(svc) {
-                    log::info!("Got duplicate new item: {:?}", svc);
-                } else {
-                    match Item::from_address(snw.connection(), svc).await {
-                        Ok(item) => {
-                            item_names.insert(svc.to_owned());
-                            host.add_item(svc, item);
-                        }
-                        Err(e) => {
-                            log::warn!("Could not create StatusNotifierItem from address {:?}: {:?}", svc, e);
-                        }
+    loop {
+        tokio::select! {
+            ev = OrderedStreamExt::next(&mut ev_stream) => match ev {
+                Some(ItemEvent::NewItem(sig)) => {
+                    let svc = try_!(sig.args()).service;
+                    if item_names.contains(svc) {
+                        log::info!("Got duplicate new item: {:?}", svc);
+                    } else {
+                        add_item(host, snw, &mut item_names, svc).await;
                     }
                 }
-            }
-            ItemEvent::GoneItem(sig) => {
-                let svc = try_!(sig.args()).service;
-                if item_names.remove(svc) {
-                    host.remove_item(svc);
+                Some(ItemEvent::GoneItem(sig)) => {
+                    let svc = try_!(sig.args()).service;
+                    if item_names.remove(svc) {
+                        host.remove_item(svc);
+                    }
+                }
+                None => break,
+            },
+            Some(owner) = StreamExt::next(&mut owner_changes) => {
+                let status = watcher_status(snw, owner.as_ref());
+                log::info!("StatusNotifierWatcher changed, now {:?}", status);
+                host.watcher_changed(status);
+                if owner.is_none() {
+                    continue;
+                }
+
+                // the new watcher doesn't know about us yet, and might know about different items
+                if let Some(name) = snw.connection().unique_name() {
+                    try_!(snw.register_status_notifier_host(name.as_str()).await);
+                }
+                let current_items: HashSet<String> =
+                    try_!(snw.registered_status_notifier_items().await).into_iter().collect();
+                let gone_items: Vec<String> = item_names.difference(&current_items).cloned().collect();
+                for svc in gone_items {
+                    item_names.remove(&svc);
+                    host.remove_item(&svc);
+                }
+                for svc in current_items.difference(&item_names.clone()) {
+                    add_item(host, snw, &mut item_names, svc).await;
                 }
             }
         }
@@ -133,3 +158,35 @@ pub async fn run_host(host: &mut dyn Host, snw: &proxy::StatusNotifierWatcherPro
     // I do not know whether this is possible to reach or not.
     unreachable!("StatusNotifierWatcher stopped producing events")
 }
+
+/// Create the item at the given address and add it to the host, unless that fails.
+async fn add_item(
+    host: &mut dyn Host,
+    snw: &proxy::StatusNotifierWatcherProxy<'static>,
+    item_names: &mut HashSet<String>,
+    svc: &str,
+) {
+    match Item::from_address(snw.connection(), svc).await {
+        Ok(item) => {
+            item_names.insert(svc.to_owned());
+            host.add_item(svc, item);
+        }
+        Err(e) => {
+            log::warn!("Could not create StatusNotifierItem from address {:?}: {:?}", svc, e);
+        }
+    }
+}
+
+/// Determine who provides the watcher, given the unique name of the current owner of the watcher name.
+fn watcher_status(
+    snw: &proxy::StatusNotifierWatcherProxy<'static>,
+    owner: Option<&zbus::names::UniqueName<'_>>,
+) -> WatcherStatus {
+    match owner {
+        Some(owner) if snw.connection().unique_name().is_some_and(|name| name.as_str() == owner.as_str()) => {
+            WatcherStatus::Internal
+        }
+        Some(_) => WatcherStatus::External,
+        None => WatcherStatus::Missing,
+    }
+}
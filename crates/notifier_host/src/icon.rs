@@ -1,6 +1,11 @@
 use crate::*;
 
 use gtk::{self, prelude::*};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    hash::{Hash, Hasher},
+};
 
 #[derive(thiserror::Error, Debug)]
 enum IconError {
@@ -27,8 +32,78 @@ enum IconError {
     NotAvailable,
 }
 
+/// Maximum number of icons kept in the icon cache.
+const ICON_CACHE_CAPACITY: usize = 128;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum IconCacheKey {
+    /// Icon looked up by name, in the theme at `theme_path` or the default theme.
+    Name { icon_name: String, theme_path: Option<String>, size: i32, scale: i32, flags: u32 },
+    /// Icon decoded from a list of pixmaps, identified by a hash of their contents.
+    Pixmaps { hash: u64, size: i32 },
+}
+
+/// Cache of loaded icons, so that icons which alternate between a few states (i.e. animated or blinking icons) don't
+/// need to be decoded and scaled again on every change.
+/// Once full, the least recently used icon is evicted.
+#[derive(Default)]
+struct IconCache {
+    /// Cached icons, together with the value of `clock` when they were last used.
+    entries: HashMap<IconCacheKey, (gtk::gdk_pixbuf::Pixbuf, u64)>,
+    clock: u64,
+}
+
+impl IconCache {
+    fn get(&mut self, key: &IconCacheKey) -> Option<gtk::gdk_pixbuf::Pixbuf> {
+        self.clock += 1;
+        let (pixbuf, last_used) = self.entries.get_mut(key)?;
+        *last_used = self.clock;
+        Some(pixbuf.clone())
+    }
+
+    fn insert(&mut self, key: IconCacheKey, pixbuf: gtk::gdk_pixbuf::Pixbuf) {
+        self.clock += 1;
+        if self.entries.len() >= ICON_CACHE_CAPACITY && !self.entries.contains_key(&key) {
+            let least_recently_used = self.entries.iter().min_by_key(|(_, (_, last_used))| *last_used).map(|(k, _)| k.clone());
+            if let Some(least_recently_used) = least_recently_used {
+                self.entries.remove(&least_recently_used);
+            }
+        }
+        self.entries.insert(key, (pixbuf, self.clock));
+    }
+}
+
+thread_local! {
+    // pixbufs can't be shared between threads, and icons are only ever loaded on the gtk main thread anyways
+    static ICON_CACHE: RefCell<IconCache> = {
+        // icons looked up by name may look different once the icon theme changes
+        if let Some(theme) = gtk::IconTheme::default() {
+            theme.connect_changed(|_| clear_icon_cache());
+        }
+        RefCell::new(IconCache::default())
+    };
+}
+
+/// Look up an icon in the icon cache, or load and cache it if it isn't cached yet.
+fn cached_icon<E>(
+    key: IconCacheKey,
+    load: impl FnOnce() -> std::result::Result<gtk::gdk_pixbuf::Pixbuf, E>,
+) -> std::result::Result<gtk::gdk_pixbuf::Pixbuf, E> {
+    if let Some(pixbuf) = ICON_CACHE.with(|cache| cache.borrow_mut().get(&key)) {
+        return Ok(pixbuf);
+    }
+    let pixbuf = load()?;
+    ICON_CACHE.with(|cache| cache.borrow_mut().insert(key, pixbuf.clone()));
+    Ok(pixbuf)
+}
+
+/// Remove all icons from the icon cache, i.e. after the icon theme changed.
+pub fn clear_icon_cache() {
+    ICON_CACHE.with(|cache| cache.borrow_mut().entries.clear());
+}
+
 /// Get the fallback GTK icon, as a final fallback if the tray item has no icon.
-async fn fallback_icon(size: i32, scale: i32) -> Option<gtk::gdk_pixbuf::Pixbuf> {
+fn fallback_icon(size: i32, scale: i32) -> Option<gtk::gdk_pixbuf::Pixbuf> {
     let theme = gtk::IconTheme::default().expect("Could not get default gtk theme");
     match theme.load_icon_for_scale("image-missing", size, scale, gtk::IconLookupFlags::FORCE_SIZE) {
         Ok(pb) => pb,
@@ -71,6 +146,14 @@ fn icon_from_pixmap(width: i32, height: i32, mut data: Vec<u8>) -> gtk::gdk_pixb
 ///
 /// This function returns None if and only if no pixmaps are provided.
 fn icon_from_pixmaps(pixmaps: Vec<(i32, i32, Vec<u8>)>, size: i32) -> Option<gtk::gdk_pixbuf::Pixbuf> {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    pixmaps.hash(&mut hasher);
+    let key = IconCacheKey::Pixmaps { hash: hasher.finish(), size };
+    cached_icon(key, || decode_pixmaps(pixmaps, size).ok_or(())).ok()
+}
+
+/// Decode and scale the most appropriately sized pixmap, see [`icon_from_pixmaps`].
+fn decode_pixmaps(pixmaps: Vec<(i32, i32, Vec<u8>)>, size: i32) -> Option<gtk::gdk_pixbuf::Pixbuf> {
     pixmaps
         .into_iter()
         .max_by(|(w1, h1, _), (w2, h2, _)| {
@@ -102,6 +185,25 @@ fn icon_from_name(
     theme_path: Option<&str>,
     size: i32,
     scale: i32,
+    flags: gtk::IconLookupFlags,
+) -> std::result::Result<gtk::gdk_pixbuf::Pixbuf, IconError> {
+    let key = IconCacheKey::Name {
+        icon_name: icon_name.to_owned(),
+        theme_path: theme_path.map(str::to_owned),
+        size,
+        scale,
+        flags: flags.bits(),
+    };
+    cached_icon(key, || load_icon_from_theme(icon_name, theme_path, size, scale, flags))
+}
+
+/// Load an icon from a theme without going through the icon cache, see [`icon_from_name`].
+fn load_icon_from_theme(
+    icon_name: &str,
+    theme_path: Option<&str>,
+    size: i32,
+    scale: i32,
+    flags: gtk::IconLookupFlags,
 ) -> std::result::Result<gtk::gdk_pixbuf::Pixbuf, IconError> {
     let theme = if let Some(path) = theme_path {
         let theme = gtk::IconTheme::new();
@@ -111,7 +213,7 @@ fn icon_from_name(
         gtk::IconTheme::default().expect("Could not get default gtk theme")
     };
 
-    match theme.load_icon_for_scale(icon_name, size, scale, gtk::IconLookupFlags::FORCE_SIZE) {
+    match theme.load_icon_for_scale(icon_name, size, scale, flags) {
         Ok(pb) => Ok(pb.expect("no pixbuf from theme.load_icon despite no error")),
         Err(e) => Err(IconError::LoadIconFromTheme {
             icon_name: icon_name.to_owned(),
@@ -172,7 +274,7 @@ pub async fn load_icon_from_sni(
             Some(s) => Some(s),
             None => None,
         };
-        icon_from_name(&icon_name, icon_theme_path, size, scale)
+        icon_from_name(&icon_name, icon_theme_path, size, scale, gtk::IconLookupFlags::FORCE_SIZE)
     })
     .await;
 
@@ -203,5 +305,33 @@ pub async fn load_icon_from_sni(
     };
 
     // Tray didn't provide a valid icon so use the default fallback one.
-    fallback_icon(size, scale).await
+    fallback_icon(size, scale)
+}
+
+/// Load an icon by trying each of the given names in order, falling back to the "image-missing" icon
+/// if none of them can be loaded.
+///
+/// Names are interpreted as absolute paths if possible, and otherwise looked up in the default theme,
+/// preferring the symbolic variant of an icon if `symbolic` is set.
+pub fn load_icon_from_names(icon_names: &[String], size: i32, scale: i32, symbolic: bool) -> Option<gtk::gdk_pixbuf::Pixbuf> {
+    let scaled_size = size * scale;
+    let flags = if symbolic {
+        gtk::IconLookupFlags::FORCE_SIZE | gtk::IconLookupFlags::FORCE_SYMBOLIC
+    } else {
+        gtk::IconLookupFlags::FORCE_SIZE
+    };
+    for icon_name in icon_names.iter().filter(|name| !name.is_empty()) {
+        let icon_path = std::path::Path::new(icon_name);
+        let icon = if icon_path.is_absolute() && icon_path.is_file() {
+            gtk::gdk_pixbuf::Pixbuf::from_file_at_size(icon_path, scaled_size, scaled_size)
+                .map_err(|e| IconError::LoadIconFromFile { path: icon_name.to_owned(), source: e })
+        } else {
+            icon_from_name(icon_name, None, size, scale, flags)
+        };
+        match icon {
+            Ok(p) => return Some(p),
+            Err(e) => log::debug!("failed to load icon {:?}: {}", icon_name, e),
+        }
+    }
+    fallback_icon(size, scale)
 }
@@ -19,6 +19,18 @@ pub struct Watcher {
     items: std::sync::Arc<std::sync::Mutex<std::collections::HashSet<String>>>,
 }
 
+/// Who provides the `StatusNotifierWatcher` service on the bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatcherStatus {
+    /// The watcher is provided by this connection.
+    Internal,
+    /// The watcher is provided by another process, usually another tray that was started first.
+    /// If it was attached via [`Watcher::attach_to`], this connection takes over once that process exits.
+    External,
+    /// There is no watcher on the bus.
+    Missing,
+}
+
 /// Implementation of the `StatusNotifierWatcher` service.
 ///
 /// Methods and properties correspond to methods and properties on the DBus service that can be
@@ -189,7 +201,10 @@ impl Watcher {
     }
 
     /// Attach and run the Watcher (in the background) on a connection.
-    pub async fn attach_to(self, con: &zbus::Connection) -> zbus::Result<()> {
+    ///
+    /// If another process already provides the watcher, this connection is queued for the watcher name and takes
+    /// over as soon as the other process releases it.
+    pub async fn attach_to(self, con: &zbus::Connection) -> zbus::Result<WatcherStatus> {
         if !con.object_server().at(names::WATCHER_OBJECT, self).await? {
             return Err(zbus::Error::Failure(format!(
                 "Object already exists at {} on this connection -- is StatusNotifierWatcher already running?",
@@ -199,11 +214,34 @@ impl Watcher {
 
         // not AllowReplacement, not ReplaceExisting, not DoNotQueue
         let flags: [zbus::fdo::RequestNameFlags; 0] = [];
-        match con.request_name_with_flags(names::WATCHER_BUS, flags.into_iter().collect()).await {
-            Ok(zbus::fdo::RequestNameReply::PrimaryOwner) => Ok(()),
-            Ok(_) | Err(zbus::Error::NameTaken) => Ok(()), // defer to existing
-            Err(e) => Err(e),
+        let status = match con.request_name_with_flags(names::WATCHER_BUS, flags.into_iter().collect()).await {
+            Ok(zbus::fdo::RequestNameReply::PrimaryOwner) => WatcherStatus::Internal,
+            Ok(_) | Err(zbus::Error::NameTaken) => WatcherStatus::External, // defer to existing
+            Err(e) => return Err(e),
+        };
+        if status == WatcherStatus::External {
+            log::info!("{} is provided by another process, waiting for it to exit", names::WATCHER_BUS);
+            let dbus = zbus::fdo::DBusProxy::new(con).await?;
+            let mut name_acquired = dbus.receive_name_acquired().await?;
+            tokio::spawn(async move {
+                while let Some(sig) = name_acquired.next().await {
+                    if sig.args().is_ok_and(|args| args.name() == names::WATCHER_BUS) {
+                        log::info!("Took over {} after the previous owner exited", names::WATCHER_BUS);
+                        break;
+                    }
+                }
+            });
         }
+        Ok(status)
+    }
+
+    /// Stop providing the watcher on the given connection, so that another tray can take over.
+    ///
+    /// Hosts and items are notified by the bus that the watcher name changed owner.
+    pub async fn detach_from(con: &zbus::Connection) -> zbus::Result<()> {
+        con.release_name(names::WATCHER_BUS).await?;
+        con.object_server().remove::<Watcher, _>(names::WATCHER_OBJECT).await?;
+        Ok(())
     }
 
     /// Equivalent to `is_status_notifier_host_registered_invalidate`, but without requiring
@@ -23,6 +23,11 @@ pub enum BinOp {
     #[strum(serialize = "<") ] LT,
     #[strum(serialize = "?:")] Elvis,
     #[strum(serialize = "=~")] RegexMatch,
+    #[strum(serialize = "&") ] BitAnd,
+    #[strum(serialize = "|") ] BitOr,
+    #[strum(serialize = "^") ] BitXor,
+    #[strum(serialize = "<<")] ShiftLeft,
+    #[strum(serialize = ">>")] ShiftRight,
 }
 
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, strum::EnumString, strum::Display)]
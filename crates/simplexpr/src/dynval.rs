@@ -96,7 +96,7 @@ macro_rules! impl_dynval_from {
     };
 }
 
-impl_dynval_from!(bool, i32, u32, f32, u8, f64, &str);
+impl_dynval_from!(bool, i32, u32, i64, f32, u8, f64, &str);
 
 impl TryFrom<serde_json::Value> for DynVal {
     type Error = serde_json::Error;
@@ -211,6 +211,15 @@ impl DynVal {
         }
     }
 
+    /// Parse a duration like [`Self::as_duration`], where `"none"` means that there is no duration, i.e. for a timeout that never expires.
+    pub fn as_optional_duration(&self) -> Result<Option<std::time::Duration>> {
+        if self.0 == "none" {
+            Ok(None)
+        } else {
+            self.as_duration().map(Some)
+        }
+    }
+
     // TODO this should return Result<Vec<DynVal>> and use json parsing
     pub fn as_vec(&self) -> Result<Vec<String>> {
         if self.0.is_empty() {
@@ -285,4 +294,11 @@ mod test {
         insta::assert_debug_snapshot!(DynVal::from("1h").as_duration());
         insta::assert_debug_snapshot!(DynVal::from("0.5h").as_duration());
     }
+
+    #[test]
+    fn test_parse_optional_duration() {
+        assert_eq!(DynVal::from("none").as_optional_duration().unwrap(), None);
+        assert_eq!(DynVal::from("2s").as_optional_duration().unwrap(), Some(std::time::Duration::from_secs(2)));
+        assert!(DynVal::from("never").as_optional_duration().is_err());
+    }
 }
@@ -58,6 +58,9 @@ pub enum EvalError {
     #[error(transparent)]
     JaqParseError(Box<JaqParseError>),
 
+    #[error("Invalid shift amount: {0}")]
+    InvalidShiftAmount(i64),
+
     #[error("Error parsing date: {0}")]
     ChronoError(String),
 
@@ -192,7 +195,9 @@ impl SimplExpr {
             SimplExpr::Concat(span, elems) => {
                 let mut output = String::new();
                 for elem in elems {
-                    let result = elem.eval(values)?;
+                    // point errors without a location at the interpolated expression, rather than at the whole string
+                    let result =
+                        elem.eval(values).map_err(|err| if err.span().is_dummy() { err.at(elem.span()) } else { err })?;
                     output.push_str(&result.0);
                 }
                 Ok(DynVal(output, *span))
@@ -227,13 +232,10 @@ impl SimplExpr {
                             BinOp::Equals => DynVal::from(a == b),
                             BinOp::NotEquals => DynVal::from(a != b),
                             BinOp::Plus => match (a.as_f64(), b.as_f64()) {
-                                (Ok(a), Ok(b)) => DynVal::from(a + b),
+                                (Ok(_), Ok(_)) => eval_arithmetic(&a, op, &b)?,
                                 _ => DynVal::from(format!("{}{}", a.as_string()?, b.as_string()?)),
                             },
-                            BinOp::Minus => DynVal::from(a.as_f64()? - b.as_f64()?),
-                            BinOp::Times => DynVal::from(a.as_f64()? * b.as_f64()?),
-                            BinOp::Div => DynVal::from(a.as_f64()? / b.as_f64()?),
-                            BinOp::Mod => DynVal::from(a.as_f64()? % b.as_f64()?),
+                            BinOp::Minus | BinOp::Times | BinOp::Div | BinOp::Mod => eval_arithmetic(&a, op, &b)?,
                             BinOp::GT => DynVal::from(a.as_f64()? > b.as_f64()?),
                             BinOp::LT => DynVal::from(a.as_f64()? < b.as_f64()?),
                             BinOp::GE => DynVal::from(a.as_f64()? >= b.as_f64()?),
@@ -242,6 +244,10 @@ impl SimplExpr {
                                 let regex = regex::Regex::new(&b.as_string()?)?;
                                 DynVal::from(regex.is_match(&a.as_string()?))
                             }
+                            BinOp::BitAnd => DynVal::from(a.as_i64()? & b.as_i64()?),
+                            BinOp::BitOr => DynVal::from(a.as_i64()? | b.as_i64()?),
+                            BinOp::BitXor => DynVal::from(a.as_i64()? ^ b.as_i64()?),
+                            BinOp::ShiftLeft | BinOp::ShiftRight => eval_shift(a.as_i64()?, op, b.as_i64()?)?,
                             _ => unreachable!("Lazy operators already handled"),
                         }
                     }
@@ -252,7 +258,10 @@ impl SimplExpr {
                 let a = a.eval(values)?;
                 Ok(match op {
                     UnaryOp::Not => DynVal::from(!a.as_bool()?).at(*span),
-                    UnaryOp::Negative => DynVal::from(-a.as_f64()?).at(*span),
+                    UnaryOp::Negative => match a.as_i64().ok().and_then(i64::checked_neg) {
+                        Some(negated) => DynVal::from(negated).at(*span),
+                        None => DynVal::from(-a.as_f64()?).at(*span),
+                    },
                 })
             }
             SimplExpr::IfElse(_, cond, yes, no) => {
@@ -289,6 +298,9 @@ impl SimplExpr {
                     _ => Err(EvalError::CannotIndex(format!("{}", val)).at(*span)),
                 }
             }
+            SimplExpr::FunctionCall(span, function_name, args) if function_name == "match" => {
+                eval_match(args, values).map(|x| x.at(*span)).map_err(|e| e.at(*span))
+            }
             SimplExpr::FunctionCall(span, function_name, args) => {
                 let args = args.iter().map(|a| a.eval(values)).collect::<Result<_, EvalError>>()?;
                 call_expr_function(function_name, args).map(|x| x.at(*span)).map_err(|e| e.at(*span))
@@ -312,6 +324,105 @@ impl SimplExpr {
     }
 }
 
+/// Evaluate an arithmetic operator. If both operands are integers, and the result is an integer that fits into an `i64`,
+/// the result is calculated exactly. Otherwise, floating point arithmetic is used.
+fn eval_arithmetic(a: &DynVal, op: &BinOp, b: &DynVal) -> Result<DynVal, EvalError> {
+    if let (Ok(a), Ok(b)) = (a.as_i64(), b.as_i64()) {
+        let result = match op {
+            BinOp::Plus => a.checked_add(b),
+            BinOp::Minus => a.checked_sub(b),
+            BinOp::Times => a.checked_mul(b),
+            // dividing integers only results in an integer if they divide evenly
+            BinOp::Div => a.checked_rem(b).filter(|rem| *rem == 0).and_then(|_| a.checked_div(b)),
+            BinOp::Mod => a.checked_rem(b),
+            _ => None,
+        };
+        if let Some(result) = result {
+            return Ok(DynVal::from(result));
+        }
+    }
+    let (a, b) = (a.as_f64()?, b.as_f64()?);
+    Ok(DynVal::from(match op {
+        BinOp::Plus => a + b,
+        BinOp::Minus => a - b,
+        BinOp::Times => a * b,
+        BinOp::Div => a / b,
+        BinOp::Mod => a % b,
+        _ => unreachable!("Not an arithmetic operator: {}", op),
+    }))
+}
+
+/// Evaluate a bit shift. Shifting by a negative amount or by 64 bits or more is an error.
+fn eval_shift(a: i64, op: &BinOp, amount: i64) -> Result<DynVal, EvalError> {
+    let shift = u32::try_from(amount).ok().filter(|amount| *amount < i64::BITS).ok_or(EvalError::InvalidShiftAmount(amount))?;
+    Ok(DynVal::from(match op {
+        BinOp::ShiftLeft => a << shift,
+        BinOp::ShiftRight => a >> shift,
+        _ => unreachable!("Not a shift operator: {}", op),
+    }))
+}
+
+/// Insert `separator` between each group of three digits, counting from the right.
+fn group_thousands(digits: &str, separator: &str) -> String {
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Evaluate a `match(value, pattern1, result1, ..., default)` expression.
+/// Results are evaluated lazily, such that only the result of the matching pattern is evaluated.
+fn eval_match(args: &[SimplExpr], values: &HashMap<VarName, DynVal>) -> Result<DynVal, EvalError> {
+    let [value, cases @ .., default] = args else {
+        return Err(EvalError::WrongArgCount("match".to_string()));
+    };
+    if cases.len() % 2 != 0 {
+        return Err(EvalError::WrongArgCount("match".to_string()));
+    }
+    let value = value.eval(values)?;
+    for case in cases.chunks(2) {
+        if matches_pattern(&value, &case[0].eval(values)?.as_string()?)? {
+            return case[1].eval(values);
+        }
+    }
+    default.eval(values)
+}
+
+/// Check if a value matches a pattern of a `match` expression.
+/// A pattern is either a regex enclosed in slashes (`/^ws-[0-9]+$/`),
+/// a numeric range (`1..5`, `1..=5`, `..10` or `90..`), or a literal value that is compared to the value.
+fn matches_pattern(value: &DynVal, pattern: &str) -> Result<bool, EvalError> {
+    if let Some(regex) = pattern.strip_prefix('/').and_then(|x| x.strip_suffix('/')) {
+        return Ok(regex::Regex::new(regex)?.is_match(&value.as_string()?));
+    }
+    if let Some((start, end)) = pattern.split_once("..") {
+        let (end, inclusive) = match end.strip_prefix('=') {
+            Some(end) => (end, true),
+            None => (end, false),
+        };
+        if let (Some(start), Some(end)) = (parse_range_bound(start), parse_range_bound(end)) {
+            let Ok(value) = value.as_f64() else { return Ok(false) };
+            let after_start = start.map_or(true, |start| value >= start);
+            let before_end = end.map_or(true, |end| if inclusive { value <= end } else { value < end });
+            return Ok(after_start && before_end);
+        }
+    }
+    Ok(value.as_string()? == pattern)
+}
+
+/// Parse a bound of a range pattern, where an empty bound means the range is open on that side.
+/// Returns `None` if the bound is not a number, in which case the pattern isn't a range.
+fn parse_range_bound(bound: &str) -> Option<Option<f64>> {
+    match bound.trim() {
+        "" => Some(None),
+        bound => bound.parse().ok().map(Some),
+    }
+}
+
 fn call_expr_function(name: &str, args: Vec<DynVal>) -> Result<DynVal, EvalError> {
     match name {
         "get_env" => match args.as_slice() {
@@ -321,7 +432,7 @@ fn call_expr_function(name: &str, args: Vec<DynVal>) -> Result<DynVal, EvalError
             }
             _ => Err(EvalError::WrongArgCount(name.to_string())),
         },
-        "round" => match args.as_slice() {
+        "round" | "to_fixed" => match args.as_slice() {
             [num, digits] => {
                 let num = num.as_f64()?;
                 let digits = digits.as_i32()?;
@@ -329,6 +440,37 @@ fn call_expr_function(name: &str, args: Vec<DynVal>) -> Result<DynVal, EvalError
             }
             _ => Err(EvalError::WrongArgCount(name.to_string())),
         },
+        "format_number" => match args.as_slice() {
+            [num, decimals] | [num, decimals, _] => {
+                let separator = match args.get(2) {
+                    Some(separator) => separator.as_string()?,
+                    None => String::new(),
+                };
+                let formatted = format!("{:.1$}", num.as_f64()?, decimals.as_i32()?.max(0) as usize);
+                let (sign, unsigned) = match formatted.strip_prefix('-') {
+                    Some(unsigned) => ("-", unsigned),
+                    None => ("", formatted.as_str()),
+                };
+                let (integer_part, fraction) = match unsigned.split_once('.') {
+                    Some((integer_part, fraction)) => (integer_part, format!(".{}", fraction)),
+                    None => (unsigned, String::new()),
+                };
+                Ok(DynVal::from(format!("{}{}{}", sign, group_thousands(integer_part, &separator), fraction)))
+            }
+            _ => Err(EvalError::WrongArgCount(name.to_string())),
+        },
+        "hex" => match args.as_slice() {
+            [num] | [num, _] => {
+                let num = num.as_i64()?;
+                let digits = match args.get(1) {
+                    Some(digits) => digits.as_i32()?.max(0) as usize,
+                    None => 0,
+                };
+                let formatted = format!("{:0>1$x}", num.unsigned_abs(), digits);
+                Ok(DynVal::from(if num < 0 { format!("-{}", formatted) } else { formatted }))
+            }
+            _ => Err(EvalError::WrongArgCount(name.to_string())),
+        },
         "min" => match args.as_slice() {
             [a, b] => {
                 let a = a.as_f64()?;
@@ -551,6 +693,17 @@ fn run_jaq_function(json: serde_json::Value, code: String, args: &str) -> Result
 #[cfg(test)]
 mod tests {
     use crate::dynval::DynVal;
+    use eww_shared_util::{Span, Spanned};
+
+    #[test]
+    fn errors_in_interpolations_point_at_inner_expression() {
+        for (source, expr) in [(r#""foo ${"(" =~ "("} bar""#, r#""(" =~ "(""#), (r#""foo ${1 << 99} bar""#, "1 << 99")] {
+            // parse at an offset, as if the string was part of a larger file
+            let err = crate::parser::parse_string(10, 0, source).unwrap().eval_no_vars().unwrap_err();
+            let start = 10 + source.find(expr).unwrap();
+            assert_eq!(err.span(), Span(start, start + expr.len(), 0));
+        }
+    }
 
     macro_rules! evals_as {
         ($name:ident($simplexpr:expr) => $expected:expr $(,)?) => {
@@ -610,5 +763,29 @@ mod tests {
         jq_empty_arg(r#"jq("[ \"foo\" ]", ".[0]", "")"#) => Ok(DynVal::from(r#""foo""#)),
         jq_invalid_arg(r#"jq("[ \"foo\" ]", ".[0]", "hello")"#) => Ok(DynVal::from(r#""foo""#)),
         jq_no_arg(r#"jq("[ \"foo\" ]", ".[0]")"#) => Ok(DynVal::from(r#""foo""#)),
+        match_literal(r#"match("b", "a", 1, "b", 2, 3)"#) => Ok(DynVal::from(2)),
+        match_default(r#"match("c", "a", 1, "b", 2, 3)"#) => Ok(DynVal::from(3)),
+        match_regex(r#"match("ws-12", "/^ws-[0-9]+$/", "workspace", "other")"#) => Ok(DynVal::from("workspace")),
+        match_range(r#"match(42, "..20", "low", "20..50", "medium", "high")"#) => Ok(DynVal::from("medium")),
+        match_range_inclusive(r#"match(50, "20..50", "medium", "20..=50", "inclusive", "high")"#) => Ok(DynVal::from("inclusive")),
+        match_range_open_end(r#"match(90, "80..", "full", "other")"#) => Ok(DynVal::from("full")),
+        match_lazy_evaluation(r#"match("a", "a", 1, "null".test)"#) => Ok(DynVal::from(1)),
+        match_missing_default(r#"match("a", "a", 1)"#) => Err(super::EvalError::WrongArgCount("match".to_string())),
+        integer_addition(r#"9007199254740993 + 2"#) => Ok(DynVal::from(9007199254740995_i64)),
+        integer_division_exact(r#"12 / 4"#) => Ok(DynVal::from(3)),
+        integer_division_inexact(r#"7 / 2"#) => Ok(DynVal::from(3.5)),
+        float_addition(r#"0.5 + 1"#) => Ok(DynVal::from(1.5)),
+        format_number_with_separator(r#"format_number(1234567.891, 2, ",")"#) => Ok(DynVal::from("1,234,567.89")),
+        format_number_negative(r#"format_number(-1234, 0, " ")"#) => Ok(DynVal::from("-1 234")),
+        bitwise_and("12 & 10") => Ok(DynVal::from(8)),
+        bitwise_or("12 | 3") => Ok(DynVal::from(15)),
+        bitwise_xor("12 ^ 10") => Ok(DynVal::from(6)),
+        shift_left("1 << 4") => Ok(DynVal::from(16)),
+        shift_right("255 >> 4") => Ok(DynVal::from(15)),
+        bitwise_precedence("1 + 1 << 2 | 1 == 9") => Ok(DynVal::from(true)),
+        shift_negative_amount("1 << -1") => Err(super::EvalError::InvalidShiftAmount(-1)),
+        hex_padded("hex(255, 4)") => Ok(DynVal::from("00ff")),
+        hex_color("hex(16711680 | 65280 >> 8, 6)") => Ok(DynVal::from("ff00ff")),
+        to_fixed(r#"to_fixed(2, 2)"#) => Ok(DynVal::from("2.00")),
     }
 }
@@ -13,17 +13,13 @@ pub fn parse_stringlit(
     mut segs: Vec<Sp<StrLitSegment>>,
 ) -> Result<SimplExpr, lalrpop_util::ParseError<usize, Token, LexicalError>> {
     let file_id = span.2;
-    let parser = crate::simplexpr_parser::ExprParser::new();
 
     if segs.len() == 1 {
         let (lo, seg, hi) = segs.remove(0);
         let span = Span(lo, hi, file_id);
         match seg {
             StrLitSegment::Literal(lit) => Ok(SimplExpr::literal(span, lit)),
-            StrLitSegment::Interp(toks) => {
-                let token_stream = toks.into_iter().map(Ok);
-                parser.parse(file_id, token_stream)
-            }
+            StrLitSegment::Interp(toks) => parse_interpolation(file_id, toks, hi),
         }
     } else {
         let elems = segs
@@ -33,13 +29,26 @@ pub fn parse_stringlit(
                 match segment {
                     StrLitSegment::Literal(lit) if lit.is_empty() => None,
                     StrLitSegment::Literal(lit) => Some(Ok(SimplExpr::literal(span, lit))),
-                    StrLitSegment::Interp(toks) => {
-                        let token_stream = toks.into_iter().map(Ok);
-                        Some(parser.parse(file_id, token_stream))
-                    }
+                    StrLitSegment::Interp(toks) => Some(parse_interpolation(file_id, toks, hi)),
                 }
             })
             .collect::<Result<Vec<SimplExpr>, _>>()?;
         Ok(SimplExpr::Concat(span, elems))
     }
 }
+
+/// Parse the tokens of a `${...}` interpolation, which ends with the closing `}` at `end`.
+fn parse_interpolation(
+    file_id: usize,
+    toks: Vec<Sp<Token>>,
+    end: usize,
+) -> Result<SimplExpr, lalrpop_util::ParseError<usize, Token, LexicalError>> {
+    let parser = crate::simplexpr_parser::ExprParser::new();
+    parser.parse(file_id, toks.into_iter().map(Ok)).map_err(|err| match err {
+        // the input of the parser ends at the closing `}`, and not at the end of the file as the error would suggest
+        lalrpop_util::ParseError::UnrecognizedEof { expected, .. } => {
+            lalrpop_util::ParseError::UnrecognizedToken { token: (end, Token::RCurl, end + 1), expected }
+        }
+        other => other,
+    })
+}
@@ -28,6 +28,11 @@ pub enum Token {
     Elvis,
     SafeAccess,
     RegexMatch,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
 
     Not,
     Negative,
@@ -82,6 +87,8 @@ regex_rules! {
     r"!="    => |_| Token::NotEquals,
     r"&&"    => |_| Token::And,
     r"\|\|"    => |_| Token::Or,
+    r"<<"    => |_| Token::ShiftLeft,
+    r">>"    => |_| Token::ShiftRight,
     r">="    => |_| Token::GE,
     r"<="    => |_| Token::LE,
     r">"     => |_| Token::GT,
@@ -89,6 +96,9 @@ regex_rules! {
     r"\?:"    => |_| Token::Elvis,
     r"\?\."    => |_| Token::SafeAccess,
     r"=~"    => |_| Token::RegexMatch,
+    r"&"     => |_| Token::BitAnd,
+    r"\|"     => |_| Token::BitOr,
+    r"\^"     => |_| Token::BitXor,
 
     r"!"     => |_| Token::Not,
     r"-"     => |_| Token::Negative,
@@ -11,6 +11,8 @@ pub fn parse_string(byte_offset: usize, file_id: usize, s: &str) -> Result<Simpl
 
 #[cfg(test)]
 mod tests {
+    use eww_shared_util::{Span, Spanned};
+
     macro_rules! test_parser {
         ($($text:literal),* $(,)?) => {{
             let p = crate::simplexpr_parser::ExprParser::new();
@@ -23,6 +25,15 @@ mod tests {
         }}
     }
 
+    #[test]
+    fn test_incomplete_interpolation_span() {
+        for source in [r#""foo ${} bar""#, r#""foo ${1 +} bar""#, r#""${(1 + 2}""#] {
+            let err = super::parse_string(10, 0, source).unwrap_err();
+            let closing_curl = 10 + source.rfind('}').unwrap();
+            assert_eq!(err.span(), Span(closing_curl, closing_curl + 1, 0), "{}", source);
+        }
+    }
+
     #[test]
     fn test() {
         test_parser!(
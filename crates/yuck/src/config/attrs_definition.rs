@@ -0,0 +1,34 @@
+use crate::{
+    error::{DiagError, DiagResult},
+    gen_diagnostic,
+    parser::{ast::Ast, ast_iterator::AstIterator, from_ast::FromAstElementContent},
+};
+use eww_shared_util::Span;
+
+use super::attributes::Attributes;
+
+/// A named set of attributes, created from `defattrs`, which can be spread onto widgets via `:@name`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct AttrsDefinition {
+    pub name: String,
+    pub attrs: Attributes,
+    pub span: Span,
+    pub name_span: Span,
+}
+
+impl FromAstElementContent for AttrsDefinition {
+    const ELEMENT_NAME: &'static str = "defattrs";
+
+    fn from_tail<I: Iterator<Item = Ast>>(span: Span, mut iter: AstIterator<I>) -> DiagResult<Self> {
+        let (name_span, name) = iter.expect_symbol().map_err(DiagError::from)?;
+        let attrs = iter.expect_key_values()?;
+        iter.expect_done()?;
+        if let Some(entry) = attrs.attrs.keys().find(|key| key.0.starts_with('@')).and_then(|key| attrs.attrs.get(key)) {
+            return Err(DiagError(gen_diagnostic! {
+                msg = "defattrs can't include other attribute sets",
+                label = entry.key_span,
+            }));
+        }
+        Ok(Self { name, attrs, span, name_span })
+    }
+}
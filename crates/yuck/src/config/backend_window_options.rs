@@ -53,6 +53,11 @@ impl BackendWindowOptionsDef {
             struts,
             window_type,
             wm_ignore: attrs.ast_optional("wm-ignore")?,
+            skip_taskbar: attrs.ast_optional("skip-taskbar")?,
+            skip_pager: attrs.ast_optional("skip-pager")?,
+            demands_attention: attrs.ast_optional("demands-attention")?,
+            above: attrs.ast_optional("above")?,
+            below: attrs.ast_optional("below")?,
         };
         let wayland = WlBackendWindowOptionsDef {
             exclusive: attrs.ast_optional("exclusive")?,
@@ -77,6 +82,13 @@ pub struct X11BackendWindowOptions {
     pub sticky: bool,
     pub window_type: X11WindowType,
     pub struts: X11StrutDefinition,
+    pub skip_taskbar: bool,
+    pub skip_pager: bool,
+    pub demands_attention: bool,
+    /// Whether the window is kept above other windows. Derived from the `stacking` of the window if not given.
+    pub above: Option<bool>,
+    /// Whether the window is kept below other windows. Derived from the `stacking` of the window if not given.
+    pub below: Option<bool>,
 }
 
 /// Unevaluated form of [`X11BackendWindowOptions`]
@@ -86,6 +98,11 @@ pub struct X11BackendWindowOptionsDef {
     pub struts: Option<X11StrutDefinitionExpr>,
     pub window_type: Option<SimplExpr>,
     pub wm_ignore: Option<SimplExpr>,
+    pub skip_taskbar: Option<SimplExpr>,
+    pub skip_pager: Option<SimplExpr>,
+    pub demands_attention: Option<SimplExpr>,
+    pub above: Option<SimplExpr>,
+    pub below: Option<SimplExpr>,
 }
 
 impl X11BackendWindowOptionsDef {
@@ -105,13 +122,18 @@ impl X11BackendWindowOptionsDef {
                 self.window_type.is_none() && self.struts.is_none(),
                 local_variables,
             )?,
+            skip_taskbar: eval_opt_expr_as_bool(&self.skip_taskbar, true, local_variables)?,
+            skip_pager: eval_opt_expr_as_bool(&self.skip_pager, true, local_variables)?,
+            demands_attention: eval_opt_expr_as_bool(&self.demands_attention, false, local_variables)?,
+            above: eval_opt_expr_as_opt_bool(&self.above, local_variables)?,
+            below: eval_opt_expr_as_opt_bool(&self.below, local_variables)?,
         })
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
 pub struct WlBackendWindowOptions {
-    pub exclusive: bool,
+    pub exclusive: WlExclusiveZone,
     pub focusable: WlWindowFocusable,
     pub namespace: Option<String>,
 }
@@ -127,7 +149,10 @@ pub struct WlBackendWindowOptionsDef {
 impl WlBackendWindowOptionsDef {
     fn eval(&self, local_variables: &HashMap<VarName, DynVal>) -> Result<WlBackendWindowOptions, Error> {
         Ok(WlBackendWindowOptions {
-            exclusive: eval_opt_expr_as_bool(&self.exclusive, false, local_variables)?,
+            exclusive: match &self.exclusive {
+                Some(expr) => WlExclusiveZone::from_dynval(&expr.eval(local_variables)?)?,
+                None => WlExclusiveZone::default(),
+            },
             focusable: match &self.focusable {
                 Some(expr) => WlWindowFocusable::from_dynval(&expr.eval(local_variables)?)?,
                 None => WlWindowFocusable::default(),
@@ -151,6 +176,16 @@ fn eval_opt_expr_as_bool(
     })
 }
 
+fn eval_opt_expr_as_opt_bool(
+    opt_expr: &Option<SimplExpr>,
+    local_variables: &HashMap<VarName, DynVal>,
+) -> Result<Option<bool>, EvalError> {
+    Ok(match opt_expr {
+        Some(expr) => Some(expr.eval(local_variables)?.as_bool()?),
+        None => None,
+    })
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, smart_default::SmartDefault, serde::Serialize)]
 pub enum WlWindowFocusable {
     #[default]
@@ -173,6 +208,49 @@ impl FromStr for WlWindowFocusable {
     }
 }
 
+/// Space a wayland window reserves on the edge it is anchored to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, smart_default::SmartDefault, serde::Serialize)]
+pub enum WlExclusiveZone {
+    /// Don't reserve any space, but respect the space reserved by other surfaces
+    #[default]
+    None,
+    /// Reserve exactly as much space as the window takes up
+    Auto,
+    /// Reserve the given amount of pixels, independent of the size of the window
+    Pixels(i32),
+    /// Don't reserve any space, and extend into the space reserved by other surfaces
+    Ignore,
+}
+
+impl WlExclusiveZone {
+    /// Whether the window makes other windows keep their distance from it.
+    pub fn reserves_space(&self) -> bool {
+        match self {
+            WlExclusiveZone::Auto => true,
+            WlExclusiveZone::Pixels(pixels) => *pixels > 0,
+            WlExclusiveZone::None | WlExclusiveZone::Ignore => false,
+        }
+    }
+}
+
+impl FromStr for WlExclusiveZone {
+    type Err = EnumParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(pixels) = s.trim().trim_end_matches("px").parse() {
+            return Ok(Self::Pixels(pixels));
+        }
+        enum_parse! { "exclusive zone", s,
+            "auto" => Self::Auto,
+            "ignore" => Self::Ignore,
+            "none" => Self::None,
+            // legacy support
+            "true" => Self::Auto,
+            "false" => Self::None,
+        }
+    }
+}
+
 /// Window type of an x11 window
 #[derive(Debug, Clone, PartialEq, Eq, smart_default::SmartDefault, serde::Serialize)]
 pub enum X11WindowType {
@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+use simplexpr::dynval::DynVal;
+
+use crate::{
+    error::{DiagError, DiagResult, DiagResultExt},
+    format_diagnostic::ToDiagnostic,
+    parser::{ast::Ast, ast_iterator::AstIterator, from_ast::FromAstElementContent},
+};
+use eww_shared_util::Span;
+
+/// Global options for the eww daemon, declared via `defconfig`.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Serialize)]
+pub struct ConfigOptions {
+    /// Command to run before the configuration gets (re)loaded
+    pub pre_reload: Option<String>,
+    /// Command to run after the configuration got (re)loaded
+    pub post_reload: Option<String>,
+    /// Icon theme used by eww, overriding the system setting
+    pub icon_theme: Option<String>,
+    /// Default font used by eww, overriding the system setting, i.e. `Inter 11`
+    pub font_name: Option<String>,
+    /// Whether eww should use the dark variant of the GTK theme, overriding the system setting
+    pub prefer_dark_theme: Option<bool>,
+    /// Whether to show errors in a window on screen, rather than only in the logs
+    pub error_overlay: bool,
+    /// Shell that commands from widgets are run with, instead of `/bin/sh`
+    pub shell: Option<String>,
+    /// Whether windows fail to open when evaluating an expression fails, rather than showing an empty value
+    pub strict: bool,
+    /// Names of built-in magic variables that are never computed
+    pub disabled_magic_vars: Vec<String>,
+    /// Number of processes listed in `EWW_TOP`
+    pub top_processes: usize,
+    /// Refresh interval of `EWW_TOP`
+    pub top_interval: Duration,
+    /// Where `EWW_BATTERY` gets its data from, either "sysfs" or "upower"
+    pub battery_source: String,
+    pub span: Span,
+}
+
+impl Default for ConfigOptions {
+    fn default() -> Self {
+        Self {
+            pre_reload: None,
+            post_reload: None,
+            icon_theme: None,
+            font_name: None,
+            prefer_dark_theme: None,
+            error_overlay: false,
+            shell: None,
+            strict: false,
+            disabled_magic_vars: Vec::new(),
+            top_processes: 10,
+            top_interval: Duration::from_secs(2),
+            battery_source: "sysfs".to_string(),
+            span: Span::DUMMY,
+        }
+    }
+}
+
+impl FromAstElementContent for ConfigOptions {
+    const ELEMENT_NAME: &'static str = "defconfig";
+
+    fn from_tail<I: Iterator<Item = Ast>>(span: Span, mut iter: AstIterator<I>) -> DiagResult<Self> {
+        let result: DiagResult<_> = (move || {
+            let mut attrs = iter.expect_key_values()?;
+            let pre_reload = attrs.primitive_optional("pre-reload")?;
+            let post_reload = attrs.primitive_optional("post-reload")?;
+            let icon_theme = attrs.primitive_optional("icon-theme")?;
+            let font_name = attrs.primitive_optional("font-name")?;
+            let prefer_dark_theme = attrs.primitive_optional("prefer-dark-theme")?;
+            let error_overlay = attrs.primitive_optional("error-overlay")?.unwrap_or(false);
+            let shell = attrs.primitive_optional("shell")?;
+            let strict = attrs.primitive_optional("strict")?.unwrap_or(false);
+            let disabled_magic_vars = match attrs.primitive_optional::<DynVal, _>("disable-magic-vars")? {
+                Some(value) => value
+                    .as_json_array()
+                    .map_err(|e| DiagError(e.to_diagnostic()))?
+                    .into_iter()
+                    .map(|name| name.as_str().map(|name| name.to_string()).unwrap_or_else(|| name.to_string()))
+                    .collect(),
+                None => Vec::new(),
+            };
+            let top_processes = attrs.primitive_optional("top-processes")?.unwrap_or(10);
+            let top_interval = match attrs.primitive_optional::<DynVal, _>("top-interval")? {
+                Some(value) => value.as_duration().map_err(|e| DiagError(e.to_diagnostic()))?,
+                None => Duration::from_secs(2),
+            };
+            let battery_source = attrs.primitive_optional("battery-source")?.unwrap_or_else(|| "sysfs".to_string());
+            iter.expect_done()?;
+            Ok(Self {
+                pre_reload,
+                post_reload,
+                icon_theme,
+                font_name,
+                prefer_dark_theme,
+                error_overlay,
+                shell,
+                strict,
+                disabled_magic_vars,
+                top_processes,
+                top_interval,
+                battery_source,
+                span,
+            })
+        })();
+        result.note(r#"Expected format: `(defconfig :pre-reload "./generate.sh" :post-reload "notify-send reloaded")`"#)
+    }
+}
@@ -1,6 +1,13 @@
+use std::path::{Path, PathBuf};
+
 use eww_shared_util::Span;
 
-use crate::{error::DiagError, parser::ast::Ast};
+use crate::{
+    error::{DiagError, DiagResult},
+    format_diagnostic::{span_to_secondary_label, DiagnosticExt},
+    gen_diagnostic,
+    parser::ast::Ast,
+};
 
 #[derive(thiserror::Error, Debug)]
 pub enum FilesError {
@@ -15,4 +22,56 @@ pub trait YuckFileProvider {
     fn load_yuck_file(&mut self, path: std::path::PathBuf) -> Result<(Span, Vec<Ast>), FilesError>;
     fn load_yuck_str(&mut self, name: String, content: String) -> Result<(Span, Vec<Ast>), DiagError>;
     fn unload(&mut self, id: usize);
+
+    /// List the entries of a directory. Used to resolve directories and glob patterns in `include`s.
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<PathBuf>> {
+        std::fs::read_dir(path)?.map(|entry| Ok(entry?.path())).collect()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    /// Normalize a path, such that different paths to the same file can be recognized as such.
+    fn canonicalize(&self, path: &Path) -> PathBuf {
+        std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+    }
+}
+
+/// The files that are currently being loaded while generating a config, each one included from the one before it.
+/// Used to detect files that (transitively) include themselves.
+#[derive(Debug, Default)]
+pub struct IncludeChain {
+    files: Vec<(PathBuf, Option<Span>)>,
+}
+
+impl IncludeChain {
+    pub fn new(main_file: PathBuf) -> Self {
+        Self { files: vec![(main_file, None)] }
+    }
+
+    /// Start loading a file included at the given span.
+    /// Fails if that file is already being loaded further up the chain, as it would end up including itself.
+    pub fn enter(&mut self, path: PathBuf, included_at: Span) -> DiagResult<()> {
+        if let Some(position) = self.files.iter().position(|(file, _)| *file == path) {
+            let chain = self.files[position..].iter().map(|(file, _)| file.as_path()).chain(std::iter::once(path.as_path()));
+            let chain = chain.map(|file| file.display().to_string()).collect::<Vec<_>>().join(" -> ");
+            let mut diag = gen_diagnostic! {
+                msg = format!("`{}` includes itself", path.display()),
+                label = included_at => "Included again here",
+                note = format!("Include chain: {}", chain),
+            };
+            for span in self.files[position + 1..].iter().filter_map(|(_, span)| *span) {
+                diag = diag.with_label(span_to_secondary_label(span).with_message("Included from here"));
+            }
+            return Err(DiagError(diag));
+        }
+        self.files.push((path, Some(included_at)));
+        Ok(())
+    }
+
+    /// Finish loading the most recently entered file.
+    pub fn leave(&mut self) {
+        self.files.pop();
+    }
 }
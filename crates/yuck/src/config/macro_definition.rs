@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+
+use simplexpr::SimplExpr;
+
+use crate::{
+    error::{DiagError, DiagResult},
+    gen_diagnostic,
+    parser::{ast::Ast, ast_iterator::AstIterator, from_ast::FromAstElementContent},
+};
+use eww_shared_util::{Span, Spanned};
+
+/// How deeply macros may expand into further macro uses, to catch macros that (indirectly) use themselves.
+const MAX_EXPANSION_DEPTH: usize = 64;
+
+/// A macro, created from `defmacro`, which is expanded into the forms of its body wherever it is used.
+/// Unlike a `defwidget`, this happens while loading the configuration, so a macro can produce multiple forms,
+/// including top level definitions, and use its parameters as part of symbol and attribute names via `$name`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct MacroDefinition {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Vec<Ast>,
+    pub span: Span,
+    pub name_span: Span,
+}
+
+impl FromAstElementContent for MacroDefinition {
+    const ELEMENT_NAME: &'static str = "defmacro";
+
+    fn from_tail<I: Iterator<Item = Ast>>(span: Span, mut iter: AstIterator<I>) -> DiagResult<Self> {
+        let (name_span, name) = iter.expect_symbol().map_err(DiagError::from)?;
+        let (_, params) = iter.expect_array().map_err(|_| {
+            DiagError(gen_diagnostic! {
+                msg = "Macro definition missing parameter list",
+                label = name_span.point_span_at_end() => "Insert the parameter list (e.g.: `[]`) here",
+            })
+        })?;
+        let params = params.into_iter().map(|param| param.as_symbol().map_err(DiagError::from)).collect::<DiagResult<_>>()?;
+        let body: Vec<Ast> = iter.collect();
+        if body.is_empty() {
+            return Err(DiagError(gen_diagnostic! {
+                msg = format!("Macro {} does not expand to anything", name),
+                label = span,
+            }));
+        }
+        Ok(Self { name, params, body, span, name_span })
+    }
+}
+
+impl MacroDefinition {
+    /// The forms a use of this macro with the given arguments expands to.
+    fn expand(&self, use_span: Span, args: Vec<Ast>) -> DiagResult<Vec<Ast>> {
+        if args.len() != self.params.len() {
+            return Err(DiagError(gen_diagnostic! {
+                msg = format!("Macro {} expects {} arguments, but got {}", self.name, self.params.len(), args.len()),
+                label = use_span,
+            }));
+        }
+        let mut bindings: Vec<(&str, Ast)> = self.params.iter().map(String::as_str).zip(args).collect();
+        // substitute longer names first, such that `$n` doesn't replace the start of `$name`
+        bindings.sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
+        self.body.iter().map(|ast| substitute(ast.clone(), &bindings)).collect()
+    }
+}
+
+/// Replace the parameters of a macro within the given ast by the arguments they are bound to.
+fn substitute(ast: Ast, bindings: &[(&str, Ast)]) -> DiagResult<Ast> {
+    Ok(match ast {
+        Ast::Symbol(span, name) => match bindings.iter().find(|(param, _)| *param == name) {
+            Some((_, arg)) => arg.clone(),
+            None => Ast::Symbol(span, substitute_in_name(span, name, bindings)?),
+        },
+        Ast::Keyword(span, name) => Ast::Keyword(span, substitute_in_name(span, name, bindings)?),
+        Ast::SimplExpr(span, expr) => Ast::SimplExpr(
+            span,
+            expr.try_map_var_refs(|var_span, var_name| match bindings.iter().find(|(param, _)| *param == var_name.0) {
+                Some((_, arg)) => arg.as_simplexpr().map_err(|_| {
+                    DiagError(gen_diagnostic! {
+                        msg = format!("Argument {} can't be used within an expression", var_name),
+                        label = arg.span() => "Given here",
+                    })
+                }),
+                None => Ok(SimplExpr::VarRef(var_span, var_name)),
+            })?,
+        ),
+        Ast::List(span, items) => Ast::List(span, items.into_iter().map(|x| substitute(x, bindings)).collect::<DiagResult<_>>()?),
+        Ast::Array(span, items) => {
+            Ast::Array(span, items.into_iter().map(|x| substitute(x, bindings)).collect::<DiagResult<_>>()?)
+        }
+        comment @ Ast::Comment(_) => comment,
+    })
+}
+
+/// Replace `$param` within the name of a symbol or keyword by the text of the argument bound to `param`.
+fn substitute_in_name(span: Span, mut name: String, bindings: &[(&str, Ast)]) -> DiagResult<String> {
+    for (param, arg) in bindings {
+        let placeholder = format!("${}", param);
+        if !name.contains(&placeholder) {
+            continue;
+        }
+        let text = match arg {
+            Ast::Symbol(_, text) => text.clone(),
+            Ast::SimplExpr(_, SimplExpr::Literal(value)) => value.0.clone(),
+            _ => {
+                return Err(DiagError(gen_diagnostic! {
+                    msg = format!("Argument {} can't be used as part of a name", param),
+                    label = span,
+                    note = "Only symbols and literal values can be used in names",
+                }))
+            }
+        };
+        name = name.replace(&placeholder, &text);
+    }
+    Ok(name)
+}
+
+/// Expand all uses of the given macros within the ast, returning the forms it expands to.
+/// Forms that are not themselves a use of a macro always expand to exactly one form.
+pub fn expand_macros(macros: &HashMap<String, MacroDefinition>, ast: Ast) -> DiagResult<Vec<Ast>> {
+    expand_macros_at_depth(macros, ast, 0)
+}
+
+fn expand_macros_at_depth(macros: &HashMap<String, MacroDefinition>, ast: Ast, depth: usize) -> DiagResult<Vec<Ast>> {
+    let expand_all = |items: Vec<Ast>| -> DiagResult<Vec<Ast>> {
+        Ok(items.into_iter().map(|x| expand_macros_at_depth(macros, x, depth)).collect::<DiagResult<Vec<_>>>()?.concat())
+    };
+    match ast {
+        Ast::List(span, mut items) => {
+            let used_macro = match items.first() {
+                Some(Ast::Symbol(_, name)) => macros.get(name),
+                _ => None,
+            };
+            let Some(used_macro) = used_macro else { return Ok(vec![Ast::List(span, expand_all(items)?)]) };
+            if depth >= MAX_EXPANSION_DEPTH {
+                return Err(DiagError(gen_diagnostic! {
+                    msg = format!("Macro {} expands into itself", used_macro.name),
+                    label = span,
+                    note = format!("Macros may only be nested {} levels deep", MAX_EXPANSION_DEPTH),
+                }));
+            }
+            let args = items.split_off(1);
+            let forms = used_macro.expand(span, args)?;
+            Ok(forms.into_iter().map(|x| expand_macros_at_depth(macros, x, depth + 1)).collect::<DiagResult<Vec<_>>>()?.concat())
+        }
+        Ast::Array(span, items) => Ok(vec![Ast::Array(span, expand_all(items)?)]),
+        other => Ok(vec![other]),
+    }
+}
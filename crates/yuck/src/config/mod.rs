@@ -1,6 +1,9 @@
 pub mod attributes;
+pub mod attrs_definition;
 pub mod backend_window_options;
+pub mod config_options;
 pub mod file_provider;
+pub mod macro_definition;
 pub mod monitor;
 pub mod script_var_definition;
 pub mod toplevel;
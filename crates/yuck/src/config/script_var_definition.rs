@@ -1,16 +1,28 @@
 use simplexpr::{dynval::DynVal, SimplExpr};
 
+use std::str::FromStr;
+
 use crate::{
+    config::{
+        attributes::{AttrError, Attributes},
+        var_definition::VarType,
+        window_definition::EnumParseError,
+    },
+    enum_parse,
     error::{DiagError, DiagResult, DiagResultExt},
     format_diagnostic::ToDiagnostic,
+    gen_diagnostic,
     parser::{ast::Ast, ast_iterator::AstIterator, from_ast::FromAstElementContent},
 };
-use eww_shared_util::{Span, VarName};
+use eww_shared_util::{AttrName, Span, Spanned, VarName};
 
 #[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+#[allow(clippy::large_enum_variant)]
 pub enum ScriptVarDefinition {
     Poll(PollScriptVar),
     Listen(ListenScriptVar),
+    DBus(DBusListenVar),
+    Watch(FileWatchVar),
 }
 
 impl ScriptVarDefinition {
@@ -18,6 +30,8 @@ impl ScriptVarDefinition {
         match self {
             ScriptVarDefinition::Poll(x) => x.name_span,
             ScriptVarDefinition::Listen(x) => x.name_span,
+            ScriptVarDefinition::DBus(x) => x.name_span,
+            ScriptVarDefinition::Watch(x) => x.name_span,
         }
     }
 
@@ -25,16 +39,30 @@ impl ScriptVarDefinition {
         match self {
             ScriptVarDefinition::Poll(x) => &x.name,
             ScriptVarDefinition::Listen(x) => &x.name,
+            ScriptVarDefinition::DBus(x) => &x.name,
+            ScriptVarDefinition::Watch(x) => &x.name,
+        }
+    }
+
+    /// Command that is run whenever the value of the variable changes
+    pub fn onchange(&self) -> Option<&str> {
+        match self {
+            ScriptVarDefinition::Poll(x) => x.onchange.as_deref(),
+            ScriptVarDefinition::Listen(x) => x.onchange.as_deref(),
+            ScriptVarDefinition::DBus(x) => x.onchange.as_deref(),
+            ScriptVarDefinition::Watch(x) => x.onchange.as_deref(),
         }
     }
 
     pub fn command_span(&self) -> Option<Span> {
         match self {
             ScriptVarDefinition::Poll(x) => match x.command {
-                VarSource::Shell(span, ..) => Some(span),
+                VarSource::Shell(span, ..) | VarSource::Http(span, ..) => Some(span),
                 VarSource::Function(_) => None,
             },
             ScriptVarDefinition::Listen(x) => Some(x.command_span),
+            ScriptVarDefinition::DBus(_) => None,
+            ScriptVarDefinition::Watch(x) => Some(x.path_span),
         }
     }
 }
@@ -43,10 +71,67 @@ impl ScriptVarDefinition {
 pub enum VarSource {
     // TODO allow for other executors? (python, etc)
     Shell(Span, String),
+    /// Fetch the value with an HTTP GET request, given by the `:url` attribute of a defpoll.
+    Http(Span, HttpRequest),
     #[serde(skip)]
     Function(fn() -> Result<DynVal, Box<dyn std::error::Error + Sync + Send + 'static>>),
 }
 
+/// HTTP GET request that a poll var performs instead of running a script.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct HttpRequest {
+    pub url: String,
+    /// Additional headers sent with the request, given by the `:headers` attribute as a JSON object.
+    pub headers: Vec<(String, String)>,
+}
+
+impl HttpRequest {
+    fn from_attrs(attrs: &mut Attributes) -> DiagResult<Option<(Span, Self)>> {
+        let Some(url_expr) = attrs.ast_optional::<SimplExpr>("url")? else {
+            return Ok(None);
+        };
+        let url_span = url_expr.span();
+        let url = url_expr
+            .eval_no_vars()
+            .map_err(|e| DiagError(e.to_diagnostic()))?
+            .as_string()
+            .map_err(|e| DiagError(e.to_diagnostic()))?;
+        let headers = match attrs.primitive_optional::<DynVal, _>("headers")? {
+            Some(headers) => headers
+                .as_json_object()
+                .map_err(|e| DiagError(e.to_diagnostic()))?
+                .into_iter()
+                .map(|(name, value)| (name, value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string())))
+                .collect(),
+            None => Vec::new(),
+        };
+        Ok(Some((url_span, Self { url, headers })))
+    }
+}
+
+/// Limits how often the value of a script var gets updated, given by the `:debounce` and `:throttle` attributes.
+/// In both cases, the last value wins once the variable is allowed to update again.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub struct UpdateRateLimit {
+    /// Only update once no new value was produced for this duration.
+    pub debounce: Option<std::time::Duration>,
+    /// Update at most once within this duration.
+    pub throttle: Option<std::time::Duration>,
+}
+
+impl UpdateRateLimit {
+    fn from_attrs(attrs: &mut Attributes) -> DiagResult<Self> {
+        let mut duration_attr = |name: &str| -> DiagResult<_> {
+            attrs
+                .primitive_optional::<DynVal, _>(name)?
+                .map(|x| x.as_duration())
+                .transpose()
+                .map_err(|e| DiagError(e.to_diagnostic()))
+        };
+        Ok(Self { debounce: duration_attr("debounce")?, throttle: duration_attr("throttle")? })
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
 pub struct PollScriptVar {
     pub name: VarName,
@@ -54,6 +139,10 @@ pub struct PollScriptVar {
     pub command: VarSource,
     pub initial_value: Option<DynVal>,
     pub interval: std::time::Duration,
+    pub rate_limit: UpdateRateLimit,
+    pub onchange: Option<String>,
+    /// The type of values the variable may hold
+    pub var_type: Option<VarType>,
     pub name_span: Span,
 }
 
@@ -64,10 +153,23 @@ impl FromAstElementContent for PollScriptVar {
         let result: DiagResult<_> = (move || {
             let (name_span, name) = iter.expect_symbol()?;
             let mut attrs = iter.expect_key_values()?;
-            let initial_value = Some(attrs.primitive_optional("initial")?.unwrap_or_else(|| DynVal::from_string(String::new())));
+            let initial_value: Option<DynVal> = attrs.primitive_optional("initial")?;
+            let var_type: Option<VarType> = attrs.primitive_optional("type")?;
+            if let (Some(var_type), Some(initial_value)) = (var_type, &initial_value) {
+                var_type.check_initial_value(initial_value)?;
+            }
+            let initial_value = Some(initial_value.unwrap_or_else(|| DynVal::from_string(String::new())));
             let interval =
                 attrs.primitive_required::<DynVal, _>("interval")?.as_duration().map_err(|e| DiagError(e.to_diagnostic()))?;
-            let (script_span, script) = iter.expect_literal()?;
+            let rate_limit = UpdateRateLimit::from_attrs(&mut attrs)?;
+            let onchange = attrs.primitive_optional("onchange")?;
+            let command = match HttpRequest::from_attrs(&mut attrs)? {
+                Some((url_span, request)) => VarSource::Http(url_span, request),
+                None => {
+                    let (script_span, script) = iter.expect_literal()?;
+                    VarSource::Shell(script_span, script.to_string())
+                }
+            };
 
             let run_while_expr =
                 attrs.ast_optional::<SimplExpr>("run-while")?.unwrap_or_else(|| SimplExpr::Literal(DynVal::from(true)));
@@ -77,20 +179,131 @@ impl FromAstElementContent for PollScriptVar {
                 name_span,
                 name: VarName(name),
                 run_while_expr,
-                command: VarSource::Shell(script_span, script.to_string()),
+                command,
                 initial_value,
                 interval,
+                rate_limit,
+                onchange,
+                var_type,
             })
         })();
-        result.note(r#"Expected format: `(defpoll name :interval "10s" "echo 'a shell script'")`"#)
+        result.note(
+            r#"Expected format: `(defpoll name :interval "10s" "echo 'a shell script'")` or `(defpoll name :interval "10m" :url "https://example.com")`"#,
+        )
+    }
+}
+
+/// What to do once the script of a listen var exits, given by the `:on-exit` attribute.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub enum ListenExitBehavior {
+    /// Keep the last value the script produced.
+    #[default]
+    Keep,
+    /// Reset the variable to its initial value.
+    Clear,
+    /// Run the script again, waiting longer after each consecutive failure.
+    Restart,
+}
+
+impl FromStr for ListenExitBehavior {
+    type Err = EnumParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        enum_parse! { "on-exit behavior", s,
+            "keep" => Self::Keep,
+            "clear" => Self::Clear,
+            "restart" => Self::Restart,
+        }
+    }
+}
+
+/// How a listen var reacts to its script exiting, given by the `:on-exit`, `:max-restarts` and `:restart-delay` attributes.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct ListenRestartPolicy {
+    pub on_exit: ListenExitBehavior,
+    /// How often the script may be restarted in a row before giving up. `None` means there is no limit.
+    pub max_restarts: Option<u32>,
+    /// Time to wait before the first restart. This doubles with every consecutive restart.
+    pub restart_delay: std::time::Duration,
+}
+
+impl Default for ListenRestartPolicy {
+    fn default() -> Self {
+        Self { on_exit: ListenExitBehavior::default(), max_restarts: None, restart_delay: std::time::Duration::from_secs(1) }
+    }
+}
+
+impl ListenRestartPolicy {
+    fn from_attrs(attrs: &mut Attributes) -> DiagResult<Self> {
+        let on_exit = attrs.primitive_optional("on-exit")?.unwrap_or_default();
+        let max_restarts = attrs.primitive_optional("max-restarts")?;
+        let restart_delay = attrs
+            .primitive_optional::<DynVal, _>("restart-delay")?
+            .map(|x| x.as_duration())
+            .transpose()
+            .map_err(|e| DiagError(e.to_diagnostic()))?
+            .unwrap_or_else(|| Self::default().restart_delay);
+        Ok(Self { on_exit, max_restarts, restart_delay })
+    }
+}
+
+/// Where a listen var reads its lines from.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub enum ListenSource {
+    /// Run a shell command, reading its output.
+    Command(String),
+    /// Connect to an existing unix socket, given by `:source "socket:/path"`.
+    Socket(std::path::PathBuf),
+    /// Read from a named pipe, given by `:source "fifo:/path"`.
+    Fifo(std::path::PathBuf),
+    /// Lines produced by eww itself for the built-in magic variable of the given name. Can't be used in configurations.
+    Inbuilt(String),
+}
+
+impl ListenSource {
+    fn from_attrs(attrs: &mut Attributes) -> DiagResult<Option<(Span, Self)>> {
+        let Some(source_expr) = attrs.ast_optional::<SimplExpr>("source")? else {
+            return Ok(None);
+        };
+        let source_span = source_expr.span();
+        let source = source_expr
+            .eval_no_vars()
+            .map_err(|e| DiagError(e.to_diagnostic()))?
+            .as_string()
+            .map_err(|e| DiagError(e.to_diagnostic()))?;
+        if let Some(path) = source.strip_prefix("socket:") {
+            Ok(Some((source_span, Self::Socket(path.into()))))
+        } else if let Some(path) = source.strip_prefix("fifo:") {
+            Ok(Some((source_span, Self::Fifo(path.into()))))
+        } else {
+            Err(DiagError(gen_diagnostic! {
+                msg = format!("Invalid listen source `{}`", source),
+                label = source_span,
+                note = "Expected `socket:/path/to/socket` or `fifo:/path/to/fifo`",
+            }))
+        }
+    }
+}
+
+impl std::fmt::Display for ListenSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ListenSource::Command(command) => write!(f, "{}", command),
+            ListenSource::Socket(path) => write!(f, "socket:{}", path.display()),
+            ListenSource::Fifo(path) => write!(f, "fifo:{}", path.display()),
+            ListenSource::Inbuilt(name) => write!(f, "inbuilt:{}", name),
+        }
     }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
 pub struct ListenScriptVar {
     pub name: VarName,
-    pub command: String,
+    pub source: ListenSource,
     pub initial_value: DynVal,
+    pub rate_limit: UpdateRateLimit,
+    pub restart_policy: ListenRestartPolicy,
+    pub onchange: Option<String>,
     pub command_span: Span,
     pub name_span: Span,
 }
@@ -102,10 +315,158 @@ impl FromAstElementContent for ListenScriptVar {
             let (name_span, name) = iter.expect_symbol()?;
             let mut attrs = iter.expect_key_values()?;
             let initial_value = attrs.primitive_optional("initial")?.unwrap_or_else(|| DynVal::from_string(String::new()));
-            let (command_span, script) = iter.expect_literal()?;
+            let rate_limit = UpdateRateLimit::from_attrs(&mut attrs)?;
+            let restart_policy = ListenRestartPolicy::from_attrs(&mut attrs)?;
+            let onchange = attrs.primitive_optional("onchange")?;
+            let (command_span, source) = match ListenSource::from_attrs(&mut attrs)? {
+                Some(source) => source,
+                None => {
+                    let (command_span, script) = iter.expect_literal()?;
+                    (command_span, ListenSource::Command(script.to_string()))
+                }
+            };
             iter.expect_done()?;
-            Ok(Self { name_span, name: VarName(name), command: script.to_string(), initial_value, command_span })
+            Ok(Self { name_span, name: VarName(name), source, initial_value, rate_limit, restart_policy, onchange, command_span })
+        })();
+        result.note(
+            r#"Expected format: `(deflisten name :initial "0" "tail -f /tmp/example")` or `(deflisten name :source "socket:/tmp/example.sock")`"#,
+        )
+    }
+}
+
+/// The message bus a [DBusListenVar] connects to, given by the `:bus` attribute.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub enum DBusBusType {
+    #[default]
+    Session,
+    System,
+}
+
+impl FromStr for DBusBusType {
+    type Err = EnumParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        enum_parse! { "bus", s,
+            "session" => Self::Session,
+            "system" => Self::System,
+        }
+    }
+}
+
+/// What a [DBusListenVar] subscribes to.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub enum DBusSubscription {
+    /// Every emission of the signal with this name, given by the `:signal` attribute.
+    Signal(String),
+    /// The value of the property with this name, given by the `:property` attribute.
+    Property(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct DBusListenVar {
+    pub name: VarName,
+    pub bus: DBusBusType,
+    /// Bus name of the service that sends the signal or owns the property.
+    pub destination: Option<String>,
+    pub path: Option<String>,
+    pub interface: Option<String>,
+    pub subscription: DBusSubscription,
+    pub initial_value: DynVal,
+    pub rate_limit: UpdateRateLimit,
+    pub onchange: Option<String>,
+    pub name_span: Span,
+    pub span: Span,
+}
+
+impl FromAstElementContent for DBusListenVar {
+    const ELEMENT_NAME: &'static str = "deflisten-dbus";
+
+    fn from_tail<I: Iterator<Item = Ast>>(span: Span, mut iter: AstIterator<I>) -> DiagResult<Self> {
+        let result: DiagResult<_> = (move || {
+            let (name_span, name) = iter.expect_symbol()?;
+            let mut attrs = iter.expect_key_values()?;
+            let initial_value = attrs.primitive_optional("initial")?.unwrap_or_else(|| DynVal::from_string(String::new()));
+            let rate_limit = UpdateRateLimit::from_attrs(&mut attrs)?;
+            let onchange = attrs.primitive_optional("onchange")?;
+            let bus = attrs.primitive_optional("bus")?.unwrap_or_default();
+            let destination = attrs.primitive_optional("destination")?;
+            let path = attrs.primitive_optional("path")?;
+            let interface = attrs.primitive_optional("interface")?;
+            let subscription = match (attrs.primitive_optional("signal")?, attrs.primitive_optional("property")?) {
+                (Some(signal), None) => DBusSubscription::Signal(signal),
+                (None, Some(property)) => DBusSubscription::Property(property),
+                _ => {
+                    return Err(DiagError(gen_diagnostic! {
+                        msg = "Exactly one of `:signal` or `:property` must be given",
+                        label = attrs.span,
+                    }))
+                }
+            };
+            if let DBusSubscription::Property(_) = subscription {
+                for (attr_name, value) in [("destination", &destination), ("path", &path), ("interface", &interface)] {
+                    if value.is_none() {
+                        return Err(AttrError::MissingRequiredAttr(attrs.span, AttrName(attr_name.to_string())).into());
+                    }
+                }
+            }
+            iter.expect_done()?;
+            Ok(Self {
+                name_span,
+                name: VarName(name),
+                bus,
+                destination,
+                path,
+                interface,
+                subscription,
+                initial_value,
+                rate_limit,
+                onchange,
+                span,
+            })
+        })();
+        result.note(
+            r#"Expected format: `(deflisten-dbus name :bus "system" :destination "org.freedesktop.UPower" :path "/org/freedesktop/UPower/devices/DisplayDevice" :interface "org.freedesktop.UPower.Device" :property "Percentage")`"#,
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct FileWatchVar {
+    pub name: VarName,
+    pub path: std::path::PathBuf,
+    /// Whether the contents of the file should be parsed as JSON, given by the `:json` attribute.
+    pub json: bool,
+    pub initial_value: DynVal,
+    pub rate_limit: UpdateRateLimit,
+    pub onchange: Option<String>,
+    pub path_span: Span,
+    pub name_span: Span,
+}
+
+impl FromAstElementContent for FileWatchVar {
+    const ELEMENT_NAME: &'static str = "defwatch";
+
+    fn from_tail<I: Iterator<Item = Ast>>(_span: Span, mut iter: AstIterator<I>) -> DiagResult<Self> {
+        let result: DiagResult<_> = (move || {
+            let (name_span, name) = iter.expect_symbol()?;
+            let mut attrs = iter.expect_key_values()?;
+            let initial_value = attrs.primitive_optional("initial")?.unwrap_or_else(|| DynVal::from_string(String::new()));
+            let rate_limit = UpdateRateLimit::from_attrs(&mut attrs)?;
+            let onchange = attrs.primitive_optional("onchange")?;
+            let json = attrs.primitive_optional("json")?.unwrap_or(false);
+            let (path_span, path) = iter.expect_literal()?;
+            iter.expect_done()?;
+            Ok(Self {
+                name_span,
+                name: VarName(name),
+                path: std::path::PathBuf::from(path.to_string()),
+                json,
+                initial_value,
+                rate_limit,
+                onchange,
+                path_span,
+            })
         })();
-        result.note(r#"Expected format: `(deflisten name :initial "0" "tail -f /tmp/example")`"#)
+        result.note(r#"Expected format: `(defwatch name :json true "/path/to/file")`"#)
     }
 }
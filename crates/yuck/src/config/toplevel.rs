@@ -1,20 +1,26 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
 };
 
 use itertools::Itertools;
+use regex::Regex;
 
 use super::{
-    file_provider::{FilesError, YuckFileProvider},
+    attrs_definition::AttrsDefinition,
+    config_options::ConfigOptions,
+    file_provider::{FilesError, IncludeChain, YuckFileProvider},
+    macro_definition::{expand_macros, MacroDefinition},
     script_var_definition::ScriptVarDefinition,
     var_definition::VarDefinition,
     widget_definition::WidgetDefinition,
-    window_definition::WindowDefinition,
+    widget_use::BasicWidgetUse,
+    window_definition::{WindowDefinition, WindowDefinitionSource},
 };
 use crate::{
-    config::script_var_definition::{ListenScriptVar, PollScriptVar},
+    config::script_var_definition::{DBusListenVar, FileWatchVar, ListenScriptVar, PollScriptVar},
     error::{DiagError, DiagResult},
+    format_diagnostic::{span_to_secondary_label, DiagnosticExt},
     gen_diagnostic,
     parser::{
         ast::Ast,
@@ -24,13 +30,50 @@ use crate::{
 };
 use eww_shared_util::{Span, Spanned, VarName};
 
+/// Keyword that can be given directly after the name of a definition to intentionally replace a previous definition of the same name.
+static OVERRIDE_FLAG: &str = "override";
+
+/// Remove the `:override` flag from a definition like `(defwidget name :override [] ...)`,
+/// returning whether it was present.
+fn take_override_flag(ast: Ast) -> (bool, Ast) {
+    match ast {
+        Ast::List(span, mut items)
+            if matches!(
+                items.as_slice(),
+                [Ast::Symbol(..), Ast::Symbol(..), Ast::Keyword(_, flag), ..] if flag == OVERRIDE_FLAG
+            ) =>
+        {
+            items.remove(2);
+            (true, Ast::List(span, items))
+        }
+        other => (false, other),
+    }
+}
+
+fn redefinition_error(kind: &str, name: impl std::fmt::Display, span: Span, previous_span: Span) -> DiagError {
+    DiagError(
+        gen_diagnostic! {
+            msg = format!("{} {} defined twice", kind, name),
+            label = span => "defined again here",
+            note = format!("To intentionally replace the previous definition, add `:{}` after the name", OVERRIDE_FLAG),
+        }
+        .with_label(span_to_secondary_label(previous_span).with_message("previously defined here")),
+    )
+}
+
 static TOP_LEVEL_DEFINITION_NAMES: &[&str] = &[
     WidgetDefinition::ELEMENT_NAME,
-    WindowDefinition::ELEMENT_NAME,
+    WindowDefinitionSource::ELEMENT_NAME,
+    AttrsDefinition::ELEMENT_NAME,
+    MacroDefinition::ELEMENT_NAME,
     VarDefinition::ELEMENT_NAME,
     ListenScriptVar::ELEMENT_NAME,
+    DBusListenVar::ELEMENT_NAME,
+    FileWatchVar::ELEMENT_NAME,
     PollScriptVar::ELEMENT_NAME,
     Include::ELEMENT_NAME,
+    Import::ELEMENT_NAME,
+    ConfigOptions::ELEMENT_NAME,
 ];
 
 #[derive(Debug, PartialEq, Eq, Clone, serde::Serialize)]
@@ -49,12 +92,114 @@ impl FromAstElementContent for Include {
     }
 }
 
+impl Include {
+    /// Resolve the included path into the files that should be loaded, sorted by path.
+    /// The path may contain the wildcards `*` and `?`, or point to a directory, which includes all `.yuck` files in that directory.
+    pub fn resolve_paths(&self, files: &impl YuckFileProvider) -> std::io::Result<Vec<PathBuf>> {
+        let path = Path::new(&self.path);
+        let mut paths = if is_glob_pattern(&self.path) {
+            resolve_glob(files, path)
+        } else if files.is_dir(path) {
+            let entries = files.read_dir(path)?;
+            entries.into_iter().filter(|entry| entry.extension() == Some("yuck".as_ref()) && !files.is_dir(entry)).collect()
+        } else {
+            vec![path.to_path_buf()]
+        };
+        paths.sort();
+        Ok(paths)
+    }
+}
+
+fn is_glob_pattern(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+/// Find all files matching the given glob pattern, resolving one path component at a time.
+fn resolve_glob(files: &impl YuckFileProvider, pattern: &Path) -> Vec<PathBuf> {
+    let mut candidates = vec![PathBuf::new()];
+    for component in pattern.components() {
+        let component_pattern = component.as_os_str().to_string_lossy();
+        if !is_glob_pattern(&component_pattern) {
+            candidates.iter_mut().for_each(|candidate| candidate.push(component));
+            continue;
+        }
+        let regex = glob_component_to_regex(&component_pattern);
+        candidates = candidates
+            .into_iter()
+            .flat_map(|candidate| {
+                let dir = if candidate.as_os_str().is_empty() { Path::new(".") } else { candidate.as_path() };
+                // directories that don't exist simply don't match anything
+                let entries = files.read_dir(dir).unwrap_or_default();
+                entries
+                    .into_iter()
+                    .filter_map(|entry| entry.file_name().map(|name| name.to_string_lossy().to_string()))
+                    // like in shells, wildcards don't match hidden files
+                    .filter(|name| regex.is_match(name) && (!name.starts_with('.') || component_pattern.starts_with('.')))
+                    .map(|name| candidate.join(name))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+    }
+    candidates.into_iter().filter(|candidate| !files.is_dir(candidate)).collect()
+}
+
+/// Convert a single path component of a glob pattern into a regex,
+/// where `*` matches any amount of characters and `?` matches any single character.
+fn glob_component_to_regex(pattern: &str) -> Regex {
+    let mut regex = String::from("^");
+    for (index, part) in pattern.split('*').enumerate() {
+        if index > 0 {
+            regex.push_str(".*");
+        }
+        regex.push_str(&part.split('?').map(regex::escape).join("."));
+    }
+    regex.push('$');
+    Regex::new(&regex).expect("Glob pattern converted into invalid regex")
+}
+
+/// Import of a module installed via `eww module add`, which lives in the `modules` directory of the configuration.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Serialize)]
+pub struct Import {
+    pub name: String,
+    pub name_span: Span,
+}
+
+impl Import {
+    /// Path of the main yuck file of the imported module, relative to the configuration directory.
+    pub fn path(&self) -> PathBuf {
+        PathBuf::from("modules").join(&self.name).join("eww.yuck")
+    }
+}
+
+impl FromAstElementContent for Import {
+    const ELEMENT_NAME: &'static str = "import";
+
+    fn from_tail<I: Iterator<Item = Ast>>(_span: Span, mut iter: AstIterator<I>) -> DiagResult<Self> {
+        let (name_span, name) = iter.expect_literal()?;
+        iter.expect_done()?;
+        let name = name.to_string();
+        // the module has to stay within the modules directory
+        if !Path::new(&name).components().all(|component| matches!(component, std::path::Component::Normal(_))) {
+            return Err(DiagError(gen_diagnostic! {
+                msg = format!("Invalid module name `{}`", name),
+                label = name_span,
+                note = "Module names can't contain `..` or be absolute paths",
+            }));
+        }
+        Ok(Import { name, name_span })
+    }
+}
+
 pub enum TopLevel {
     Include(Include),
+    Import(Import),
     VarDefinition(VarDefinition),
     ScriptVarDefinition(ScriptVarDefinition),
     WidgetDefinition(WidgetDefinition),
-    WindowDefinition(WindowDefinition),
+    WindowDefinition(WindowDefinitionSource),
+    AttrsDefinition(AttrsDefinition),
+    MacroDefinition(MacroDefinition),
+    ConfigOptions(ConfigOptions),
 }
 
 impl FromAst for TopLevel {
@@ -64,6 +209,7 @@ impl FromAst for TopLevel {
         let (sym_span, element_name) = iter.expect_symbol()?;
         Ok(match element_name.as_str() {
             x if x == Include::ELEMENT_NAME => Self::Include(Include::from_tail(span, iter)?),
+            x if x == Import::ELEMENT_NAME => Self::Import(Import::from_tail(span, iter)?),
             x if x == WidgetDefinition::ELEMENT_NAME => Self::WidgetDefinition(WidgetDefinition::from_tail(span, iter)?),
             x if x == VarDefinition::ELEMENT_NAME => Self::VarDefinition(VarDefinition::from_tail(span, iter)?),
             x if x == PollScriptVar::ELEMENT_NAME => {
@@ -72,7 +218,18 @@ impl FromAst for TopLevel {
             x if x == ListenScriptVar::ELEMENT_NAME => {
                 Self::ScriptVarDefinition(ScriptVarDefinition::Listen(ListenScriptVar::from_tail(span, iter)?))
             }
-            x if x == WindowDefinition::ELEMENT_NAME => Self::WindowDefinition(WindowDefinition::from_tail(span, iter)?),
+            x if x == DBusListenVar::ELEMENT_NAME => {
+                Self::ScriptVarDefinition(ScriptVarDefinition::DBus(DBusListenVar::from_tail(span, iter)?))
+            }
+            x if x == FileWatchVar::ELEMENT_NAME => {
+                Self::ScriptVarDefinition(ScriptVarDefinition::Watch(FileWatchVar::from_tail(span, iter)?))
+            }
+            x if x == WindowDefinitionSource::ELEMENT_NAME => {
+                Self::WindowDefinition(WindowDefinitionSource::from_tail(span, iter)?)
+            }
+            x if x == AttrsDefinition::ELEMENT_NAME => Self::AttrsDefinition(AttrsDefinition::from_tail(span, iter)?),
+            x if x == MacroDefinition::ELEMENT_NAME => Self::MacroDefinition(MacroDefinition::from_tail(span, iter)?),
+            x if x == ConfigOptions::ELEMENT_NAME => Self::ConfigOptions(ConfigOptions::from_tail(span, iter)?),
             x => {
                 return Err(DiagError(gen_diagnostic! {
                     msg = format!("Unknown toplevel declaration `{x}`"),
@@ -88,64 +245,241 @@ impl FromAst for TopLevel {
 pub struct Config {
     pub widget_definitions: HashMap<String, WidgetDefinition>,
     pub window_definitions: HashMap<String, WindowDefinition>,
+    pub attrs_definitions: HashMap<String, AttrsDefinition>,
+    pub macro_definitions: HashMap<String, MacroDefinition>,
     pub var_definitions: HashMap<VarName, VarDefinition>,
     pub script_vars: HashMap<VarName, ScriptVarDefinition>,
+    pub config_options: Option<ConfigOptions>,
+}
+
+/// Window definitions collected while generating a [`Config`], which are only resolved once all of them are known.
+type WindowSources = HashMap<String, WindowDefinitionSource>;
+
+/// State kept while generating a [`Config`] that is not part of the resulting config itself.
+#[derive(Debug, Default)]
+struct GenerationState {
+    window_sources: WindowSources,
+    include_chain: IncludeChain,
+    /// Files that have already been loaded. Files included from multiple places are only loaded once,
+    /// such that their definitions don't conflict with themselves.
+    loaded_files: HashSet<PathBuf>,
 }
 
 impl Config {
-    fn append_toplevel(&mut self, files: &mut impl YuckFileProvider, toplevel: TopLevel) -> DiagResult<()> {
+    /// Expand the macros used in a top level form and add the resulting definitions to the config.
+    /// Macros can be used anywhere after they have been defined.
+    fn append_ast(&mut self, files: &mut impl YuckFileProvider, state: &mut GenerationState, ast: Ast) -> DiagResult<()> {
+        let is_macro_definition = matches!(ast.as_list_ref().map(|items| items.first()), Ok(Some(Ast::Symbol(_, name))) if name == MacroDefinition::ELEMENT_NAME);
+        let forms = if is_macro_definition { vec![ast] } else { expand_macros(&self.macro_definitions, ast)? };
+        for form in forms {
+            let (overrides, form) = take_override_flag(form);
+            self.append_toplevel(files, state, TopLevel::from_ast(form)?, overrides)?;
+        }
+        Ok(())
+    }
+
+    fn append_toplevel(
+        &mut self,
+        files: &mut impl YuckFileProvider,
+        state: &mut GenerationState,
+        toplevel: TopLevel,
+        overrides: bool,
+    ) -> DiagResult<()> {
         match toplevel {
             TopLevel::VarDefinition(x) => {
-                if self.var_definitions.contains_key(&x.name) || self.script_vars.contains_key(&x.name) {
-                    return Err(DiagError(gen_diagnostic! {
-                        msg = format!("Variable {} defined twice", x.name),
-                        label = x.span => "defined again here",
-                    }));
-                } else {
-                    self.var_definitions.insert(x.name.clone(), x);
-                }
+                self.check_var_redefinition(&x.name, x.span, overrides)?;
+                self.var_definitions.insert(x.name.clone(), x);
             }
             TopLevel::ScriptVarDefinition(x) => {
-                if self.var_definitions.contains_key(x.name()) || self.script_vars.contains_key(x.name()) {
-                    return Err(DiagError(gen_diagnostic! {
-                        msg = format!("Variable {} defined twice", x.name()),
-                        label = x.name_span() => "defined again here",
-                    }));
-                } else {
-                    self.script_vars.insert(x.name().clone(), x);
-                }
+                self.check_var_redefinition(x.name(), x.name_span(), overrides)?;
+                self.script_vars.insert(x.name().clone(), x);
             }
             TopLevel::WidgetDefinition(x) => {
+                if let Some(existing) = self.widget_definitions.get(&x.name).filter(|_| !overrides) {
+                    return Err(redefinition_error("Widget", &x.name, x.span, existing.span));
+                }
                 self.widget_definitions.insert(x.name.clone(), x);
             }
             TopLevel::WindowDefinition(x) => {
-                self.window_definitions.insert(x.name.clone(), x);
+                if let Some(existing) = state.window_sources.get(&x.name).filter(|_| !overrides) {
+                    return Err(redefinition_error("Window", &x.name, x.span, existing.span));
+                }
+                state.window_sources.insert(x.name.clone(), x);
+            }
+            TopLevel::AttrsDefinition(x) => {
+                if let Some(existing) = self.attrs_definitions.get(&x.name).filter(|_| !overrides) {
+                    return Err(redefinition_error("Attribute set", &x.name, x.span, existing.span));
+                }
+                self.attrs_definitions.insert(x.name.clone(), x);
+            }
+            TopLevel::MacroDefinition(x) => {
+                if let Some(existing) = self.macro_definitions.get(&x.name).filter(|_| !overrides) {
+                    return Err(redefinition_error("Macro", &x.name, x.span, existing.span));
+                }
+                self.macro_definitions.insert(x.name.clone(), x);
+            }
+            TopLevel::ConfigOptions(x) => {
+                if let Some(existing) = &self.config_options {
+                    return Err(DiagError(
+                        gen_diagnostic! {
+                            msg = format!("{} defined twice", ConfigOptions::ELEMENT_NAME),
+                            label = x.span => "defined again here",
+                        }
+                        .with_label(span_to_secondary_label(existing.span).with_message("previously defined here")),
+                    ));
+                } else {
+                    self.config_options = Some(x);
+                }
             }
             TopLevel::Include(include) => {
-                let (_, toplevels) = files.load_yuck_file(PathBuf::from(&include.path)).map_err(|err| match err {
-                    FilesError::IoError(_) => DiagError(gen_diagnostic! {
-                        msg = format!("Included file `{}` not found", include.path),
+                let paths = include.resolve_paths(files).map_err(|err| {
+                    DiagError(gen_diagnostic! {
+                        msg = format!("Failed to read included directory `{}`: {}", include.path, err),
                         label = include.path_span => "Included here",
-                    }),
-                    FilesError::DiagError(x) => x,
+                    })
                 })?;
-                for element in toplevels {
-                    self.append_toplevel(files, TopLevel::from_ast(element)?)?;
+                if paths.is_empty() {
+                    return Err(DiagError(gen_diagnostic! {
+                        msg = format!("`{}` did not match any yuck files", include.path),
+                        label = include.path_span => "Included here",
+                    }));
+                }
+                for path in paths {
+                    let file = files.canonicalize(&path);
+                    state.include_chain.enter(file.clone(), include.path_span)?;
+                    if state.loaded_files.insert(file) {
+                        let (_, toplevels) = files.load_yuck_file(path.clone()).map_err(|err| match err {
+                            FilesError::IoError(_) => DiagError(gen_diagnostic! {
+                                msg = format!("Included file `{}` not found", path.display()),
+                                label = include.path_span => "Included here",
+                            }),
+                            FilesError::DiagError(x) => x,
+                        })?;
+                        for element in toplevels {
+                            self.append_ast(files, state, element)?;
+                        }
+                    }
+                    state.include_chain.leave();
                 }
             }
+            TopLevel::Import(import) => {
+                let file = files.canonicalize(&import.path());
+                state.include_chain.enter(file.clone(), import.name_span)?;
+                if state.loaded_files.insert(file) {
+                    let (_, toplevels) = files.load_yuck_file(import.path()).map_err(|err| match err {
+                        FilesError::IoError(_) => DiagError(gen_diagnostic! {
+                            msg = format!("Module `{}` not found", import.name),
+                            label = import.name_span => "Imported here",
+                            note = format!("Install it via `eww module add`, such that `{}` exists", import.path().display()),
+                        }),
+                        FilesError::DiagError(x) => x,
+                    })?;
+                    for element in toplevels {
+                        self.append_ast(files, state, element)?;
+                    }
+                }
+                state.include_chain.leave();
+            }
         }
         Ok(())
     }
 
+    /// Resolve the window definition with the given name, applying the definitions it extends.
+    /// `extended_by` contains the windows currently being resolved that (indirectly) extend this one.
+    fn resolve_window_source(
+        window_sources: &WindowSources,
+        source: &WindowDefinitionSource,
+        extended_by: &mut Vec<String>,
+    ) -> DiagResult<WindowDefinitionSource> {
+        let Some((extends_span, parent_name)) = &source.extends else { return Ok(source.clone()) };
+        if extended_by.contains(parent_name) || *parent_name == source.name {
+            let cycle = extended_by.iter().chain([&source.name, parent_name]).join(" -> ");
+            return Err(DiagError(gen_diagnostic! {
+                msg = format!("Window {} can't extend {}, as the windows would extend each other in a cycle", source.name, parent_name),
+                label = *extends_span => "extended here",
+                note = format!("Cycle: {}", cycle),
+            }));
+        }
+        let parent = window_sources.get(parent_name).ok_or_else(|| {
+            DiagError(gen_diagnostic! {
+                msg = format!("Window {} extends unknown window {}", source.name, parent_name),
+                label = *extends_span,
+            })
+        })?;
+        extended_by.push(source.name.clone());
+        let parent = Self::resolve_window_source(window_sources, parent, extended_by)?;
+        extended_by.pop();
+        Ok(source.clone().inherit_from(&parent))
+    }
+
+    /// Merge the attributes of the `defattrs` spread onto the widget into its own attributes.
+    /// Later attribute sets override earlier ones, and attributes given on the widget itself override all of them.
+    fn apply_spread_attrs(attrs_definitions: &HashMap<String, AttrsDefinition>, widget: &mut BasicWidgetUse) -> DiagResult<()> {
+        let mut attrs = HashMap::new();
+        for (span, name) in &widget.spread_attrs {
+            let definition = attrs_definitions.get(name).ok_or_else(|| {
+                DiagError(gen_diagnostic! {
+                    msg = format!("Unknown attribute set {}", name),
+                    label = *span,
+                    note = format!("Define it via `({} {} ...)`", AttrsDefinition::ELEMENT_NAME, name),
+                })
+            })?;
+            attrs.extend(definition.attrs.attrs.clone());
+        }
+        attrs.extend(std::mem::take(&mut widget.attrs.attrs));
+        widget.attrs.attrs = attrs;
+        Ok(())
+    }
+
+    /// Make sure a variable is not defined already, unless the new definition is meant to override it.
+    fn check_var_redefinition(&mut self, name: &VarName, span: Span, overrides: bool) -> DiagResult<()> {
+        let existing_span = match (self.var_definitions.get(name), self.script_vars.get(name)) {
+            (Some(existing), _) => existing.span,
+            (_, Some(existing)) => existing.name_span(),
+            (None, None) => return Ok(()),
+        };
+        if !overrides {
+            return Err(redefinition_error("Variable", name, span, existing_span));
+        }
+        // the new definition may be of a different kind than the one it replaces
+        self.var_definitions.remove(name);
+        self.script_vars.remove(name);
+        Ok(())
+    }
+
     pub fn generate(files: &mut impl YuckFileProvider, elements: Vec<Ast>) -> DiagResult<Self> {
+        Self::generate_with_state(files, GenerationState::default(), elements)
+    }
+
+    fn generate_with_state(
+        files: &mut impl YuckFileProvider,
+        mut state: GenerationState,
+        elements: Vec<Ast>,
+    ) -> DiagResult<Self> {
         let mut config = Self {
             widget_definitions: HashMap::new(),
             window_definitions: HashMap::new(),
+            attrs_definitions: HashMap::new(),
+            macro_definitions: HashMap::new(),
             var_definitions: HashMap::new(),
             script_vars: HashMap::new(),
+            config_options: None,
         };
         for element in elements {
-            config.append_toplevel(files, TopLevel::from_ast(element)?)?;
+            config.append_ast(files, &mut state, element)?;
+        }
+        for source in state.window_sources.values() {
+            let source = Self::resolve_window_source(&state.window_sources, source, &mut Vec::new())?;
+            config.window_definitions.insert(source.name.clone(), WindowDefinition::from_source(source)?);
+        }
+
+        let widget_uses = config
+            .widget_definitions
+            .values_mut()
+            .map(|def| &mut def.widget)
+            .chain(config.window_definitions.values_mut().map(|def| &mut def.widget));
+        for widget_use in widget_uses {
+            widget_use.try_for_each_basic_mut(&mut |widget| Self::apply_spread_attrs(&config.attrs_definitions, widget))?;
         }
         Ok(config)
     }
@@ -155,6 +489,233 @@ impl Config {
             FilesError::IoError(err) => DiagError(gen_diagnostic!(err)),
             FilesError::DiagError(x) => x,
         })?;
-        Self::generate(files, top_levels)
+        let main_file = files.canonicalize(path.as_ref());
+        let state = GenerationState {
+            include_chain: IncludeChain::new(main_file.clone()),
+            loaded_files: HashSet::from([main_file]),
+            ..Default::default()
+        };
+        Self::generate_with_state(files, state, top_levels)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{glob_component_to_regex, Config};
+    use crate::{
+        config::{
+            file_provider::{FilesError, YuckFileProvider},
+            widget_use::{BasicWidgetUse, WidgetUse},
+        },
+        error::DiagError,
+        parser::{self, ast::Ast},
+    };
+    use eww_shared_util::{AttrName, Span, VarName};
+    use std::{collections::HashMap, path::PathBuf};
+
+    /// File provider for configurations that consist of a single file, without any includes.
+    struct NoFiles;
+
+    impl YuckFileProvider for NoFiles {
+        fn load_yuck_file(&mut self, path: std::path::PathBuf) -> Result<(Span, Vec<Ast>), FilesError> {
+            Err(std::io::Error::new(std::io::ErrorKind::NotFound, path.display().to_string()).into())
+        }
+
+        fn load_yuck_str(&mut self, _name: String, content: String) -> Result<(Span, Vec<Ast>), DiagError> {
+            parser::parse_toplevel(0, content)
+        }
+
+        fn unload(&mut self, _id: usize) {}
+    }
+
+    fn generate(content: &str) -> Result<Config, DiagError> {
+        let (_, elements) = parser::parse_toplevel(0, content.to_string())?;
+        Config::generate(&mut NoFiles, elements)
+    }
+
+    /// File provider serving files from memory, keyed by their path.
+    struct MemoryFiles(HashMap<PathBuf, &'static str>);
+
+    impl YuckFileProvider for MemoryFiles {
+        fn load_yuck_file(&mut self, path: PathBuf) -> Result<(Span, Vec<Ast>), FilesError> {
+            let content = self.0.get(&path).ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound))?;
+            Ok(parser::parse_toplevel(0, content.to_string())?)
+        }
+
+        fn load_yuck_str(&mut self, _name: String, content: String) -> Result<(Span, Vec<Ast>), DiagError> {
+            parser::parse_toplevel(0, content)
+        }
+
+        fn unload(&mut self, _id: usize) {}
+    }
+
+    #[test]
+    fn test_import_stays_in_modules_dir() {
+        for name in ["../outside", "/etc", "foo/../../bar"] {
+            let err = generate(&format!(r#"(import "{}")"#, name)).unwrap_err();
+            assert!(err.0.message.starts_with("Invalid module name"), "{}", err.0.message);
+        }
+    }
+
+    #[test]
+    fn test_include_cycles() {
+        let mut files = MemoryFiles(HashMap::from([
+            (PathBuf::from("eww.yuck"), r#"(include "a.yuck") (include "b.yuck")"#),
+            (PathBuf::from("a.yuck"), r#"(include "c.yuck") (defvar a 1)"#),
+            (PathBuf::from("b.yuck"), r#"(include "c.yuck") (defvar b 1)"#),
+            (PathBuf::from("c.yuck"), r#"(defwidget c [] (label :text "c"))"#),
+        ]));
+        let config = Config::generate_from_main_file(&mut files, "eww.yuck").unwrap();
+        assert!(config.widget_definitions.contains_key("c"));
+        assert_eq!(config.var_definitions.len(), 2);
+
+        files.0.insert(PathBuf::from("c.yuck"), r#"(include "a.yuck")"#);
+        let err = Config::generate_from_main_file(&mut files, "eww.yuck").unwrap_err();
+        let note = err.0.notes.join("\n");
+        assert!(note.contains("a.yuck -> c.yuck -> a.yuck"), "{}", note);
+
+        files.0.insert(PathBuf::from("a.yuck"), r#"(include "eww.yuck")"#);
+        let err = Config::generate_from_main_file(&mut files, "eww.yuck").unwrap_err();
+        let note = err.0.notes.join("\n");
+        assert!(note.contains("eww.yuck -> a.yuck -> eww.yuck"), "{}", note);
+    }
+
+    #[test]
+    fn test_redefinitions() {
+        let mut files = MemoryFiles(HashMap::from([
+            (PathBuf::from("eww.yuck"), r#"(include "a.yuck") (defwidget foo [] (label :text "main"))"#),
+            (PathBuf::from("a.yuck"), r#"(defwidget foo [] (label :text "a")) (defvar bar 1)"#),
+        ]));
+        let err = Config::generate_from_main_file(&mut files, "eww.yuck").unwrap_err();
+        assert_eq!(err.0.message, "Widget foo defined twice");
+        assert_eq!(err.0.labels.len(), 2);
+
+        files.0.insert(
+            PathBuf::from("eww.yuck"),
+            r#"(include "a.yuck") (defwidget foo :override [] (label :text "main")) (defpoll bar :override :interval "1s" "date")"#,
+        );
+        let config = Config::generate_from_main_file(&mut files, "eww.yuck").unwrap();
+        let WidgetUse::Basic(widget) = &config.widget_definitions["foo"].widget else { panic!("expected basic widget") };
+        assert_eq!(widget.attrs.attrs[&AttrName::from("text")].value.to_string(), "\"main\"");
+        assert!(config.var_definitions.is_empty());
+        assert!(config.script_vars.contains_key(&VarName::from("bar")));
+
+        assert!(generate(r#"(defvar a 1) (deflisten a "tail -F file")"#).is_err());
+        assert!(generate(r#"(defwindow a (box)) (defwindow a (box))"#).is_err());
+    }
+
+    #[test]
+    fn test_window_extends() {
+        let config = generate(
+            r#"
+            (defwindow bar [?screen] :monitor 0 :stacking "fg" :geometry (geometry :width "100%") (label :text "bar"))
+            (defwindow bar2 :extends "bar" :monitor 1)
+            (defwindow bar3 :extends "bar2" :stacking "bg" (label :text "bar3"))
+            "#,
+        )
+        .unwrap();
+        let bar = &config.window_definitions["bar"];
+        let bar2 = &config.window_definitions["bar2"];
+        let bar3 = &config.window_definitions["bar3"];
+        assert_ne!(bar.monitor, bar2.monitor);
+        assert_eq!(bar2.monitor, bar3.monitor);
+        assert_eq!(bar.stacking, bar2.stacking);
+        assert_ne!(bar2.stacking, bar3.stacking);
+        assert_eq!(bar.geometry, bar3.geometry);
+        assert_eq!(bar.expected_args, bar3.expected_args);
+        assert_eq!(bar.widget, bar2.widget);
+        assert_ne!(bar2.widget, bar3.widget);
+    }
+
+    #[test]
+    fn test_window_var_refs() {
+        let config = generate(
+            r#"
+            (defwindow bar :monitor EWW_MONITOR :geometry (geometry :width "${EWW_WIDTH}px") :exclusive EWW_EXCLUSIVE
+              (label :text EWW_TEXT))
+            "#,
+        )
+        .unwrap();
+        let mut refs = Vec::new();
+        config.window_definitions["bar"].collect_var_refs_into(&mut refs);
+        refs.sort_by(|a, b| a.0.cmp(&b.0));
+        let expected: Vec<VarName> =
+            ["EWW_EXCLUSIVE", "EWW_MONITOR", "EWW_TEXT", "EWW_WIDTH"].into_iter().map(VarName::from).collect();
+        assert_eq!(refs, expected);
+    }
+
+    #[test]
+    fn test_spread_attrs() {
+        let config = generate(
+            r#"
+            (defattrs centered :valign "center" :halign "center" :class "a")
+            (defattrs highlighted :class "b")
+            (defwidget foo [] (box :@centered :@highlighted :halign "end" (label :@centered :text "hi")))
+            "#,
+        )
+        .unwrap();
+        let WidgetUse::Basic(outer) = &config.widget_definitions["foo"].widget else { panic!("expected basic widget") };
+        let attr = |widget: &BasicWidgetUse, name: &str| widget.attrs.attrs[&AttrName::from(name)].value.to_string();
+        assert_eq!(attr(outer, "valign"), "\"center\"");
+        assert_eq!(attr(outer, "halign"), "\"end\"");
+        assert_eq!(attr(outer, "class"), "\"b\"");
+        let WidgetUse::Basic(inner) = &outer.children[0] else { panic!("expected basic widget") };
+        assert_eq!(attr(inner, "class"), "\"a\"");
+        assert_eq!(attr(inner, "text"), "\"hi\"");
+
+        assert!(generate(r#"(defwidget foo [] (box :@missing))"#).is_err());
+    }
+
+    #[test]
+    fn test_macros() {
+        let config = generate(
+            r#"
+            (defmacro ws-button [n] (button :class "ws-$n" :onclick "hyprctl dispatch workspace ${n}" n))
+            (defmacro attr-label [attr value] (label :$attr value))
+            (defwidget workspaces [] (box (ws-button 1) (ws-button 2) (attr-label text "hi")))
+            (defmacro bar-on [monitor] (defwindow bar-$monitor :monitor monitor (workspaces)) (defvar shown-$monitor true))
+            (bar-on 0)
+            (bar-on 1)
+            "#,
+        )
+        .unwrap();
+        let WidgetUse::Basic(outer) = &config.widget_definitions["workspaces"].widget else { panic!("expected basic widget") };
+        assert_eq!(outer.children.len(), 3);
+        let WidgetUse::Basic(button) = &outer.children[1] else { panic!("expected basic widget") };
+        let attr = |widget: &BasicWidgetUse, name: &str| {
+            let value = widget.attrs.attrs[&AttrName::from(name)].value.as_simplexpr().unwrap();
+            value.eval_no_vars().unwrap().0
+        };
+        assert_eq!(attr(button, "class"), "ws-$n");
+        assert_eq!(attr(button, "onclick"), "hyprctl dispatch workspace 2");
+        let WidgetUse::Basic(label) = &outer.children[2] else { panic!("expected basic widget") };
+        assert_eq!(attr(label, "text"), "hi");
+        assert!(config.window_definitions.contains_key("bar-0"));
+        assert!(config.window_definitions.contains_key("bar-1"));
+        assert!(config.var_definitions.contains_key("shown-1"));
+
+        assert!(generate(r#"(defmacro foo [a] (label :text a)) (defwidget x [] (foo))"#).is_err());
+        assert!(generate(r#"(defmacro foo [] (foo)) (defwidget x [] (foo))"#).is_err());
+    }
+
+    #[test]
+    fn test_window_extends_errors() {
+        assert!(generate(r#"(defwindow bar :extends "foo")"#).is_err());
+        assert!(generate(r#"(defwindow bar :extends "bar" (label))"#).is_err());
+        assert!(generate(r#"(defwindow a :extends "b" (label)) (defwindow b :extends "a" (label))"#).is_err());
+        assert!(generate(r#"(defwindow a :monitor 0)"#).is_err());
+    }
+
+    #[test]
+    fn test_glob_component_to_regex() {
+        let regex = glob_component_to_regex("*.yuck");
+        assert!(regex.is_match("bar.yuck"));
+        assert!(regex.is_match(".yuck"));
+        assert!(!regex.is_match("bar.yuck.bak"));
+        assert!(!regex.is_match("bar_yuck"));
+
+        let regex = glob_component_to_regex("bar-?.y*");
+        assert!(regex.is_match("bar-1.yuck"));
+        assert!(!regex.is_match("bar-12.yuck"));
     }
 }
@@ -2,7 +2,7 @@ use std::collections::{HashMap, HashSet};
 
 use simplexpr::SimplExpr;
 
-use super::{widget_definition::WidgetDefinition, widget_use::WidgetUse, Config};
+use super::{widget_definition::WidgetDefinition, widget_use::WidgetUse, window_definition::POINTER_VAR_NAME, Config};
 use eww_shared_util::{AttrName, Span, Spanned, VarName};
 
 #[derive(Debug, thiserror::Error)]
@@ -42,6 +42,7 @@ pub fn validate(config: &Config, additional_globals: Vec<VarName>) -> Result<(),
         let local_var_names: HashSet<VarName> = std::iter::empty()
             .chain(var_names.iter().cloned())
             .chain(window.expected_args.iter().map(|x| VarName::from(x.name.clone())))
+            .chain(window.track_pointer.then(|| VarName::from(POINTER_VAR_NAME)))
             .collect();
         validate_variables_in_widget_use(&config.widget_definitions, &local_var_names, &window.widget, false)?;
     }
@@ -115,6 +116,34 @@ pub fn validate_variables_in_widget_use(
         let mut variables = variables.clone();
         variables.insert(widget.element_name.clone());
         validate_variables_in_widget_use(defs, &variables, &widget.body, is_in_definition)?;
+    } else if let WidgetUse::Conditional(widget) = widget {
+        let unknown_var = widget
+            .condition
+            .var_refs_with_span()
+            .iter()
+            .cloned()
+            .map(|(span, var_ref)| (span, var_ref.clone()))
+            .find(|(_, var_ref)| !variables.contains(var_ref));
+        if let Some((span, var)) = unknown_var {
+            return Err(ValidationError::UnknownVariable { span, name: var, in_definition: is_in_definition });
+        }
+        for body in widget.then_body.iter().chain(widget.else_body.iter()) {
+            validate_variables_in_widget_use(defs, variables, body, is_in_definition)?;
+        }
+    } else if let WidgetUse::Case(widget) = widget {
+        let unknown_var = widget
+            .expr
+            .var_refs_with_span()
+            .iter()
+            .cloned()
+            .map(|(span, var_ref)| (span, var_ref.clone()))
+            .find(|(_, var_ref)| !variables.contains(var_ref));
+        if let Some((span, var)) = unknown_var {
+            return Err(ValidationError::UnknownVariable { span, name: var, in_definition: is_in_definition });
+        }
+        for body in widget.branches.iter().map(|branch| &branch.body).chain(widget.else_body.as_deref()) {
+            validate_variables_in_widget_use(defs, variables, body, is_in_definition)?;
+        }
     }
 
     Ok(())
@@ -1,15 +1,75 @@
+use std::str::FromStr;
+
 use simplexpr::dynval::DynVal;
 
 use crate::{
-    error::{DiagResult, DiagResultExt},
+    config::window_definition::EnumParseError,
+    enum_parse,
+    error::{DiagError, DiagResult, DiagResultExt},
+    gen_diagnostic,
     parser::{ast::Ast, ast_iterator::AstIterator, from_ast::FromAstElementContent},
 };
-use eww_shared_util::{Span, VarName};
+use eww_shared_util::{Span, Spanned, VarName};
+
+/// The type of values a variable may hold, given by the `:type` attribute of its definition.
+/// Updates with a value of a different type are rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, derive_more::Display, serde::Serialize)]
+pub enum VarType {
+    #[display("string")]
+    String,
+    #[display("number")]
+    Number,
+    #[display("bool")]
+    Bool,
+    #[display("json")]
+    Json,
+}
+
+impl FromStr for VarType {
+    type Err = EnumParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        enum_parse! { "type", s,
+            "string" => Self::String,
+            "number" => Self::Number,
+            "bool" => Self::Bool,
+            "json" => Self::Json,
+        }
+    }
+}
+
+impl VarType {
+    /// Whether the given value is of this type.
+    pub fn matches(&self, value: &DynVal) -> bool {
+        match self {
+            VarType::String => true,
+            VarType::Number => value.as_f64().is_ok(),
+            VarType::Bool => value.as_bool().is_ok(),
+            VarType::Json => value.as_json_value().is_ok(),
+        }
+    }
+
+    /// Check that the initial value given in the definition of a variable is of this type.
+    pub fn check_initial_value(&self, value: &DynVal) -> DiagResult<()> {
+        if self.matches(value) {
+            Ok(())
+        } else {
+            Err(DiagError(gen_diagnostic! {
+                msg = format!("Initial value `{}` is not of type {}", value, self),
+                label = value.span() => format!("Expected a {} here", self),
+            }))
+        }
+    }
+}
 
 #[derive(Debug, PartialEq, Eq, Clone, serde::Serialize)]
 pub struct VarDefinition {
     pub name: VarName,
     pub initial_value: DynVal,
+    /// Command that is run whenever the value of the variable changes
+    pub onchange: Option<String>,
+    /// The type of values the variable may hold
+    pub var_type: Option<VarType>,
     pub span: Span,
 }
 
@@ -19,10 +79,16 @@ impl FromAstElementContent for VarDefinition {
     fn from_tail<I: Iterator<Item = Ast>>(span: Span, mut iter: AstIterator<I>) -> DiagResult<Self> {
         let result = (move || {
             let (_, name) = iter.expect_symbol()?;
+            let mut attrs = iter.expect_key_values()?;
+            let onchange = attrs.primitive_optional("onchange")?;
+            let var_type: Option<VarType> = attrs.primitive_optional("type")?;
             let (_, initial_value) = iter.expect_literal()?;
             iter.expect_done()?;
-            Ok(Self { name: VarName(name), initial_value, span })
+            if let Some(var_type) = var_type {
+                var_type.check_initial_value(&initial_value)?;
+            }
+            Ok(Self { name: VarName(name), initial_value, onchange, var_type, span })
         })();
-        result.note(r#"Expected format: `(defvar name "initial-value")`"#)
+        result.note(r#"Expected format: `(defvar name :onchange "optional-command" :type "string" "initial-value")`"#)
     }
 }
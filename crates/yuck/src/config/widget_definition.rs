@@ -19,6 +19,8 @@ pub struct WidgetDefinition {
     pub widget: WidgetUse,
     pub span: Span,
     pub args_span: Span,
+    /// Description of the widget, given via `:doc`, shown by `eww widgets`
+    pub doc: Option<String>,
 }
 
 impl FromAstElementContent for WidgetDefinition {
@@ -40,6 +42,8 @@ impl FromAstElementContent for WidgetDefinition {
             })
             .note(EXPECTED_WIDGET_DEF_FORMAT)?;
         let expected_args = expected_args.into_iter().map(AttrSpec::from_ast).collect::<DiagResult<_>>()?;
+        let mut attrs = iter.expect_key_values()?;
+        let doc = attrs.primitive_optional("doc")?;
         let widget = iter.expect_any().map_err(DiagError::from).note(EXPECTED_WIDGET_DEF_FORMAT).and_then(WidgetUse::from_ast)?;
         iter.expect_done().map_err(|e| {
             DiagError(gen_diagnostic! {
@@ -51,8 +55,9 @@ impl FromAstElementContent for WidgetDefinition {
             })
         })?;
 
-        Ok(Self { name, expected_args, widget, span, args_span })
+        Ok(Self { name, expected_args, widget, span, args_span, doc })
     }
 }
 
-static EXPECTED_WIDGET_DEF_FORMAT: &str = r#"Expected format: `(defwidget name [] (contained-widgets))`"#;
+static EXPECTED_WIDGET_DEF_FORMAT: &str =
+    r#"Expected format: `(defwidget name [] :doc "optional description" (contained-widgets))`"#;
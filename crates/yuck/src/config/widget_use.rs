@@ -1,4 +1,4 @@
-use simplexpr::SimplExpr;
+use simplexpr::{dynval::DynVal, SimplExpr};
 
 use crate::{
     config::attributes::AttrEntry,
@@ -19,6 +19,8 @@ pub enum WidgetUse {
     Basic(BasicWidgetUse),
     Loop(LoopWidgetUse),
     Children(ChildrenWidgetUse),
+    Conditional(ConditionalWidgetUse),
+    Case(CaseWidgetUse),
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, serde::Serialize)]
@@ -30,6 +32,37 @@ pub struct LoopWidgetUse {
     pub span: Span,
 }
 
+/// A widget that is only built while its condition holds, created from `when`, `unless` or `if`.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Serialize)]
+pub struct ConditionalWidgetUse {
+    pub condition: SimplExpr,
+    pub condition_span: Span,
+    /// Widget shown while the condition is true
+    pub then_body: Option<Box<WidgetUse>>,
+    /// Widget shown while the condition is false
+    pub else_body: Option<Box<WidgetUse>>,
+    pub span: Span,
+}
+
+/// A widget that shows one of multiple branches, depending on the value of an expression, created from `case`.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Serialize)]
+pub struct CaseWidgetUse {
+    pub expr: SimplExpr,
+    pub expr_span: Span,
+    pub branches: Vec<CaseBranch>,
+    /// Widget shown when none of the branches match
+    pub else_body: Option<Box<WidgetUse>>,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, serde::Serialize)]
+pub struct CaseBranch {
+    /// Value the expression of the `case` has to be equal to for this branch to be shown
+    pub value: DynVal,
+    pub body: WidgetUse,
+    pub span: Span,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, serde::Serialize)]
 pub struct ChildrenWidgetUse {
     pub span: Span,
@@ -40,11 +73,73 @@ pub struct ChildrenWidgetUse {
 pub struct BasicWidgetUse {
     pub name: String,
     pub attrs: Attributes,
+    /// Names of the `defattrs` spread onto this widget via `:@name`, in order, together with their spans.
+    /// Once the configuration is loaded, their attributes are already part of [`Self::attrs`].
+    pub spread_attrs: Vec<(Span, String)>,
     pub children: Vec<WidgetUse>,
     pub span: Span,
     pub name_span: Span,
 }
 
+impl WidgetUse {
+    /// Collect the names of all variables referenced by this widget use and its children into `dest`.
+    /// This includes the variables introduced by `for`, which aren't global variables.
+    pub fn collect_var_refs_into(&self, dest: &mut Vec<VarName>) {
+        match self {
+            WidgetUse::Basic(widget) => {
+                for expr in widget.attrs.attrs.values().filter_map(|entry| entry.value.as_simplexpr().ok()) {
+                    expr.collect_var_refs_into(dest);
+                }
+                for child in &widget.children {
+                    child.collect_var_refs_into(dest);
+                }
+            }
+            WidgetUse::Loop(widget) => {
+                widget.elements_expr.collect_var_refs_into(dest);
+                widget.body.collect_var_refs_into(dest);
+            }
+            WidgetUse::Children(widget) => {
+                if let Some(nth_expr) = &widget.nth_expr {
+                    nth_expr.collect_var_refs_into(dest);
+                }
+            }
+            WidgetUse::Conditional(widget) => {
+                widget.condition.collect_var_refs_into(dest);
+                for body in widget.then_body.iter().chain(widget.else_body.iter()) {
+                    body.collect_var_refs_into(dest);
+                }
+            }
+            WidgetUse::Case(widget) => {
+                widget.expr.collect_var_refs_into(dest);
+                for body in widget.branches.iter().map(|branch| &branch.body).chain(widget.else_body.as_deref()) {
+                    body.collect_var_refs_into(dest);
+                }
+            }
+        }
+    }
+
+    /// Call `f` on every [`BasicWidgetUse`] within this widget use, including itself, stopping at the first error.
+    pub fn try_for_each_basic_mut(&mut self, f: &mut impl FnMut(&mut BasicWidgetUse) -> DiagResult<()>) -> DiagResult<()> {
+        match self {
+            WidgetUse::Basic(widget) => {
+                f(widget)?;
+                widget.children.iter_mut().try_for_each(|child| child.try_for_each_basic_mut(f))
+            }
+            WidgetUse::Loop(widget) => widget.body.try_for_each_basic_mut(f),
+            WidgetUse::Children(_) => Ok(()),
+            WidgetUse::Conditional(widget) => {
+                widget.then_body.iter_mut().chain(widget.else_body.iter_mut()).try_for_each(|body| body.try_for_each_basic_mut(f))
+            }
+            WidgetUse::Case(widget) => widget
+                .branches
+                .iter_mut()
+                .map(|branch| &mut branch.body)
+                .chain(widget.else_body.as_deref_mut())
+                .try_for_each(|body| body.try_for_each_basic_mut(f)),
+        }
+    }
+}
+
 impl BasicWidgetUse {
     pub fn children_span(&self) -> Span {
         if self.children.is_empty() {
@@ -54,15 +149,21 @@ impl BasicWidgetUse {
         }
     }
 
-    fn from_iter<I: Iterator<Item = Ast>>(
-        span: Span,
-        name: String,
-        name_span: Span,
-        mut iter: AstIterator<I>,
-    ) -> DiagResult<Self> {
+    fn from_iter<I: Iterator<Item = Ast>>(span: Span, name: String, name_span: Span, iter: AstIterator<I>) -> DiagResult<Self> {
+        // `:@name` has no value, so it is taken out before the remaining attributes are parsed as key-value pairs
+        let (spread_attrs, rest): (Vec<_>, Vec<_>) =
+            iter.partition(|ast| matches!(ast, Ast::Keyword(_, keyword) if keyword.starts_with('@')));
+        let spread_attrs = spread_attrs
+            .into_iter()
+            .filter_map(|ast| match ast {
+                Ast::Keyword(span, keyword) => Some((span, keyword[1..].to_string())),
+                _ => None,
+            })
+            .collect();
+        let mut iter = AstIterator::new(Span(name_span.1, span.1, span.2), rest.into_iter());
         let attrs = iter.expect_key_values()?;
         let children = iter.map(WidgetUse::from_ast).collect::<DiagResult<Vec<_>>>()?;
-        Ok(Self { name, attrs, children, span, name_span })
+        Ok(Self { name, attrs, spread_attrs, children, span, name_span })
     }
 }
 
@@ -91,6 +192,86 @@ impl FromAstElementContent for LoopWidgetUse {
     }
 }
 
+impl ConditionalWidgetUse {
+    const WHEN_ELEMENT_NAME: &'static str = "when";
+    const UNLESS_ELEMENT_NAME: &'static str = "unless";
+    const IF_ELEMENT_NAME: &'static str = "if";
+
+    /// Parse the tail of a `when`, `unless` or `if` element, as given by `element_name`.
+    fn from_tail<I: Iterator<Item = Ast>>(span: Span, element_name: &str, mut iter: AstIterator<I>) -> DiagResult<Self> {
+        let (condition_span, condition) = iter.expect_simplexpr()?;
+        let body = iter
+            .expect_any()
+            .map_err(DiagError::from)
+            .note(&format!("Expected a widget to show depending on the condition of this `{element_name}`"))
+            .and_then(WidgetUse::from_ast)?;
+        let else_body = if element_name == Self::IF_ELEMENT_NAME {
+            iter.next().map(WidgetUse::from_ast).transpose()?.map(Box::new)
+        } else {
+            None
+        };
+        iter.expect_done()
+            .map_err(DiagError::from)
+            .note(&format!("`{element_name}` takes a condition and exactly one widget. Wrap multiple widgets in a `box`."))?;
+        let body = Some(Box::new(body));
+        let (then_body, else_body) = if element_name == Self::UNLESS_ELEMENT_NAME { (None, body) } else { (body, else_body) };
+        Ok(Self { condition, condition_span, then_body, else_body, span })
+    }
+}
+
+impl CaseWidgetUse {
+    /// Find the widget that should be shown for the given value of the expression, together with the index of its branch.
+    /// The index of the `else` branch is the number of branches.
+    pub fn select_branch(&self, value: &DynVal) -> (usize, Option<&WidgetUse>) {
+        match self.branches.iter().position(|branch| branch.value.0 == value.0) {
+            Some(index) => (index, Some(&self.branches[index].body)),
+            None => (self.branches.len(), self.else_body.as_deref()),
+        }
+    }
+}
+
+impl FromAstElementContent for CaseWidgetUse {
+    const ELEMENT_NAME: &'static str = "case";
+
+    fn from_tail<I: Iterator<Item = Ast>>(span: Span, mut iter: AstIterator<I>) -> DiagResult<Self> {
+        let (expr_span, expr) = iter.expect_simplexpr()?;
+        let mut branches = Vec::new();
+        let mut else_body: Option<Box<WidgetUse>> = None;
+        for branch in iter {
+            let branch_span = branch.span();
+            if else_body.is_some() {
+                return Err(DiagError(gen_diagnostic! {
+                    msg = "The `else` branch must be the last branch of a `case`",
+                    label = branch_span => "This branch comes after the `else` branch",
+                }));
+            }
+            let mut branch_iter = branch.try_ast_iter()?;
+            let is_else = match branch_iter.expect_any()? {
+                Ast::Symbol(_, symbol) if symbol == "else" => true,
+                other => {
+                    branch_iter.put_back(other);
+                    false
+                }
+            };
+            let value = if is_else { None } else { Some(branch_iter.expect_literal()?.1) };
+            let body = branch_iter
+                .expect_any()
+                .map_err(DiagError::from)
+                .note("Expected a widget to show for this branch")
+                .and_then(WidgetUse::from_ast)?;
+            branch_iter
+                .expect_done()
+                .map_err(DiagError::from)
+                .note("Each branch takes exactly one widget. Wrap multiple widgets in a `box`.")?;
+            match value {
+                Some(value) => branches.push(CaseBranch { value, body, span: branch_span }),
+                None => else_body = Some(Box::new(body)),
+            }
+        }
+        Ok(Self { expr, expr_span, branches, else_body, span })
+    }
+}
+
 impl FromAstElementContent for ChildrenWidgetUse {
     const ELEMENT_NAME: &'static str = "children";
 
@@ -113,6 +294,12 @@ impl FromAst for WidgetUse {
             match name.as_ref() {
                 LoopWidgetUse::ELEMENT_NAME => Ok(WidgetUse::Loop(LoopWidgetUse::from_tail(span, iter)?)),
                 ChildrenWidgetUse::ELEMENT_NAME => Ok(WidgetUse::Children(ChildrenWidgetUse::from_tail(span, iter)?)),
+                ConditionalWidgetUse::WHEN_ELEMENT_NAME
+                | ConditionalWidgetUse::UNLESS_ELEMENT_NAME
+                | ConditionalWidgetUse::IF_ELEMENT_NAME => {
+                    Ok(WidgetUse::Conditional(ConditionalWidgetUse::from_tail(span, &name, iter)?))
+                }
+                CaseWidgetUse::ELEMENT_NAME => Ok(WidgetUse::Case(CaseWidgetUse::from_tail(span, iter)?)),
                 _ => Ok(WidgetUse::Basic(BasicWidgetUse::from_iter(span, name, name_span, iter)?)),
             }
         }
@@ -132,6 +319,7 @@ fn label_from_simplexpr(value: SimplExpr, span: Span) -> BasicWidgetUse {
                 )
             },
         ),
+        spread_attrs: Vec::new(),
         children: Vec::new(),
         span,
     }
@@ -147,4 +335,4 @@ macro_rules! impl_spanned {
         }
     }
 }
-impl_spanned!(Basic => BasicWidgetUse, Loop => LoopWidgetUse, Children => ChildrenWidgetUse);
+impl_spanned!(Basic => BasicWidgetUse, Loop => LoopWidgetUse, Children => ChildrenWidgetUse, Conditional => ConditionalWidgetUse, Case => CaseWidgetUse);
@@ -1,15 +1,16 @@
 use std::{collections::HashMap, fmt::Display};
 
 use crate::{
-    config::monitor::MonitorIdentifier,
+    config::{attributes::Attributes, monitor::MonitorIdentifier},
     error::{DiagError, DiagResult},
+    gen_diagnostic,
     parser::{
         ast::Ast,
         ast_iterator::AstIterator,
         from_ast::{FromAst, FromAstElementContent},
     },
 };
-use eww_shared_util::{Span, VarName};
+use eww_shared_util::{AttrName, Span, Spanned, VarName};
 use simplexpr::{
     dynval::{DynVal, FromDynVal},
     eval::EvalError,
@@ -21,6 +22,9 @@ use super::{
     window_geometry::WindowGeometryDef,
 };
 
+/// Name of the window-local variable that windows with `:track-pointer true` expose the pointer position through.
+pub const POINTER_VAR_NAME: &str = "EWW_POINTER";
+
 #[derive(Debug, thiserror::Error)]
 pub enum WindowStackingConversionError {
     #[error(transparent)]
@@ -29,6 +33,14 @@ pub enum WindowStackingConversionError {
     EnumParseError(#[from] EnumParseError),
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum WindowTransitionConversionError {
+    #[error(transparent)]
+    EvalError(#[from] EvalError),
+    #[error(transparent)]
+    EnumParseError(#[from] EnumParseError),
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, serde::Serialize)]
 pub struct WindowDefinition {
     pub name: String,
@@ -36,13 +48,76 @@ pub struct WindowDefinition {
     pub args_span: Span,
     pub geometry: Option<WindowGeometryDef>,
     pub stacking: Option<SimplExpr>,
+    /// Order of this window relative to other eww windows on the same layer. Windows with a higher z-index are placed on top.
+    pub z_index: Option<SimplExpr>,
     pub monitor: Option<SimplExpr>,
     pub widget: WidgetUse,
     pub resizable: Option<SimplExpr>,
+    pub close_on_unfocus: Option<SimplExpr>,
+    pub open_transition: Option<SimplExpr>,
+    pub close_transition: Option<SimplExpr>,
+    pub transition_duration: Option<SimplExpr>,
+    /// Whether the window is only built once it is opened. Non-lazy windows are built, but kept hidden, when eww starts.
+    pub lazy: bool,
+    /// Whether the window is opened through XWayland, even when running on wayland.
+    pub force_x11: bool,
+    /// Path of a stylesheet, relative to the config directory, that only applies to this window.
+    pub stylesheet: Option<String>,
+    /// Command to run once the window has been opened
+    pub onopen: Option<String>,
+    /// Command to run once the window has been closed
+    pub onclose: Option<String>,
+    /// Whether the window exposes the position of the pointer as the [`POINTER_VAR_NAME`] variable.
+    pub track_pointer: bool,
+    /// Duration without user input after which the window is hidden, until the user is back
+    pub hide_when_idle: Option<SimplExpr>,
+    /// Whether the window fails to open when evaluating an expression fails, overriding `(defconfig :strict)`
+    pub strict: Option<bool>,
     pub backend_options: BackendWindowOptionsDef,
+    pub span: Span,
 }
 
 impl WindowDefinition {
+    /// Collect the names of all variables referenced by this window definition into `dest`,
+    /// including the ones referenced by its properties, such as its geometry or monitor.
+    pub fn collect_var_refs_into(&self, dest: &mut Vec<VarName>) {
+        self.widget.collect_var_refs_into(dest);
+        let geometry = self.geometry.iter().flat_map(|geometry| {
+            [&geometry.anchor_point, &geometry.offset.x, &geometry.offset.y, &geometry.size.x, &geometry.size.y]
+        });
+        let wayland = &self.backend_options.wayland;
+        let x11 = &self.backend_options.x11;
+        let struts = x11.struts.iter().map(|struts| &struts.side);
+        let exprs = [
+            &self.stacking,
+            &self.z_index,
+            &self.monitor,
+            &self.resizable,
+            &self.close_on_unfocus,
+            &self.open_transition,
+            &self.close_transition,
+            &self.transition_duration,
+            &self.hide_when_idle,
+            &wayland.exclusive,
+            &wayland.focusable,
+            &wayland.namespace,
+            &x11.sticky,
+            &x11.window_type,
+            &x11.wm_ignore,
+            &x11.skip_taskbar,
+            &x11.skip_pager,
+            &x11.demands_attention,
+            &x11.above,
+            &x11.below,
+        ];
+        for expr in exprs.into_iter().chain(geometry).chain(struts).flatten() {
+            expr.collect_var_refs_into(dest);
+        }
+        if let Some(struts) = &x11.struts {
+            struts.distance.collect_var_refs_into(dest);
+        }
+    }
+
     /// Evaluate the `monitor` field of the window definition
     pub fn eval_monitor(&self, local_variables: &HashMap<VarName, DynVal>) -> Result<Option<MonitorIdentifier>, EvalError> {
         Ok(match &self.monitor {
@@ -59,6 +134,49 @@ impl WindowDefinition {
         })
     }
 
+    /// Evaluate the `close-on-unfocus` field of the window definition
+    pub fn eval_close_on_unfocus(&self, local_variables: &HashMap<VarName, DynVal>) -> Result<bool, EvalError> {
+        Ok(match &self.close_on_unfocus {
+            Some(expr) => expr.eval(local_variables)?.as_bool()?,
+            None => false,
+        })
+    }
+
+    /// Evaluate the `open-transition` field of the window definition
+    pub fn eval_open_transition(
+        &self,
+        local_variables: &HashMap<VarName, DynVal>,
+    ) -> Result<WindowTransition, WindowTransitionConversionError> {
+        eval_transition(&self.open_transition, local_variables)
+    }
+
+    /// Evaluate the `close-transition` field of the window definition
+    pub fn eval_close_transition(
+        &self,
+        local_variables: &HashMap<VarName, DynVal>,
+    ) -> Result<WindowTransition, WindowTransitionConversionError> {
+        eval_transition(&self.close_transition, local_variables)
+    }
+
+    /// Evaluate the `transition-duration` field of the window definition
+    pub fn eval_transition_duration(&self, local_variables: &HashMap<VarName, DynVal>) -> Result<std::time::Duration, EvalError> {
+        Ok(match &self.transition_duration {
+            Some(expr) => expr.eval(local_variables)?.as_duration()?,
+            None => std::time::Duration::from_millis(200),
+        })
+    }
+
+    /// Evaluate the `hide-when-idle` field of the window definition
+    pub fn eval_hide_when_idle(
+        &self,
+        local_variables: &HashMap<VarName, DynVal>,
+    ) -> Result<Option<std::time::Duration>, EvalError> {
+        Ok(match &self.hide_when_idle {
+            Some(expr) => Some(expr.eval(local_variables)?.as_duration()?),
+            None => None,
+        })
+    }
+
     /// Evaluate the `stacking` field of the window definition
     pub fn eval_stacking(
         &self,
@@ -72,24 +190,127 @@ impl WindowDefinition {
             None => Ok(WindowStacking::Foreground),
         }
     }
+
+    /// Evaluate the `z-index` field of the window definition
+    pub fn eval_z_index(&self, local_variables: &HashMap<VarName, DynVal>) -> Result<i32, EvalError> {
+        Ok(match &self.z_index {
+            Some(expr) => expr.eval(local_variables)?.as_i32()?,
+            None => 0,
+        })
+    }
+}
+
+fn eval_transition(
+    expr: &Option<SimplExpr>,
+    local_variables: &HashMap<VarName, DynVal>,
+) -> Result<WindowTransition, WindowTransitionConversionError> {
+    match expr {
+        Some(expr) => Ok(WindowTransition::from_dynval(&expr.eval(local_variables)?)?),
+        None => Ok(WindowTransition::None),
+    }
 }
 
-impl FromAstElementContent for WindowDefinition {
+/// A `defwindow` as written in the configuration, before it is turned into a [`WindowDefinition`].
+/// A window definition may extend another one via `:extends`, inheriting the arguments, attributes and widget
+/// it does not specify itself. Resolving this requires all window definitions to be known.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WindowDefinitionSource {
+    pub name: String,
+    pub args: Option<(Span, Vec<Ast>)>,
+    pub attrs: Attributes,
+    pub widget: Option<Ast>,
+    /// Name of the window definition this one extends, together with the span of that name
+    pub extends: Option<(Span, String)>,
+    pub span: Span,
+}
+
+impl FromAstElementContent for WindowDefinitionSource {
     const ELEMENT_NAME: &'static str = "defwindow";
 
-    fn from_tail<I: Iterator<Item = Ast>>(_span: Span, mut iter: AstIterator<I>) -> DiagResult<Self> {
+    fn from_tail<I: Iterator<Item = Ast>>(span: Span, mut iter: AstIterator<I>) -> DiagResult<Self> {
         let (_, name) = iter.expect_symbol()?;
-        let (args_span, expected_args) = iter.expect_array().unwrap_or((Span::DUMMY, Vec::new()));
-        let expected_args = expected_args.into_iter().map(AttrSpec::from_ast).collect::<DiagResult<_>>()?;
+        let args = iter.expect_array().ok();
         let mut attrs = iter.expect_key_values()?;
+        let extends_span = attrs.attrs.get(&AttrName::from("extends")).map(|entry| entry.value.span());
+        let extends = attrs.primitive_optional::<String, _>("extends")?.zip(extends_span).map(|(name, span)| (span, name));
+        let widget = iter.next();
+        iter.expect_done()?;
+        Ok(Self { name, args, attrs, widget, extends, span })
+    }
+}
+
+impl WindowDefinitionSource {
+    /// Fill in everything this definition doesn't specify itself from the given, already resolved, parent definition.
+    pub fn inherit_from(mut self, parent: &WindowDefinitionSource) -> Self {
+        for (name, entry) in &parent.attrs.attrs {
+            self.attrs.attrs.entry(name.clone()).or_insert_with(|| entry.clone());
+        }
+        Self {
+            args: self.args.or_else(|| parent.args.clone()),
+            widget: self.widget.or_else(|| parent.widget.clone()),
+            extends: None,
+            ..self
+        }
+    }
+}
+
+impl WindowDefinition {
+    /// Parse a resolved [`WindowDefinitionSource`], which must not extend another definition anymore.
+    pub fn from_source(source: WindowDefinitionSource) -> DiagResult<Self> {
+        let WindowDefinitionSource { name, args, mut attrs, widget, span, .. } = source;
+        let (args_span, expected_args) = args.unwrap_or((Span::DUMMY, Vec::new()));
+        let expected_args = expected_args.into_iter().map(AttrSpec::from_ast).collect::<DiagResult<_>>()?;
         let monitor = attrs.ast_optional("monitor")?;
         let resizable = attrs.ast_optional("resizable")?;
         let stacking = attrs.ast_optional("stacking")?;
+        let z_index = attrs.ast_optional("z-index")?;
+        let close_on_unfocus = attrs.ast_optional("close-on-unfocus")?;
+        let lazy = attrs.primitive_optional("lazy")?.unwrap_or(true);
+        let force_x11 = attrs.primitive_optional("force-x11")?.unwrap_or(false);
+        let stylesheet = attrs.primitive_optional("stylesheet")?;
+        let onopen = attrs.primitive_optional("onopen")?;
+        let onclose = attrs.primitive_optional("onclose")?;
+        let strict = attrs.primitive_optional("strict")?;
+        let track_pointer = attrs.primitive_optional("track-pointer")?.unwrap_or(false);
+        let hide_when_idle = attrs.ast_optional("hide-when-idle")?;
+        let open_transition = attrs.ast_optional("open-transition")?;
+        let close_transition = attrs.ast_optional("close-transition")?;
+        let transition_duration = attrs.ast_optional("transition-duration")?;
         let geometry = attrs.ast_optional("geometry")?;
         let backend_options = BackendWindowOptionsDef::from_attrs(&mut attrs)?;
-        let widget = iter.expect_any().map_err(DiagError::from).and_then(WidgetUse::from_ast)?;
-        iter.expect_done()?;
-        Ok(Self { name, expected_args, args_span, monitor, resizable, widget, stacking, geometry, backend_options })
+        let widget = widget.ok_or_else(|| {
+            DiagError(gen_diagnostic! {
+                msg = format!("Window {} does not have a widget", name),
+                label = span,
+                note = "Specify a widget, or use `:extends` to inherit it from another window",
+            })
+        })?;
+        let widget = WidgetUse::from_ast(widget)?;
+        Ok(Self {
+            name,
+            expected_args,
+            args_span,
+            monitor,
+            resizable,
+            close_on_unfocus,
+            open_transition,
+            close_transition,
+            transition_duration,
+            lazy,
+            force_x11,
+            stylesheet,
+            onopen,
+            onclose,
+            strict,
+            track_pointer,
+            hide_when_idle,
+            widget,
+            stacking,
+            z_index,
+            geometry,
+            backend_options,
+            span,
+        })
     }
 }
 
@@ -148,3 +369,30 @@ impl std::str::FromStr for WindowStacking {
         }
     }
 }
+
+/// Animation used when a window is opened or closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, derive_more::Display, smart_default::SmartDefault, serde::Serialize)]
+pub enum WindowTransition {
+    #[default]
+    None,
+    Fade,
+    SlideUp,
+    SlideDown,
+    SlideLeft,
+    SlideRight,
+}
+
+impl std::str::FromStr for WindowTransition {
+    type Err = EnumParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        enum_parse! { "WindowTransition", s,
+            "none" => WindowTransition::None,
+            "fade" | "crossfade" => WindowTransition::Fade,
+            "slideup" | "slide-up" => WindowTransition::SlideUp,
+            "slidedown" | "slide-down" => WindowTransition::SlideDown,
+            "slideleft" | "slide-left" => WindowTransition::SlideLeft,
+            "slideright" | "slide-right" => WindowTransition::SlideRight,
+        }
+    }
+}
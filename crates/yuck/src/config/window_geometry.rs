@@ -171,13 +171,16 @@ impl FromAstElementContent for WindowGeometryDef {
 
 impl WindowGeometryDef {
     pub fn eval(&self, local_variables: &HashMap<VarName, DynVal>) -> Result<WindowGeometry, Error> {
+        let anchor = self.anchor_point.as_ref().map(|expr| expr.eval(local_variables)).transpose()?;
+        let at_pointer = anchor.as_ref().map_or(false, |anchor| anchor.0 == "pointer");
         Ok(WindowGeometry {
-            anchor_point: match &self.anchor_point {
-                Some(expr) => AnchorPoint::from_dynval(&expr.eval(local_variables)?)?,
-                None => AnchorPoint::default(),
+            anchor_point: match anchor {
+                Some(anchor) if !at_pointer => AnchorPoint::from_dynval(&anchor)?,
+                _ => AnchorPoint::default(),
             },
             size: self.size.eval(local_variables)?,
             offset: self.offset.eval(local_variables)?,
+            at_pointer,
         })
     }
 }
@@ -187,6 +190,9 @@ pub struct WindowGeometry {
     pub anchor_point: AnchorPoint,
     pub offset: Coords,
     pub size: Coords,
+    /// Whether the window should be placed at the position of the pointer, given as `:anchor "pointer"`.
+    /// The offset is then applied relative to the pointer.
+    pub at_pointer: bool,
 }
 
 impl WindowGeometry {
@@ -195,12 +201,18 @@ impl WindowGeometry {
             anchor_point: anchor_point.unwrap_or(self.anchor_point),
             offset: offset.unwrap_or(self.offset),
             size: size.unwrap_or(self.size),
+            // an explicitly given anchor takes precedence over placing the window at the pointer
+            at_pointer: self.at_pointer && anchor_point.is_none(),
         }
     }
 }
 
 impl std::fmt::Display for WindowGeometry {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}-{} ({})", self.offset, self.size, self.anchor_point)
+        if self.at_pointer {
+            write!(f, "{}-{} (pointer)", self.offset, self.size)
+        } else {
+            write!(f, "{}-{} ({})", self.offset, self.size, self.anchor_point)
+        }
     }
 }